@@ -0,0 +1,378 @@
+//! Auto-detecting decoder
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::{ptr, slice};
+
+use super::{Decode, DecodeError, DecodeErrorKind, DecodeStatus, Decoder, Detection, Interface};
+
+static AUTO: Interface = Interface {
+    drop_fn,
+    reset_fn,
+    decode_fn,
+    describe_error_fn,
+    gzip_header_fn,
+    trailing_data_offset_fn: super::no_trailing_data_offset,
+    memory_tracker_fn,
+};
+
+const UNKNOWN_FORMAT: i32 = 1;
+const BUFFER_ALLOC_FAILED: i32 = 2;
+const DECODER_INIT_FAILED: i32 = 3;
+const ZSTD_DICTIONARY: i32 = 4;
+const ZSTD_DISABLED: i32 = 5;
+const ZLIB_DISABLED: i32 = 6;
+const BROTLI_DISABLED: i32 = 7;
+const XZ_DISABLED: i32 = 8;
+const LZ4_DISABLED: i32 = 9;
+const BZIP2_DISABLED: i32 = 10;
+
+#[derive(Copy, Clone)]
+///Auto-detecting decoder options.
+pub struct AutoOptions {
+    detection_limit: usize,
+    #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+    zlib: super::ZlibDecoderOptions<'static>,
+    #[cfg(feature = "zstd")]
+    zstd: super::ZstdOptions<'static>,
+    #[cfg(feature = "xz")]
+    xz: super::XzDecoderOptions,
+}
+
+impl AutoOptions {
+    #[inline(always)]
+    ///Creates new default value
+    pub const fn new() -> Self {
+        Self {
+            detection_limit: 16 * 1024,
+            #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+            zlib: super::ZlibDecoderOptions::new(),
+            #[cfg(feature = "zstd")]
+            zstd: super::ZstdOptions::new(),
+            #[cfg(feature = "xz")]
+            xz: super::XzDecoderOptions::new(),
+        }
+    }
+
+    #[inline(always)]
+    ///Sets maximum number of bytes to buffer while detecting format.
+    ///
+    ///If format cannot be determined within this limit, decoding fails with
+    ///[UnknownFormat](enum.DecodeErrorKind.html#variant.UnknownFormat).
+    ///
+    ///Defaults to 16KiB.
+    pub const fn detection_limit(mut self, detection_limit: usize) -> Self {
+        self.detection_limit = detection_limit;
+        self
+    }
+
+    #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+    #[inline(always)]
+    ///Sets options to use when `gzip` or `zlib` is detected
+    ///
+    ///Mode is set according to detected format.
+    pub const fn zlib(mut self, zlib: super::ZlibDecoderOptions<'static>) -> Self {
+        self.zlib = zlib;
+        self
+    }
+
+    #[cfg(feature = "zstd")]
+    #[inline(always)]
+    ///Sets options to use when `zstd` is detected
+    pub const fn zstd(mut self, zstd: super::ZstdOptions<'static>) -> Self {
+        self.zstd = zstd;
+        self
+    }
+
+    #[cfg(feature = "xz")]
+    #[inline(always)]
+    ///Sets options to use when `xz` is detected
+    pub const fn xz(mut self, xz: super::XzDecoderOptions) -> Self {
+        self.xz = xz;
+        self
+    }
+}
+
+impl Default for AutoOptions {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct State {
+    opts: AutoOptions,
+    //Input accumulated until format is detected
+    buffer: Vec<u8>,
+    //Number of bytes in `buffer` already consumed by `decoder`
+    cursor: usize,
+    decoder: Option<Decoder<'static>>,
+}
+
+impl Interface {
+    #[inline]
+    ///Creates decoder that detects format using [Detection](enum.Detection.html) and delegates
+    ///to matching backend among enabled ones.
+    ///
+    ///Input is buffered internally until format is detected, so detection works with any chunk size.
+    ///
+    ///Supported formats:
+    ///- `gzip` and `zlib` - uses `zlib-ng`, `zlib` or `zlib-rust` in this order of preference;
+    ///- `zstd`;
+    ///- `brotli` - uses `brotli-c` or `brotli-rust` in this order of preference;
+    ///- `lz4` - uses `lz4` or `lz4-rust` in this order of preference;
+    ///- `xz`;
+    ///- `bzip2`.
+    ///
+    ///If format is unknown or its backend is not enabled, decoding fails with
+    ///[UnknownFormat](enum.DecodeErrorKind.html#variant.UnknownFormat) or
+    ///[UnsupportedFormat](enum.DecodeErrorKind.html#variant.UnsupportedFormat) accordingly.
+    ///
+    ///Format that is still undetermined at the end of input, signaled by empty input, is considered unknown.
+    ///
+    ///Note that when stream is finished within buffered input, trailing bytes buffered by previous calls are not
+    ///reported back as `input_remain`.
+    ///
+    ///Memory usage of backend is available only once format is detected. Backend always uses Rust's global allocator.
+    pub fn auto(opts: AutoOptions) -> Decoder<'static> {
+        let instance = Box::new(State {
+            opts,
+            buffer: Vec::new(),
+            cursor: 0,
+            decoder: None,
+        });
+        let instance = ptr::NonNull::from(Box::leak(instance)).cast();
+        AUTO.inner_decoder(instance)
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn error(code: i32) -> DecodeError {
+    let kind = match code {
+        UNKNOWN_FORMAT => DecodeErrorKind::UnknownFormat,
+        BUFFER_ALLOC_FAILED | DECODER_INIT_FAILED => DecodeErrorKind::OutOfMemory,
+        _ => DecodeErrorKind::UnsupportedFormat,
+    };
+    DecodeError::new(kind, code)
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+#[inline(always)]
+fn zlib_decoder(opts: super::ZlibDecoderOptions<'_>) -> Option<Decoder<'static>> {
+    #[cfg(feature = "zlib-ng")]
+    {
+        Interface::zlib_ng(opts)
+    }
+    #[cfg(all(not(feature = "zlib-ng"), any(feature = "zlib", feature = "zlib-static")))]
+    {
+        Interface::zlib(opts)
+    }
+    #[cfg(all(not(any(feature = "zlib-ng", feature = "zlib", feature = "zlib-static")), feature = "zlib-rust"))]
+    {
+        Interface::zlib_rust(opts)
+    }
+}
+
+#[cfg(any(feature = "brotli-c", feature = "brotli-rust"))]
+#[inline(always)]
+fn brotli_decoder() -> Option<Decoder<'static>> {
+    #[cfg(feature = "brotli-c")]
+    {
+        Interface::brotli_c()
+    }
+    #[cfg(not(feature = "brotli-c"))]
+    {
+        Some(Interface::brotli_rust())
+    }
+}
+
+#[cfg(any(feature = "lz4", feature = "lz4-rust"))]
+#[inline(always)]
+fn lz4_decoder() -> Option<Decoder<'static>> {
+    #[cfg(feature = "lz4")]
+    {
+        Interface::lz4()
+    }
+    #[cfg(not(feature = "lz4"))]
+    {
+        Some(Interface::lz4_rust())
+    }
+}
+
+fn create_decoder(_opts: &AutoOptions, detection: Detection) -> Result<Decoder<'static>, i32> {
+    let decoder: Result<Option<Decoder<'static>>, i32> = match detection {
+        #[cfg(feature = "zstd")]
+        Detection::Zstd => Ok(Interface::zstd(_opts.zstd)),
+        #[cfg(not(feature = "zstd"))]
+        Detection::Zstd => Err(ZSTD_DISABLED),
+        #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+        Detection::Gzip => Ok(zlib_decoder(_opts.zlib.mode(super::ZlibMode::Gzip))),
+        #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+        Detection::Zlib => Ok(zlib_decoder(_opts.zlib.mode(super::ZlibMode::Zlib))),
+        #[cfg(not(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust")))]
+        Detection::Gzip | Detection::Zlib => Err(ZLIB_DISABLED),
+        #[cfg(any(feature = "brotli-c", feature = "brotli-rust"))]
+        Detection::Brotli => Ok(brotli_decoder()),
+        #[cfg(not(any(feature = "brotli-c", feature = "brotli-rust")))]
+        Detection::Brotli => Err(BROTLI_DISABLED),
+        Detection::ZstdDictionary => Err(ZSTD_DICTIONARY),
+        #[cfg(feature = "xz")]
+        Detection::Xz => Ok(Interface::xz(_opts.xz)),
+        #[cfg(not(feature = "xz"))]
+        Detection::Xz => Err(XZ_DISABLED),
+        #[cfg(any(feature = "lz4", feature = "lz4-rust"))]
+        Detection::Lz4 => Ok(lz4_decoder()),
+        #[cfg(not(any(feature = "lz4", feature = "lz4-rust")))]
+        Detection::Lz4 => Err(LZ4_DISABLED),
+        #[cfg(feature = "bzip2")]
+        Detection::Bzip2 => Ok(Interface::bzip2(false)),
+        #[cfg(not(feature = "bzip2"))]
+        Detection::Bzip2 => Err(BZIP2_DISABLED),
+        Detection::Unknown => Err(UNKNOWN_FORMAT),
+    };
+
+    match decoder {
+        Ok(Some(decoder)) => Ok(decoder),
+        Ok(None) => Err(DECODER_INIT_FAILED),
+        Err(code) => Err(code),
+    }
+}
+
+unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, mut input_remain: usize, mut output: *mut u8, mut output_remain: usize) -> Decode {
+    let state = unsafe { &mut *(state.as_ptr() as *mut State) };
+    macro_rules! fail {
+        ($code:expr) => {
+            return Decode {
+                input_remain,
+                output_remain,
+                status: Err(error($code)),
+            }
+        };
+    }
+
+    if state.decoder.is_none() {
+        let detection = if state.buffer.is_empty() {
+            Detection::detect(unsafe { slice::from_raw_parts(input, input_remain) })
+        } else {
+            None
+        };
+
+        let detection = match detection {
+            Some(detection) => detection,
+            None => {
+                if state.buffer.try_reserve(input_remain).is_err() {
+                    fail!(BUFFER_ALLOC_FAILED);
+                }
+                state.buffer.extend_from_slice(unsafe { slice::from_raw_parts(input, input_remain) });
+                let appended = input_remain;
+                input_remain = 0;
+
+                match Detection::detect(&state.buffer) {
+                    Some(detection) => {
+                        //Only previous input needs to be buffered, while current one is decoded directly
+                        state.buffer.truncate(state.buffer.len() - appended);
+                        input_remain = appended;
+                        detection
+                    }
+                    //Empty input signals end of input, after which format cannot be determined anymore
+                    None if appended == 0 && !state.buffer.is_empty() => Detection::Unknown,
+                    None if state.buffer.len() >= state.opts.detection_limit => fail!(UNKNOWN_FORMAT),
+                    None => {
+                        return Decode {
+                            input_remain,
+                            output_remain,
+                            status: Ok(DecodeStatus::NeedInput),
+                        }
+                    }
+                }
+            }
+        };
+
+        match create_decoder(&state.opts, detection) {
+            Ok(decoder) => state.decoder = Some(decoder),
+            Err(code) => fail!(code),
+        }
+    }
+
+    let decoder = match state.decoder.as_mut() {
+        Some(decoder) => decoder,
+        None => unreachable!(),
+    };
+
+    if state.cursor < state.buffer.len() {
+        let pending = &state.buffer[state.cursor..];
+        let result = unsafe { decoder.raw_decode(pending.as_ptr(), pending.len(), output, output_remain) };
+        state.cursor += pending.len() - result.input_remain;
+
+        if result.status == Ok(DecodeStatus::Finished) {
+            state.cursor = state.buffer.len();
+        }
+        if result.input_remain > 0 || result.status != Ok(DecodeStatus::NeedInput) || input_remain == 0 {
+            return Decode {
+                input_remain,
+                output_remain: result.output_remain,
+                status: result.status,
+            };
+        }
+
+        state.buffer.clear();
+        state.cursor = 0;
+        output = unsafe { output.add(output_remain - result.output_remain) };
+        output_remain = result.output_remain;
+    }
+
+    unsafe { decoder.raw_decode(input, input_remain, output, output_remain) }
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
+    let instance = unsafe { &mut *(state.as_ptr() as *mut State) };
+    instance.decoder = None;
+    instance.buffer.clear();
+    instance.cursor = 0;
+    Some(state)
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    unsafe {
+        drop(Box::from_raw(state.as_ptr() as *mut State));
+    }
+}
+
+#[inline]
+fn gzip_header_fn(state: ptr::NonNull<u8>) -> Option<crate::gzip::GzipHeader> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    state.decoder.as_ref().and_then(Decoder::gzip_header)
+}
+
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<crate::mem::MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    state.decoder.as_ref().and_then(|decoder| (decoder.interface.memory_tracker_fn)(decoder.instance))
+}
+
+#[inline]
+fn describe_error_fn(state: ptr::NonNull<u8>, error: DecodeError) -> Option<&'static str> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    if let Some(decoder) = state.decoder.as_ref() {
+        return decoder.describe_error(error);
+    }
+
+    match error.as_raw() {
+        UNKNOWN_FORMAT => Some("unable to detect compression format"),
+        BUFFER_ALLOC_FAILED => Some("unable to buffer input for format detection"),
+        DECODER_INIT_FAILED => Some("unable to initialize decoder for detected format"),
+        ZSTD_DICTIONARY => Some("zstd dictionary is not compressed stream"),
+        ZSTD_DISABLED => Some("zstd format detected, but `zstd` feature is not enabled"),
+        ZLIB_DISABLED => Some("gzip/zlib format detected, but none of `zlib-ng`, `zlib` or `zlib-rust` features is enabled"),
+        BROTLI_DISABLED => Some("brotli format detected, but none of `brotli-c` or `brotli-rust` features is enabled"),
+        XZ_DISABLED => Some("xz format detected, but `xz` feature is not enabled"),
+        LZ4_DISABLED => Some("lz4 format detected, but none of `lz4` or `lz4-rust` features is enabled"),
+        BZIP2_DISABLED => Some("bzip2 format detected, but `bzip2` feature is not enabled"),
+        _ => None,
+    }
+}
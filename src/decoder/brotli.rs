@@ -1,10 +1,16 @@
 use core::{ptr, slice};
 
-use super::{Decode, DecodeError, DecodeStatus, Decoder, Interface};
+use super::{brotli_common, Decode, DecodeError, DecodeStatus};
+#[cfg(feature = "alloc")]
+use super::{Decoder, Interface};
+#[cfg(feature = "alloc")]
 use crate::mem::brotli_rust::BrotliAllocator;
+#[cfg(feature = "alloc")]
 use crate::mem::Box;
+#[cfg(feature = "alloc")]
 pub(crate) type Instance = brotli::BrotliState<BrotliAllocator, BrotliAllocator, BrotliAllocator>;
 
+#[cfg(feature = "alloc")]
 static BROTLI_RUST: Interface = Interface::new(
     decode_fn,
     reset_fn,
@@ -12,29 +18,36 @@ static BROTLI_RUST: Interface = Interface::new(
     describe_error_fn
 );
 
+#[cfg(feature = "alloc")]
 impl Interface {
     #[inline]
     ///Creates decoder with `brotli-rust` interface
     ///
     ///Panics on OOM issues
-    pub fn brotli_rust() -> Decoder {
+    pub fn brotli_rust() -> Decoder<'static> {
         let state = Box::new(instance());
 
         let ptr = ptr::NonNull::from(Box::leak(state));
         BROTLI_RUST.inner_decoder(ptr.cast())
     }
 }
+#[cfg(feature = "alloc")]
 #[inline]
 fn instance() -> Instance {
     Instance::new(Default::default(), Default::default(), Default::default())
 }
 
+#[cfg(feature = "alloc")]
 #[inline]
-unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, mut input_remain: usize, output: *mut u8, mut output_remain: usize) -> Decode {
+unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
     let state = unsafe {
         &mut *(state.as_ptr() as *mut Instance)
     };
+    decode(state, input, input_remain, output, output_remain)
+}
 
+//Shared with workspace variant
+pub(super) unsafe fn decode<A: brotli::Allocator<u8> + brotli::Allocator<u32> + brotli::Allocator<brotli::HuffmanCode>>(state: &mut brotli::BrotliState<A, A, A>, input: *const u8, mut input_remain: usize, output: *mut u8, mut output_remain: usize) -> Decode {
     let input = unsafe {
         slice::from_raw_parts(input, input_remain)
     };
@@ -54,11 +67,12 @@ unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, mut input_remain:
             brotli::BrotliResult::ResultSuccess => Ok(DecodeStatus::Finished),
             brotli::BrotliResult::NeedsMoreInput => Ok(DecodeStatus::NeedInput),
             brotli::BrotliResult::NeedsMoreOutput => Ok(DecodeStatus::NeedOutput),
-            brotli::BrotliResult::ResultFailure => Err(DecodeError(state.error_code as _)),
+            brotli::BrotliResult::ResultFailure => Err(brotli_common::decode_error(state.error_code as _)),
         },
     }
 }
 
+#[cfg(feature = "alloc")]
 #[inline]
 fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
     let mut state = unsafe {
@@ -70,14 +84,15 @@ fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
     Some(ptr::NonNull::from(ptr).cast())
 }
 
+#[cfg(feature = "alloc")]
 #[inline]
 fn drop_fn(state: ptr::NonNull<u8>) {
     let _ = unsafe { Box::from_raw(state.as_ptr() as *mut Instance) };
 }
 
 #[inline]
-fn describe_error_fn(code: i32) -> Option<&'static str> {
-    match code {
+pub(super) fn describe_error_fn(_state: ptr::NonNull<u8>, error: DecodeError) -> Option<&'static str> {
+    match error.as_raw() {
         0 => Some("NO_ERROR"),
         //1 => Some("SUCCESS"),
         //2 => Some("NEEDS_MORE_INPUT"),
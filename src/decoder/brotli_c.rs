@@ -1,44 +1,113 @@
 //! `brotli` interface implementation
 
+extern crate alloc;
+
 use compu_brotli_sys as sys;
 
+use alloc::boxed::Box;
+use alloc::vec::Vec;
 use core::ptr;
 
-use super::{Decode, DecodeError, DecodeStatus, Decoder, Interface};
-use crate::mem::{compu_free_with_state, compu_malloc_with_state};
+use super::{brotli_common, Decode, DecodeError, DecodeErrorKind, DecodeStatus, Decoder, Interface};
+use crate::mem::{compu_free_with_state, compu_malloc_with_state, Allocator, MemoryTracker};
 
 static BROTLI_C: Interface = Interface::new(
     decode_fn,
     reset_fn,
     drop_fn,
     describe_error_fn
-);
+).with_memory_tracker(memory_tracker_fn);
+
+struct State {
+    instance: ptr::NonNull<sys::BrotliDecoderState>,
+    //Attached dictionary must outlive decoder
+    dictionary: Vec<u8>,
+    //Referenced by decoder as `opaque`, hence it must outlive it
+    memory: Box<MemoryTracker>,
+}
+
+impl Drop for State {
+    #[inline(always)]
+    fn drop(&mut self) {
+        unsafe {
+            sys::BrotliDecoderDestroyInstance(self.instance.as_ptr());
+        }
+    }
+}
 
 impl Interface {
     #[inline]
     ///Creates decoder with `brotli-c` interface
     ///
     ///Returns `None` if unable to initialize it (likely due to lack of memory)
-    pub fn brotli_c() -> Option<Decoder> {
-        match new_decoder() {
-            Some(ptr) => Some(BROTLI_C.inner_decoder(ptr.cast())),
-            None => None,
-        }
+    pub fn brotli_c() -> Option<Decoder<'static>> {
+        Self::brotli_c_with_dictionary(&[])
+    }
+
+    #[inline]
+    ///Creates decoder with `brotli-c` interface, attaching raw prefix `dictionary`.
+    ///
+    ///Dictionary is copied and stays attached across resets.
+    ///Empty dictionary means no dictionary.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn brotli_c_with_dictionary(dictionary: &[u8]) -> Option<Decoder<'static>> {
+        create(dictionary, MemoryTracker::new())
+    }
+
+    #[inline]
+    ///Creates decoder with `brotli-c` interface, using `allocator` for all allocations of decoder.
+    ///
+    ///`dictionary` has the same meaning as in [brotli_c_with_dictionary](#method.brotli_c_with_dictionary).
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn brotli_c_with_allocator(dictionary: &[u8], allocator: impl Allocator + 'static) -> Option<Decoder<'static>> {
+        create(dictionary, MemoryTracker::with_allocator(allocator))
     }
 }
 
+fn create(dictionary: &[u8], memory: MemoryTracker) -> Option<Decoder<'static>> {
+    //Attach own copy, as brotli only references dictionary
+    let dictionary = dictionary.to_vec();
+    let memory = Box::new(memory);
+    let instance = new_decoder(&dictionary, &memory)?;
+    let state = Box::new(State {
+        instance,
+        dictionary,
+        memory,
+    });
+
+    let ptr = ptr::NonNull::from(Box::leak(state));
+    Some(BROTLI_C.inner_decoder(ptr.cast()))
+}
+
 #[inline]
-fn new_decoder() -> Option<ptr::NonNull<u8>> {
-    let result = unsafe {
-        sys::BrotliDecoderCreateInstance(Some(compu_malloc_with_state), Some(compu_free_with_state), ptr::null_mut())
+fn new_decoder(dictionary: &[u8], memory: &MemoryTracker) -> Option<ptr::NonNull<sys::BrotliDecoderState>> {
+    let instance = unsafe {
+        sys::BrotliDecoderCreateInstance(Some(compu_malloc_with_state), Some(compu_free_with_state), memory.as_opaque())
     };
+    let instance = ptr::NonNull::new(instance)?;
 
-    ptr::NonNull::new(result as *mut u8)
+    if !dictionary.is_empty() {
+        let result = unsafe {
+            sys::BrotliDecoderAttachDictionary(instance.as_ptr(), sys::BrotliSharedDictionaryType_BROTLI_SHARED_DICTIONARY_RAW, dictionary.len(), dictionary.as_ptr())
+        };
+        if result == 0 {
+            unsafe {
+                sys::BrotliDecoderDestroyInstance(instance.as_ptr());
+            }
+            return None;
+        }
+    }
+
+    Some(instance)
 }
 
 #[inline]
 unsafe fn decode_fn(state: ptr::NonNull<u8>, mut input: *const u8, mut input_remain: usize, mut output: *mut u8, mut output_remain: usize) -> Decode {
-    let state = state.as_ptr() as _;
+    let state = unsafe {
+        (*(state.as_ptr() as *mut State)).instance.as_ptr()
+    };
     let result = unsafe {
         sys::BrotliDecoderDecompressStream(state, &mut input_remain, &mut input, &mut output_remain, &mut output, ptr::null_mut())
     };
@@ -51,22 +120,28 @@ unsafe fn decode_fn(state: ptr::NonNull<u8>, mut input: *const u8, mut input_rem
                 let code = unsafe {
                     sys::BrotliDecoderGetErrorCode(state)
                 };
-                Err(DecodeError(code as _))
+                Err(brotli_common::decode_error(code as _))
             }
             sys::BrotliDecoderResult_BROTLI_DECODER_RESULT_SUCCESS => Ok(DecodeStatus::Finished),
             sys::BrotliDecoderResult_BROTLI_DECODER_RESULT_NEEDS_MORE_INPUT => Ok(DecodeStatus::NeedInput),
             sys::BrotliDecoderResult_BROTLI_DECODER_RESULT_NEEDS_MORE_OUTPUT => Ok(DecodeStatus::NeedOutput),
-            other => Err(DecodeError(other)),
+            other => Err(DecodeError::new(DecodeErrorKind::Other, other)),
         },
     }
 }
 
 #[inline]
 fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
-    match new_decoder() {
-        Some(new) => {
-            drop_fn(state);
-            Some(new)
+    let state_ref = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    match new_decoder(&state_ref.dictionary, &state_ref.memory) {
+        Some(instance) => {
+            unsafe {
+                sys::BrotliDecoderDestroyInstance(state_ref.instance.as_ptr());
+            }
+            state_ref.instance = instance;
+            Some(state)
         }
         None => None,
     }
@@ -75,14 +150,20 @@ fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
 #[inline]
 fn drop_fn(state: ptr::NonNull<u8>) {
     unsafe {
-        sys::BrotliDecoderDestroyInstance(state.as_ptr() as _);
+        drop(Box::from_raw(state.as_ptr() as *mut State));
     }
 }
 
 #[inline]
-fn describe_error_fn(code: i32) -> Option<&'static str> {
+fn describe_error_fn(_state: ptr::NonNull<u8>, error: DecodeError) -> Option<&'static str> {
     let result = unsafe {
-        sys::BrotliDecoderErrorString(code as _)
+        sys::BrotliDecoderErrorString(error.as_raw() as _)
     };
     crate::utils::convert_c_str(result)
 }
+
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    Some(ptr::NonNull::from(&*state.memory))
+}
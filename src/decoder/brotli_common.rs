@@ -0,0 +1,22 @@
+use super::{DecodeError, DecodeErrorKind};
+
+#[cold]
+#[inline(never)]
+///Maps `BrotliDecoderErrorCode` to [DecodeError](../struct.DecodeError.html)
+///
+///Codes are shared by C and Rust implementations.
+pub(crate) fn decode_error(code: i32) -> DecodeError {
+    const ERROR_DICTIONARY_NOT_SET: i32 = -19;
+    const ERROR_INVALID_ARGUMENTS: i32 = -20;
+
+    let kind = match code {
+        ERROR_DICTIONARY_NOT_SET => DecodeErrorKind::DictionaryRequired,
+        ERROR_INVALID_ARGUMENTS => DecodeErrorKind::UnsupportedParameter,
+        //ERROR_FORMAT_*
+        -18..=-1 => DecodeErrorKind::Corrupted,
+        //ERROR_ALLOC_*
+        -30..=-21 => DecodeErrorKind::OutOfMemory,
+        _ => DecodeErrorKind::Other,
+    };
+    DecodeError::new(kind, code)
+}
@@ -0,0 +1,452 @@
+//!Heuristic check for brotli stream, which has no magic number.
+//!
+//!Stream is accepted only if its headers are valid up to and including all prefix codes of the
+//!first compressed meta-block.
+//!Refer to RFC 7932 for details on each step.
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Probe {
+    Brotli,
+    NotBrotli,
+    NeedMore,
+}
+
+macro_rules! tri {
+    ($expr:expr) => {
+        match $expr {
+            Ok(value) => value,
+            Err(probe) => return Err(probe),
+        }
+    };
+}
+
+macro_rules! ensure {
+    ($cond:expr) => {
+        if !$cond {
+            return Err(Probe::NotBrotli);
+        }
+    };
+}
+
+//Largest alphabet is insert-and-copy length code
+const MAX_ALPHABET: usize = 704;
+const MAX_CODE_LEN: usize = 15;
+const CODE_LENGTH_CODES: usize = 18;
+const CODE_LENGTH_ORDER: [u8; CODE_LENGTH_CODES] = [1, 2, 3, 4, 0, 5, 17, 6, 16, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+//Static prefix code for code length code lengths, indexed by next 4 bits
+const CODE_LENGTH_PREFIX_LEN: [u8; 16] = [2, 2, 2, 3, 2, 2, 2, 4, 2, 2, 2, 3, 2, 2, 2, 4];
+const CODE_LENGTH_PREFIX_VALUE: [u8; 16] = [0, 4, 3, 2, 0, 4, 3, 1, 0, 4, 3, 2, 0, 4, 3, 5];
+const BLOCK_COUNT_EXTRA_BITS: [u8; 26] = [2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 6, 6, 7, 8, 9, 10, 11, 12, 13, 24];
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    //Position in bits
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    const fn bits_left(&self) -> usize {
+        self.bytes.len() * 8 - self.pos
+    }
+
+    const fn read(&mut self, num: usize) -> Result<u32, Probe> {
+        //Result must fit u32, while no field of brotli header is that large
+        ensure!(num <= u32::BITS as usize);
+        if self.bits_left() < num {
+            return Err(Probe::NeedMore);
+        }
+
+        let mut result = 0;
+        let mut idx = 0;
+        while idx < num {
+            let bit = (self.bytes[self.pos >> 3] >> (self.pos & 7)) & 1;
+            result |= (bit as u32) << idx;
+            self.pos += 1;
+            idx += 1;
+        }
+        Ok(result)
+    }
+
+    const fn peek4(&self) -> usize {
+        let mut result = 0;
+        let mut idx = 0;
+        while idx < 4 && idx < self.bits_left() {
+            let pos = self.pos + idx;
+            let bit = (self.bytes[pos >> 3] >> (pos & 7)) & 1;
+            result |= (bit as usize) << idx;
+            idx += 1;
+        }
+        result
+    }
+
+    //Padding up to byte boundary must be zero
+    const fn align(&mut self) -> Result<(), Probe> {
+        let padding = tri!(self.read((8 - (self.pos & 7)) & 7));
+        ensure!(padding == 0);
+        Ok(())
+    }
+
+    //Skipped data must be fully available as otherwise there is nothing to check
+    const fn skip_bytes(&mut self, num: usize) -> Result<(), Probe> {
+        ensure!(self.bits_left() / 8 >= num);
+        self.pos += num * 8;
+        Ok(())
+    }
+
+    const fn read_var_len_u8(&mut self) -> Result<u32, Probe> {
+        if tri!(self.read(1)) == 0 {
+            return Ok(1);
+        }
+        match tri!(self.read(3)) {
+            0 => Ok(2),
+            num => Ok((1 << num) + tri!(self.read(num as usize)) + 1),
+        }
+    }
+}
+
+//Canonical prefix code
+struct PrefixCode {
+    //Set when code consists of single symbol, which takes no bits
+    single: Option<u16>,
+    counts: [u16; MAX_CODE_LEN + 1],
+    //Symbols sorted by code length, then by value
+    symbols: [u16; MAX_ALPHABET],
+}
+
+impl PrefixCode {
+    const fn from_lengths(lengths: &[u8; MAX_ALPHABET], alphabet: usize) -> Self {
+        let mut code = Self {
+            single: None,
+            counts: [0; MAX_CODE_LEN + 1],
+            symbols: [0; MAX_ALPHABET],
+        };
+
+        let mut symbol = 0;
+        while symbol < alphabet {
+            code.counts[lengths[symbol] as usize] += 1;
+            symbol += 1;
+        }
+
+        let mut idx = 0;
+        let mut len = 1;
+        while len <= MAX_CODE_LEN {
+            let mut symbol = 0;
+            while symbol < alphabet {
+                if lengths[symbol] as usize == len {
+                    code.symbols[idx] = symbol as u16;
+                    idx += 1;
+                }
+                symbol += 1;
+            }
+            len += 1;
+        }
+        code
+    }
+
+    const fn single(symbol: u16) -> Self {
+        Self {
+            single: Some(symbol),
+            counts: [0; MAX_CODE_LEN + 1],
+            symbols: [0; MAX_ALPHABET],
+        }
+    }
+
+    const fn decode(&self, reader: &mut BitReader<'_>) -> Result<u16, Probe> {
+        if let Some(symbol) = self.single {
+            return Ok(symbol);
+        }
+
+        //Codes are packed starting from most significant bit
+        let mut code = 0;
+        let mut first = 0;
+        let mut idx = 0;
+        let mut len = 1;
+        while len <= MAX_CODE_LEN {
+            code |= tri!(reader.read(1));
+            let count = self.counts[len] as u32;
+            if code - first < count {
+                return Ok(self.symbols[idx + (code - first) as usize]);
+            }
+            idx += count as usize;
+            first = (first + count) << 1;
+            code <<= 1;
+            len += 1;
+        }
+        Err(Probe::NotBrotli)
+    }
+}
+
+const fn bit_len(value: usize) -> usize {
+    (usize::BITS - value.leading_zeros()) as usize
+}
+
+const fn read_simple_prefix_code(reader: &mut BitReader<'_>, alphabet: usize) -> Result<PrefixCode, Probe> {
+    let num = tri!(reader.read(2)) as usize + 1;
+    let symbol_bits = bit_len(alphabet - 1);
+    let mut symbols = [0u16; 4];
+
+    let mut idx = 0;
+    while idx < num {
+        let symbol = tri!(reader.read(symbol_bits)) as usize;
+        ensure!(symbol < alphabet);
+        let mut prev = 0;
+        while prev < idx {
+            ensure!(symbols[prev] as usize != symbol);
+            prev += 1;
+        }
+        symbols[idx] = symbol as u16;
+        idx += 1;
+    }
+
+    let mut lengths = [0u8; MAX_ALPHABET];
+    match num {
+        1 => return Ok(PrefixCode::single(symbols[0])),
+        2 => {
+            lengths[symbols[0] as usize] = 1;
+            lengths[symbols[1] as usize] = 1;
+        }
+        3 => {
+            lengths[symbols[0] as usize] = 1;
+            lengths[symbols[1] as usize] = 2;
+            lengths[symbols[2] as usize] = 2;
+        }
+        _ => {
+            //Tree select
+            if tri!(reader.read(1)) == 0 {
+                lengths[symbols[0] as usize] = 2;
+                lengths[symbols[1] as usize] = 2;
+                lengths[symbols[2] as usize] = 2;
+                lengths[symbols[3] as usize] = 2;
+            } else {
+                lengths[symbols[0] as usize] = 1;
+                lengths[symbols[1] as usize] = 2;
+                lengths[symbols[2] as usize] = 3;
+                lengths[symbols[3] as usize] = 3;
+            }
+        }
+    }
+    Ok(PrefixCode::from_lengths(&lengths, alphabet))
+}
+
+const fn read_complex_prefix_code(reader: &mut BitReader<'_>, alphabet: usize, skip: usize) -> Result<PrefixCode, Probe> {
+    let mut lengths = [0u8; MAX_ALPHABET];
+
+    let mut space = 32;
+    let mut num = 0;
+    let mut idx = skip;
+    while idx < CODE_LENGTH_CODES {
+        let prefix = reader.peek4();
+        tri!(reader.read(CODE_LENGTH_PREFIX_LEN[prefix] as usize));
+        let len = CODE_LENGTH_PREFIX_VALUE[prefix];
+        lengths[CODE_LENGTH_ORDER[idx] as usize] = len;
+        if len != 0 {
+            space -= 32 >> len;
+            num += 1;
+            if space <= 0 {
+                break;
+            }
+        }
+        idx += 1;
+    }
+    ensure!(num == 1 || space == 0);
+
+    let code_length_code = if num == 1 {
+        let mut symbol = 0;
+        while lengths[symbol] == 0 {
+            symbol += 1;
+        }
+        PrefixCode::single(symbol as u16)
+    } else {
+        PrefixCode::from_lengths(&lengths, CODE_LENGTH_CODES)
+    };
+
+    lengths = [0u8; MAX_ALPHABET];
+    let mut symbol = 0;
+    let mut space = 1 << MAX_CODE_LEN;
+    let mut prev_len = 8;
+    let mut repeat = 0;
+    let mut repeat_len = 0;
+    while symbol < alphabet && space > 0 {
+        let len = tri!(code_length_code.decode(reader)) as usize;
+        if len < 16 {
+            repeat = 0;
+            lengths[symbol] = len as u8;
+            if len != 0 {
+                prev_len = len;
+                space -= (1 << MAX_CODE_LEN) >> len;
+            }
+            symbol += 1;
+        } else {
+            let (extra_bits, new_len) = match len {
+                16 => (2, prev_len),
+                _ => (3, 0),
+            };
+            if repeat_len != new_len {
+                repeat = 0;
+                repeat_len = new_len;
+            }
+            let old_repeat = repeat;
+            if repeat > 0 {
+                repeat = (repeat - 2) << extra_bits;
+            }
+            repeat += tri!(reader.read(extra_bits)) as usize + 3;
+
+            let delta = repeat - old_repeat;
+            ensure!(symbol + delta <= alphabet);
+            let mut idx = 0;
+            while idx < delta {
+                lengths[symbol + idx] = repeat_len as u8;
+                idx += 1;
+            }
+            symbol += delta;
+            if repeat_len != 0 {
+                space -= delta as i32 * ((1 << MAX_CODE_LEN) >> repeat_len);
+            }
+        }
+    }
+    ensure!(space == 0);
+
+    Ok(PrefixCode::from_lengths(&lengths, alphabet))
+}
+
+const fn read_prefix_code(reader: &mut BitReader<'_>, alphabet: usize) -> Result<PrefixCode, Probe> {
+    match tri!(reader.read(2)) {
+        1 => read_simple_prefix_code(reader, alphabet),
+        skip => read_complex_prefix_code(reader, alphabet, skip as usize),
+    }
+}
+
+//Reads block types and returns their number
+const fn read_block_types(reader: &mut BitReader<'_>) -> Result<u32, Probe> {
+    let types = tri!(reader.read_var_len_u8());
+    if types >= 2 {
+        tri!(read_prefix_code(reader, types as usize + 2));
+        let count_code = tri!(read_prefix_code(reader, BLOCK_COUNT_EXTRA_BITS.len()));
+        let count = tri!(count_code.decode(reader));
+        tri!(reader.read(BLOCK_COUNT_EXTRA_BITS[count as usize] as usize));
+    }
+    Ok(types)
+}
+
+//Reads context map and returns number of trees
+const fn read_context_map(reader: &mut BitReader<'_>, size: usize) -> Result<u32, Probe> {
+    let trees = tri!(reader.read_var_len_u8());
+    if trees < 2 {
+        return Ok(trees);
+    }
+
+    let rle_max = match tri!(reader.read(1)) {
+        0 => 0,
+        _ => tri!(reader.read(4)) + 1,
+    };
+    let code = tri!(read_prefix_code(reader, (trees + rle_max) as usize));
+    let mut idx = 0;
+    while idx < size {
+        let symbol = tri!(code.decode(reader)) as u32;
+        if symbol == 0 || symbol > rle_max {
+            idx += 1;
+        } else {
+            idx += (1 << symbol) + tri!(reader.read(symbol as usize)) as usize;
+            ensure!(idx <= size);
+        }
+    }
+    //Inverse move-to-front
+    tri!(reader.read(1));
+
+    Ok(trees)
+}
+
+const fn read_stream(bytes: &[u8]) -> Result<(), Probe> {
+    let mut reader = BitReader {
+        bytes,
+        pos: 0,
+    };
+
+    //WBITS
+    if tri!(reader.read(1)) == 1 && tri!(reader.read(3)) == 0 {
+        //Large window requires out of band signal, so it cannot be assumed
+        ensure!(tri!(reader.read(3)) != 1);
+    }
+
+    //Skip meta-blocks without compressed data
+    loop {
+        let is_last = tri!(reader.read(1)) == 1;
+        if is_last && tri!(reader.read(1)) == 1 {
+            tri!(reader.align());
+            ensure!(reader.bits_left() == 0);
+            return Ok(());
+        }
+
+        let nibbles = tri!(reader.read(2));
+        if nibbles == 3 {
+            //Metadata
+            ensure!(tri!(reader.read(1)) == 0);
+            let size_bytes = tri!(reader.read(2)) as usize;
+            let mut size = 0;
+            if size_bytes > 0 {
+                size = tri!(reader.read(size_bytes * 8)) as usize;
+                ensure!(size_bytes == 1 || (size >> ((size_bytes - 1) * 8)) != 0);
+                size += 1;
+            }
+            tri!(reader.align());
+            tri!(reader.skip_bytes(size));
+            if is_last {
+                ensure!(reader.bits_left() == 0);
+                return Ok(());
+            }
+            continue;
+        }
+
+        let nibbles = nibbles as usize + 4;
+        let len = tri!(reader.read(nibbles * 4)) as usize;
+        ensure!(nibbles == 4 || (len >> ((nibbles - 1) * 4)) != 0);
+        if !is_last && tri!(reader.read(1)) == 1 {
+            //Uncompressed
+            tri!(reader.align());
+            tri!(reader.skip_bytes(len + 1));
+            continue;
+        }
+        break;
+    }
+
+    let literal_types = tri!(read_block_types(&mut reader));
+    let command_types = tri!(read_block_types(&mut reader));
+    let distance_types = tri!(read_block_types(&mut reader));
+
+    let postfix = tri!(reader.read(2));
+    let direct = tri!(reader.read(4)) << postfix;
+    //Context modes
+    let mut idx = 0;
+    while idx < literal_types {
+        tri!(reader.read(2));
+        idx += 1;
+    }
+
+    let literal_trees = tri!(read_context_map(&mut reader, 64 * literal_types as usize));
+    let distance_trees = tri!(read_context_map(&mut reader, 4 * distance_types as usize));
+
+    idx = 0;
+    while idx < literal_trees {
+        tri!(read_prefix_code(&mut reader, 256));
+        idx += 1;
+    }
+    idx = 0;
+    while idx < command_types {
+        tri!(read_prefix_code(&mut reader, MAX_ALPHABET));
+        idx += 1;
+    }
+    idx = 0;
+    while idx < distance_trees {
+        tri!(read_prefix_code(&mut reader, 16 + direct as usize + (48 << postfix)));
+        idx += 1;
+    }
+
+    Ok(())
+}
+
+pub(crate) const fn probe(bytes: &[u8]) -> Probe {
+    match read_stream(bytes) {
+        Ok(()) => Probe::Brotli,
+        Err(probe) => probe,
+    }
+}
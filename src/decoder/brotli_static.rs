@@ -0,0 +1,67 @@
+//! `brotli` interface implementation with state in caller provided workspace
+
+use core::alloc::Layout;
+use core::mem::MaybeUninit;
+use core::ptr;
+
+use super::brotli::{decode, describe_error_fn};
+use super::{Decode, Decoder, Interface};
+use crate::mem::brotli_rust::WorkspaceAllocator;
+use crate::mem::Workspace;
+
+type Instance<'a> = brotli::BrotliState<WorkspaceAllocator<'a>, WorkspaceAllocator<'a>, WorkspaceAllocator<'a>>;
+
+static BROTLI_STATIC: Interface = Interface::new(
+    decode_fn,
+    reset_fn,
+    drop_fn,
+    describe_error_fn
+);
+
+impl Interface {
+    ///Creates decoder with `brotli-rust` interface, placing all of its state within `workspace`.
+    ///
+    ///Decoder never allocates, hence its memory usage is limited by `workspace`.
+    ///Most of it is taken by window, which size is specified by stream and is up to 16MiB.
+    ///
+    ///Running out of workspace is reported as error where `brotli` handles allocation failure, but it may panic otherwise.
+    ///
+    ///Returns `None` if `workspace` is too small to fit decoder state.
+    pub fn brotli_rust_static<'a>(workspace: &'a mut [MaybeUninit<u8>]) -> Option<Decoder<'a>> {
+        let workspace = Workspace::new(workspace)?;
+        let state = workspace.alloc(Layout::new::<Instance<'a>>()) as *mut Instance<'a>;
+        let state = ptr::NonNull::new(state)?;
+
+        let allocator = WorkspaceAllocator::new(workspace);
+        unsafe {
+            state.as_ptr().write(Instance::new(allocator, allocator, allocator));
+        }
+        Some(BROTLI_STATIC.inner_decoder(state.cast()))
+    }
+}
+
+#[inline]
+unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut Instance)
+    };
+    decode(state, input, input_remain, output, output_remain)
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
+    let instance = unsafe {
+        &mut *(state.as_ptr() as *mut Instance)
+    };
+    let allocator = instance.alloc_u8;
+    *instance = Instance::new(allocator, allocator, allocator);
+    Some(state)
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    //Returns all memory to workspace, which itself is owned by caller
+    unsafe {
+        ptr::drop_in_place(state.as_ptr() as *mut Instance);
+    }
+}
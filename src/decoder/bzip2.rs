@@ -0,0 +1,181 @@
+//! `bzip2` interface implementation
+
+extern crate alloc;
+
+use bzip2_sys as sys;
+
+use alloc::boxed::Box;
+use core::ffi::c_uint;
+use core::{cmp, ptr};
+
+use super::{Decode, DecodeError, DecodeErrorKind, DecodeStatus, Decoder, Interface};
+use crate::mem::{compu_bzalloc, compu_bzfree, Allocator, MemoryTracker};
+
+static BZIP2: Interface = Interface {
+    drop_fn,
+    reset_fn,
+    decode_fn,
+    describe_error_fn,
+    gzip_header_fn: super::no_gzip_header,
+    trailing_data_offset_fn: super::no_trailing_data_offset,
+    memory_tracker_fn,
+};
+
+struct State {
+    inner: sys::bz_stream,
+    small: bool,
+    //Referenced by stream as `opaque`, hence it must not be moved after being set
+    memory: MemoryTracker,
+}
+
+impl State {
+    #[inline(always)]
+    fn init(&mut self) -> bool {
+        unsafe { sys::BZ2_bzDecompressInit(&mut self.inner, 0, self.small as _) == sys::BZ_OK }
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) -> bool {
+        //bzip2 has no reset, so stream has to be initialized again
+        unsafe {
+            sys::BZ2_bzDecompressEnd(&mut self.inner);
+        }
+        self.init()
+    }
+}
+
+impl Drop for State {
+    #[inline(always)]
+    fn drop(&mut self) {
+        unsafe {
+            sys::BZ2_bzDecompressEnd(&mut self.inner);
+        }
+    }
+}
+
+impl Interface {
+    #[inline]
+    ///Creates decoder with `bzip2` interface
+    ///
+    ///`small` enables alternative algorithm, which uses less memory at the cost of speed.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn bzip2(small: bool) -> Option<Decoder<'static>> {
+        create(small, MemoryTracker::new())
+    }
+
+    #[inline]
+    ///Creates decoder with `bzip2` interface, using `allocator` for all allocations of stream.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn bzip2_with_allocator(small: bool, allocator: impl Allocator + 'static) -> Option<Decoder<'static>> {
+        create(small, MemoryTracker::with_allocator(allocator))
+    }
+}
+
+fn create(small: bool, memory: MemoryTracker) -> Option<Decoder<'static>> {
+    let mut instance = Box::new(State {
+        inner: sys::bz_stream {
+            next_in: ptr::null_mut(),
+            avail_in: 0,
+            total_in_lo32: 0,
+            total_in_hi32: 0,
+            next_out: ptr::null_mut(),
+            avail_out: 0,
+            total_out_lo32: 0,
+            total_out_hi32: 0,
+            state: ptr::null_mut(),
+            bzalloc: Some(compu_bzalloc),
+            bzfree: Some(compu_bzfree),
+            opaque: ptr::null_mut(),
+        },
+        small,
+        memory,
+    });
+    instance.inner.opaque = instance.memory.as_opaque();
+
+    if instance.init() {
+        let instance = ptr::NonNull::from(Box::leak(instance)).cast();
+        Some(BZIP2.inner_decoder(instance))
+    } else {
+        None
+    }
+}
+
+unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+
+    let avail_in = cmp::min(input_remain, c_uint::MAX as usize);
+    let avail_out = cmp::min(output_remain, c_uint::MAX as usize);
+
+    state.inner.next_in = input as *mut _;
+    state.inner.avail_in = avail_in as _;
+    state.inner.next_out = output as *mut _;
+    state.inner.avail_out = avail_out as _;
+
+    let result = unsafe {
+        sys::BZ2_bzDecompress(&mut state.inner)
+    };
+
+    let output_remain = output_remain - (avail_out - state.inner.avail_out as usize);
+    Decode {
+        input_remain: input_remain - (avail_in - state.inner.avail_in as usize),
+        output_remain,
+        status: match result {
+            sys::BZ_STREAM_END => Ok(DecodeStatus::Finished),
+            sys::BZ_OK => {
+                if output_remain == 0 {
+                    Ok(DecodeStatus::NeedOutput)
+                } else {
+                    Ok(DecodeStatus::NeedInput)
+                }
+            }
+            code => Err(decode_error(code)),
+        },
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn decode_error(code: i32) -> DecodeError {
+    let kind = match code {
+        //Checksum mismatch is reported as BZ_DATA_ERROR too
+        sys::BZ_DATA_ERROR | sys::BZ_DATA_ERROR_MAGIC => DecodeErrorKind::Corrupted,
+        sys::BZ_PARAM_ERROR | sys::BZ_CONFIG_ERROR => DecodeErrorKind::UnsupportedParameter,
+        sys::BZ_MEM_ERROR => DecodeErrorKind::OutOfMemory,
+        _ => DecodeErrorKind::Other,
+    };
+    DecodeError::new(kind, code)
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
+    let result = unsafe {
+        (*(state.as_ptr() as *mut State)).reset()
+    };
+    if result {
+        Some(state)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    unsafe {
+        drop(Box::from_raw(state.as_ptr() as *mut State));
+    }
+}
+
+#[inline]
+fn describe_error_fn(_state: ptr::NonNull<u8>, error: DecodeError) -> Option<&'static str> {
+    crate::utils::bz_error_name(error.as_raw())
+}
+
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    Some(ptr::NonNull::from(&state.memory))
+}
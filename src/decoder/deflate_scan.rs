@@ -0,0 +1,285 @@
+//!Locates end of deflate stream without producing output.
+//!
+//!Blocks are parsed according to RFC 1951, but only to the extent necessary to skip them:
+//!back references are not resolved, so distance validity is not checked.
+
+const MAX_CODE_LEN: usize = 15;
+//Codes up to this length are decoded via lookup table
+const TABLE_BITS: u32 = 11;
+const MAX_LIT_LEN_CODES: usize = 286;
+const MAX_DIST_CODES: usize = 30;
+const FIXED_LIT_LEN_CODES: usize = 288;
+const CODE_LENGTH_CODES: usize = 19;
+const CODE_LENGTH_ORDER: [u8; CODE_LENGTH_CODES] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+const END_OF_BLOCK: u16 = 256;
+//Extra bits for length codes 257..285
+const LENGTH_EXTRA_BITS: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+//Extra bits for distance codes 0..29
+const DIST_EXTRA_BITS: [u8; MAX_DIST_CODES] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    //Position of next byte to load
+    pos: usize,
+    buf: u64,
+    len: u32,
+}
+
+impl<'a> BitReader<'a> {
+    #[inline(always)]
+    fn peek(&mut self, num: u32) -> Option<u32> {
+        if self.len < num {
+            if let Some(word) = self.bytes.get(self.pos..self.pos + 8) {
+                //Loads as many whole bytes as fit into buffer
+                let word = u64::from_le_bytes([word[0], word[1], word[2], word[3], word[4], word[5], word[6], word[7]]);
+                self.buf |= word << self.len;
+                self.pos += ((63 - self.len) / 8) as usize;
+                self.len |= 56;
+            }
+        }
+        while self.len < num {
+            let byte = *self.bytes.get(self.pos)?;
+            self.buf |= (byte as u64) << self.len;
+            self.pos += 1;
+            self.len += 8;
+        }
+        Some((self.buf & ((1 << num) - 1)) as u32)
+    }
+
+    #[inline(always)]
+    fn consume(&mut self, num: u32) {
+        self.buf >>= num;
+        self.len -= num;
+    }
+
+    #[inline(always)]
+    fn read(&mut self, num: u32) -> Option<u32> {
+        let result = self.peek(num)?;
+        self.consume(num);
+        Some(result)
+    }
+
+    //Drops bits up to byte boundary
+    #[inline(always)]
+    fn align(&mut self) {
+        let padding = self.len & 7;
+        self.buf >>= padding;
+        self.len -= padding;
+    }
+
+    //Number of bytes consumed so far
+    #[inline(always)]
+    const fn consumed(&self) -> usize {
+        self.pos - (self.len / 8) as usize
+    }
+
+    fn skip_bytes(&mut self, num: usize) -> Option<()> {
+        //Skip is only performed at byte boundary
+        let pos = self.consumed().checked_add(num)?;
+        if pos > self.bytes.len() {
+            return None;
+        }
+        self.pos = pos;
+        self.buf = 0;
+        self.len = 0;
+        Some(())
+    }
+}
+
+//Canonical prefix code
+struct PrefixCode {
+    counts: [u16; MAX_CODE_LEN + 1],
+    //Symbols sorted by code length, then by value
+    symbols: [u16; FIXED_LIT_LEN_CODES],
+    //Indexed by next `TABLE_BITS` bits, contains `symbol << 4 | length` or 0 for longer code
+    table: [u16; 1 << TABLE_BITS],
+}
+
+impl PrefixCode {
+    //Incomplete codes are accepted, as unused codes are rejected on decoding anyway.
+    fn from_lengths(lengths: &[u8]) -> Option<Self> {
+        let mut code = Self {
+            counts: [0; MAX_CODE_LEN + 1],
+            symbols: [0; FIXED_LIT_LEN_CODES],
+            table: [0; 1 << TABLE_BITS],
+        };
+
+        for len in lengths {
+            code.counts[*len as usize] += 1;
+        }
+
+        let mut left = 1i32;
+        let mut offsets = [0u16; MAX_CODE_LEN + 2];
+        for len in 1..=MAX_CODE_LEN {
+            left = (left << 1) - code.counts[len] as i32;
+            if left < 0 {
+                return None;
+            }
+            offsets[len + 1] = offsets[len] + code.counts[len];
+        }
+
+        for (symbol, len) in lengths.iter().enumerate() {
+            if *len != 0 {
+                let offset = &mut offsets[*len as usize];
+                code.symbols[*offset as usize] = symbol as u16;
+                *offset += 1;
+            }
+        }
+
+        //Codes are assigned in order of symbols, so table is filled in the same order
+        let mut next = 0u32;
+        let mut idx = 0usize;
+        for len in 1..=TABLE_BITS {
+            for _ in 0..code.counts[len as usize] {
+                let symbol = code.symbols[idx];
+                //Codes are packed starting from most significant bit
+                let mut entry = next.reverse_bits() >> (u32::BITS - len);
+                while entry < (1 << TABLE_BITS) {
+                    code.table[entry as usize] = symbol << 4 | len as u16;
+                    entry += 1 << len;
+                }
+                next += 1;
+                idx += 1;
+            }
+            next <<= 1;
+        }
+        Some(code)
+    }
+
+    #[inline(always)]
+    fn decode(&self, reader: &mut BitReader<'_>) -> Option<u16> {
+        if let Some(bits) = reader.peek(TABLE_BITS) {
+            let entry = self.table[bits as usize];
+            if entry != 0 {
+                reader.consume((entry & 0xf) as u32);
+                return Some(entry >> 4);
+            }
+        }
+        self.decode_slow(reader)
+    }
+
+    fn decode_slow(&self, reader: &mut BitReader<'_>) -> Option<u16> {
+        //Codes are packed starting from most significant bit
+        let mut code = 0u32;
+        let mut first = 0u32;
+        let mut idx = 0usize;
+        for len in 1..=MAX_CODE_LEN {
+            code |= reader.read(1)?;
+            let count = self.counts[len] as u32;
+            if code.wrapping_sub(first) < count {
+                return Some(self.symbols[idx + (code - first) as usize]);
+            }
+            idx += count as usize;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        None
+    }
+}
+
+fn read_codes(reader: &mut BitReader<'_>, lit_len: &PrefixCode, dist: &PrefixCode) -> Option<()> {
+    loop {
+        let symbol = lit_len.decode(reader)?;
+        if symbol < END_OF_BLOCK {
+            continue;
+        } else if symbol == END_OF_BLOCK {
+            break Some(());
+        }
+
+        let extra = *LENGTH_EXTRA_BITS.get((symbol - END_OF_BLOCK - 1) as usize)?;
+        reader.read(extra as u32)?;
+        let symbol = dist.decode(reader)?;
+        let extra = *DIST_EXTRA_BITS.get(symbol as usize)?;
+        reader.read(extra as u32)?;
+    }
+}
+
+fn fixed_codes() -> Option<(PrefixCode, PrefixCode)> {
+    let mut lengths = [8u8; FIXED_LIT_LEN_CODES];
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    Some((PrefixCode::from_lengths(&lengths)?, PrefixCode::from_lengths(&[5; MAX_DIST_CODES])?))
+}
+
+fn dynamic_codes(reader: &mut BitReader<'_>) -> Option<(PrefixCode, PrefixCode)> {
+    let lit_len_num = reader.read(5)? as usize + 257;
+    let dist_num = reader.read(5)? as usize + 1;
+    let code_length_num = reader.read(4)? as usize + 4;
+    if lit_len_num > MAX_LIT_LEN_CODES || dist_num > MAX_DIST_CODES {
+        return None;
+    }
+
+    let mut lengths = [0u8; CODE_LENGTH_CODES];
+    for idx in CODE_LENGTH_ORDER.iter().take(code_length_num) {
+        lengths[*idx as usize] = reader.read(3)? as u8;
+    }
+    let code_length = PrefixCode::from_lengths(&lengths)?;
+
+    let mut lengths = [0u8; MAX_LIT_LEN_CODES + MAX_DIST_CODES];
+    let total = lit_len_num + dist_num;
+    let mut idx = 0;
+    while idx < total {
+        let symbol = code_length.decode(reader)?;
+        let (len, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => (*lengths.get(idx.checked_sub(1)?)?, 3 + reader.read(2)? as usize),
+            17 => (0, 3 + reader.read(3)? as usize),
+            _ => (0, 11 + reader.read(7)? as usize),
+        };
+        if idx + repeat > total {
+            return None;
+        }
+        lengths[idx..idx + repeat].fill(len);
+        idx += repeat;
+    }
+
+    //Block cannot be terminated without end of block code
+    if lengths[END_OF_BLOCK as usize] == 0 {
+        return None;
+    }
+    Some((
+        PrefixCode::from_lengths(&lengths[..lit_len_num])?,
+        PrefixCode::from_lengths(&lengths[lit_len_num..total])?,
+    ))
+}
+
+///Returns size of deflate stream at the start of `bytes`
+///
+///Returns `None` if stream is incomplete or invalid.
+pub(crate) fn stream_size(bytes: &[u8]) -> Option<usize> {
+    let mut reader = BitReader {
+        bytes,
+        pos: 0,
+        buf: 0,
+        len: 0,
+    };
+
+    loop {
+        let is_final = reader.read(1)? == 1;
+        match reader.read(2)? {
+            0 => {
+                reader.align();
+                let len = reader.read(16)?;
+                let nlen = reader.read(16)?;
+                if len != !nlen & 0xffff {
+                    return None;
+                }
+                reader.skip_bytes(len as usize)?;
+            }
+            1 => {
+                let (lit_len, dist) = fixed_codes()?;
+                read_codes(&mut reader, &lit_len, &dist)?;
+            }
+            2 => {
+                let (lit_len, dist) = dynamic_codes(&mut reader)?;
+                read_codes(&mut reader, &lit_len, &dist)?;
+            }
+            _ => return None,
+        }
+
+        if is_final {
+            reader.align();
+            break Some(reader.consumed());
+        }
+    }
+}
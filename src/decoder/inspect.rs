@@ -0,0 +1,136 @@
+//!Frame metadata inspection
+
+use super::Detection;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+///Frame metadata, available without decoding.
+///
+///Note that values are taken from untrusted input, so they should be validated before use.
+pub struct FrameInfo<'a> {
+    ///Format of the frame
+    pub format: Detection,
+    ///Size of original content, if known.
+    ///
+    ///For `gzip` it is taken from `ISIZE` trailer, so it is size modulo 2^32 of the first member.
+    pub content_size: Option<u64>,
+    ///Window size required to decode frame, if known.
+    pub window_size: Option<u64>,
+    ///ID of dictionary required to decode frame, 0 if none or unknown.
+    pub dictionary_id: u32,
+    ///Whether frame contains checksum of content.
+    pub checksum: bool,
+    ///Original file name, without terminating zero (`gzip` only)
+    pub name: Option<&'a [u8]>,
+    ///Modification time as unix timestamp, where 0 means unknown (`gzip` only)
+    pub mtime: Option<u32>,
+    ///Operating system on which compression took place (`gzip` only)
+    pub os: Option<u8>,
+}
+
+impl FrameInfo<'_> {
+    #[inline(always)]
+    pub(crate) const fn new(format: Detection) -> Self {
+        Self {
+            format,
+            content_size: None,
+            window_size: None,
+            dictionary_id: 0,
+            checksum: false,
+            name: None,
+            mtime: None,
+            os: None,
+        }
+    }
+}
+
+///Inspects frame at the start of `bytes`, returning its metadata without decoding.
+///
+///Supported formats:
+///- `zstd` - requires `zstd` feature. Skippable frames are not inspected.
+///- `gzip` - content size is reported only if `bytes` contains whole first member, as its deflate stream
+///has to be parsed in order to locate `ISIZE` trailer. Data following first member is ignored.
+///
+///Returns `None` if format is not supported or header is incomplete/invalid.
+pub fn inspect(bytes: &[u8]) -> Option<FrameInfo<'_>> {
+    match Detection::detect(bytes) {
+        #[cfg(feature = "zstd")]
+        Some(Detection::Zstd) => super::zstd::frame_info(bytes),
+        Some(Detection::Gzip) => gzip(bytes),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "alloc")]
+///Returns content size specified by frame header at the start of `bytes`.
+///
+///Unlike [inspect], it ignores `gzip` as its trailer can be located only by parsing whole deflate stream.
+pub(crate) fn header_content_size(bytes: &[u8]) -> Option<u64> {
+    match Detection::detect(bytes) {
+        #[cfg(feature = "zstd")]
+        Some(Detection::Zstd) => super::zstd::frame_info(bytes)?.content_size,
+        _ => None,
+    }
+}
+
+//https://www.rfc-editor.org/rfc/rfc1952#section-2.3
+fn gzip(bytes: &[u8]) -> Option<FrameInfo<'_>> {
+    const HEADER_SIZE: usize = 10;
+    const TRAILER_SIZE: usize = 8;
+    const DEFLATE: u8 = 8;
+    const FHCRC: u8 = 0b0000_0010;
+    const FEXTRA: u8 = 0b0000_0100;
+    const FNAME: u8 = 0b0000_1000;
+    const FCOMMENT: u8 = 0b0001_0000;
+    const RESERVED: u8 = 0b1110_0000;
+
+    //Skips zero-terminated field, returning its content
+    fn zero_terminated(bytes: &[u8]) -> Option<&[u8]> {
+        let end = bytes.iter().position(|byte| *byte == 0)?;
+        Some(&bytes[..end])
+    }
+
+    if bytes.len() < HEADER_SIZE {
+        return None;
+    }
+    let flags = bytes[3];
+    if bytes[2] != DEFLATE || flags & RESERVED != 0 {
+        return None;
+    }
+
+    let mut info = FrameInfo::new(Detection::Gzip);
+    info.checksum = true;
+    info.mtime = Some(u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]));
+    info.os = Some(bytes[9]);
+
+    let mut cursor = HEADER_SIZE;
+    if flags & FEXTRA != 0 {
+        let len = bytes.get(cursor..cursor + 2)?;
+        cursor += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+    }
+    if flags & FNAME != 0 {
+        let name = zero_terminated(bytes.get(cursor..)?)?;
+        cursor += name.len() + 1;
+        info.name = Some(name);
+    }
+    if flags & FCOMMENT != 0 {
+        let comment = zero_terminated(bytes.get(cursor..)?)?;
+        cursor += comment.len() + 1;
+    }
+    if flags & FHCRC != 0 {
+        cursor += 2;
+    }
+    if cursor > bytes.len() {
+        return None;
+    }
+
+    //Trailer location is only known once deflate stream is parsed, as data may follow member
+    if let Some(size) = super::deflate_scan::stream_size(&bytes[cursor..]) {
+        let trailer = cursor + size;
+        if let Some(isize) = bytes.get(trailer + TRAILER_SIZE - 4..trailer + TRAILER_SIZE) {
+            info.content_size = Some(u32::from_le_bytes([isize[0], isize[1], isize[2], isize[3]]) as u64);
+        }
+    }
+
+    Some(info)
+}
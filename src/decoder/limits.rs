@@ -0,0 +1,266 @@
+//! Decompression limits
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use core::{cmp, ptr, slice};
+
+use super::{inspect, Decode, DecodeError, DecodeErrorKind, Decoder, Interface};
+
+static LIMITS: Interface = Interface {
+    drop_fn,
+    reset_fn,
+    decode_fn,
+    describe_error_fn,
+    gzip_header_fn,
+    trailing_data_offset_fn,
+    memory_tracker_fn,
+};
+
+const OUTPUT_LIMIT: i32 = 1;
+const RATIO_LIMIT: i32 = 2;
+const WINDOW_LIMIT: i32 = 3;
+
+//Maximum size of `zstd` frame header, which is enough for `inspect` to report window size
+const HEADER_SIZE: usize = 18;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+///Decompression limits, protecting against decompression bombs.
+///
+///Use [Decoder::with_limits](struct.Decoder.html#method.with_limits) to apply them.
+///All limits are disabled by default.
+pub struct DecodeLimits {
+    max_output: Option<u64>,
+    max_ratio: Option<u64>,
+    max_window: Option<u64>,
+}
+
+impl DecodeLimits {
+    #[inline(always)]
+    ///Creates new default value
+    pub const fn new() -> Self {
+        Self {
+            max_output: None,
+            max_ratio: None,
+            max_window: None,
+        }
+    }
+
+    #[inline(always)]
+    ///Sets maximum total size of decompressed data.
+    pub const fn max_output(mut self, max_output: u64) -> Self {
+        self.max_output = Some(max_output);
+        self
+    }
+
+    #[inline(always)]
+    ///Sets maximum ratio of decompressed size to compressed size.
+    ///
+    ///Output of each call is capped by ratio of input consumed so far plus input passed to the call,
+    ///while limit is exceeded once decompressed size is above ratio of input actually consumed.
+    ///It should leave some margin as beginning of stream may decompress better than whole.
+    pub const fn max_ratio(mut self, max_ratio: u64) -> Self {
+        self.max_ratio = Some(max_ratio);
+        self
+    }
+
+    #[inline(always)]
+    ///Sets maximum window size, declared in frame header.
+    ///
+    ///Applied only to frames whose window size is reported by [inspect](fn.inspect.html), i.e. the first `zstd` frame.
+    ///Prefer backend specific options (e.g. [ZstdOptions::window_log](struct.ZstdOptions.html#method.window_log))
+    ///when backend is known in advance, as they also cover subsequent frames.
+    pub const fn max_window(mut self, max_window: u64) -> Self {
+        self.max_window = Some(max_window);
+        self
+    }
+}
+
+impl Default for DecodeLimits {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct State<'a> {
+    decoder: Decoder<'a>,
+    limits: DecodeLimits,
+    total_in: u64,
+    total_out: u64,
+    //Beginning of stream, accumulated until window size can be checked
+    header: [u8; HEADER_SIZE],
+    header_len: usize,
+    window_checked: bool,
+    //Set once limit is exceeded, failing all further decoding until reset
+    exceeded: Option<DecodeError>,
+}
+
+impl State<'_> {
+    //Checks window size, returning `false` if it exceeds limit
+    fn check_window(&mut self, input: &[u8]) -> bool {
+        let max_window = match self.limits.max_window {
+            Some(max_window) if !self.window_checked => max_window,
+            _ => return true,
+        };
+
+        let mut header = self.header;
+        let len = cmp::min(HEADER_SIZE - self.header_len, input.len());
+        header[self.header_len..self.header_len + len].copy_from_slice(&input[..len]);
+        let header = &header[..self.header_len + len];
+
+        match inspect(header) {
+            Some(info) => {
+                self.window_checked = true;
+                info.window_size.map_or(true, |window_size| window_size <= max_window)
+            }
+            None => {
+                //Either not supported format or header is not complete yet
+                self.window_checked = header.len() == HEADER_SIZE;
+                true
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn record_header(&mut self, consumed: &[u8]) {
+        if !self.window_checked {
+            let len = cmp::min(HEADER_SIZE - self.header_len, consumed.len());
+            self.header[self.header_len..self.header_len + len].copy_from_slice(&consumed[..len]);
+            self.header_len += len;
+        }
+    }
+}
+
+impl<'a> Decoder<'a> {
+    #[inline]
+    ///Wraps decoder to enforce `limits`.
+    ///
+    ///Once limit is exceeded, decoding fails with [LimitExceeded](enum.DecodeErrorKind.html#variant.LimitExceeded)
+    ///until [reset](#method.reset).
+    ///Output is passed to backend limited, so that it is never written more than one byte above `max_output` or `max_ratio` of input passed so far.
+    pub fn with_limits(self, limits: DecodeLimits) -> Decoder<'a> {
+        let instance = Box::new(State {
+            decoder: self,
+            limits,
+            total_in: 0,
+            total_out: 0,
+            header: [0; HEADER_SIZE],
+            header_len: 0,
+            window_checked: false,
+            exceeded: None,
+        });
+        let instance = ptr::NonNull::from(Box::leak(instance)).cast();
+        LIMITS.inner_decoder(instance)
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn error(code: i32) -> DecodeError {
+    DecodeError::new(DecodeErrorKind::LimitExceeded, code)
+}
+
+unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
+    let state = unsafe { &mut *(state.as_ptr() as *mut State) };
+    let input_slice = unsafe { slice::from_raw_parts(input, input_remain) };
+
+    if state.exceeded.is_none() && !state.check_window(input_slice) {
+        state.exceeded = Some(error(WINDOW_LIMIT));
+    }
+    if let Some(error) = state.exceeded {
+        return Decode {
+            input_remain,
+            output_remain,
+            status: Err(error),
+        };
+    }
+
+    //Allow one byte above limit to tell exceeding limit from reaching it
+    let mut output_limit = output_remain as u64;
+    if let Some(max_output) = state.limits.max_output {
+        output_limit = cmp::min(output_limit, max_output.saturating_sub(state.total_out).saturating_add(1));
+    }
+    if let Some(max_ratio) = state.limits.max_ratio {
+        let max_output = state.total_in.saturating_add(input_remain as u64).saturating_mul(max_ratio);
+        output_limit = cmp::min(output_limit, max_output.saturating_sub(state.total_out).saturating_add(1));
+    }
+    let output_limit = output_limit as usize;
+
+    let mut result = unsafe { state.decoder.raw_decode(input, input_remain, output, output_limit) };
+    let consumed = input_remain - result.input_remain;
+    state.record_header(&input_slice[..consumed]);
+    state.total_in += consumed as u64;
+    state.total_out += (output_limit - result.output_remain) as u64;
+    result.output_remain += output_remain - output_limit;
+
+    if result.status.is_ok() {
+        if state.limits.max_output.map_or(false, |max_output| state.total_out > max_output) {
+            state.exceeded = Some(error(OUTPUT_LIMIT));
+        } else if state.limits.max_ratio.map_or(false, |max_ratio| state.total_out > state.total_in.saturating_mul(max_ratio)) {
+            state.exceeded = Some(error(RATIO_LIMIT));
+        }
+        if let Some(error) = state.exceeded {
+            result.status = Err(error);
+        }
+    }
+
+    result
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
+    let instance = unsafe { &mut *(state.as_ptr() as *mut State) };
+    if instance.decoder.reset() {
+        instance.total_in = 0;
+        instance.total_out = 0;
+        instance.header_len = 0;
+        instance.window_checked = false;
+        instance.exceeded = None;
+        Some(state)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    unsafe {
+        drop(Box::from_raw(state.as_ptr() as *mut State));
+    }
+}
+
+#[inline]
+fn describe_error_fn(state: ptr::NonNull<u8>, error: DecodeError) -> Option<&'static str> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    state.decoder.describe_error(error)
+}
+
+#[inline]
+fn gzip_header_fn(state: ptr::NonNull<u8>) -> Option<crate::gzip::GzipHeader> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    state.decoder.gzip_header()
+}
+
+#[inline]
+fn trailing_data_offset_fn(state: ptr::NonNull<u8>) -> Option<u64> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    state.decoder.trailing_data_offset()
+}
+
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<crate::mem::MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    (state.decoder.interface.memory_tracker_fn)(state.decoder.instance)
+}
+
+#[inline]
+//Describes errors reported by limits, rather than backend
+pub(crate) const fn describe(code: i32) -> Option<&'static str> {
+    match code {
+        OUTPUT_LIMIT => Some("decompressed size exceeds limit"),
+        RATIO_LIMIT => Some("compression ratio exceeds limit"),
+        WINDOW_LIMIT => Some("window size exceeds limit"),
+        _ => None,
+    }
+}
@@ -0,0 +1,132 @@
+//! `lz4` interface implementation
+
+use lz4_sys as sys;
+
+use core::ffi::{c_uint, c_void};
+use core::ptr;
+
+use super::{lz4_common, Decode, DecodeError, DecodeStatus, Decoder, Interface};
+use crate::mem::{Allocator, Box, Lz4CustomMem, MemoryTracker};
+
+static LZ4: Interface = Interface {
+    drop_fn,
+    reset_fn,
+    decode_fn,
+    describe_error_fn,
+    gzip_header_fn: super::no_gzip_header,
+    trailing_data_offset_fn: super::no_trailing_data_offset,
+    memory_tracker_fn,
+};
+
+extern "C" {
+    fn LZ4F_createDecompressionContext_advanced(mem: Lz4CustomMem, version: c_uint) -> *mut c_void;
+}
+
+struct State {
+    ctx: sys::LZ4FDecompressionContext,
+    //Referenced by context as `opaque`, hence it must outlive it
+    memory: Box<MemoryTracker>,
+}
+
+impl Drop for State {
+    #[inline(always)]
+    fn drop(&mut self) {
+        let result = unsafe {
+            sys::LZ4F_freeDecompressionContext(self.ctx)
+        };
+        debug_assert_eq!(unsafe { sys::LZ4F_isError(result) }, 0);
+    }
+}
+
+impl Interface {
+    #[inline]
+    ///Creates decoder with `lz4` interface
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn lz4() -> Option<Decoder<'static>> {
+        create(MemoryTracker::new())
+    }
+
+    #[inline]
+    ///Creates decoder with `lz4` interface, using `allocator` for all allocations of context.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn lz4_with_allocator(allocator: impl Allocator + 'static) -> Option<Decoder<'static>> {
+        create(MemoryTracker::with_allocator(allocator))
+    }
+}
+
+fn create(memory: MemoryTracker) -> Option<Decoder<'static>> {
+    let memory = Box::new(memory);
+    let ctx = unsafe {
+        LZ4F_createDecompressionContext_advanced(Lz4CustomMem::new(&memory), sys::LZ4F_VERSION)
+    };
+    if ctx.is_null() {
+        return None;
+    }
+
+    let state = Box::new(State {
+        ctx: sys::LZ4FDecompressionContext(ctx),
+        memory,
+    });
+    let ptr = ptr::NonNull::from(Box::leak(state));
+    Some(LZ4.inner_decoder(ptr.cast()))
+}
+
+unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
+    let state = unsafe {
+        &*(state.as_ptr() as *const State)
+    };
+    let mut input_size = input_remain;
+    let mut output_size = output_remain;
+    let result = unsafe {
+        sys::LZ4F_decompress(state.ctx, output, &mut output_size, input, &mut input_size, ptr::null())
+    };
+
+    Decode {
+        input_remain: input_remain - input_size,
+        output_remain: output_remain - output_size,
+        status: match result {
+            //Frame is fully decoded and flushed
+            0 => Ok(DecodeStatus::Finished),
+            result if unsafe { sys::LZ4F_isError(result) } != 0 => Err(lz4_common::decode_error((result as isize).wrapping_neg() as i32)),
+            //Otherwise it is hint how much input is expected
+            _ => {
+                if output_size == output_remain {
+                    Ok(DecodeStatus::NeedOutput)
+                } else {
+                    Ok(DecodeStatus::NeedInput)
+                }
+            }
+        },
+    }
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
+    unsafe {
+        sys::LZ4F_resetDecompressionContext((*(state.as_ptr() as *const State)).ctx);
+    }
+    Some(state)
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    unsafe {
+        drop(Box::from_raw(state.as_ptr() as *mut State));
+    }
+}
+
+#[inline]
+fn describe_error_fn(_: ptr::NonNull<u8>, error: DecodeError) -> Option<&'static str> {
+    let result = unsafe {
+        sys::LZ4F_getErrorName((error.as_raw() as isize).wrapping_neg() as usize)
+    };
+    crate::utils::convert_c_str(result)
+}
+
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    Some(ptr::NonNull::from(&*state.memory))
+}
@@ -0,0 +1,46 @@
+use super::{DecodeError, DecodeErrorKind};
+
+//https://github.com/lz4/lz4/blob/dev/lib/lz4frame.h#L653
+pub(crate) const ERROR_MAX_BLOCK_SIZE_INVALID: i32 = 2;
+pub(crate) const ERROR_HEADER_VERSION_WRONG: i32 = 6;
+pub(crate) const ERROR_BLOCK_CHECKSUM_INVALID: i32 = 7;
+pub(crate) const ERROR_RESERVED_FLAG_SET: i32 = 8;
+pub(crate) const ERROR_ALLOCATION_FAILED: i32 = 9;
+pub(crate) const ERROR_FRAME_TYPE_UNKNOWN: i32 = 13;
+pub(crate) const ERROR_FRAME_SIZE_WRONG: i32 = 14;
+pub(crate) const ERROR_DECOMPRESSION_FAILED: i32 = 16;
+pub(crate) const ERROR_HEADER_CHECKSUM_INVALID: i32 = 17;
+pub(crate) const ERROR_CONTENT_CHECKSUM_INVALID: i32 = 18;
+
+#[cold]
+#[inline(never)]
+///Maps LZ4F error code to [DecodeError](../struct.DecodeError.html)
+pub(crate) fn decode_error(code: i32) -> DecodeError {
+    let kind = match code {
+        ERROR_HEADER_VERSION_WRONG => DecodeErrorKind::UnsupportedParameter,
+        ERROR_BLOCK_CHECKSUM_INVALID | ERROR_HEADER_CHECKSUM_INVALID | ERROR_CONTENT_CHECKSUM_INVALID => DecodeErrorKind::Checksum,
+        ERROR_ALLOCATION_FAILED => DecodeErrorKind::OutOfMemory,
+        ERROR_MAX_BLOCK_SIZE_INVALID | ERROR_RESERVED_FLAG_SET | ERROR_FRAME_TYPE_UNKNOWN | ERROR_FRAME_SIZE_WRONG | ERROR_DECOMPRESSION_FAILED => DecodeErrorKind::Corrupted,
+        _ => DecodeErrorKind::Other,
+    };
+    DecodeError::new(kind, code)
+}
+
+#[cfg(feature = "lz4-rust")]
+///Returns description for errors that can be produced by `lz4-rust`, matching LZ4F naming
+pub(crate) fn describe_error(code: i32) -> Option<&'static str> {
+    match code {
+        0 => Some("Unspecified error code"),
+        ERROR_MAX_BLOCK_SIZE_INVALID => Some("ERROR_maxBlockSize_invalid"),
+        ERROR_HEADER_VERSION_WRONG => Some("ERROR_headerVersion_wrong"),
+        ERROR_BLOCK_CHECKSUM_INVALID => Some("ERROR_blockChecksum_invalid"),
+        ERROR_RESERVED_FLAG_SET => Some("ERROR_reservedFlag_set"),
+        ERROR_ALLOCATION_FAILED => Some("ERROR_allocation_failed"),
+        ERROR_FRAME_TYPE_UNKNOWN => Some("ERROR_frameType_unknown"),
+        ERROR_FRAME_SIZE_WRONG => Some("ERROR_frameSize_wrong"),
+        ERROR_DECOMPRESSION_FAILED => Some("ERROR_decompressionFailed"),
+        ERROR_HEADER_CHECKSUM_INVALID => Some("ERROR_headerChecksum_invalid"),
+        ERROR_CONTENT_CHECKSUM_INVALID => Some("ERROR_contentChecksum_invalid"),
+        _ => None,
+    }
+}
@@ -0,0 +1,381 @@
+//! `lz4-rust` interface implementation
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::hash::Hasher;
+use core::{cmp, mem, ptr, slice};
+use twox_hash::XxHash32;
+
+use super::lz4_common::{self, *};
+use super::{Decode, DecodeError, DecodeStatus, Decoder, Interface};
+use crate::mem::Box;
+
+//https://github.com/lz4/lz4/blob/dev/doc/lz4_Frame_format.md#general-structure-of-lz4-frame-format
+const MAGIC: u32 = 0x184D2204;
+const SKIPPABLE_MAGIC: u32 = 0x184D2A50;
+const SKIPPABLE_MAGIC_MASK: u32 = 0xFFFFFFF0;
+const BLOCK_UNCOMPRESSED: u32 = 0x8000_0000;
+//Maximum distance of match, hence linked block needs only this much of previous data
+const WINDOW_SIZE: usize = 64 * 1024;
+
+const FLAG_VERSION_MASK: u8 = 0b1100_0000;
+const FLAG_VERSION: u8 = 0b0100_0000;
+const FLAG_BLOCK_INDEPENDENT: u8 = 0b0010_0000;
+const FLAG_BLOCK_CHECKSUM: u8 = 0b0001_0000;
+const FLAG_CONTENT_SIZE: u8 = 0b0000_1000;
+const FLAG_CONTENT_CHECKSUM: u8 = 0b0000_0100;
+const FLAG_RESERVED: u8 = 0b0000_0010;
+const FLAG_DICT_ID: u8 = 0b0000_0001;
+
+static LZ4_RUST: Interface = Interface {
+    drop_fn,
+    reset_fn,
+    decode_fn,
+    describe_error_fn,
+    gzip_header_fn: super::no_gzip_header,
+    trailing_data_offset_fn: super::no_trailing_data_offset,
+    memory_tracker_fn: super::no_memory_tracker,
+};
+
+#[derive(Copy, Clone)]
+enum Stage {
+    Magic,
+    Flags,
+    //Rest of frame descriptor after flags
+    Descriptor(usize),
+    BlockSize,
+    Block {
+        size: usize,
+        compressed: bool,
+    },
+    ContentChecksum,
+    SkippableSize,
+    Skip(usize),
+    Finished,
+}
+
+impl Stage {
+    #[inline(always)]
+    const fn required_size(&self, block_checksum: bool) -> usize {
+        match self {
+            Self::Magic | Self::BlockSize | Self::ContentChecksum | Self::SkippableSize => 4,
+            Self::Flags => 1,
+            Self::Descriptor(size) => *size,
+            Self::Block { size, .. } => match block_checksum {
+                true => *size + 4,
+                false => *size,
+            },
+            Self::Skip(_) | Self::Finished => 0,
+        }
+    }
+}
+
+#[inline(always)]
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+struct State {
+    stage: Stage,
+    //Input accumulated until current stage has enough data
+    pending: Vec<u8>,
+    flags: u8,
+    block_max_size: usize,
+    content_size: Option<u64>,
+    total_size: u64,
+    hasher: XxHash32,
+    //Decoded data, including previous data for linked blocks
+    decoded: Vec<u8>,
+    cursor: usize,
+}
+
+impl State {
+    fn new() -> Self {
+        Self {
+            stage: Stage::Magic,
+            pending: Vec::new(),
+            flags: 0,
+            block_max_size: 0,
+            content_size: None,
+            total_size: 0,
+            hasher: XxHash32::with_seed(0),
+            decoded: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.stage = Stage::Magic;
+        self.pending.clear();
+        self.decoded.clear();
+        self.cursor = 0;
+    }
+
+    fn start_frame(&mut self, flags: u8) -> Result<(), DecodeError> {
+        if flags & FLAG_VERSION_MASK != FLAG_VERSION {
+            return Err(lz4_common::decode_error(ERROR_HEADER_VERSION_WRONG));
+        } else if flags & FLAG_RESERVED != 0 {
+            return Err(lz4_common::decode_error(ERROR_RESERVED_FLAG_SET));
+        }
+
+        self.flags = flags;
+        self.content_size = None;
+        self.total_size = 0;
+        self.hasher = XxHash32::with_seed(0);
+        self.decoded.clear();
+        self.cursor = 0;
+
+        let mut size = 2;
+        if flags & FLAG_CONTENT_SIZE != 0 {
+            size += 8;
+        }
+        if flags & FLAG_DICT_ID != 0 {
+            size += 4;
+        }
+        self.stage = Stage::Descriptor(size);
+        Ok(())
+    }
+
+    fn read_descriptor(&mut self, descriptor: &[u8]) -> Result<(), DecodeError> {
+        let block_descriptor = descriptor[0];
+        if block_descriptor & 0b1000_1111 != 0 {
+            return Err(lz4_common::decode_error(ERROR_RESERVED_FLAG_SET));
+        }
+        self.block_max_size = match block_descriptor >> 4 {
+            size @ 4..=7 => 1 << (8 + 2 * size),
+            _ => return Err(lz4_common::decode_error(ERROR_MAX_BLOCK_SIZE_INVALID)),
+        };
+
+        let mut hasher = XxHash32::with_seed(0);
+        hasher.write(&[self.flags]);
+        hasher.write(&descriptor[..descriptor.len() - 1]);
+        if (hasher.finish_32() >> 8) as u8 != descriptor[descriptor.len() - 1] {
+            return Err(lz4_common::decode_error(ERROR_HEADER_CHECKSUM_INVALID));
+        }
+
+        if self.flags & FLAG_CONTENT_SIZE != 0 {
+            let size = &descriptor[1..9];
+            self.content_size = Some(u64::from_le_bytes([size[0], size[1], size[2], size[3], size[4], size[5], size[6], size[7]]));
+        }
+        //Dictionary ID is ignored, same as LZ4F does when no dictionary is provided
+
+        self.stage = Stage::BlockSize;
+        Ok(())
+    }
+
+    fn read_block_size(&mut self, size: u32) -> Result<(), DecodeError> {
+        if size == 0 {
+            if let Some(content_size) = self.content_size {
+                if content_size != self.total_size {
+                    return Err(lz4_common::decode_error(ERROR_FRAME_SIZE_WRONG));
+                }
+            }
+            self.stage = match self.flags & FLAG_CONTENT_CHECKSUM != 0 {
+                true => Stage::ContentChecksum,
+                false => Stage::Finished,
+            };
+            return Ok(());
+        }
+
+        let compressed = size & BLOCK_UNCOMPRESSED == 0;
+        let size = (size & !BLOCK_UNCOMPRESSED) as usize;
+        if size > self.block_max_size {
+            return Err(lz4_common::decode_error(ERROR_MAX_BLOCK_SIZE_INVALID));
+        }
+        self.stage = Stage::Block {
+            size,
+            compressed,
+        };
+        Ok(())
+    }
+
+    fn read_block(&mut self, data: &[u8], size: usize, compressed: bool) -> Result<(), DecodeError> {
+        let (data, checksum) = data.split_at(size);
+        if self.flags & FLAG_BLOCK_CHECKSUM != 0 && XxHash32::oneshot(0, data) != read_u32(checksum) {
+            return Err(lz4_common::decode_error(ERROR_BLOCK_CHECKSUM_INVALID));
+        }
+
+        //Previous data is always fully written out at this point
+        if self.flags & FLAG_BLOCK_INDEPENDENT == 0 {
+            let keep = cmp::min(self.decoded.len(), WINDOW_SIZE);
+            self.decoded.drain(..self.decoded.len() - keep);
+        } else {
+            self.decoded.clear();
+        }
+        let start = self.decoded.len();
+        self.cursor = start;
+
+        let required_size = match compressed {
+            true => self.block_max_size,
+            false => data.len(),
+        };
+        if self.decoded.try_reserve(required_size).is_err() {
+            return Err(lz4_common::decode_error(ERROR_ALLOCATION_FAILED));
+        }
+
+        if compressed {
+            self.decoded.resize(start + self.block_max_size, 0);
+            let (window, output) = self.decoded.split_at_mut(start);
+            let result = match window.is_empty() {
+                true => lz4_flex::block::decompress_into(data, output),
+                false => lz4_flex::block::decompress_into_with_dict(data, output, window),
+            };
+            match result {
+                Ok(size) => self.decoded.truncate(start + size),
+                Err(_) => return Err(lz4_common::decode_error(ERROR_DECOMPRESSION_FAILED)),
+            }
+        } else {
+            self.decoded.extend_from_slice(data);
+        }
+
+        let block = &self.decoded[start..];
+        self.total_size += block.len() as u64;
+        if self.flags & FLAG_CONTENT_CHECKSUM != 0 {
+            self.hasher.write(block);
+        }
+        self.stage = Stage::BlockSize;
+        Ok(())
+    }
+
+    fn process(&mut self, data: &[u8]) -> Result<(), DecodeError> {
+        match self.stage {
+            Stage::Magic => {
+                let magic = read_u32(data);
+                if magic == MAGIC {
+                    self.stage = Stage::Flags;
+                } else if magic & SKIPPABLE_MAGIC_MASK == SKIPPABLE_MAGIC {
+                    self.stage = Stage::SkippableSize;
+                } else {
+                    return Err(lz4_common::decode_error(ERROR_FRAME_TYPE_UNKNOWN));
+                }
+            }
+            Stage::Flags => self.start_frame(data[0])?,
+            Stage::Descriptor(_) => self.read_descriptor(data)?,
+            Stage::BlockSize => self.read_block_size(read_u32(data))?,
+            Stage::Block { size, compressed } => self.read_block(data, size, compressed)?,
+            Stage::ContentChecksum => {
+                if self.hasher.finish_32() != read_u32(data) {
+                    return Err(lz4_common::decode_error(ERROR_CONTENT_CHECKSUM_INVALID));
+                }
+                self.stage = Stage::Finished;
+            }
+            Stage::SkippableSize => self.stage = Stage::Skip(read_u32(data) as usize),
+            Stage::Skip(_) | Stage::Finished => unreachable!(),
+        }
+        Ok(())
+    }
+
+    //Writes decoded data into output, returning number of bytes written
+    fn drain(&mut self, output: *mut u8, output_len: usize) -> usize {
+        let pending = &self.decoded[self.cursor..];
+        let size = cmp::min(pending.len(), output_len);
+        unsafe {
+            ptr::copy_nonoverlapping(pending.as_ptr(), output, size);
+        }
+        self.cursor += size;
+        size
+    }
+
+    fn decode(&mut self, input: &mut &[u8], output: *mut u8, output_len: usize) -> (usize, Result<DecodeStatus, DecodeError>) {
+        let mut written = 0;
+        loop {
+            written += self.drain(unsafe { output.add(written) }, output_len - written);
+            if self.cursor < self.decoded.len() {
+                return (written, Ok(DecodeStatus::NeedOutput));
+            }
+
+            let required_size = match self.stage {
+                Stage::Finished => return (written, Ok(DecodeStatus::Finished)),
+                Stage::Skip(size) => {
+                    let skipped = cmp::min(size, input.len());
+                    *input = &input[skipped..];
+                    if skipped < size {
+                        self.stage = Stage::Skip(size - skipped);
+                        return (written, Ok(DecodeStatus::NeedInput));
+                    }
+                    self.stage = Stage::Magic;
+                    continue;
+                }
+                stage => stage.required_size(self.flags & FLAG_BLOCK_CHECKSUM != 0),
+            };
+
+            let result = if self.pending.is_empty() && input.len() >= required_size {
+                //Fast path when whole data is available in input
+                let (data, rest) = input.split_at(required_size);
+                *input = rest;
+                self.process(data)
+            } else {
+                let size = cmp::min(required_size - self.pending.len(), input.len());
+                if self.pending.try_reserve(size).is_err() {
+                    return (written, Err(lz4_common::decode_error(ERROR_ALLOCATION_FAILED)));
+                }
+                self.pending.extend_from_slice(&input[..size]);
+                *input = &input[size..];
+                if self.pending.len() < required_size {
+                    return (written, Ok(DecodeStatus::NeedInput));
+                }
+
+                let pending = mem::take(&mut self.pending);
+                let result = self.process(&pending);
+                self.pending = pending;
+                self.pending.clear();
+                result
+            };
+
+            if let Err(error) = result {
+                return (written, Err(error));
+            }
+        }
+    }
+}
+
+impl Interface {
+    #[inline]
+    ///Creates decoder with `lz4-rust` interface
+    ///
+    ///Never returns `None` (probably panics on OOM)
+    pub fn lz4_rust() -> Decoder<'static> {
+        let state = Box::new(State::new());
+        let ptr = ptr::NonNull::from(Box::leak(state));
+        LZ4_RUST.inner_decoder(ptr.cast())
+    }
+}
+
+unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    let mut input = unsafe {
+        slice::from_raw_parts(input, input_remain)
+    };
+
+    let (written, status) = state.decode(&mut input, output, output_remain);
+    Decode {
+        input_remain: input.len(),
+        output_remain: output_remain - written,
+        status,
+    }
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
+    let instance = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    instance.reset();
+    Some(state)
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    let _ = unsafe {
+        Box::from_raw(state.as_ptr() as *mut State)
+    };
+}
+
+#[inline]
+fn describe_error_fn(_: ptr::NonNull<u8>, error: DecodeError) -> Option<&'static str> {
+    lz4_common::describe_error(error.as_raw())
+}
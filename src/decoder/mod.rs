@@ -1,20 +1,43 @@
 //! Decoder
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
-use core::{mem, ptr};
+use core::{fmt, mem, ptr};
+use core::marker::PhantomData;
 
+#[cfg(feature = "alloc")]
 use alloc::collections::TryReserveError;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
+#[cfg(feature = "alloc")]
+use crate::gzip::GzipHeader;
+use crate::mem::{MemoryTracker, MemoryUsage};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 ///Possible compression archive based on known signatures
 pub enum Detection {
     ///ZSTD
+    ///
+    ///Includes skippable frames.
     Zstd,
+    ///ZSTD dictionary
+    ZstdDictionary,
     ///GZIP
     Gzip,
     ///ZLIB
     Zlib,
+    ///XZ
+    Xz,
+    ///LZ4 frame
+    Lz4,
+    ///BZIP2
+    Bzip2,
+    ///Brotli
+    ///
+    ///Brotli has no signature, so this is heuristic which validates stream headers.
+    ///Stream starting with uncompressed data, that is not fully available, cannot be recognized.
+    Brotli,
     ///Indicates that all possible options are exhausted and it is impossible to deduce
     ///compression.
     Unknown,
@@ -25,10 +48,22 @@ impl Detection {
     ///
     ///Returns `None` if there is not enough `bytes` to perform all possible checks.
     ///In this case you need to append more data to your buffer and provide it again
+    ///
+    ///Formats with signature are checked first, while [Brotli](#variant.Brotli) is checked last.
+    ///As brotli headers may span hundreds of bytes, short input that is valid start of brotli stream
+    ///(e.g. short plain text or run of zero bytes) results in `None` too.
+    ///Once there is no more input, such result should be treated as [Unknown](#variant.Unknown).
     pub const fn detect(bytes: &[u8]) -> Option<Detection> {
         //https://github.com/facebook/zstd/blob/dev/doc/zstd_compression_format.md#zstandard-frames
         const ZSTD_HEADER: u32 = 0xFD2FB528u32.to_le();
+        const ZSTD_SKIPPABLE_HEADER: u32 = 0x184D2A50u32.to_le();
+        const ZSTD_SKIPPABLE_MASK: u32 = 0xFFFFFFF0u32.to_le();
+        //https://github.com/facebook/zstd/blob/dev/doc/zstd_compression_format.md#dictionary-format
+        const ZSTD_DICTIONARY_HEADER: u32 = 0xEC30A437u32.to_le();
+        //https://github.com/lz4/lz4/blob/dev/doc/lz4_Frame_format.md#general-structure-of-lz4-frame-format
+        const LZ4_HEADER: u32 = 0x184D2204u32.to_le();
         const GZIP_HEADER: u16 = 0x1f8bu16.to_be();
+        const XZ_HEADER: [u8; 6] = [0xFD, b'7', b'z', b'X', b'Z', 0x00];
 
         macro_rules! detect_gzip {
             ($word:ident) => {
@@ -78,7 +113,7 @@ impl Detection {
                             return Some(Detection::Zlib);
                         }
                         0x68 => if let 0x05 | 0x43 | 0x81 | 0xde = bytes[1] {
-                            Some(Detection::Zlib);
+                            return Some(Detection::Zlib);
                         }
                         _ => (),
                     }
@@ -88,8 +123,51 @@ impl Detection {
 
         macro_rules! detect_zstd {
             ($dword:ident) => {
-                if $dword == ZSTD_HEADER {
+                if $dword == ZSTD_HEADER || ($dword & ZSTD_SKIPPABLE_MASK) == ZSTD_SKIPPABLE_HEADER {
                     return Some(Detection::Zstd);
+                } else if $dword == ZSTD_DICTIONARY_HEADER {
+                    return Some(Detection::ZstdDictionary);
+                }
+            };
+        }
+
+        macro_rules! detect_lz4 {
+            ($dword:ident) => {
+                if $dword == LZ4_HEADER {
+                    return Some(Detection::Lz4);
+                }
+            };
+        }
+
+        //`BZh` followed by block size from 1 to 9
+        macro_rules! detect_bzip2 {
+            () => {
+                if let [b'B', b'Z', b'h', b'1'..=b'9', ..] = bytes {
+                    return Some(Detection::Bzip2);
+                }
+            };
+        }
+
+        macro_rules! detect_xz {
+            () => {
+                let mut idx = 0;
+                while idx < bytes.len() && idx < XZ_HEADER.len() && bytes[idx] == XZ_HEADER[idx] {
+                    idx += 1;
+                }
+                if idx == XZ_HEADER.len() {
+                    return Some(Detection::Xz);
+                } else if idx == bytes.len() {
+                    return None;
+                }
+            };
+        }
+
+        macro_rules! detect_brotli {
+            () => {
+                match brotli_probe::probe(bytes) {
+                    brotli_probe::Probe::Brotli => return Some(Detection::Brotli),
+                    brotli_probe::Probe::NeedMore => return None,
+                    brotli_probe::Probe::NotBrotli => (),
                 }
             };
         }
@@ -108,6 +186,10 @@ impl Detection {
             detect_zlib!(word);
             let dword = u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
             detect_zstd!(dword);
+            detect_lz4!(dword);
+            detect_bzip2!();
+            detect_xz!();
+            detect_brotli!();
 
             Some(Detection::Unknown)
         }
@@ -115,25 +197,111 @@ impl Detection {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-#[repr(transparent)]
+#[non_exhaustive]
+///Backend independent category of [DecodeError]
+///
+///Category is best-effort for backends that do not distinguish errors by code:
+///`zlib` backends tell checksum mismatch from corrupted data only by error message.
+pub enum DecodeErrorKind {
+    ///Input is not valid compressed stream
+    Corrupted,
+    ///Decompressed data does not match stored checksum
+    Checksum,
+    ///Stream uses feature or parameter that is not supported
+    UnsupportedParameter,
+    ///Stream requires dictionary to be decompressed
+    DictionaryRequired,
+    ///Failed to allocate memory
+    OutOfMemory,
+    ///Stream requires window above configured limit
+    WindowTooLarge,
+    ///Compression format cannot be detected
+    UnknownFormat,
+    ///Compression format is detected, but it is not supported
+    UnsupportedFormat,
+    ///Input continues after end of stream
+    TrailingData,
+    ///Decoding exceeds [DecodeLimits]
+    LimitExceeded,
+    ///Any other error
+    Other,
+}
+
+impl DecodeErrorKind {
+    #[inline]
+    ///Returns generic description of error kind
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Corrupted => "corrupted data",
+            Self::Checksum => "checksum mismatch",
+            Self::UnsupportedParameter => "unsupported parameter",
+            Self::DictionaryRequired => "dictionary required",
+            Self::OutOfMemory => "out of memory",
+            Self::WindowTooLarge => "window too large",
+            Self::UnknownFormat => "unknown format",
+            Self::UnsupportedFormat => "unsupported format",
+            Self::TrailingData => "trailing data after end of stream",
+            Self::LimitExceeded => "limit exceeded",
+            Self::Other => "decompression failed",
+        }
+    }
+}
+
+impl fmt::Display for DecodeErrorKind {
+    #[inline(always)]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 ///Decoding error
-pub struct DecodeError(i32);
+///
+///Consists of backend specific raw code and its [DecodeErrorKind]
+pub struct DecodeError {
+    code: i32,
+    kind: DecodeErrorKind,
+}
 
 impl DecodeError {
+    #[inline(always)]
+    ///Creates new error from its kind and backend specific raw code
+    pub const fn new(kind: DecodeErrorKind, code: i32) -> Self {
+        Self {
+            code,
+            kind,
+        }
+    }
+
     ///Creates error which means no error.
     ///
     ///Specifically its code is 0
     pub const fn no_error() -> Self {
-        Self(0)
+        Self::new(DecodeErrorKind::Other, 0)
     }
 
     #[inline(always)]
     ///Returns raw integer
     pub const fn as_raw(&self) -> i32 {
-        self.0
+        self.code
+    }
+
+    #[inline(always)]
+    ///Returns kind of error
+    pub const fn kind(&self) -> DecodeErrorKind {
+        self.kind
     }
 }
 
+impl fmt::Display for DecodeError {
+    #[inline(always)]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_fmt(format_args!("{} (code {})", self.kind, self.code))
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 ///Result of decoding
 pub enum DecodeStatus {
@@ -162,7 +330,27 @@ pub struct Interface {
     //returns new/updated instance, MUST be replaced
     reset_fn: fn(ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>>,
     drop_fn: fn(ptr::NonNull<u8>),
-    describe_error_fn: fn(i32) -> Option<&'static str>,
+    describe_error_fn: fn(ptr::NonNull<u8>, DecodeError) -> Option<&'static str>,
+    #[cfg(feature = "alloc")]
+    gzip_header_fn: fn(ptr::NonNull<u8>) -> Option<GzipHeader>,
+    trailing_data_offset_fn: fn(ptr::NonNull<u8>) -> Option<u64>,
+    memory_tracker_fn: fn(ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>>,
+}
+
+#[cfg(feature = "alloc")]
+//Used by backends that have no gzip header
+pub(crate) fn no_gzip_header(_: ptr::NonNull<u8>) -> Option<GzipHeader> {
+    None
+}
+
+//Used by decoders that do not track trailing data
+pub(crate) fn no_trailing_data_offset(_: ptr::NonNull<u8>) -> Option<u64> {
+    None
+}
+
+//Used by backends that do not allocate via crate allocator
+pub(crate) fn no_memory_tracker(_: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    None
 }
 
 impl Interface {
@@ -171,20 +359,50 @@ impl Interface {
     ///First argument of every function is state as pointer.
     ///
     ///It is user responsibility to pass correct function pointers
-    pub const fn new(decode_fn: unsafe fn(ptr::NonNull<u8>, *const u8, usize, *mut u8, usize) -> Decode, reset_fn: fn(ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>>, drop_fn: fn(ptr::NonNull<u8>), describe_error_fn: fn(i32) -> Option<&'static str>) -> Self {
+    pub const fn new(decode_fn: unsafe fn(ptr::NonNull<u8>, *const u8, usize, *mut u8, usize) -> Decode, reset_fn: fn(ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>>, drop_fn: fn(ptr::NonNull<u8>), describe_error_fn: fn(ptr::NonNull<u8>, DecodeError) -> Option<&'static str>) -> Self {
         Self {
             decode_fn,
             reset_fn,
             drop_fn,
             describe_error_fn,
+            #[cfg(feature = "alloc")]
+            gzip_header_fn: no_gzip_header,
+            trailing_data_offset_fn: no_trailing_data_offset,
+            memory_tracker_fn: no_memory_tracker,
         }
     }
 
+    #[cfg(feature = "alloc")]
+    ///Sets function to handle [Decoder::gzip_header].
+    ///
+    ///By default there is no header.
+    pub const fn with_gzip_header(mut self, gzip_header_fn: fn(ptr::NonNull<u8>) -> Option<GzipHeader>) -> Self {
+        self.gzip_header_fn = gzip_header_fn;
+        self
+    }
+
+    ///Sets function to handle [Decoder::trailing_data_offset].
+    ///
+    ///By default trailing data is not tracked.
+    pub const fn with_trailing_data_offset(mut self, trailing_data_offset_fn: fn(ptr::NonNull<u8>) -> Option<u64>) -> Self {
+        self.trailing_data_offset_fn = trailing_data_offset_fn;
+        self
+    }
+
+    ///Sets function to access [MemoryTracker] used by decoder's allocations.
+    ///
+    ///By default memory usage is not tracked.
+    pub const fn with_memory_tracker(mut self, memory_tracker_fn: fn(ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>>) -> Self {
+        self.memory_tracker_fn = memory_tracker_fn;
+        self
+    }
+
     #[inline(always)]
-    pub(crate) fn inner_decoder(&'static self, instance: ptr::NonNull<u8>) -> Decoder {
+    pub(crate) fn inner_decoder<'a>(&'static self, instance: ptr::NonNull<u8>) -> Decoder<'a> {
         Decoder {
             instance,
             interface: self,
+            _lifetime: PhantomData,
         }
     }
 
@@ -194,7 +412,9 @@ impl Interface {
     ///This function is unsafe as it is up to user to ensure correctness of `Interface
     ///
     ///`instance` - Decoder state, passed as first argument to every function in vtable
-    pub unsafe fn decoder(&'static self, state: ptr::NonNull<u8>) -> Decoder {
+    ///
+    ///Decoder must not outlive `'a`, which can be used to bind state stored in borrowed memory.
+    pub unsafe fn decoder<'a>(&'static self, state: ptr::NonNull<u8>) -> Decoder<'a> {
         self.inner_decoder(state)
     }
 }
@@ -266,16 +486,17 @@ impl Interface {
 ///output.truncate(0);
 ///compressed.truncate(0);
 ///```
-pub struct Decoder {
+pub struct Decoder<'a> {
     instance: ptr::NonNull<u8>,
     interface: &'static Interface,
+    _lifetime: PhantomData<&'a mut [mem::MaybeUninit<u8>]>,
 }
 
 const _: () = {
-    assert!(mem::size_of::<Decoder>() == mem::size_of::<usize>() * 2);
+    assert!(mem::size_of::<Decoder<'static>>() == mem::size_of::<usize>() * 2);
 };
 
-impl Decoder {
+impl Decoder<'_> {
     #[inline(always)]
     ///Raw decoding function, with no checks.
     ///
@@ -314,6 +535,7 @@ impl Decoder {
         }
     }
 
+    #[cfg(feature = "alloc")]
     #[inline(always)]
     ///Decodes `input` into spare space in `output`.
     ///
@@ -325,7 +547,8 @@ impl Decoder {
         let spare_capacity_len = spare_capacity.len();
         let result = self.decode_uninit(input, spare_capacity);
 
-        if result.status.is_ok() {
+        //Output preceding trailing data is valid
+        if result.status.is_ok() || result.status.map_err(|error| error.kind()) == Err(DecodeErrorKind::TrailingData) {
             let new_len = output.len() + spare_capacity_len - result.output_remain;
             unsafe {
                 output.set_len(new_len);
@@ -334,6 +557,7 @@ impl Decoder {
         result
     }
 
+    #[cfg(feature = "alloc")]
     #[inline(always)]
     ///Decodes `input` into `output` Vec, performing allocation when necessary
     ///
@@ -352,6 +576,13 @@ impl Decoder {
     ///   - Allocates `input.len() * 2`
     ///   - Re-alloc size `8 * 1024`
     ///
+    ///If header of frame at the start of `input` specifies content size (see [inspect]), then it is allocated instead,
+    ///but no more than the larger of above initial size and `64 * 1024`, as it comes from untrusted input.
+    ///If such allocation fails, initial size above is allocated instead.
+    ///Beyond that buffer only grows as output is produced.
+    ///
+    ///Content size is taken only from `zstd` frame header, as `gzip` trailer requires to parse whole `input`.
+    ///
     ///Note that the best strategy is always to re-use buffer
     ///
     ///## Result
@@ -359,17 +590,20 @@ impl Decoder {
     ///- `Decode::output_remain` will be relatieve to spare capacity of the `output`.
     pub fn decode_vec_full(&mut self, mut input: &[u8], output: &mut Vec<u8>) -> Result<Decode, TryReserveError> {
         const RESERVE_DEFAULT: usize = 1024;
+        const MAX_CONTENT_SIZE_HINT: usize = 64 * 1024;
         let input_len = input.len();
-        let reserve_size = if input_len < RESERVE_DEFAULT {
-            output.try_reserve_exact(input_len)?;
-            input_len / 3
+        let (alloc_size, reserve_size) = if input_len < RESERVE_DEFAULT {
+            (input_len, input_len / 3)
         } else if input_len < (RESERVE_DEFAULT * 16) {
-            output.try_reserve_exact(input_len + input_len / 3)?;
-            RESERVE_DEFAULT
+            (input_len + input_len / 3, RESERVE_DEFAULT)
         } else {
-            output.try_reserve_exact(input.len() * 2)?;
-            RESERVE_DEFAULT * 8
+            (input_len * 2, RESERVE_DEFAULT * 8)
         };
+        //Content size comes from untrusted input, so it cannot be trusted with large allocation
+        let content_size = inspect::header_content_size(input).map(|size| size.min(alloc_size.max(MAX_CONTENT_SIZE_HINT) as u64) as usize);
+        if content_size.map_or(true, |size| output.try_reserve_exact(size).is_err()) {
+            output.try_reserve_exact(alloc_size)?;
+        }
 
         loop {
             let result = self.decode_vec(input, output);
@@ -443,11 +677,65 @@ impl Decoder {
     #[inline(always)]
     ///Returns descriptive text for error.
     pub fn describe_error(&self, error: DecodeError) -> Option<&'static str> {
-        (self.interface.describe_error_fn)(error.as_raw())
+        match error.kind() {
+            //Reported by decoder itself, rather than backend
+            DecodeErrorKind::TrailingData => Some(error.kind().as_str()),
+            #[cfg(feature = "alloc")]
+            DecodeErrorKind::LimitExceeded => limits::describe(error.as_raw()),
+            _ => (self.interface.describe_error_fn)(self.instance, error),
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline(always)]
+    ///Returns `gzip` header of the stream being decoded.
+    ///
+    ///Available only for `zlib` backends with [gzip_header](struct.ZlibDecoderOptions.html#method.gzip_header) enabled,
+    ///once header is fully decoded and until [reset](#method.reset).
+    ///Returns `None` otherwise, including when stream is not `gzip`.
+    ///
+    ///Note that `name` and `comment` longer than [MAX_FIELD_SIZE](../gzip/constant.MAX_FIELD_SIZE.html) are truncated.
+    pub fn gzip_header(&self) -> Option<GzipHeader> {
+        (self.interface.gzip_header_fn)(self.instance)
+    }
+
+    #[inline(always)]
+    ///Returns offset of data left after end of stream, relative to the start of stream.
+    ///
+    ///Available only for decoder wrapped via [with_trailing_data](#method.with_trailing_data), once
+    ///trailing data is found and until [reset](#method.reset).
+    pub fn trailing_data_offset(&self) -> Option<u64> {
+        (self.interface.trailing_data_offset_fn)(self.instance)
+    }
+
+    #[inline(always)]
+    ///Returns memory allocated by backend.
+    ///
+    ///Available only for backends allocating via crate allocator (i.e. C libraries and `zlib-rust`),
+    ///returning `None` otherwise.
+    pub fn memory_usage(&self) -> Option<MemoryUsage> {
+        (self.interface.memory_tracker_fn)(self.instance).map(|tracker| unsafe { tracker.as_ref() }.usage())
+    }
+
+    #[inline(always)]
+    ///Sets maximum number of bytes backend can allocate at once.
+    ///
+    ///Allocations above budget fail, which results in error of kind [OutOfMemory](enum.DecodeErrorKind.html#variant.OutOfMemory).
+    ///Memory allocated already, including on creation, is counted, but not released.
+    ///
+    ///Returns `false` if backend's memory usage is not tracked (see [memory_usage](#method.memory_usage)).
+    pub fn set_memory_budget(&mut self, budget: Option<usize>) -> bool {
+        match (self.interface.memory_tracker_fn)(self.instance) {
+            Some(tracker) => {
+                unsafe { tracker.as_ref() }.set_budget(budget);
+                true
+            }
+            None => false,
+        }
     }
 }
 
-impl Drop for Decoder {
+impl Drop for Decoder<'_> {
     #[inline]
     fn drop(&mut self) {
         (self.interface.drop_fn)(self.instance);
@@ -467,7 +755,31 @@ macro_rules! internal_zlib_impl_decode {
         state.inner.avail_in = $input_len as _;
         state.inner.next_in = $input as *mut _;
 
-        let result = sys::inflate(state.as_mut(), DEFAULT_INFLATE);
+        //Previous member finished at the end of input, so continue with the next one
+        let mut result = if state.member_end && !state.reset() {
+            sys::Z_STREAM_ERROR
+        } else {
+            sys::inflate(state.as_mut(), DEFAULT_INFLATE)
+        };
+        loop {
+            //Supply preset dictionary once stream asks for it
+            if result == sys::Z_NEED_DICT && state.set_dictionary() {
+                result = sys::inflate(state.as_mut(), DEFAULT_INFLATE);
+            }
+
+            if result == sys::Z_STREAM_END && state.multi_member {
+                if state.inner.avail_in == 0 {
+                    //Reset is postponed, so that member's state (e.g. gzip header) is still available
+                    state.member_end = true;
+                } else if state.reset() {
+                    result = sys::inflate(state.as_mut(), DEFAULT_INFLATE);
+                    continue;
+                } else {
+                    result = sys::Z_STREAM_ERROR;
+                }
+            }
+            break;
+        }
 
         $crate::decoder::Decode {
             input_remain: state.inner.avail_in as usize,
@@ -479,7 +791,7 @@ macro_rules! internal_zlib_impl_decode {
                 },
                 sys::Z_STREAM_END => Ok(DecodeStatus::Finished),
                 sys::Z_BUF_ERROR => Ok(DecodeStatus::NeedOutput),
-                other => Err(crate::decoder::DecodeError(other as _)),
+                other => Err($crate::decoder::zlib_common::decode_error(other as _, state.inner.msg)),
             },
         }
     }};
@@ -488,9 +800,29 @@ macro_rules! internal_zlib_impl_decode {
 #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
 mod zlib_common;
 #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
-pub use zlib_common::ZlibMode;
+pub use zlib_common::{ZlibDecoderOptions, ZlibMode};
+mod brotli_probe;
+mod deflate_scan;
+mod inspect;
+pub use inspect::{inspect, FrameInfo};
+#[cfg(feature = "alloc")]
+mod auto;
+#[cfg(feature = "alloc")]
+pub use auto::AutoOptions;
+#[cfg(feature = "alloc")]
+mod trailing;
+#[cfg(feature = "alloc")]
+pub use trailing::TrailingData;
+#[cfg(feature = "alloc")]
+mod limits;
+#[cfg(feature = "alloc")]
+pub use limits::DecodeLimits;
+#[cfg(any(feature = "brotli-c", feature = "brotli-rust"))]
+mod brotli_common;
 #[cfg(feature = "brotli-rust")]
 mod brotli;
+#[cfg(feature = "brotli-rust")]
+mod brotli_static;
 #[cfg(feature = "brotli-c")]
 mod brotli_c;
 #[cfg(any(feature = "zlib", feature = "zlib-static"))]
@@ -499,10 +831,30 @@ mod zlib;
 mod zlib_ng;
 #[cfg(feature = "zlib-rust")]
 mod zlib_rust;
+#[cfg(feature = "zlib-rust")]
+mod zlib_rust_static;
 #[cfg(feature = "zstd")]
 mod zstd;
 #[cfg(feature = "zstd")]
-pub use zstd::ZstdOptions;
+mod zstd_static;
+#[cfg(feature = "zstd")]
+pub use zstd::{zstd_frame_dictionary_id, ZstdOptions};
+#[cfg(all(feature = "zstd", feature = "alloc"))]
+pub use zstd::ZstdDDict;
+#[cfg(any(feature = "lz4", feature = "lz4-rust"))]
+mod lz4_common;
+#[cfg(feature = "lz4")]
+mod lz4;
+#[cfg(feature = "lz4-rust")]
+mod lz4_rust;
+#[cfg(feature = "xz")]
+mod xz;
+#[cfg(feature = "xz")]
+pub use xz::XzDecoderOptions;
+#[cfg(feature = "bzip2")]
+mod bzip2;
+#[cfg(feature = "snappy")]
+mod snappy;
 
 impl<const N: usize> crate::Buffer<N> {
     ///Decodes `input` using `decoder` returning number of bytes consumed in `input`
@@ -514,7 +866,7 @@ impl<const N: usize> crate::Buffer<N> {
     ///    - In case of `NeedOutput`, you should consume internal buffer.
     ///
     ///In case of error, internal buffer size will not change
-    pub fn decode(&mut self, decoder: &mut Decoder, input: &[u8]) -> Result<(usize, DecodeStatus), DecodeError> {
+    pub fn decode(&mut self, decoder: &mut Decoder<'_>, input: &[u8]) -> Result<(usize, DecodeStatus), DecodeError> {
         let spare_capacity = self.spare_capacity_mut();
         let spare_capacity_len = spare_capacity.len();
 
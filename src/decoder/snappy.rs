@@ -0,0 +1,372 @@
+//! `snappy` interface implementation
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::{cmp, mem, ptr, slice};
+
+use super::{Decode, DecodeError, DecodeErrorKind, DecodeStatus, Decoder, Interface};
+use crate::mem::Box;
+use crate::snappy::*;
+
+//Reserved unskippable chunks
+const CHUNK_RESERVED_UNSKIPPABLE_MAX: u8 = 0x7F;
+//Compressed data chunk can hold at most this much
+const MAX_CHUNK_SIZE: usize = CHECKSUM_SIZE + 32 + MAX_BLOCK_SIZE + MAX_BLOCK_SIZE / 6;
+
+static SNAPPY: Interface = Interface {
+    drop_fn,
+    reset_fn,
+    decode_fn,
+    describe_error_fn,
+    gzip_header_fn: super::no_gzip_header,
+    trailing_data_offset_fn: super::no_trailing_data_offset,
+    memory_tracker_fn: super::no_memory_tracker,
+};
+
+#[derive(Copy, Clone)]
+enum Stage {
+    ChunkHeader,
+    Chunk {
+        kind: u8,
+        size: usize,
+    },
+    Skip(usize),
+}
+
+#[inline(always)]
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+///Decompresses raw snappy format `input` into `output`, which must be empty
+fn decompress(input: &[u8], output: &mut Vec<u8>) -> Result<(), DecodeError> {
+    let corrupted = || decode_error(ERROR_DECOMPRESSION_FAILED);
+
+    let mut size = 0usize;
+    let mut idx = 0;
+    loop {
+        let byte = match input.get(idx) {
+            Some(byte) => *byte,
+            None => return Err(corrupted()),
+        };
+        size |= ((byte & 0x7F) as usize) << (7 * idx);
+        idx += 1;
+        if byte & 0x80 == 0 {
+            break;
+        } else if idx == 3 {
+            //Size never exceeds MAX_BLOCK_SIZE
+            return Err(corrupted());
+        }
+    }
+    if size > MAX_BLOCK_SIZE {
+        return Err(corrupted());
+    }
+    if output.try_reserve(size).is_err() {
+        return Err(decode_error(ERROR_ALLOCATION_FAILED));
+    }
+
+    while idx < input.len() {
+        let tag = input[idx];
+        idx += 1;
+
+        let (len, offset) = match tag & 0b11 {
+            //Literal
+            0b00 => {
+                let mut len = (tag >> 2) as usize;
+                if len >= 60 {
+                    let bytes = len - 59;
+                    if idx + bytes > input.len() {
+                        return Err(corrupted());
+                    }
+                    len = 0;
+                    for (shift, byte) in input[idx..idx + bytes].iter().enumerate() {
+                        len |= (*byte as usize) << (8 * shift);
+                    }
+                    idx += bytes;
+                }
+                let len = len + 1;
+                if len > input.len() - idx || output.len() + len > size {
+                    return Err(corrupted());
+                }
+                output.extend_from_slice(&input[idx..idx + len]);
+                idx += len;
+                continue;
+            }
+            0b01 => {
+                if idx + 1 > input.len() {
+                    return Err(corrupted());
+                }
+                let len = 4 + ((tag >> 2) & 0b111) as usize;
+                let offset = (((tag >> 5) as usize) << 8) | input[idx] as usize;
+                idx += 1;
+                (len, offset)
+            }
+            0b10 => {
+                if idx + 2 > input.len() {
+                    return Err(corrupted());
+                }
+                let offset = u16::from_le_bytes([input[idx], input[idx + 1]]) as usize;
+                idx += 2;
+                (1 + (tag >> 2) as usize, offset)
+            }
+            _ => {
+                if idx + 4 > input.len() {
+                    return Err(corrupted());
+                }
+                let offset = read_u32(&input[idx..]) as usize;
+                idx += 4;
+                (1 + (tag >> 2) as usize, offset)
+            }
+        };
+
+        if offset == 0 || offset > output.len() || output.len() + len > size {
+            return Err(corrupted());
+        }
+        //Copy may overlap with its own output
+        let start = output.len() - offset;
+        for pos in start..start + len {
+            let byte = output[pos];
+            output.push(byte);
+        }
+    }
+
+    if output.len() == size {
+        Ok(())
+    } else {
+        Err(corrupted())
+    }
+}
+
+struct State {
+    stage: Stage,
+    //Whether stream identifier is read
+    started: bool,
+    //Input accumulated until current stage has enough data
+    pending: Vec<u8>,
+    decoded: Vec<u8>,
+    cursor: usize,
+}
+
+impl State {
+    fn new() -> Self {
+        Self {
+            stage: Stage::ChunkHeader,
+            started: false,
+            pending: Vec::new(),
+            decoded: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) {
+        self.stage = Stage::ChunkHeader;
+        self.started = false;
+        self.pending.clear();
+        self.decoded.clear();
+        self.cursor = 0;
+    }
+
+    fn read_chunk_header(&mut self, header: &[u8]) -> Result<(), DecodeError> {
+        let kind = header[0];
+        let size = u32::from_le_bytes([header[1], header[2], header[3], 0]) as usize;
+
+        if !self.started && kind != CHUNK_STREAM_IDENTIFIER {
+            return Err(decode_error(ERROR_STREAM_IDENTIFIER_INVALID));
+        }
+
+        self.stage = match kind {
+            CHUNK_STREAM_IDENTIFIER => {
+                if size != STREAM_IDENTIFIER.len() - CHUNK_HEADER_SIZE {
+                    return Err(decode_error(ERROR_STREAM_IDENTIFIER_INVALID));
+                }
+                Stage::Chunk { kind, size }
+            }
+            CHUNK_COMPRESSED | CHUNK_UNCOMPRESSED => {
+                let max_size = match kind {
+                    CHUNK_COMPRESSED => MAX_CHUNK_SIZE,
+                    _ => CHECKSUM_SIZE + MAX_BLOCK_SIZE,
+                };
+                if size < CHECKSUM_SIZE || size > max_size {
+                    return Err(decode_error(ERROR_CHUNK_SIZE_INVALID));
+                }
+                Stage::Chunk { kind, size }
+            }
+            0x02..=CHUNK_RESERVED_UNSKIPPABLE_MAX => return Err(decode_error(ERROR_CHUNK_TYPE_UNSUPPORTED)),
+            //Padding and reserved skippable chunks
+            _ => Stage::Skip(size),
+        };
+        Ok(())
+    }
+
+    fn read_chunk(&mut self, kind: u8, data: &[u8]) -> Result<(), DecodeError> {
+        //Previous data is always fully written out at this point
+        self.decoded.clear();
+        self.cursor = 0;
+        self.stage = Stage::ChunkHeader;
+
+        match kind {
+            CHUNK_STREAM_IDENTIFIER => {
+                if data != &STREAM_IDENTIFIER[CHUNK_HEADER_SIZE..] {
+                    return Err(decode_error(ERROR_STREAM_IDENTIFIER_INVALID));
+                }
+                self.started = true;
+                return Ok(());
+            }
+            CHUNK_COMPRESSED => decompress(&data[CHECKSUM_SIZE..], &mut self.decoded)?,
+            _ => {
+                if self.decoded.try_reserve(data.len() - CHECKSUM_SIZE).is_err() {
+                    return Err(decode_error(ERROR_ALLOCATION_FAILED));
+                }
+                self.decoded.extend_from_slice(&data[CHECKSUM_SIZE..]);
+            }
+        }
+
+        if checksum(&self.decoded) != read_u32(data) {
+            self.decoded.clear();
+            return Err(decode_error(ERROR_CHECKSUM_INVALID));
+        }
+        Ok(())
+    }
+
+    //Writes decoded data into output, returning number of bytes written
+    fn drain(&mut self, output: *mut u8, output_len: usize) -> usize {
+        let pending = &self.decoded[self.cursor..];
+        let size = cmp::min(pending.len(), output_len);
+        unsafe {
+            ptr::copy_nonoverlapping(pending.as_ptr(), output, size);
+        }
+        self.cursor += size;
+        size
+    }
+
+    fn decode(&mut self, input: &mut &[u8], output: *mut u8, output_len: usize) -> (usize, Result<DecodeStatus, DecodeError>) {
+        //Stream has no end marker, so empty input at chunk boundary means end of stream
+        let is_end = input.is_empty();
+        let mut written = 0;
+        loop {
+            written += self.drain(unsafe { output.add(written) }, output_len - written);
+            if self.cursor < self.decoded.len() {
+                return (written, Ok(DecodeStatus::NeedOutput));
+            }
+
+            let required_size = match self.stage {
+                Stage::ChunkHeader if is_end && written == 0 && self.started && self.pending.is_empty() => return (written, Ok(DecodeStatus::Finished)),
+                Stage::ChunkHeader => CHUNK_HEADER_SIZE,
+                Stage::Chunk { size, .. } => size,
+                Stage::Skip(size) => {
+                    let skipped = cmp::min(size, input.len());
+                    *input = &input[skipped..];
+                    if skipped < size {
+                        self.stage = Stage::Skip(size - skipped);
+                        return (written, Ok(DecodeStatus::NeedInput));
+                    }
+                    self.stage = Stage::ChunkHeader;
+                    continue;
+                }
+            };
+
+            let result = if self.pending.is_empty() && input.len() >= required_size {
+                //Fast path when whole data is available in input
+                let (data, rest) = input.split_at(required_size);
+                *input = rest;
+                self.process(data)
+            } else {
+                let size = cmp::min(required_size - self.pending.len(), input.len());
+                if self.pending.try_reserve(size).is_err() {
+                    return (written, Err(decode_error(ERROR_ALLOCATION_FAILED)));
+                }
+                self.pending.extend_from_slice(&input[..size]);
+                *input = &input[size..];
+                if self.pending.len() < required_size {
+                    return (written, Ok(DecodeStatus::NeedInput));
+                }
+
+                let pending = mem::take(&mut self.pending);
+                let result = self.process(&pending);
+                self.pending = pending;
+                self.pending.clear();
+                result
+            };
+
+            if let Err(error) = result {
+                return (written, Err(error));
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn process(&mut self, data: &[u8]) -> Result<(), DecodeError> {
+        match self.stage {
+            Stage::ChunkHeader => self.read_chunk_header(data),
+            Stage::Chunk { kind, .. } => self.read_chunk(kind, data),
+            Stage::Skip(_) => unreachable!(),
+        }
+    }
+}
+
+impl Interface {
+    #[inline]
+    ///Creates decoder with `snappy` framing format interface
+    ///
+    ///As stream has no end marker, it is considered finished only when decoder is invoked with
+    ///empty input at the end of chunk, after all output is written.
+    ///
+    ///Never returns `None` (probably panics on OOM)
+    pub fn snappy() -> Decoder<'static> {
+        let state = Box::new(State::new());
+        let ptr = ptr::NonNull::from(Box::leak(state));
+        SNAPPY.inner_decoder(ptr.cast())
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn decode_error(code: i32) -> DecodeError {
+    let kind = match code {
+        ERROR_STREAM_IDENTIFIER_INVALID | ERROR_CHUNK_SIZE_INVALID | ERROR_DECOMPRESSION_FAILED => DecodeErrorKind::Corrupted,
+        ERROR_CHUNK_TYPE_UNSUPPORTED => DecodeErrorKind::UnsupportedParameter,
+        ERROR_CHECKSUM_INVALID => DecodeErrorKind::Checksum,
+        ERROR_ALLOCATION_FAILED => DecodeErrorKind::OutOfMemory,
+        _ => DecodeErrorKind::Other,
+    };
+    DecodeError::new(kind, code)
+}
+
+unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    let mut input = unsafe {
+        slice::from_raw_parts(input, input_remain)
+    };
+
+    let (written, status) = state.decode(&mut input, output, output_remain);
+    Decode {
+        input_remain: input.len(),
+        output_remain: output_remain - written,
+        status,
+    }
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
+    let instance = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    instance.reset();
+    Some(state)
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    let _ = unsafe {
+        Box::from_raw(state.as_ptr() as *mut State)
+    };
+}
+
+#[inline]
+fn describe_error_fn(_: ptr::NonNull<u8>, error: DecodeError) -> Option<&'static str> {
+    describe_error(error.as_raw())
+}
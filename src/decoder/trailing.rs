@@ -0,0 +1,126 @@
+//! Trailing data detection
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use core::ptr;
+
+use super::{Decode, DecodeError, DecodeErrorKind, DecodeStatus, Decoder, Interface};
+
+static TRAILING: Interface = Interface {
+    drop_fn,
+    reset_fn,
+    decode_fn,
+    describe_error_fn,
+    gzip_header_fn,
+    trailing_data_offset_fn,
+    memory_tracker_fn,
+};
+
+const TRAILING_DATA: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Handling of input left after end of stream.
+pub enum TrailingData {
+    ///Stream is finished as usual, leaving trailing data in `input_remain`.
+    ///
+    ///Offset of trailing data is reported by [trailing_data_offset](struct.Decoder.html#method.trailing_data_offset).
+    Lenient,
+    ///Trailing data results in error of kind [TrailingData](enum.DecodeErrorKind.html#variant.TrailingData).
+    ///
+    ///Output decoded before is still valid, while trailing data is left in `input_remain`.
+    Strict,
+}
+
+impl Default for TrailingData {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::Lenient
+    }
+}
+
+struct State<'a> {
+    decoder: Decoder<'a>,
+    mode: TrailingData,
+    //Number of input bytes consumed since reset
+    total_in: u64,
+    trailing_data_offset: Option<u64>,
+}
+
+impl<'a> Decoder<'a> {
+    #[inline]
+    ///Wraps decoder to detect data left after end of stream, according to `mode`.
+    ///
+    ///Trailing data is detected when backend finishes stream with input left.
+    ///Decoders of concatenated streams (e.g. [multi-member](struct.ZlibDecoderOptions.html#method.multi_member) `gzip`)
+    ///continue with the next stream instead, so trailing data is rather reported as corrupted stream.
+    pub fn with_trailing_data(self, mode: TrailingData) -> Decoder<'a> {
+        let instance = Box::new(State {
+            decoder: self,
+            mode,
+            total_in: 0,
+            trailing_data_offset: None,
+        });
+        let instance = ptr::NonNull::from(Box::leak(instance)).cast();
+        TRAILING.inner_decoder(instance)
+    }
+}
+
+unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
+    let state = unsafe { &mut *(state.as_ptr() as *mut State) };
+
+    let mut result = unsafe { state.decoder.raw_decode(input, input_remain, output, output_remain) };
+    state.total_in += (input_remain - result.input_remain) as u64;
+
+    if result.status == Ok(DecodeStatus::Finished) && result.input_remain > 0 {
+        state.trailing_data_offset = Some(state.total_in);
+        if state.mode == TrailingData::Strict {
+            result.status = Err(DecodeError::new(DecodeErrorKind::TrailingData, TRAILING_DATA));
+        }
+    }
+
+    result
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
+    let instance = unsafe { &mut *(state.as_ptr() as *mut State) };
+    if instance.decoder.reset() {
+        instance.total_in = 0;
+        instance.trailing_data_offset = None;
+        Some(state)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    unsafe {
+        drop(Box::from_raw(state.as_ptr() as *mut State));
+    }
+}
+
+#[inline]
+fn describe_error_fn(state: ptr::NonNull<u8>, error: DecodeError) -> Option<&'static str> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    state.decoder.describe_error(error)
+}
+
+#[inline]
+fn gzip_header_fn(state: ptr::NonNull<u8>) -> Option<crate::gzip::GzipHeader> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    state.decoder.gzip_header()
+}
+
+#[inline]
+fn trailing_data_offset_fn(state: ptr::NonNull<u8>) -> Option<u64> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    state.trailing_data_offset
+}
+
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<crate::mem::MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    (state.decoder.interface.memory_tracker_fn)(state.decoder.instance)
+}
@@ -0,0 +1,249 @@
+//! `xz` interface implementation
+
+extern crate alloc;
+
+use lzma_sys as sys;
+
+use alloc::boxed::Box;
+use core::{mem, ptr};
+
+use super::{Decode, DecodeError, DecodeErrorKind, DecodeStatus, Decoder, Interface};
+use crate::mem::{lzma_allocator, Allocator, MemoryTracker};
+
+static XZ: Interface = Interface {
+    drop_fn,
+    reset_fn,
+    decode_fn,
+    describe_error_fn,
+    gzip_header_fn: super::no_gzip_header,
+    trailing_data_offset_fn: super::no_trailing_data_offset,
+    memory_tracker_fn,
+};
+
+#[derive(Copy, Clone)]
+///XZ decoder options.
+pub struct XzDecoderOptions {
+    memlimit: u64,
+    concatenated: bool,
+}
+
+impl XzDecoderOptions {
+    #[inline(always)]
+    ///Creates new default value
+    pub const fn new() -> Self {
+        Self {
+            memlimit: u64::MAX,
+            concatenated: false,
+        }
+    }
+
+    #[inline(always)]
+    ///Sets memory usage limit in bytes, refusing to decompress streams that require more.
+    ///
+    ///Defaults to no limit.
+    pub const fn memlimit(mut self, memlimit: u64) -> Self {
+        assert!(memlimit > 0);
+        self.memlimit = memlimit;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets whether to decode concatenated streams.
+    ///
+    ///When enabled, decoding continues with next stream if there is input after the end of stream.
+    ///As decoder cannot know whether more input is coming, it reports `Finished` when stream ends
+    ///with all available input consumed, but decoding can be continued by providing more input.
+    ///
+    ///Defaults to `false`.
+    pub const fn concatenated(mut self, concatenated: bool) -> Self {
+        self.concatenated = concatenated;
+        self
+    }
+
+    #[inline(always)]
+    fn apply(&self, stream: &mut sys::lzma_stream) -> sys::lzma_ret {
+        unsafe { sys::lzma_stream_decoder(stream, self.memlimit, 0) }
+    }
+}
+
+impl Default for XzDecoderOptions {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct State {
+    inner: sys::lzma_stream,
+    opts: XzDecoderOptions,
+    //Set when stream is finished, but next one is allowed
+    stream_end: bool,
+    //Referenced by stream, hence it must not be moved after being set
+    allocator: sys::lzma_allocator,
+    memory: MemoryTracker,
+}
+
+impl State {
+    #[inline(always)]
+    fn reset(&mut self) -> bool {
+        self.stream_end = false;
+        //Initializing existing stream re-uses its memory
+        self.opts.apply(&mut self.inner) == sys::LZMA_OK
+    }
+}
+
+impl Drop for State {
+    #[inline(always)]
+    fn drop(&mut self) {
+        unsafe {
+            sys::lzma_end(&mut self.inner);
+        }
+    }
+}
+
+impl Interface {
+    #[inline]
+    ///Creates decoder with `xz` interface
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn xz(opts: XzDecoderOptions) -> Option<Decoder<'static>> {
+        create(opts, MemoryTracker::new())
+    }
+
+    #[inline]
+    ///Creates decoder with `xz` interface, using `allocator` for all allocations of stream.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn xz_with_allocator(opts: XzDecoderOptions, allocator: impl Allocator + 'static) -> Option<Decoder<'static>> {
+        create(opts, MemoryTracker::with_allocator(allocator))
+    }
+}
+
+fn create(opts: XzDecoderOptions, memory: MemoryTracker) -> Option<Decoder<'static>> {
+    let mut instance = Box::new(State {
+        //LZMA_STREAM_INIT
+        inner: unsafe { mem::zeroed() },
+        opts,
+        stream_end: false,
+        allocator: unsafe { mem::zeroed() },
+        memory,
+    });
+    instance.allocator = lzma_allocator(&instance.memory);
+    instance.inner.allocator = &instance.allocator;
+
+    if opts.apply(&mut instance.inner) == sys::LZMA_OK {
+        let instance = ptr::NonNull::from(Box::leak(instance)).cast();
+        Some(XZ.inner_decoder(instance))
+    } else {
+        None
+    }
+}
+
+unsafe fn decode_fn(state: ptr::NonNull<u8>, mut input: *const u8, mut input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+
+    state.inner.next_out = output;
+    state.inner.avail_out = output_remain;
+
+    loop {
+        if state.stream_end {
+            //Skip stream padding, which consists of null bytes
+            while input_remain > 0 && unsafe { *input } == 0 {
+                input = unsafe { input.add(1) };
+                input_remain -= 1;
+            }
+
+            if input_remain == 0 {
+                return Decode {
+                    input_remain,
+                    output_remain: state.inner.avail_out,
+                    status: Ok(DecodeStatus::Finished),
+                };
+            } else if !state.reset() {
+                return Decode {
+                    input_remain,
+                    output_remain: state.inner.avail_out,
+                    status: Err(decode_error(sys::LZMA_MEM_ERROR as _)),
+                };
+            }
+        }
+
+        state.inner.next_in = input;
+        state.inner.avail_in = input_remain;
+
+        let result = unsafe {
+            sys::lzma_code(&mut state.inner, sys::LZMA_RUN)
+        };
+
+        input = state.inner.next_in;
+        input_remain = state.inner.avail_in;
+
+        let status = match result {
+            sys::LZMA_STREAM_END if state.opts.concatenated => {
+                state.stream_end = true;
+                continue;
+            }
+            sys::LZMA_STREAM_END => Ok(DecodeStatus::Finished),
+            sys::LZMA_OK | sys::LZMA_BUF_ERROR => {
+                if state.inner.avail_out == 0 {
+                    Ok(DecodeStatus::NeedOutput)
+                } else {
+                    Ok(DecodeStatus::NeedInput)
+                }
+            }
+            code => Err(decode_error(code as _)),
+        };
+
+        break Decode {
+            input_remain,
+            output_remain: state.inner.avail_out,
+            status,
+        };
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn decode_error(code: i32) -> DecodeError {
+    let kind = match code as sys::lzma_ret {
+        //Integrity check mismatch is reported as LZMA_DATA_ERROR too
+        sys::LZMA_FORMAT_ERROR | sys::LZMA_DATA_ERROR => DecodeErrorKind::Corrupted,
+        sys::LZMA_OPTIONS_ERROR | sys::LZMA_UNSUPPORTED_CHECK => DecodeErrorKind::UnsupportedParameter,
+        sys::LZMA_MEM_ERROR => DecodeErrorKind::OutOfMemory,
+        sys::LZMA_MEMLIMIT_ERROR => DecodeErrorKind::WindowTooLarge,
+        _ => DecodeErrorKind::Other,
+    };
+    DecodeError::new(kind, code)
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
+    let result = unsafe {
+        (*(state.as_ptr() as *mut State)).reset()
+    };
+    if result {
+        Some(state)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    unsafe {
+        drop(Box::from_raw(state.as_ptr() as *mut State));
+    }
+}
+
+#[inline]
+fn describe_error_fn(_state: ptr::NonNull<u8>, error: DecodeError) -> Option<&'static str> {
+    crate::utils::lzma_error_name(error.as_raw())
+}
+
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    Some(ptr::NonNull::from(&state.memory))
+}
@@ -1,3 +1,5 @@
+use super::{DecodeError, DecodeErrorKind};
+
 #[derive(Copy, Clone)]
 #[repr(i8)]
 ///Decompression mode
@@ -27,3 +29,112 @@ impl Default for ZlibMode {
         Self::Auto
     }
 }
+
+#[derive(Copy, Clone)]
+///Zlib configuration for decoder.
+pub struct ZlibDecoderOptions<'a> {
+    pub(super) mode: ZlibMode,
+    pub(super) dictionary: &'a [u8],
+    pub(super) multi_member: bool,
+    pub(super) gzip_header: bool,
+}
+
+impl<'a> ZlibDecoderOptions<'a> {
+    #[inline(always)]
+    ///Creates new default value
+    pub const fn new() -> Self {
+        Self {
+            mode: ZlibMode::Auto,
+            dictionary: &[],
+            multi_member: false,
+            gzip_header: false,
+        }
+    }
+
+    #[inline(always)]
+    ///Sets decompression mode
+    ///
+    ///Defaults to [Auto](enum.ZlibMode.html#variant.Auto).
+    pub const fn mode(mut self, mode: ZlibMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets preset dictionary, where empty dictionary means no dictionary.
+    ///
+    ///Dictionary is copied into decoder and supplied whenever stream requires it, including after reset.
+    ///In `Deflate` mode it is set upfront, as raw stream has no means to request it.
+    pub const fn dictionary(mut self, dictionary: &'a [u8]) -> Self {
+        self.dictionary = dictionary;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets whether to decode concatenated members as single stream.
+    ///
+    ///Whenever member ends within input, decoder is reset to continue with the next one.
+    ///Hence [Finished](enum.DecodeStatus.html#variant.Finished) is reported only when input ends at member boundary,
+    ///while feeding more input afterwards continues decoding with the next member.
+    ///
+    ///This is how files produced by concatenation (e.g. `cat a.gz b.gz`) or parallel compressors (e.g. `pigz`) must be decoded.
+    ///
+    ///Defaults to `false`.
+    pub const fn multi_member(mut self, multi_member: bool) -> Self {
+        self.multi_member = multi_member;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets whether to capture `gzip` header, making it available via [Decoder::gzip_header](struct.Decoder.html#method.gzip_header).
+    ///
+    ///Capturing requires buffers for all variable length fields (about 72KiB), which are allocated on creation.
+    ///It has no effect in `Deflate` and `Zlib` modes.
+    ///
+    ///Defaults to `false`.
+    pub const fn gzip_header(mut self, gzip_header: bool) -> Self {
+        self.gzip_header = gzip_header;
+        self
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline(always)]
+    //Allocates buffers to capture gzip header, if enabled and mode allows it
+    pub(super) fn raw_gzip_header(&self) -> Option<crate::gzip::RawHeader> {
+        match self.mode {
+            ZlibMode::Gzip | ZlibMode::Auto if self.gzip_header => Some(crate::gzip::RawHeader::decoder()),
+            _ => None,
+        }
+    }
+}
+
+impl Default for ZlibDecoderOptions<'_> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cold]
+#[inline(never)]
+///Maps zlib return code to [DecodeError](../struct.DecodeError.html)
+///
+///`Z_DATA_ERROR` is refined using stream message, as zlib uses it for all kinds of invalid data.
+///Messages are not part of zlib API, so refinement is best-effort: unknown message is reported as `Corrupted`.
+pub(crate) fn decode_error(code: i32, msg: *const core::ffi::c_char) -> DecodeError {
+    const Z_NEED_DICT: i32 = 2;
+    const Z_DATA_ERROR: i32 = -3;
+    const Z_MEM_ERROR: i32 = -4;
+
+    let kind = match code {
+        Z_NEED_DICT => DecodeErrorKind::DictionaryRequired,
+        Z_MEM_ERROR => DecodeErrorKind::OutOfMemory,
+        Z_DATA_ERROR => match crate::utils::convert_c_str(msg as _) {
+            Some("incorrect data check" | "incorrect length check") => DecodeErrorKind::Checksum,
+            Some("invalid window size") => DecodeErrorKind::WindowTooLarge,
+            _ => DecodeErrorKind::Corrupted,
+        },
+        _ => DecodeErrorKind::Other,
+    };
+    DecodeError::new(kind, code)
+}
@@ -5,12 +5,13 @@ extern crate alloc;
 use libz_ng_sys as sys;
 
 use alloc::boxed::Box;
+use alloc::vec::Vec;
 use core::ffi::c_int;
 use core::{mem, ptr};
 
-use super::zlib_common::ZlibMode;
-use super::{Decode, Decoder, Interface};
-use crate::mem::{compu_alloc, compu_free_with_state};
+use super::zlib_common::{ZlibDecoderOptions, ZlibMode};
+use super::{Decode, DecodeError, Decoder, Interface};
+use crate::mem::{compu_alloc, compu_free_with_state, Allocator, MemoryTracker};
 
 const DEFAULT_INFLATE: i32 = 0;
 
@@ -24,11 +25,22 @@ static ZLIB_NG: Interface = Interface {
     reset_fn,
     decode_fn,
     describe_error_fn,
+    gzip_header_fn,
+    trailing_data_offset_fn: super::no_trailing_data_offset,
+    memory_tracker_fn,
 };
 
-#[repr(transparent)]
 struct State {
     inner: sys::z_stream,
+    mode: ZlibMode,
+    dictionary: Vec<u8>,
+    multi_member: bool,
+    //Set once member is finished at the end of input, postponing reset until next decode
+    member_end: bool,
+    //Referenced by stream, hence it must not be moved after being set
+    gzip_header: Option<(crate::gzip::RawHeader, sys::gz_header)>,
+    //Referenced by stream as `opaque`, hence it must not be moved after being set
+    memory: MemoryTracker,
 }
 
 impl State {
@@ -38,11 +50,36 @@ impl State {
     }
 
     #[inline(always)]
-    fn reset(&mut self) -> bool {
-        unsafe {
-            sys::inflateReset(&mut self.inner) == sys::Z_OK
+    fn set_dictionary(&mut self) -> bool {
+        !self.dictionary.is_empty() && unsafe { sys::inflateSetDictionary(&mut self.inner, self.dictionary.as_ptr(), self.dictionary.len() as _) == sys::Z_OK }
+    }
+
+    #[inline(always)]
+    fn set_gzip_header(&mut self) -> bool {
+        match self.gzip_header.as_mut() {
+            Some((raw, header)) => {
+                //Absent fields are reset to null, so pointers must be restored
+                *header = internal_gz_header_inflate!(raw);
+                unsafe { sys::inflateGetHeader(&mut self.inner, header) == sys::Z_OK }
+            }
+            None => true,
         }
     }
+
+    #[inline(always)]
+    //Raw deflate has no header to request dictionary, so it is set upfront
+    fn set_raw_dictionary(&mut self) -> bool {
+        !matches!(self.mode, ZlibMode::Deflate) || self.dictionary.is_empty() || self.set_dictionary()
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) -> bool {
+        self.member_end = false;
+        let result = unsafe {
+            sys::inflateReset(&mut self.inner)
+        };
+        result == sys::Z_OK && self.set_raw_dictionary() && self.set_gzip_header()
+    }
 }
 
 impl Drop for State {
@@ -55,38 +92,61 @@ impl Drop for State {
 }
 
 impl Interface {
+    #[inline]
     ///Creates decoder with `zlib-ng` interface
     ///
     ///Returns `None` if unable to initialize it (likely due to lack of memory)
-    pub fn zlib_ng(mode: ZlibMode) -> Option<Decoder> {
-        let mut instance = Box::new(State {
-            inner: sys::z_stream {
-                next_in: ptr::null_mut(),
-                avail_in: 0,
-                total_in: 0,
-                next_out: ptr::null_mut(),
-                avail_out: 0,
-                total_out: 0,
-                msg: ptr::null_mut(),
-                state: ptr::null_mut(),
-                zalloc: compu_alloc,
-                zfree: compu_free_with_state,
-                opaque: ptr::null_mut(),
-                data_type: 0,
-                adler: 0,
-                reserved: 0,
-            },
-        });
-        let result = unsafe {
-            sys::inflateInit2_(&mut instance.inner, mode.max_bits(), sys::zlibVersion(), mem::size_of::<sys::z_stream>() as _)
-        };
+    pub fn zlib_ng(opts: ZlibDecoderOptions<'_>) -> Option<Decoder<'static>> {
+        create(opts, MemoryTracker::new())
+    }
 
-        if result == 0 {
-            let instance = ptr::NonNull::from(Box::leak(instance)).cast();
-            Some(ZLIB_NG.inner_decoder(instance))
-        } else {
-            None
-        }
+    ///Creates decoder with `zlib-ng` interface, using `allocator` for all allocations of stream.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn zlib_ng_with_allocator(opts: ZlibDecoderOptions<'_>, allocator: impl Allocator + 'static) -> Option<Decoder<'static>> {
+        create(opts, MemoryTracker::with_allocator(allocator))
+    }
+}
+
+fn create(opts: ZlibDecoderOptions<'_>, memory: MemoryTracker) -> Option<Decoder<'static>> {
+    let mode = opts.mode;
+    let mut instance = Box::new(State {
+        inner: sys::z_stream {
+            next_in: ptr::null_mut(),
+            avail_in: 0,
+            total_in: 0,
+            next_out: ptr::null_mut(),
+            avail_out: 0,
+            total_out: 0,
+            msg: ptr::null_mut(),
+            state: ptr::null_mut(),
+            zalloc: compu_alloc,
+            zfree: compu_free_with_state,
+            opaque: ptr::null_mut(),
+            data_type: 0,
+            adler: 0,
+            reserved: 0,
+        },
+        mode,
+        dictionary: opts.dictionary.to_vec(),
+        multi_member: opts.multi_member,
+        member_end: false,
+        gzip_header: opts.raw_gzip_header().map(|mut raw| {
+            let header = internal_gz_header_inflate!(&mut raw);
+            (raw, header)
+        }),
+        memory,
+    });
+    instance.inner.opaque = instance.memory.as_opaque();
+    let result = unsafe {
+        sys::inflateInit2_(&mut instance.inner, mode.max_bits(), sys::zlibVersion(), mem::size_of::<sys::z_stream>() as _)
+    };
+
+    if result == 0 && instance.set_raw_dictionary() && instance.set_gzip_header() {
+        let instance = ptr::NonNull::from(Box::leak(instance)).cast();
+        Some(ZLIB_NG.inner_decoder(instance))
+    } else {
+        None
     }
 }
 
@@ -115,9 +175,24 @@ fn drop_fn(data: ptr::NonNull<u8>) {
 }
 
 #[inline]
-fn describe_error_fn(code: i32) -> Option<&'static str> {
+fn gzip_header_fn(state: ptr::NonNull<u8>) -> Option<crate::gzip::GzipHeader> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    match state.gzip_header.as_ref() {
+        Some((raw, header)) => internal_gz_header_read!(raw, header),
+        None => None,
+    }
+}
+
+#[inline]
+fn describe_error_fn(_state: ptr::NonNull<u8>, error: DecodeError) -> Option<&'static str> {
     let result = unsafe {
-        zError(code)
+        zError(error.as_raw())
     };
     crate::utils::convert_c_str(result)
 }
+
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    Some(ptr::NonNull::from(&state.memory))
+}
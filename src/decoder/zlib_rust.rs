@@ -1,42 +1,71 @@
 //! `zlib-rs` wrapper
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "alloc")]
 use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 
-use core::{mem, ptr};
+use core::ptr;
+#[cfg(feature = "alloc")]
+use core::mem;
 
-use super::zlib_common::ZlibMode;
+#[cfg(feature = "alloc")]
+use super::zlib_common::{ZlibDecoderOptions, ZlibMode};
+use super::DecodeError;
+#[cfg(feature = "alloc")]
 use super::{Decode, Decoder, Interface};
+#[cfg(feature = "alloc")]
+use crate::mem::{compu_alloc, compu_free_with_state, Allocator, MemoryTracker};
 
-mod sys {
+pub(super) mod sys {
     pub use zlib_rs::c_api::z_stream;
+    #[cfg(feature = "alloc")]
+    pub use zlib_rs::c_api::gz_header;
     pub use zlib_rs::InflateFlush;
     pub use zlib_rs::inflate::*;
     pub use zlib_rs::ReturnCode;
     pub use zlib_rs::ReturnCode::Ok as Z_OK;
     pub use zlib_rs::ReturnCode::StreamEnd as Z_STREAM_END;
     pub use zlib_rs::ReturnCode::BufError as Z_BUF_ERROR;
+    pub use zlib_rs::ReturnCode::StreamError as Z_STREAM_ERROR;
+    pub use zlib_rs::ReturnCode::NeedDict as Z_NEED_DICT;
 }
 
-const DEFAULT_INFLATE: sys::InflateFlush = sys::InflateFlush::NoFlush;
+pub(super) const DEFAULT_INFLATE: sys::InflateFlush = sys::InflateFlush::NoFlush;
 
+#[cfg(feature = "alloc")]
 static ZLIB_RUST: Interface = Interface {
     drop_fn,
     reset_fn,
     decode_fn,
     describe_error_fn,
+    gzip_header_fn,
+    trailing_data_offset_fn: super::no_trailing_data_offset,
+    memory_tracker_fn,
 };
 
-#[repr(transparent)]
+#[cfg(feature = "alloc")]
 struct State {
     inner: sys::z_stream,
+    mode: ZlibMode,
+    dictionary: Vec<u8>,
+    multi_member: bool,
+    //Set once member is finished at the end of input, postponing reset until next decode
+    member_end: bool,
+    //Referenced by stream, hence it must not be moved after being set
+    gzip_header: Option<(crate::gzip::RawHeader, sys::gz_header)>,
+    //Referenced by stream as `opaque`, hence it must not be moved after being set
+    memory: MemoryTracker,
 }
 
+#[cfg(feature = "alloc")]
 impl State {
     #[inline(always)]
-    pub fn new() -> Self {
-        let mut this = Self {
+    pub fn new(opts: &ZlibDecoderOptions<'_>, memory: MemoryTracker) -> Self {
+        Self {
             inner: sys::z_stream {
                 next_in: ptr::null_mut(),
                 avail_in: 0,
@@ -46,21 +75,61 @@ impl State {
                 total_out: 0,
                 msg: ptr::null_mut(),
                 state: ptr::null_mut(),
-                zalloc: None,
-                zfree: None,
+                zalloc: Some(compu_alloc),
+                zfree: Some(compu_free_with_state),
                 opaque: ptr::null_mut(),
                 data_type: 0,
                 adler: 0,
                 reserved: 0,
-            }
+            },
+            mode: opts.mode,
+            dictionary: opts.dictionary.to_vec(),
+            multi_member: opts.multi_member,
+            member_end: false,
+            gzip_header: opts.raw_gzip_header().map(|mut raw| {
+                let header = internal_gz_header_inflate!(&mut raw);
+                (raw, header)
+            }),
+            memory,
+        }
+    }
+
+    #[inline(always)]
+    fn set_dictionary(&mut self) -> bool {
+        if self.dictionary.is_empty() {
+            return false;
+        }
+        let stream: &mut sys::InflateStream<'_> = unsafe {
+            mem::transmute(&mut self.inner)
         };
-        this.inner.configure_default_rust_allocator();
-        this
+        sys::set_dictionary(stream, &self.dictionary) == sys::Z_OK
+    }
+
+    #[inline(always)]
+    fn set_gzip_header(&mut self) -> bool {
+        match self.gzip_header.as_mut() {
+            Some((raw, header)) => {
+                //Absent fields are reset to null, so pointers must be restored
+                *header = internal_gz_header_inflate!(raw);
+                let stream: &mut sys::InflateStream<'_> = unsafe {
+                    mem::transmute(&mut self.inner)
+                };
+                unsafe { sys::get_header(stream, Some(header)) == sys::Z_OK }
+            }
+            None => true,
+        }
+    }
+
+    #[inline(always)]
+    //Raw deflate has no header to request dictionary, so it is set upfront
+    fn set_raw_dictionary(&mut self) -> bool {
+        !matches!(self.mode, ZlibMode::Deflate) || self.dictionary.is_empty() || self.set_dictionary()
     }
 
     #[inline(always)]
     pub fn reset(&mut self) -> bool {
-        sys::reset(self.as_mut()) == sys::Z_OK
+        self.member_end = false;
+        sys::reset(self.as_mut()) == sys::Z_OK && self.set_raw_dictionary() && self.set_gzip_header()
     }
 
     //z_stream has the same layout as DeflateStream,
@@ -73,6 +142,7 @@ impl State {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl Drop for State {
     #[inline(always)]
     fn drop(&mut self) {
@@ -80,30 +150,47 @@ impl Drop for State {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl Interface {
+    #[inline]
     ///Creates decoder with `zlib-rust` interface
     ///
     ///Returns `None` if unable to initialize it (likely due to lack of memory)
-    pub fn zlib_rust(mode: ZlibMode) -> Option<Decoder> {
-        let mut instance = Box::new(State::new());
-        let config = sys::InflateConfig {
-            window_bits: mode.max_bits(),
-        };
-        let result = sys::init(&mut instance.inner, config);
+    pub fn zlib_rust(opts: ZlibDecoderOptions<'_>) -> Option<Decoder<'static>> {
+        create(opts, MemoryTracker::new())
+    }
 
-        if result == sys::ReturnCode::Ok {
-            let instance = ptr::NonNull::from(Box::leak(instance)).cast();
-            Some(ZLIB_RUST.inner_decoder(instance))
-        } else {
-            None
-        }
+    ///Creates decoder with `zlib-rust` interface, using `allocator` for all allocations of stream.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn zlib_rust_with_allocator(opts: ZlibDecoderOptions<'_>, allocator: impl Allocator + 'static) -> Option<Decoder<'static>> {
+        create(opts, MemoryTracker::with_allocator(allocator))
     }
 }
+
+#[cfg(feature = "alloc")]
+fn create(opts: ZlibDecoderOptions<'_>, memory: MemoryTracker) -> Option<Decoder<'static>> {
+    let mut instance = Box::new(State::new(&opts, memory));
+    instance.inner.opaque = instance.memory.as_opaque();
+    let config = sys::InflateConfig {
+        window_bits: opts.mode.max_bits(),
+    };
+    let result = sys::init(&mut instance.inner, config);
+
+    if result == sys::ReturnCode::Ok && instance.set_raw_dictionary() && instance.set_gzip_header() {
+        let instance = ptr::NonNull::from(Box::leak(instance)).cast();
+        Some(ZLIB_RUST.inner_decoder(instance))
+    } else {
+        None
+    }
+}
+#[cfg(feature = "alloc")]
 #[inline]
 unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
     internal_zlib_impl_decode!(state, input, input_remain, output, output_remain)
 }
 
+#[cfg(feature = "alloc")]
 #[inline]
 fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
     let result = unsafe {
@@ -116,6 +203,7 @@ fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
     }
 }
 
+#[cfg(feature = "alloc")]
 #[inline]
 fn drop_fn(data: ptr::NonNull<u8>) {
     unsafe {
@@ -123,9 +211,19 @@ fn drop_fn(data: ptr::NonNull<u8>) {
     }
 }
 
+#[cfg(feature = "alloc")]
+#[inline]
+fn gzip_header_fn(state: ptr::NonNull<u8>) -> Option<crate::gzip::GzipHeader> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    match state.gzip_header.as_ref() {
+        Some((raw, header)) => internal_gz_header_read!(raw, header),
+        None => None,
+    }
+}
+
 #[inline]
-fn describe_error_fn(code: i32) -> Option<&'static str> {
-    match sys::ReturnCode::try_from_c_int(code as _) {
+pub(super) fn describe_error_fn(_state: ptr::NonNull<u8>, error: DecodeError) -> Option<&'static str> {
+    match sys::ReturnCode::try_from_c_int(error.as_raw() as _) {
         Some(sys::ReturnCode::Ok) => Some("ok"),
         Some(sys::ReturnCode::StreamEnd) => Some("stream end"),
         Some(sys::ReturnCode::NeedDict) => Some("need dictionary"),
@@ -138,3 +236,10 @@ fn describe_error_fn(code: i32) -> Option<&'static str> {
         _ => Some("impossible error"),
     }
 }
+
+#[cfg(feature = "alloc")]
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    Some(ptr::NonNull::from(&state.memory))
+}
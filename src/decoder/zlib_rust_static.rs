@@ -0,0 +1,129 @@
+//! `zlib-rs` interface implementation with state in caller provided workspace
+
+use core::alloc::Layout;
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+
+use super::zlib_common::ZlibDecoderOptions;
+use super::zlib_rust::{describe_error_fn, sys, DEFAULT_INFLATE};
+use super::{Decode, Decoder, Interface};
+use crate::mem::{workspace_alloc, workspace_free, Workspace};
+
+static ZLIB_RUST_STATIC: Interface = Interface::new(
+    decode_fn,
+    reset_fn,
+    drop_fn,
+    describe_error_fn
+);
+
+struct State {
+    inner: sys::z_stream,
+    multi_member: bool,
+    //Set once member is finished at the end of input, postponing reset until next decode
+    member_end: bool,
+}
+
+impl State {
+    #[inline(always)]
+    //Dictionary cannot be stored without allocation
+    fn set_dictionary(&mut self) -> bool {
+        false
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) -> bool {
+        self.member_end = false;
+        sys::reset(self.as_mut()) == sys::Z_OK
+    }
+
+    #[inline(always)]
+    fn as_mut(&mut self) -> &mut sys::InflateStream<'_> {
+        unsafe {
+            mem::transmute(&mut self.inner)
+        }
+    }
+}
+
+impl Drop for State {
+    #[inline(always)]
+    fn drop(&mut self) {
+        let _ = sys::end(self.as_mut());
+    }
+}
+
+impl Interface {
+    ///Creates decoder with `zlib-rust` interface, placing all of its state within `workspace`.
+    ///
+    ///Decoder never allocates, hence its memory usage is limited by `workspace`, which requires about 48KiB.
+    ///
+    ///Preset dictionary and [gzip header](struct.Decoder.html#method.gzip_header) are not supported, as they cannot be stored without allocation.
+    ///
+    ///Returns `None` if `workspace` is too small or `opts` specify dictionary or `gzip` header capture.
+    pub fn zlib_rust_static<'a>(opts: ZlibDecoderOptions<'_>, workspace: &'a mut [MaybeUninit<u8>]) -> Option<Decoder<'a>> {
+        if !opts.dictionary.is_empty() || opts.gzip_header {
+            return None;
+        }
+        let workspace = Workspace::new(workspace)?;
+        let state = workspace.alloc(Layout::new::<State>()) as *mut State;
+        let state = ptr::NonNull::new(state)?;
+
+        let instance = unsafe {
+            state.as_ptr().write(State {
+                inner: sys::z_stream {
+                    next_in: ptr::null_mut(),
+                    avail_in: 0,
+                    total_in: 0,
+                    next_out: ptr::null_mut(),
+                    avail_out: 0,
+                    total_out: 0,
+                    msg: ptr::null_mut(),
+                    state: ptr::null_mut(),
+                    zalloc: Some(workspace_alloc),
+                    zfree: Some(workspace_free),
+                    opaque: workspace as *const Workspace as _,
+                    data_type: 0,
+                    adler: 0,
+                    reserved: 0,
+                },
+                multi_member: opts.multi_member,
+                member_end: false,
+            });
+            &mut *state.as_ptr()
+        };
+        let config = sys::InflateConfig {
+            window_bits: opts.mode.max_bits(),
+        };
+
+        if sys::init(&mut instance.inner, config) == sys::Z_OK {
+            Some(ZLIB_RUST_STATIC.inner_decoder(state.cast()))
+        } else {
+            //Stream is not initialized, so there is nothing to end
+            None
+        }
+    }
+}
+
+#[inline]
+unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
+    internal_zlib_impl_decode!(state, input, input_remain, output, output_remain)
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
+    let result = unsafe {
+        (*(state.as_ptr() as *mut State)).reset()
+    };
+    if result {
+        Some(state)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    //Returns all memory to workspace, which itself is owned by caller
+    unsafe {
+        ptr::drop_in_place(state.as_ptr() as *mut State);
+    }
+}
@@ -1,34 +1,162 @@
 //! `zstd` interface implementation
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 use zstd_sys as sys;
 
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::sync::Arc;
 use core::ptr;
 
-use super::{Decode, DecodeError, DecodeStatus, Decoder, Interface};
+use super::{Decode, DecodeError, DecodeErrorKind, DecodeStatus, Detection, FrameInfo};
+#[cfg(feature = "alloc")]
+use super::{Decoder, Interface};
+#[cfg(feature = "alloc")]
 use crate::mem::compu_free_with_state;
+#[cfg(feature = "alloc")]
 use crate::mem::compu_malloc_with_state;
+#[cfg(feature = "alloc")]
+use crate::mem::{Allocator, MemoryTracker};
 
+#[cfg(feature = "alloc")]
 static ZSTD: Interface = Interface {
     drop_fn,
     reset_fn,
     decode_fn,
     describe_error_fn,
+    gzip_header_fn: super::no_gzip_header,
+    trailing_data_offset_fn: super::no_trailing_data_offset,
+    memory_tracker_fn,
 };
 
+#[cfg(feature = "alloc")]
+#[inline(always)]
+//`tracker` is `None` for dictionaries, as they are shared between instances
+fn allocator(tracker: Option<&MemoryTracker>) -> sys::ZSTD_customMem {
+    sys::ZSTD_customMem {
+        customAlloc: Some(compu_malloc_with_state),
+        customFree: Some(compu_free_with_state),
+        opaque: tracker.map_or(ptr::null_mut(), MemoryTracker::as_opaque),
+    }
+}
+
+#[cfg(feature = "alloc")]
+struct DDict(ptr::NonNull<sys::ZSTD_DDict>);
+
+//DDict is read-only once created, hence it is safe to share between threads
+#[cfg(feature = "alloc")]
+unsafe impl Send for DDict {}
+#[cfg(feature = "alloc")]
+unsafe impl Sync for DDict {}
+
+#[cfg(feature = "alloc")]
+impl Drop for DDict {
+    #[inline(always)]
+    fn drop(&mut self) {
+        unsafe {
+            sys::ZSTD_freeDDict(self.0.as_ptr());
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+///Digested `zstd` dictionary for decompression.
+///
+///It is reference counted, so that single dictionary can be shared by any number of decoders.
+pub struct ZstdDDict {
+    inner: Arc<DDict>,
+}
+
+#[cfg(feature = "alloc")]
+impl ZstdDDict {
+    ///Creates dictionary from `dict` content, which can be either in `zstd` dictionary format or raw content.
+    ///
+    ///Returns `None` if unable to create it (due to invalid dictionary or lack of memory)
+    pub fn new(dict: &[u8]) -> Option<Self> {
+        let dict = unsafe {
+            sys::ZSTD_createDDict_advanced(dict.as_ptr() as _, dict.len(), sys::ZSTD_dictLoadMethod_e::ZSTD_dlm_byCopy, sys::ZSTD_dictContentType_e::ZSTD_dct_auto, allocator(None))
+        };
+        ptr::NonNull::new(dict).map(|dict| Self {
+            inner: Arc::new(DDict(dict)),
+        })
+    }
+
+    #[inline(always)]
+    ///Returns dictionary ID, which is 0 for raw content dictionary.
+    pub fn id(&self) -> u32 {
+        unsafe {
+            sys::ZSTD_getDictID_fromDDict(self.inner.0.as_ptr()) as _
+        }
+    }
+}
+
+#[inline]
+///Returns ID of dictionary required to decompress frame at the start of `input`.
+///
+///Returns 0 if frame requires no dictionary, its ID is omitted or `input` is not `zstd` frame.
+pub fn zstd_frame_dictionary_id(input: &[u8]) -> u32 {
+    unsafe {
+        sys::ZSTD_getDictID_fromFrame(input.as_ptr() as _, input.len()) as _
+    }
+}
+
+pub(crate) fn frame_info(input: &[u8]) -> Option<FrameInfo<'static>> {
+    let mut header = core::mem::MaybeUninit::<sys::ZSTD_FrameHeader>::uninit();
+    let result = unsafe {
+        sys::ZSTD_getFrameHeader(header.as_mut_ptr(), input.as_ptr() as _, input.len())
+    };
+    //Non-zero is either error or incomplete header
+    if result != 0 {
+        return None;
+    }
+
+    let header = unsafe {
+        header.assume_init()
+    };
+    if header.frameType != sys::ZSTD_FrameType_e::ZSTD_frame {
+        return None;
+    }
+
+    let mut info = FrameInfo::new(Detection::Zstd);
+    if header.frameContentSize != sys::ZSTD_CONTENTSIZE_UNKNOWN as u64 {
+        info.content_size = Some(header.frameContentSize);
+    }
+    info.window_size = Some(header.windowSize);
+    info.dictionary_id = header.dictID;
+    info.checksum = header.checksumFlag != 0;
+    Some(info)
+}
+
+#[derive(Copy, Clone)]
+enum Dictionary<'a> {
+    None,
+    Raw(&'a [u8]),
+    #[cfg(feature = "alloc")]
+    Prepared(&'a ZstdDDict),
+}
+
 #[derive(Copy, Clone)]
 ///ZSTD options.
 ///
 ///For details refer to their crappy documentation: `http://facebook.github.io/zstd/zstd_manual.html#Chapter6`
-pub struct ZstdOptions {
+pub struct ZstdOptions<'a> {
     window_log: i32,
+    dictionary: Dictionary<'a>,
+    concatenated: bool,
 }
 
-impl ZstdOptions {
+impl<'a> ZstdOptions<'a> {
     #[inline(always)]
     ///Creates new default value
     pub const fn new() -> Self {
         Self {
-            window_log: 0
+            window_log: 0,
+            dictionary: Dictionary::None,
+            concatenated: false,
         }
     }
 
@@ -48,7 +176,65 @@ impl ZstdOptions {
     }
 
     #[inline(always)]
-    fn apply(&self, ctx: ptr::NonNull<sys::ZSTD_DCtx>) -> Option<ptr::NonNull<sys::ZSTD_DCtx>> {
+    ///Sets dictionary to load, which can be either in `zstd` dictionary format or raw content.
+    ///
+    ///Its content is copied into decoder and digested on creation.
+    ///Prefer [ddict](#method.ddict) when the same dictionary is used by multiple decoders.
+    pub const fn dictionary(mut self, dict: &'a [u8]) -> Self {
+        self.dictionary = Dictionary::Raw(dict);
+        self
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline(always)]
+    ///Sets already digested dictionary to reference.
+    ///
+    ///Decoder holds its own reference to the dictionary.
+    pub const fn ddict(mut self, dict: &'a ZstdDDict) -> Self {
+        self.dictionary = Dictionary::Prepared(dict);
+        self
+    }
+
+    #[inline(always)]
+    ///Sets whether to decode concatenated frames as single stream.
+    ///
+    ///When enabled, decoding continues with the next frame within input, hence
+    ///[Finished](enum.DecodeStatus.html#variant.Finished) is reported only when input ends at frame boundary,
+    ///while feeding more input afterwards continues decoding with the next frame.
+    ///This is how output of `zstd` multi-threaded or concatenated files must be decoded.
+    ///
+    ///Defaults to `false`.
+    pub const fn concatenated(mut self, concatenated: bool) -> Self {
+        self.concatenated = concatenated;
+        self
+    }
+
+    ///Returns size of workspace required by [zstd_static](struct.Interface.html#method.zstd_static) decoder with these options.
+    ///
+    ///It accounts for maximum window size allowed by [window_log](#method.window_log), hence default options require large workspace.
+    pub fn estimate_size(&self) -> usize {
+        let window_log = match self.window_log {
+            0 => sys::ZSTD_WINDOWLOG_LIMIT_DEFAULT,
+            window_log => window_log as _,
+        };
+        let size = unsafe {
+            sys::ZSTD_estimateDStreamSize(1 << window_log)
+        };
+        super::zstd_static::WORKSPACE_OVERHEAD + size
+    }
+
+    #[inline(always)]
+    pub(super) const fn is_concatenated(&self) -> bool {
+        self.concatenated
+    }
+
+    #[inline(always)]
+    pub(super) const fn has_dictionary(&self) -> bool {
+        !matches!(self.dictionary, Dictionary::None)
+    }
+
+    #[inline(always)]
+    pub(super) fn apply(&self, ctx: ptr::NonNull<sys::ZSTD_DCtx>) -> Option<ptr::NonNull<sys::ZSTD_DCtx>> {
         macro_rules! set {
             ($field:ident => $param:ident) => {{
                 unsafe {
@@ -62,103 +248,252 @@ impl ZstdOptions {
 
         set!(window_log => ZSTD_d_windowLogMax);
 
+        let result = match self.dictionary {
+            Dictionary::None => 0,
+            Dictionary::Raw(dict) => unsafe {
+                sys::ZSTD_DCtx_loadDictionary(ctx.as_ptr(), dict.as_ptr() as _, dict.len())
+            },
+            #[cfg(feature = "alloc")]
+            Dictionary::Prepared(dict) => unsafe {
+                sys::ZSTD_DCtx_refDDict(ctx.as_ptr(), dict.inner.0.as_ptr())
+            },
+        };
+        if unsafe { sys::ZSTD_isError(result) } != 0 {
+            return None;
+        }
+
         Some(ctx)
     }
 }
 
-impl Default for ZstdOptions {
+impl Default for ZstdOptions<'_> {
     #[inline(always)]
     fn default() -> Self {
         Self::new()
     }
 }
 
+//Decoding state, shared with static context variant
+pub(super) struct Stream {
+    ctx: ptr::NonNull<sys::ZSTD_DCtx>,
+    concatenated: bool,
+    //zstd starts next frame on its own, so input after end of frame is refused until reset
+    finished: bool,
+}
+
+impl Stream {
+    #[inline(always)]
+    pub(super) const fn new(ctx: ptr::NonNull<sys::ZSTD_DCtx>, concatenated: bool) -> Self {
+        Self {
+            ctx,
+            concatenated,
+            finished: false,
+        }
+    }
+
+    pub(super) unsafe fn decode(&mut self, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
+        if self.finished {
+            return Decode {
+                input_remain,
+                output_remain,
+                status: Ok(DecodeStatus::Finished),
+            };
+        }
+
+        let mut input = sys::ZSTD_inBuffer_s {
+            src: input as _,
+            size: input_remain,
+            pos: 0,
+        };
+        let mut output = sys::ZSTD_outBuffer_s {
+            dst: output as _,
+            size: output_remain,
+            pos: 0,
+        };
+        let mut result;
+        loop {
+            let input_pos = input.pos;
+            result = unsafe {
+                sys::ZSTD_decompressStream(self.ctx.as_ptr(), &mut output, &mut input)
+            };
+            //Frame is finished, but input continues with the next one, which is started automatically
+            if result == 0 && self.concatenated && input.pos < input.size && input.pos > input_pos {
+                continue;
+            }
+            break;
+        }
+        self.finished = result == 0 && !self.concatenated;
+
+        Decode {
+            input_remain: input.size - input.pos,
+            output_remain: output.size - output.pos,
+            status: match result {
+                0 => Ok(DecodeStatus::Finished),
+                //Unfortunately error handling in zstd is shit
+                //non-zero return value means that we're not done or it is error.
+                //ZSTD_decompressStream() always flushes to maximum, so if there is not enough space,
+                //we should check it first, otherwise assume we need more input.
+                //Even though they have error code 70 to indicate output not having enough space
+                //they do not necessary use it
+                size => {
+                    if output.pos == output.size {
+                        Ok(DecodeStatus::NeedOutput)
+                    } else if sys::ZSTD_isError(size) == 0 {
+                        //Not error, means it was able to flush out everything it had
+                        Ok(DecodeStatus::NeedInput)
+                    } else {
+                        Err(decode_error(size))
+                    }
+                }
+            },
+        }
+    }
+
+    #[inline(always)]
+    pub(super) fn reset(&mut self) -> bool {
+        //Session only reset keeps parameters and dictionary
+        let result = unsafe {
+            sys::ZSTD_DCtx_reset(self.ctx.as_ptr(), sys::ZSTD_ResetDirective::ZSTD_reset_session_only)
+        };
+        if result == 0 {
+            self.finished = false;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+struct State {
+    stream: Stream,
+    //Referenced by context as `opaque`, hence it must outlive it
+    memory: Box<MemoryTracker>,
+    //Referenced dictionary must outlive context
+    _ddict: Option<ZstdDDict>,
+}
+
+#[cfg(feature = "alloc")]
+impl Drop for State {
+    #[inline(always)]
+    fn drop(&mut self) {
+        let result = unsafe {
+            sys::ZSTD_freeDStream(self.stream.ctx.as_ptr())
+        };
+        debug_assert_eq!(result, 0);
+    }
+}
+
+#[cfg(feature = "alloc")]
 impl Interface {
     #[inline]
     ///Creates decoder with `zstd` interface
     ///
-    ///Returns `None` if unable to initialize it (likely due to lack of memory)
-    pub fn zstd(opts: ZstdOptions) -> Option<Decoder> {
-        let allocator = sys::ZSTD_customMem {
-            customAlloc: Some(compu_malloc_with_state),
-            customFree: Some(compu_free_with_state),
-            opaque: ptr::null_mut(),
-        };
-        let ctx = unsafe {
-            sys::ZSTD_createDStream_advanced(allocator)
-        };
-        match ptr::NonNull::new(ctx).and_then(|ctx| opts.apply(ctx)) {
-            Some(ctx) => Some(ZSTD.inner_decoder(ctx.cast())),
-            None => None,
-        }
+    ///Dictionary, if any, stays applied across resets.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory or invalid dictionary)
+    pub fn zstd(opts: ZstdOptions<'_>) -> Option<Decoder<'static>> {
+        create(opts, MemoryTracker::new())
     }
+
+    #[inline]
+    ///Creates decoder with `zstd` interface, using `allocator` for all allocations of context.
+    ///
+    ///Prepared dictionaries are allocated separately, using Rust's global allocator.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory or invalid dictionary)
+    pub fn zstd_with_allocator(opts: ZstdOptions<'_>, allocator: impl Allocator + 'static) -> Option<Decoder<'static>> {
+        create(opts, MemoryTracker::with_allocator(allocator))
+    }
+}
+
+#[cfg(feature = "alloc")]
+fn create(opts: ZstdOptions<'_>, memory: MemoryTracker) -> Option<Decoder<'static>> {
+    let memory = Box::new(memory);
+    let ctx = unsafe {
+        sys::ZSTD_createDStream_advanced(allocator(Some(&memory)))
+    };
+    let ctx = ptr::NonNull::new(ctx)?;
+    let state = Box::new(State {
+        stream: Stream::new(ctx, opts.concatenated),
+        memory,
+        _ddict: match opts.dictionary {
+            Dictionary::Prepared(dict) => Some(dict.clone()),
+            _ => None,
+        },
+    });
+    opts.apply(ctx)?;
+
+    let state = ptr::NonNull::from(Box::leak(state));
+    Some(ZSTD.inner_decoder(state.cast()))
 }
 
+#[cfg(feature = "alloc")]
 #[inline]
 unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
-    let mut input = sys::ZSTD_inBuffer_s {
-        src: input as _,
-        size: input_remain,
-        pos: 0,
-    };
-    let mut output = sys::ZSTD_outBuffer_s {
-        dst: output as _,
-        size: output_remain,
-        pos: 0,
-    };
-    let result = unsafe {
-        sys::ZSTD_decompressStream(state.cast().as_ptr(), &mut output, &mut input)
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut State)
     };
+    state.stream.decode(input, input_remain, output, output_remain)
+}
 
-    Decode {
-        input_remain: input.size - input.pos,
-        output_remain: output.size - output.pos,
-        status: match result {
-            0 => Ok(DecodeStatus::Finished),
-            //Unfortunately error handling in zstd is shit
-            //non-zero return value means that we're not done or it is error.
-            //ZSTD_decompressStream() always flushes to maximum, so if there is not enough space,
-            //we should check it first, otherwise assume we need more input.
-            //Even though they have error code 70 to indicate output not having enough space
-            //they do not necessary use it
-            size => {
-                if output.pos == output.size {
-                    Ok(DecodeStatus::NeedOutput)
-                } else if sys::ZSTD_isError(size) == 0 {
-                    //Not error, means it was able to flush out everything it had
-                    Ok(DecodeStatus::NeedInput)
-                } else {
-                    Err(DecodeError(size as _))
-                }
-            }
-        },
-    }
+#[cold]
+#[inline(never)]
+fn decode_error(code: usize) -> DecodeError {
+    use sys::ZSTD_ErrorCode as Code;
+
+    let kind = match unsafe { sys::ZSTD_getErrorCode(code) } {
+        Code::ZSTD_error_prefix_unknown
+        | Code::ZSTD_error_corruption_detected
+        | Code::ZSTD_error_literals_headerWrong
+        | Code::ZSTD_error_dictionary_corrupted
+        | Code::ZSTD_error_srcSize_wrong => DecodeErrorKind::Corrupted,
+        Code::ZSTD_error_checksum_wrong => DecodeErrorKind::Checksum,
+        Code::ZSTD_error_version_unsupported
+        | Code::ZSTD_error_frameParameter_unsupported
+        | Code::ZSTD_error_parameter_unsupported
+        | Code::ZSTD_error_parameter_combination_unsupported
+        | Code::ZSTD_error_parameter_outOfBound => DecodeErrorKind::UnsupportedParameter,
+        Code::ZSTD_error_dictionary_wrong => DecodeErrorKind::DictionaryRequired,
+        Code::ZSTD_error_memory_allocation | Code::ZSTD_error_workSpace_tooSmall => DecodeErrorKind::OutOfMemory,
+        Code::ZSTD_error_frameParameter_windowTooLarge => DecodeErrorKind::WindowTooLarge,
+        _ => DecodeErrorKind::Other,
+    };
+    DecodeError::new(kind, code as _)
 }
 
+#[cfg(feature = "alloc")]
 #[inline]
 fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
-    let result = unsafe {
-        sys::ZSTD_DCtx_reset(state.cast().as_ptr(), sys::ZSTD_ResetDirective::ZSTD_reset_session_only)
+    let instance = unsafe {
+        &mut *(state.as_ptr() as *mut State)
     };
-    if result == 0 {
+    if instance.stream.reset() {
         Some(state)
     } else {
         None
     }
 }
 
+#[cfg(feature = "alloc")]
 #[inline]
 fn drop_fn(state: ptr::NonNull<u8>) {
-    let result = unsafe {
-        sys::ZSTD_freeDStream(state.cast().as_ptr())
-    };
-    debug_assert_eq!(result, 0);
+    unsafe {
+        drop(Box::from_raw(state.as_ptr() as *mut State));
+    }
 }
 
 #[inline]
-fn describe_error_fn(code: i32) -> Option<&'static str> {
+pub(super) fn describe_error_fn(_state: ptr::NonNull<u8>, error: DecodeError) -> Option<&'static str> {
     let result = unsafe {
-        sys::ZSTD_getErrorName(code as _)
+        sys::ZSTD_getErrorName(error.as_raw() as _)
     };
     crate::utils::convert_c_str(result)
 }
+
+#[cfg(feature = "alloc")]
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    Some(ptr::NonNull::from(&*state.memory))
+}
@@ -0,0 +1,87 @@
+//! `zstd` interface implementation with context in caller provided workspace
+
+use zstd_sys as sys;
+
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+
+use super::zstd::{self, Stream};
+use super::{Decode, Decoder, Interface, ZstdOptions};
+
+static ZSTD_STATIC: Interface = Interface {
+    drop_fn,
+    reset_fn,
+    decode_fn,
+    describe_error_fn: zstd::describe_error_fn,
+    #[cfg(feature = "alloc")]
+    gzip_header_fn: super::no_gzip_header,
+    trailing_data_offset_fn: super::no_trailing_data_offset,
+    memory_tracker_fn: super::no_memory_tracker,
+};
+
+//zstd requires workspace to be 8 bytes aligned
+const ALIGN: usize = 8;
+const _: () = assert!(mem::align_of::<Stream>() <= ALIGN);
+//Stream is placed at the start of workspace, followed by context
+const STREAM_SIZE: usize = mem::size_of::<Stream>().div_ceil(ALIGN) * ALIGN;
+//Workspace space not available to context, including worst case alignment
+pub(super) const WORKSPACE_OVERHEAD: usize = STREAM_SIZE + ALIGN - 1;
+
+impl Interface {
+    ///Creates decoder with `zstd` interface, placing all of its state within `workspace`.
+    ///
+    ///Decoder never allocates, hence its memory usage is limited by `workspace`, and decoding
+    ///fails with [OutOfMemory](enum.DecodeErrorKind.html#variant.OutOfMemory) if frame requires more.
+    ///Required size can be computed by [ZstdOptions::estimate_size](struct.ZstdOptions.html#method.estimate_size).
+    ///
+    ///Dictionaries are not supported, as they cannot be loaded without allocation.
+    ///
+    ///Returns `None` if `workspace` is too small, options are invalid or dictionary is set.
+    pub fn zstd_static<'a>(opts: ZstdOptions<'_>, workspace: &'a mut [MaybeUninit<u8>]) -> Option<Decoder<'a>> {
+        if opts.has_dictionary() {
+            return None;
+        }
+
+        let offset = workspace.as_ptr().align_offset(ALIGN);
+        if workspace.len() < offset.saturating_add(STREAM_SIZE) {
+            return None;
+        }
+        let (stream, workspace) = workspace[offset..].split_at_mut(STREAM_SIZE);
+
+        let ctx = unsafe {
+            sys::ZSTD_initStaticDStream(workspace.as_mut_ptr() as _, workspace.len())
+        };
+        let ctx = opts.apply(ptr::NonNull::new(ctx)?)?;
+
+        let stream = stream.as_mut_ptr() as *mut Stream;
+        unsafe {
+            stream.write(Stream::new(ctx, opts.is_concatenated()));
+            Some(ZSTD_STATIC.inner_decoder(ptr::NonNull::new_unchecked(stream).cast()))
+        }
+    }
+}
+
+#[inline]
+unsafe fn decode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize) -> Decode {
+    let stream = unsafe {
+        &mut *(state.as_ptr() as *mut Stream)
+    };
+    stream.decode(input, input_remain, output, output_remain)
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<u8>> {
+    let stream = unsafe {
+        &mut *(state.as_ptr() as *mut Stream)
+    };
+    if stream.reset() {
+        Some(state)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn drop_fn(_: ptr::NonNull<u8>) {
+    //Static context owns no resources, as everything is within workspace
+}
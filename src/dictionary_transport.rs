@@ -0,0 +1,118 @@
+//!Compression Dictionary Transport framing (RFC 9842)
+//!
+//!Dictionary-compressed responses (`dcb` and `dcz` content encodings) start with fixed header,
+//!consisting of format magic and SHA-256 hash of the dictionary, followed by compressed stream.
+//!
+//!Hash is not computed here, as it is already known from `Available-Dictionary` header.
+//!
+//!- `dcb` - `brotli` stream, using dictionary as raw prefix.
+//!Use [BrotliOptions::dictionary](../encoder/struct.BrotliOptions.html#method.dictionary) to encode
+//!and [brotli_c_with_dictionary](../decoder/struct.Interface.html#method.brotli_c_with_dictionary) to decode.
+//!- `dcz` - `zstd` stream, using dictionary as raw content.
+//!Use [encoder::ZstdOptions](../encoder/struct.ZstdOptions.html#method.dictionary) to encode
+//!and [decoder::ZstdOptions](../decoder/struct.ZstdOptions.html#method.dictionary) to decode.
+//!
+//!Header must be written before encoded stream and skipped before feeding input to decoder.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::convert::TryInto;
+use core::{cmp, fmt};
+
+///Size of dictionary hash (SHA-256)
+pub const HASH_SIZE: usize = 32;
+
+const DCB_MAGIC: [u8; 4] = [0xff, 0x44, 0x43, 0x42];
+//Skippable zstd frame with hash as its content
+const DCZ_MAGIC: [u8; 8] = [0x5e, 0x2a, 0x4d, 0x18, 0x20, 0x00, 0x00, 0x00];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Header parsing error
+pub enum HeaderError {
+    ///Not enough input to parse header
+    Incomplete,
+    ///Input does not start with format magic
+    InvalidMagic,
+}
+
+impl fmt::Display for HeaderError {
+    #[inline(always)]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Incomplete => fmt.write_str("Incomplete dictionary transport header"),
+            Self::InvalidMagic => fmt.write_str("Invalid dictionary transport header magic"),
+        }
+    }
+}
+
+impl core::error::Error for HeaderError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Dictionary-compressed format
+pub enum Format {
+    ///Dictionary-compressed Brotli
+    Dcb,
+    ///Dictionary-compressed Zstandard
+    Dcz,
+}
+
+impl Format {
+    #[inline]
+    ///Returns format by its `Content-Encoding` name
+    pub fn from_content_encoding(name: &str) -> Option<Self> {
+        match name {
+            "dcb" => Some(Self::Dcb),
+            "dcz" => Some(Self::Dcz),
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    ///Returns `Content-Encoding` name of the format
+    pub const fn content_encoding(self) -> &'static str {
+        match self {
+            Self::Dcb => "dcb",
+            Self::Dcz => "dcz",
+        }
+    }
+
+    #[inline(always)]
+    const fn magic(self) -> &'static [u8] {
+        match self {
+            Self::Dcb => &DCB_MAGIC,
+            Self::Dcz => &DCZ_MAGIC,
+        }
+    }
+
+    #[inline(always)]
+    ///Returns size of the header, including hash
+    pub const fn header_size(self) -> usize {
+        self.magic().len() + HASH_SIZE
+    }
+
+    #[inline]
+    ///Appends header with dictionary `hash` to the `output`
+    pub fn write_header(self, hash: &[u8; HASH_SIZE], output: &mut Vec<u8>) {
+        output.reserve(self.header_size());
+        output.extend_from_slice(self.magic());
+        output.extend_from_slice(hash);
+    }
+
+    #[inline]
+    ///Parses header at the beginning of `input`, returning dictionary hash.
+    ///
+    ///Compressed stream starts right after [header_size](#method.header_size) bytes.
+    pub fn parse_header(self, input: &[u8]) -> Result<&[u8; HASH_SIZE], HeaderError> {
+        let magic = self.magic();
+        let check_size = cmp::min(input.len(), magic.len());
+        if input[..check_size] != magic[..check_size] {
+            return Err(HeaderError::InvalidMagic);
+        }
+
+        match input.get(magic.len()..self.header_size()).and_then(|hash| hash.try_into().ok()) {
+            Some(hash) => Ok(hash),
+            None => Err(HeaderError::Incomplete),
+        }
+    }
+}
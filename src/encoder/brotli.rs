@@ -1,25 +1,37 @@
 //! `brotli` interface implementation
 
+use super::brotli_common;
+#[cfg(feature = "alloc")]
 use super::brotli_common::BrotliOptions;
-use super::{Encode, EncodeOp, EncodeStatus, Encoder, Interface};
+use super::{Encode, EncodeOp, EncodeStatus};
+#[cfg(feature = "alloc")]
+use super::{Encoder, Interface};
+#[cfg(feature = "alloc")]
 use crate::mem::brotli_rust::BrotliAllocator;
+#[cfg(feature = "alloc")]
 use crate::mem::Box;
-use core::{ptr, slice};
+use core::slice;
+#[cfg(feature = "alloc")]
+use core::ptr;
 
+#[cfg(feature = "alloc")]
 pub(crate) type Instance = brotli::enc::encode::BrotliEncoderStateStruct<BrotliAllocator>;
 
+#[cfg(feature = "alloc")]
 static BROTLI_RUST: Interface = Interface::new(
     reset_fn,
     encode_fn,
-    drop_fn
-);
+    drop_fn,
+    brotli_common::describe_error_fn
+).with_pledged_size(set_pledged_size_fn);
 
+#[cfg(feature = "alloc")]
 impl Interface {
     #[inline]
     ///Creates decoder with `brotli-rust` interface
     ///
     ///Never returns `None` (probably panics on OOM)
-    pub fn brotli_rust(options: BrotliOptions) -> Encoder {
+    pub fn brotli_rust(options: BrotliOptions) -> Encoder<'static> {
         let mut state = Box::new(instance());
 
         options.apply_rust(&mut state);
@@ -40,15 +52,21 @@ impl EncodeOp {
     }
 }
 
+#[cfg(feature = "alloc")]
 fn instance() -> Instance {
     Instance::new(Default::default())
 }
 
-unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, mut input_remain: usize, output: *mut u8, mut output_remain: usize, op: EncodeOp) -> Encode {
+#[cfg(feature = "alloc")]
+unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize, op: EncodeOp) -> Encode {
     let state = unsafe {
         &mut *(state.as_ptr() as *mut Instance)
     };
+    encode(state, input, input_remain, output, output_remain, op)
+}
 
+//Shared with workspace variant
+pub(super) unsafe fn encode<A: brotli::enc::BrotliAlloc>(state: &mut brotli::enc::encode::BrotliEncoderStateStruct<A>, input: *const u8, mut input_remain: usize, output: *mut u8, mut output_remain: usize, op: EncodeOp) -> Encode {
     let input = unsafe {
         slice::from_raw_parts(input, input_remain)
     };
@@ -72,7 +90,7 @@ unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, mut input_remain:
         output_remain,
         status: match result {
             false => match has_more_output {
-                false => EncodeStatus::Error,
+                false => EncodeStatus::Error(brotli_common::encode_error()),
                 true => EncodeStatus::NeedOutput,
             },
             true => {
@@ -88,6 +106,7 @@ unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, mut input_remain:
     }
 }
 
+#[cfg(feature = "alloc")]
 #[inline]
 fn reset_fn(state: ptr::NonNull<u8>, opts: [u8; 2]) -> Option<ptr::NonNull<u8>> {
     let options = BrotliOptions::from_raw(opts);
@@ -101,6 +120,21 @@ fn reset_fn(state: ptr::NonNull<u8>, opts: [u8; 2]) -> Option<ptr::NonNull<u8>>
     Some(ptr::NonNull::from(ptr).cast())
 }
 
+#[cfg(feature = "alloc")]
+#[inline]
+fn set_pledged_size_fn(state: ptr::NonNull<u8>, size: u64) -> bool {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut Instance)
+    };
+    set_pledged_size(state, size)
+}
+
+#[inline(always)]
+pub(super) fn set_pledged_size<A: brotli::enc::BrotliAlloc>(state: &mut brotli::enc::encode::BrotliEncoderStateStruct<A>, size: u64) -> bool {
+    state.set_parameter(brotli::enc::encode::BrotliEncoderParameter::BROTLI_PARAM_SIZE_HINT, brotli_common::size_hint(size))
+}
+
+#[cfg(feature = "alloc")]
 #[inline]
 fn drop_fn(state: ptr::NonNull<u8>) {
     let _ = unsafe {
@@ -1,18 +1,23 @@
 //! `brotli` interface implementation
 
+extern crate alloc;
+
 use compu_brotli_sys as sys;
 
+use alloc::boxed::Box;
+use alloc::sync::Arc;
 use core::ptr;
 
-use super::brotli_common::BrotliOptions;
+use super::brotli_common::{self, BrotliOptions};
 use super::{Encode, EncodeOp, EncodeStatus, Encoder, Interface};
-use crate::mem::{compu_free_with_state, compu_malloc_with_state};
+use crate::mem::{compu_free_with_state, compu_malloc_with_state, Allocator, MemoryTracker};
 
 static BROTLI_C: Interface = Interface::new(
     reset_fn,
     encode_fn,
-    drop_fn
-);
+    drop_fn,
+    brotli_common::describe_error_fn
+).with_pledged_size(set_pledged_size_fn);
 
 impl EncodeOp {
     #[inline(always)]
@@ -25,35 +30,140 @@ impl EncodeOp {
     }
 }
 
+struct PreparedDictionary {
+    ptr: ptr::NonNull<sys::BrotliEncoderPreparedDictionary>,
+    //Prepared dictionary only references content, so it must be kept alive
+    _data: Box<[u8]>,
+}
+
+//Prepared dictionary is read-only once created, hence it is safe to share between threads
+unsafe impl Send for PreparedDictionary {}
+unsafe impl Sync for PreparedDictionary {}
+
+impl Drop for PreparedDictionary {
+    #[inline(always)]
+    fn drop(&mut self) {
+        unsafe {
+            sys::BrotliEncoderDestroyPreparedDictionary(self.ptr.as_ptr());
+        }
+    }
+}
+
+#[derive(Clone)]
+///Prepared `brotli` dictionary for compression.
+///
+///It is reference counted, so that single dictionary can be shared by any number of encoders.
+pub struct BrotliDictionary {
+    inner: Arc<PreparedDictionary>,
+}
+
+impl BrotliDictionary {
+    ///Creates raw prefix dictionary by copying `dict` content.
+    ///
+    ///Returns `None` if unable to create it (likely due to lack of memory)
+    pub fn new(dict: &[u8]) -> Option<Self> {
+        let data: Box<[u8]> = dict.into();
+        let result = unsafe {
+            sys::BrotliEncoderPrepareDictionary(
+                sys::BrotliSharedDictionaryType_BROTLI_SHARED_DICTIONARY_RAW,
+                data.len(), data.as_ptr(),
+                //BROTLI_MAX_QUALITY, so that dictionary is usable with any quality
+                11,
+                Some(compu_malloc_with_state), Some(compu_free_with_state), ptr::null_mut()
+            )
+        };
+        ptr::NonNull::new(result).map(|ptr| Self {
+            inner: Arc::new(PreparedDictionary {
+                ptr,
+                _data: data,
+            }),
+        })
+    }
+}
+
+struct State {
+    instance: ptr::NonNull<sys::BrotliEncoderState>,
+    //Attached dictionary must outlive encoder
+    dictionary: Option<BrotliDictionary>,
+    //Referenced by encoder as `opaque`, hence it must outlive it
+    memory: Box<MemoryTracker>,
+}
+
+impl Drop for State {
+    #[inline(always)]
+    fn drop(&mut self) {
+        unsafe {
+            sys::BrotliEncoderDestroyInstance(self.instance.as_ptr());
+        }
+    }
+}
+
 #[inline]
-fn new_encoder() -> Option<ptr::NonNull<u8>> {
-    let result = unsafe {
-        sys::BrotliEncoderCreateInstance(Some(compu_malloc_with_state), Some(compu_free_with_state), ptr::null_mut())
+fn new_encoder(options: &BrotliOptions<'_>, dictionary: Option<&BrotliDictionary>, memory: &MemoryTracker) -> Option<ptr::NonNull<sys::BrotliEncoderState>> {
+    //Memory usage is not exposed, as encoder terminates process on allocation failure, which makes budget unusable
+    let instance = unsafe {
+        sys::BrotliEncoderCreateInstance(Some(compu_malloc_with_state), Some(compu_free_with_state), memory.as_opaque())
     };
+    let instance = ptr::NonNull::new(instance)?;
+    options.apply_c(instance.as_ptr());
 
-    ptr::NonNull::new(result as *mut u8)
+    if let Some(dictionary) = dictionary {
+        let result = unsafe {
+            sys::BrotliEncoderAttachPreparedDictionary(instance.as_ptr(), dictionary.inner.ptr.as_ptr())
+        };
+        if result == 0 {
+            unsafe {
+                sys::BrotliEncoderDestroyInstance(instance.as_ptr());
+            }
+            return None;
+        }
+    }
+
+    Some(instance)
 }
 
 impl Interface {
     #[inline]
     ///Creates encoder with `brotli-c` interface
     ///
+    ///Dictionary, if any, stays attached across resets.
+    ///
     ///Returns `None` if unable to initialize it (likely due to lack of memory)
-    pub fn brotli_c(options: BrotliOptions) -> Option<Encoder> {
-        match new_encoder() {
-            Some(ptr) => {
-                options.apply_c(ptr.as_ptr() as _);
-                Some(BROTLI_C.inner_encoder(ptr.cast(), options.inner))
-            }
-            None => None,
-        }
+    pub fn brotli_c(options: BrotliOptions<'_>) -> Option<Encoder<'static>> {
+        create(options, MemoryTracker::new())
+    }
+
+    #[inline]
+    ///Creates encoder with `brotli-c` interface, using `allocator` for all allocations of encoder.
+    ///
+    ///Note that encoder terminates process if `allocator` fails to allocate memory.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn brotli_c_with_allocator(options: BrotliOptions<'_>, allocator: impl Allocator + 'static) -> Option<Encoder<'static>> {
+        create(options, MemoryTracker::with_allocator(allocator))
     }
 }
 
+fn create(options: BrotliOptions<'_>, memory: MemoryTracker) -> Option<Encoder<'static>> {
+    let memory = Box::new(memory);
+    let instance = new_encoder(&options, options.dictionary, &memory)?;
+    let state = Box::new(State {
+        instance,
+        dictionary: options.dictionary.cloned(),
+        memory,
+    });
+
+    let ptr = ptr::NonNull::from(Box::leak(state));
+    Some(BROTLI_C.inner_encoder(ptr.cast(), options.inner))
+}
+
 unsafe fn encode_fn(state: ptr::NonNull<u8>, mut input: *const u8, mut input_remain: usize, mut output: *mut u8, mut output_remain: usize, op: EncodeOp) -> Encode {
+    let state = unsafe {
+        (*(state.as_ptr() as *mut State)).instance.as_ptr()
+    };
     let result = unsafe {
         sys::BrotliEncoderCompressStream(
-            state.as_ptr() as _, op.into_brotli(),
+            state, op.into_brotli(),
             &mut input_remain, &mut input,
             &mut output_remain, &mut output,
             ptr::null_mut(),
@@ -61,14 +171,14 @@ unsafe fn encode_fn(state: ptr::NonNull<u8>, mut input: *const u8, mut input_rem
     };
 
     let has_more_output = unsafe {
-        sys::BrotliEncoderHasMoreOutput(state.as_ptr() as _)
+        sys::BrotliEncoderHasMoreOutput(state)
     };
     Encode {
         input_remain,
         output_remain,
         status: match result {
             0 => match has_more_output {
-                0 => EncodeStatus::Error,
+                0 => EncodeStatus::Error(brotli_common::encode_error()),
                 _ => EncodeStatus::NeedOutput,
             },
             _ => {
@@ -87,19 +197,32 @@ unsafe fn encode_fn(state: ptr::NonNull<u8>, mut input: *const u8, mut input_rem
 #[inline]
 fn reset_fn(state: ptr::NonNull<u8>, opts: [u8; 2]) -> Option<ptr::NonNull<u8>> {
     let options = BrotliOptions::from_raw(opts);
-    match new_encoder() {
-        Some(ptr) => {
-            drop_fn(state);
-            options.apply_c(ptr.as_ptr() as _);
-            Some(ptr)
+    let state_ref = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    match new_encoder(&options, state_ref.dictionary.as_ref(), &state_ref.memory) {
+        Some(instance) => {
+            unsafe {
+                sys::BrotliEncoderDestroyInstance(state_ref.instance.as_ptr());
+            }
+            state_ref.instance = instance;
+            Some(state)
         }
         None => None,
     }
 }
 
+#[inline]
+fn set_pledged_size_fn(state: ptr::NonNull<u8>, size: u64) -> bool {
+    let result = unsafe {
+        sys::BrotliEncoderSetParameter((*(state.as_ptr() as *mut State)).instance.as_ptr(), sys::BrotliEncoderParameter_BROTLI_PARAM_SIZE_HINT, brotli_common::size_hint(size))
+    };
+    result != 0
+}
+
 #[inline]
 fn drop_fn(state: ptr::NonNull<u8>) {
     unsafe {
-        sys::BrotliEncoderDestroyInstance(state.as_ptr() as _);
+        drop(Box::from_raw(state.as_ptr() as *mut State));
     }
 }
@@ -12,11 +12,15 @@ pub enum BrotliEncoderMode {
 
 ///Brotli options
 #[derive(Default, Clone)]
-pub struct BrotliOptions {
+pub struct BrotliOptions<'a> {
     pub(crate) inner: [u8; 2],
+    #[cfg(feature = "brotli-c")]
+    pub(crate) dictionary: Option<&'a super::BrotliDictionary>,
+    #[cfg(not(feature = "brotli-c"))]
+    _dictionary: core::marker::PhantomData<&'a ()>,
 }
 
-impl BrotliOptions {
+impl<'a> BrotliOptions<'a> {
     const QUALITY_IDX: usize = 0;
     const MODE_IDX: usize = 1;
 
@@ -29,7 +33,13 @@ impl BrotliOptions {
     #[inline(always)]
     ///Creates default instance
     pub(crate) const fn from_raw(inner: [u8; 2]) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            #[cfg(feature = "brotli-c")]
+            dictionary: None,
+            #[cfg(not(feature = "brotli-c"))]
+            _dictionary: core::marker::PhantomData,
+        }
     }
 
     #[inline(always)]
@@ -54,6 +64,18 @@ impl BrotliOptions {
         self
     }
 
+    #[cfg(feature = "brotli-c")]
+    #[inline(always)]
+    ///Sets prepared dictionary to use as raw prefix.
+    ///
+    ///Encoder holds its own reference to the dictionary, keeping it attached across resets.
+    ///
+    ///Only supported by `brotli-c`, `brotli-rust` ignores it.
+    pub const fn dictionary(mut self, dictionary: &'a super::BrotliDictionary) -> Self {
+        self.dictionary = Some(dictionary);
+        self
+    }
+
     #[cfg(feature = "brotli-c")]
     pub(crate) fn apply_c(&self, state: *mut compu_brotli_sys::BrotliEncoderState) {
         use compu_brotli_sys as sys;
@@ -73,7 +95,7 @@ impl BrotliOptions {
     }
 
     #[cfg(feature = "brotli-rust")]
-    pub(crate) fn apply_rust(&self, state: &mut crate::encoder::brotli::Instance) {
+    pub(crate) fn apply_rust<A: brotli::enc::BrotliAlloc>(&self, state: &mut brotli::enc::encode::BrotliEncoderStateStruct<A>) {
         let quality = self.inner[Self::QUALITY_IDX];
         if quality > 0 {
             let result = state.set_parameter(brotli::enc::encode::BrotliEncoderParameter::BROTLI_PARAM_QUALITY, quality as _);
@@ -87,3 +109,25 @@ impl BrotliOptions {
         }
     }
 }
+
+#[inline(always)]
+pub(crate) fn size_hint(size: u64) -> u32 {
+    //brotli clamps hint to 1GiB anyway
+    core::cmp::min(size, u32::MAX as u64) as u32
+}
+
+///Brotli encoder provides no error code, so every failure is reported with this code
+const ENCODER_ERROR: i32 = -1;
+
+#[cold]
+#[inline(never)]
+pub(crate) fn encode_error() -> super::EncodeError {
+    super::EncodeError::new(super::EncodeErrorKind::Other, ENCODER_ERROR)
+}
+
+pub(crate) fn describe_error_fn(_: core::ptr::NonNull<u8>, code: i32) -> Option<&'static str> {
+    match code {
+        ENCODER_ERROR => Some("brotli encoder failed"),
+        _ => None,
+    }
+}
@@ -0,0 +1,79 @@
+//! `brotli` interface implementation with state in caller provided workspace
+
+use core::alloc::Layout;
+use core::mem::MaybeUninit;
+use core::ptr;
+
+use super::brotli::{encode, set_pledged_size};
+use super::brotli_common::{self, BrotliOptions};
+use super::{Encode, EncodeOp, Encoder, Interface};
+use crate::mem::brotli_rust::WorkspaceAllocator;
+use crate::mem::Workspace;
+
+type Instance<'a> = brotli::enc::encode::BrotliEncoderStateStruct<WorkspaceAllocator<'a>>;
+
+static BROTLI_STATIC: Interface = Interface::new(
+    reset_fn,
+    encode_fn,
+    drop_fn,
+    brotli_common::describe_error_fn
+).with_pledged_size(set_pledged_size_fn);
+
+impl Interface {
+    ///Creates encoder with `brotli-rust` interface, placing all of its state within `workspace`.
+    ///
+    ///Encoder never allocates, hence its memory usage is limited by `workspace`.
+    ///Required size grows with quality and window, from hundreds of KiB for low quality to tens of MiB for the highest one.
+    ///
+    ///Running out of workspace is reported as error where `brotli` handles allocation failure, but it may panic otherwise.
+    ///
+    ///Returns `None` if `workspace` is too small to fit encoder state.
+    pub fn brotli_rust_static<'a>(options: BrotliOptions, workspace: &'a mut [MaybeUninit<u8>]) -> Option<Encoder<'a>> {
+        let workspace = Workspace::new(workspace)?;
+        let state = workspace.alloc(Layout::new::<Instance<'a>>()) as *mut Instance<'a>;
+        let state = ptr::NonNull::new(state)?;
+
+        let mut instance = Instance::new(WorkspaceAllocator::new(workspace));
+        options.apply_rust(&mut instance);
+        unsafe {
+            state.as_ptr().write(instance);
+        }
+        Some(BROTLI_STATIC.inner_encoder(state.cast(), options.inner))
+    }
+}
+
+unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize, op: EncodeOp) -> Encode {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut Instance)
+    };
+    encode(state, input, input_remain, output, output_remain, op)
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>, opts: [u8; 2]) -> Option<ptr::NonNull<u8>> {
+    let options = BrotliOptions::from_raw(opts);
+    let instance = unsafe {
+        &mut *(state.as_ptr() as *mut Instance)
+    };
+
+    *instance = Instance::new(instance.m8);
+    options.apply_rust(instance);
+
+    Some(state)
+}
+
+#[inline]
+fn set_pledged_size_fn(state: ptr::NonNull<u8>, size: u64) -> bool {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut Instance)
+    };
+    set_pledged_size(state, size)
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    //Returns all memory to workspace, which itself is owned by caller
+    unsafe {
+        ptr::drop_in_place(state.as_ptr() as *mut Instance);
+    }
+}
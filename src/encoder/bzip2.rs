@@ -0,0 +1,238 @@
+//! `bzip2` interface implementation
+
+extern crate alloc;
+
+use bzip2_sys as sys;
+
+use alloc::boxed::Box;
+use core::ffi::{c_int, c_uint};
+use core::{cmp, ptr};
+
+use super::{Encode, EncodeError, EncodeErrorKind, EncodeOp, EncodeStatus, Encoder, Interface};
+use crate::mem::{compu_bzalloc, compu_bzfree, Allocator, MemoryTracker};
+
+static BZIP2: Interface = Interface {
+    drop_fn,
+    reset_fn,
+    encode_fn,
+    describe_error_fn,
+    set_pledged_size_fn: super::ignore_pledged_size,
+    memory_tracker_fn,
+};
+
+impl EncodeOp {
+    #[inline(always)]
+    const fn into_bzip2(self) -> c_int {
+        match self {
+            Self::Process => sys::BZ_RUN,
+            Self::Flush => sys::BZ_FLUSH,
+            Self::Finish => sys::BZ_FINISH,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+///BZIP2 options.
+pub struct Bzip2Options {
+    block_size: u8,
+    work_factor: u8,
+}
+
+impl Bzip2Options {
+    #[inline(always)]
+    ///Creates new default value
+    pub const fn new() -> Self {
+        Self {
+            block_size: 9,
+            work_factor: 0,
+        }
+    }
+
+    #[inline(always)]
+    ///Sets block size in units of 100KB, in range from 1 to 9
+    ///
+    ///Defaults to 9.
+    pub const fn block_size(mut self, block_size: u8) -> Self {
+        assert!(block_size >= 1);
+        assert!(block_size <= 9);
+        self.block_size = block_size;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets work factor in range from 0 to 250, which controls when to fall back to slower sorting
+    ///algorithm on highly repetitive data.
+    ///
+    ///Defaults to 0, which means library default (30).
+    pub const fn work_factor(mut self, work_factor: u8) -> Self {
+        assert!(work_factor <= 250);
+        self.work_factor = work_factor;
+        self
+    }
+}
+
+impl Default for Bzip2Options {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct State {
+    inner: sys::bz_stream,
+    opts: Bzip2Options,
+    //Referenced by stream as `opaque`, hence it must not be moved after being set
+    memory: MemoryTracker,
+}
+
+impl State {
+    #[inline(always)]
+    fn init(&mut self) -> bool {
+        unsafe { sys::BZ2_bzCompressInit(&mut self.inner, self.opts.block_size as _, 0, self.opts.work_factor as _) == sys::BZ_OK }
+    }
+
+    #[inline(always)]
+    fn reset(&mut self) -> bool {
+        //bzip2 has no reset, so stream has to be initialized again
+        unsafe {
+            sys::BZ2_bzCompressEnd(&mut self.inner);
+        }
+        self.init()
+    }
+}
+
+impl Drop for State {
+    #[inline(always)]
+    fn drop(&mut self) {
+        unsafe {
+            sys::BZ2_bzCompressEnd(&mut self.inner);
+        }
+    }
+}
+
+impl Interface {
+    #[inline]
+    ///Creates encoder with `bzip2` interface
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn bzip2(opts: Bzip2Options) -> Option<Encoder<'static>> {
+        create(opts, MemoryTracker::new())
+    }
+
+    #[inline]
+    ///Creates encoder with `bzip2` interface, using `allocator` for all allocations of stream.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn bzip2_with_allocator(opts: Bzip2Options, allocator: impl Allocator + 'static) -> Option<Encoder<'static>> {
+        create(opts, MemoryTracker::with_allocator(allocator))
+    }
+}
+
+fn create(opts: Bzip2Options, memory: MemoryTracker) -> Option<Encoder<'static>> {
+    let mut instance = Box::new(State {
+        inner: sys::bz_stream {
+            next_in: ptr::null_mut(),
+            avail_in: 0,
+            total_in_lo32: 0,
+            total_in_hi32: 0,
+            next_out: ptr::null_mut(),
+            avail_out: 0,
+            total_out_lo32: 0,
+            total_out_hi32: 0,
+            state: ptr::null_mut(),
+            bzalloc: Some(compu_bzalloc),
+            bzfree: Some(compu_bzfree),
+            opaque: ptr::null_mut(),
+        },
+        opts,
+        memory,
+    });
+    instance.inner.opaque = instance.memory.as_opaque();
+
+    if instance.init() {
+        let instance = ptr::NonNull::from(Box::leak(instance)).cast();
+        Some(BZIP2.inner_encoder(instance, [0; 2]))
+    } else {
+        None
+    }
+}
+
+unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize, op: EncodeOp) -> Encode {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+
+    let avail_in = cmp::min(input_remain, c_uint::MAX as usize);
+    let avail_out = cmp::min(output_remain, c_uint::MAX as usize);
+    //Flush and finish can only be requested with whole input
+    let action = if avail_in < input_remain {
+        sys::BZ_RUN
+    } else {
+        op.into_bzip2()
+    };
+
+    state.inner.next_in = input as *mut _;
+    state.inner.avail_in = avail_in as _;
+    state.inner.next_out = output as *mut _;
+    state.inner.avail_out = avail_out as _;
+
+    let result = unsafe {
+        sys::BZ2_bzCompress(&mut state.inner, action)
+    };
+
+    let output_remain = output_remain - (avail_out - state.inner.avail_out as usize);
+    Encode {
+        input_remain: input_remain - (avail_in - state.inner.avail_in as usize),
+        output_remain,
+        status: match result {
+            sys::BZ_STREAM_END => EncodeStatus::Finished,
+            //Flush is complete, when it is back to running state
+            sys::BZ_RUN_OK if action == sys::BZ_FLUSH => EncodeStatus::Continue,
+            sys::BZ_RUN_OK if op == EncodeOp::Process && output_remain != 0 => EncodeStatus::Continue,
+            sys::BZ_RUN_OK | sys::BZ_FLUSH_OK | sys::BZ_FINISH_OK => EncodeStatus::NeedOutput,
+            code => EncodeStatus::Error(encode_error(code)),
+        },
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn encode_error(code: i32) -> EncodeError {
+    let kind = match code {
+        sys::BZ_PARAM_ERROR | sys::BZ_CONFIG_ERROR => EncodeErrorKind::UnsupportedParameter,
+        sys::BZ_MEM_ERROR => EncodeErrorKind::OutOfMemory,
+        sys::BZ_SEQUENCE_ERROR => EncodeErrorKind::InvalidState,
+        _ => EncodeErrorKind::Other,
+    };
+    EncodeError::new(kind, code)
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>, _: [u8; 2]) -> Option<ptr::NonNull<u8>> {
+    let result = unsafe {
+        (*(state.as_ptr() as *mut State)).reset()
+    };
+    if result {
+        Some(state)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    unsafe {
+        drop(Box::from_raw(state.as_ptr() as *mut State));
+    }
+}
+
+#[inline]
+fn describe_error_fn(_: ptr::NonNull<u8>, code: i32) -> Option<&'static str> {
+    crate::utils::bz_error_name(code)
+}
+
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    Some(ptr::NonNull::from(&state.memory))
+}
@@ -0,0 +1,253 @@
+//! `lz4` interface implementation
+
+extern crate alloc;
+
+use lz4_sys as sys;
+
+use alloc::vec::Vec;
+use core::ffi::{c_uint, c_void};
+use core::{ptr, slice};
+
+use super::lz4_common::{self, Lz4Options};
+use super::{Encode, EncodeError, EncodeOp, EncodeStatus, Encoder, Interface};
+use crate::mem::{Allocator, Box, Lz4CustomMem, MemoryTracker};
+
+//Maximum size of frame header
+const HEADER_SIZE_MAX: usize = 19;
+
+static LZ4: Interface = Interface::new(
+    reset_fn,
+    encode_fn,
+    drop_fn,
+    describe_error_fn
+).with_memory_tracker(memory_tracker_fn);
+
+extern "C" {
+    fn LZ4F_createCompressionContext_advanced(mem: Lz4CustomMem, version: c_uint) -> *mut c_void;
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Stage {
+    Init,
+    Started,
+    Finished,
+}
+
+struct State {
+    ctx: sys::LZ4FCompressionContext,
+    opts: Lz4Options,
+    //LZ4F requires output to fit worst case, so compressed data is buffered until written out
+    buffer: Vec<u8>,
+    cursor: usize,
+    stage: Stage,
+    //Referenced by context as `opaque`, hence it must outlive it
+    memory: Box<MemoryTracker>,
+}
+
+impl State {
+    //Appends output of `op` to buffer, reserving `size` bytes for it
+    fn write(&mut self, size: usize, op: impl FnOnce(sys::LZ4FCompressionContext, *mut u8, usize) -> usize) -> Result<(), EncodeError> {
+        if self.buffer.try_reserve(size).is_err() {
+            return Err(lz4_common::encode_error(lz4_common::ERROR_ALLOCATION_FAILED));
+        }
+
+        let spare_capacity = self.buffer.spare_capacity_mut();
+        let result = op(self.ctx, spare_capacity.as_mut_ptr() as _, spare_capacity.len());
+        if unsafe { sys::LZ4F_isError(result) } != 0 {
+            return Err(lz4_common::encode_error(error_code(result)));
+        }
+
+        unsafe {
+            self.buffer.set_len(self.buffer.len() + result);
+        }
+        Ok(())
+    }
+
+    fn encode(&mut self, input: &[u8], op: EncodeOp) -> Result<(), EncodeError> {
+        if self.stage == Stage::Finished {
+            if input.is_empty() && op == EncodeOp::Finish {
+                return Ok(());
+            }
+            return Err(lz4_common::encode_error(lz4_common::ERROR_COMPRESSION_STATE_UNINITIALIZED));
+        }
+
+        let prefs = self.opts.preferences();
+        if self.stage == Stage::Init {
+            self.write(HEADER_SIZE_MAX, |ctx, dst, dst_len| unsafe {
+                sys::LZ4F_compressBegin(ctx, dst, dst_len, &prefs)
+            })?;
+            self.stage = Stage::Started;
+        }
+
+        if !input.is_empty() {
+            let size = unsafe { sys::LZ4F_compressBound(input.len(), &prefs) };
+            self.write(size, |ctx, dst, dst_len| unsafe {
+                sys::LZ4F_compressUpdate(ctx, dst, dst_len, input.as_ptr(), input.len(), ptr::null())
+            })?;
+        }
+
+        match op {
+            EncodeOp::Process => (),
+            EncodeOp::Flush => {
+                let size = unsafe { sys::LZ4F_compressBound(0, &prefs) };
+                self.write(size, |ctx, dst, dst_len| unsafe {
+                    sys::LZ4F_flush(ctx, dst, dst_len, ptr::null())
+                })?;
+            }
+            EncodeOp::Finish => {
+                let size = unsafe { sys::LZ4F_compressBound(0, &prefs) };
+                self.write(size, |ctx, dst, dst_len| unsafe {
+                    sys::LZ4F_compressEnd(ctx, dst, dst_len, ptr::null())
+                })?;
+                self.stage = Stage::Finished;
+            }
+        }
+
+        Ok(())
+    }
+
+    //Writes buffered data into output, returning number of bytes written
+    fn drain(&mut self, output: *mut u8, output_len: usize) -> usize {
+        let pending = &self.buffer[self.cursor..];
+        let size = core::cmp::min(pending.len(), output_len);
+        unsafe {
+            ptr::copy_nonoverlapping(pending.as_ptr(), output, size);
+        }
+        self.cursor += size;
+        if self.cursor == self.buffer.len() {
+            self.buffer.clear();
+            self.cursor = 0;
+        }
+        size
+    }
+}
+
+impl Drop for State {
+    #[inline(always)]
+    fn drop(&mut self) {
+        let result = unsafe {
+            sys::LZ4F_freeCompressionContext(self.ctx)
+        };
+        debug_assert_eq!(unsafe { sys::LZ4F_isError(result) }, 0);
+    }
+}
+
+#[inline(always)]
+fn error_code(result: usize) -> i32 {
+    (result as isize).wrapping_neg() as i32
+}
+
+impl Interface {
+    #[inline]
+    ///Creates encoder with `lz4` interface
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn lz4(opts: Lz4Options) -> Option<Encoder<'static>> {
+        create(opts, MemoryTracker::new())
+    }
+
+    #[inline]
+    ///Creates encoder with `lz4` interface, using `allocator` for all allocations of context.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn lz4_with_allocator(opts: Lz4Options, allocator: impl Allocator + 'static) -> Option<Encoder<'static>> {
+        create(opts, MemoryTracker::with_allocator(allocator))
+    }
+}
+
+fn create(opts: Lz4Options, memory: MemoryTracker) -> Option<Encoder<'static>> {
+    let memory = Box::new(memory);
+    let ctx = unsafe {
+        LZ4F_createCompressionContext_advanced(Lz4CustomMem::new(&memory), sys::LZ4F_VERSION)
+    };
+    if ctx.is_null() {
+        return None;
+    }
+
+    let state = Box::new(State {
+        ctx: sys::LZ4FCompressionContext(ctx),
+        opts,
+        buffer: Vec::new(),
+        cursor: 0,
+        stage: Stage::Init,
+        memory,
+    });
+    let ptr = ptr::NonNull::from(Box::leak(state));
+    Some(LZ4.inner_encoder(ptr.cast(), [0; 2]))
+}
+
+unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize, op: EncodeOp) -> Encode {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    let input = unsafe {
+        slice::from_raw_parts(input, input_remain)
+    };
+
+    //Pending output must be written before accepting more input, so that buffer does not grow
+    let mut written = 0;
+    if state.cursor < state.buffer.len() {
+        written = state.drain(output, output_remain);
+        if state.cursor < state.buffer.len() {
+            return Encode {
+                input_remain,
+                output_remain: output_remain - written,
+                status: EncodeStatus::NeedOutput,
+            };
+        }
+    }
+
+    if let Err(error) = state.encode(input, op) {
+        return Encode {
+            input_remain,
+            output_remain: output_remain - written,
+            status: EncodeStatus::Error(error),
+        };
+    }
+
+    written += state.drain(unsafe { output.add(written) }, output_remain - written);
+    Encode {
+        input_remain: 0,
+        output_remain: output_remain - written,
+        status: if state.cursor < state.buffer.len() {
+            EncodeStatus::NeedOutput
+        } else if state.stage == Stage::Finished {
+            EncodeStatus::Finished
+        } else {
+            EncodeStatus::Continue
+        },
+    }
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>, _: [u8; 2]) -> Option<ptr::NonNull<u8>> {
+    let instance = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    instance.buffer.clear();
+    instance.cursor = 0;
+    //LZ4F_compressBegin always starts new frame
+    instance.stage = Stage::Init;
+    Some(state)
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    let _ = unsafe {
+        Box::from_raw(state.as_ptr() as *mut State)
+    };
+}
+
+#[inline]
+fn describe_error_fn(_: ptr::NonNull<u8>, code: i32) -> Option<&'static str> {
+    let result = unsafe {
+        sys::LZ4F_getErrorName((code as isize).wrapping_neg() as usize)
+    };
+    crate::utils::convert_c_str(result)
+}
+
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    Some(ptr::NonNull::from(&*state.memory))
+}
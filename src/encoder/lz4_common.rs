@@ -0,0 +1,159 @@
+use super::{EncodeError, EncodeErrorKind};
+
+//https://github.com/lz4/lz4/blob/dev/lib/lz4frame.h#L653
+pub(crate) const ERROR_ALLOCATION_FAILED: i32 = 9;
+pub(crate) const ERROR_COMPRESSION_STATE_UNINITIALIZED: i32 = 20;
+
+//https://github.com/lz4/lz4/blob/dev/doc/lz4_Frame_format.md#general-structure-of-lz4-frame-format
+#[cfg(feature = "lz4-rust")]
+pub(crate) const MAGIC: u32 = 0x184D2204;
+
+#[derive(Copy, Clone)]
+#[repr(u8)]
+///Maximum size of LZ4 block
+pub enum Lz4BlockSize {
+    ///64KB
+    Max64KB = 4,
+    ///256KB
+    Max256KB = 5,
+    ///1MB
+    Max1MB = 6,
+    ///4MB
+    Max4MB = 7,
+}
+
+impl Lz4BlockSize {
+    #[inline(always)]
+    ///Returns size in bytes
+    pub const fn size(self) -> usize {
+        1 << (8 + 2 * self as usize)
+    }
+}
+
+#[derive(Copy, Clone)]
+///LZ4 frame options
+pub struct Lz4Options {
+    pub(crate) block_size: Lz4BlockSize,
+    pub(crate) block_linked: bool,
+    pub(crate) content_checksum: bool,
+    pub(crate) level: u8,
+}
+
+impl Lz4Options {
+    #[inline(always)]
+    ///Creates new default value
+    pub const fn new() -> Self {
+        Self {
+            block_size: Lz4BlockSize::Max64KB,
+            block_linked: true,
+            content_checksum: false,
+            level: 0,
+        }
+    }
+
+    #[inline(always)]
+    ///Sets maximum block size.
+    ///
+    ///Default value is 64KB.
+    pub const fn block_size(mut self, block_size: Lz4BlockSize) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets whether blocks can reference data of previous blocks.
+    ///
+    ///Linked blocks improve compression ratio at cost of random access.
+    ///
+    ///Enabled by default.
+    pub const fn block_linked(mut self, block_linked: bool) -> Self {
+        self.block_linked = block_linked;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets whether to append checksum of whole content at the end of frame.
+    ///
+    ///Disabled by default.
+    pub const fn content_checksum(mut self, content_checksum: bool) -> Self {
+        self.content_checksum = content_checksum;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets compression level.
+    ///
+    ///Allowed values are from 0 to 12, where values from 3 enable high compression mode.
+    ///
+    ///Default value is 0 (fast mode).
+    ///Ignored by `lz4-rust` which supports fast mode only.
+    pub const fn level(mut self, level: u8) -> Self {
+        assert!(level <= 12);
+        self.level = level;
+        self
+    }
+
+    #[cfg(feature = "lz4")]
+    pub(crate) fn preferences(&self) -> lz4_sys::LZ4FPreferences {
+        use lz4_sys as sys;
+
+        sys::LZ4FPreferences {
+            frame_info: sys::LZ4FFrameInfo {
+                block_size_id: match self.block_size {
+                    Lz4BlockSize::Max64KB => sys::BlockSize::Max64KB,
+                    Lz4BlockSize::Max256KB => sys::BlockSize::Max256KB,
+                    Lz4BlockSize::Max1MB => sys::BlockSize::Max1MB,
+                    Lz4BlockSize::Max4MB => sys::BlockSize::Max4MB,
+                },
+                block_mode: match self.block_linked {
+                    true => sys::BlockMode::Linked,
+                    false => sys::BlockMode::Independent,
+                },
+                content_checksum_flag: match self.content_checksum {
+                    true => sys::ContentChecksum::ChecksumEnabled,
+                    false => sys::ContentChecksum::NoChecksum,
+                },
+                frame_type: sys::FrameType::Frame,
+                content_size: 0,
+                dict_id: 0,
+                block_checksum_flag: sys::BlockChecksum::NoBlockChecksum,
+            },
+            compression_level: self.level as _,
+            auto_flush: 0,
+            favor_dec_speed: 0,
+            reserved: [0; 3],
+        }
+    }
+}
+
+impl Default for Lz4Options {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cold]
+#[inline(never)]
+///Maps LZ4F error code to [EncodeError](../struct.EncodeError.html)
+pub(crate) fn encode_error(code: i32) -> EncodeError {
+    let kind = match code {
+        //maxBlockSize_invalid, blockMode_invalid, parameter_invalid, compressionLevel_invalid
+        2..=5 => EncodeErrorKind::UnsupportedParameter,
+        ERROR_ALLOCATION_FAILED => EncodeErrorKind::OutOfMemory,
+        ERROR_COMPRESSION_STATE_UNINITIALIZED => EncodeErrorKind::InvalidState,
+        _ => EncodeErrorKind::Other,
+    };
+    EncodeError::new(kind, code)
+}
+
+#[cfg(feature = "lz4-rust")]
+///Returns description for errors that can be produced by `lz4-rust`, matching LZ4F naming
+pub(crate) fn describe_error(code: i32) -> Option<&'static str> {
+    match code {
+        0 => Some("Unspecified error code"),
+        ERROR_ALLOCATION_FAILED => Some("ERROR_allocation_failed"),
+        ERROR_COMPRESSION_STATE_UNINITIALIZED => Some("ERROR_compressionState_uninitialized"),
+        _ => None,
+    }
+}
@@ -0,0 +1,271 @@
+//! `lz4-rust` interface implementation
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::hash::Hasher;
+use core::{cmp, ptr, slice};
+use twox_hash::XxHash32;
+
+use super::lz4_common::{self, Lz4Options};
+use super::{Encode, EncodeError, EncodeOp, EncodeStatus, Encoder, Interface};
+use crate::mem::Box;
+
+//Maximum distance of match, hence linked block needs only this much of previous data
+const WINDOW_SIZE: usize = 64 * 1024;
+const BLOCK_UNCOMPRESSED: u32 = 0x8000_0000;
+
+static LZ4_RUST: Interface = Interface::new(
+    reset_fn,
+    encode_fn,
+    drop_fn,
+    describe_error_fn
+);
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Stage {
+    Init,
+    Started,
+    Finished,
+}
+
+struct State {
+    opts: Lz4Options,
+    //Input accumulated until block is full
+    block: Vec<u8>,
+    //Previous input, referenced by linked blocks
+    window: Vec<u8>,
+    hasher: XxHash32,
+    //Compressed data pending to be written into output
+    buffer: Vec<u8>,
+    cursor: usize,
+    stage: Stage,
+}
+
+impl State {
+    fn new(opts: Lz4Options) -> Self {
+        Self {
+            opts,
+            block: Vec::new(),
+            window: Vec::new(),
+            hasher: XxHash32::with_seed(0),
+            buffer: Vec::new(),
+            cursor: 0,
+            stage: Stage::Init,
+        }
+    }
+
+    #[inline(always)]
+    fn reserve(&mut self, size: usize) -> Result<(), EncodeError> {
+        match self.buffer.try_reserve(size) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(lz4_common::encode_error(lz4_common::ERROR_ALLOCATION_FAILED)),
+        }
+    }
+
+    fn write_header(&mut self) -> Result<(), EncodeError> {
+        //Version 01
+        let mut flags = 0b0100_0000;
+        if !self.opts.block_linked {
+            flags |= 0b0010_0000;
+        }
+        if self.opts.content_checksum {
+            flags |= 0b0000_0100;
+        }
+        let descriptor = [flags, (self.opts.block_size as u8) << 4];
+        let checksum = (XxHash32::oneshot(0, &descriptor) >> 8) as u8;
+
+        self.reserve(7)?;
+        self.buffer.extend_from_slice(&lz4_common::MAGIC.to_le_bytes());
+        self.buffer.extend_from_slice(&descriptor);
+        self.buffer.push(checksum);
+        Ok(())
+    }
+
+    fn write_block(&mut self) -> Result<(), EncodeError> {
+        if self.block.is_empty() {
+            return Ok(());
+        }
+
+        let bound = lz4_flex::block::get_maximum_output_size(self.block.len());
+        self.reserve(4 + cmp::max(bound, self.block.len()))?;
+
+        let start = self.buffer.len();
+        self.buffer.resize(start + 4 + bound, 0);
+        let compressed = &mut self.buffer[start + 4..];
+        let result = match self.opts.block_linked {
+            true => lz4_flex::block::compress_into_with_dict(&self.block, compressed, &self.window),
+            false => lz4_flex::block::compress_into(&self.block, compressed),
+        };
+
+        match result {
+            Ok(size) if size < self.block.len() => {
+                self.buffer[start..start + 4].copy_from_slice(&(size as u32).to_le_bytes());
+                self.buffer.truncate(start + 4 + size);
+            }
+            //Incompressible data is stored as it is
+            _ => {
+                self.buffer.truncate(start);
+                self.buffer.extend_from_slice(&(self.block.len() as u32 | BLOCK_UNCOMPRESSED).to_le_bytes());
+                self.buffer.extend_from_slice(&self.block);
+            }
+        }
+
+        if self.opts.content_checksum {
+            self.hasher.write(&self.block);
+        }
+        if self.opts.block_linked {
+            let keep = cmp::min(self.window.len(), WINDOW_SIZE.saturating_sub(self.block.len()));
+            self.window.drain(..self.window.len() - keep);
+            let block_start = self.block.len() - cmp::min(self.block.len(), WINDOW_SIZE);
+            self.window.extend_from_slice(&self.block[block_start..]);
+        }
+        self.block.clear();
+        Ok(())
+    }
+
+    fn write_end(&mut self) -> Result<(), EncodeError> {
+        self.reserve(8)?;
+        self.buffer.extend_from_slice(&0u32.to_le_bytes());
+        if self.opts.content_checksum {
+            self.buffer.extend_from_slice(&self.hasher.finish_32().to_le_bytes());
+        }
+        Ok(())
+    }
+
+    fn encode(&mut self, mut input: &[u8], op: EncodeOp) -> Result<(), EncodeError> {
+        if self.stage == Stage::Finished {
+            if input.is_empty() && op == EncodeOp::Finish {
+                return Ok(());
+            }
+            return Err(lz4_common::encode_error(lz4_common::ERROR_COMPRESSION_STATE_UNINITIALIZED));
+        }
+
+        if self.stage == Stage::Init {
+            self.write_header()?;
+            self.stage = Stage::Started;
+        }
+
+        let block_size = self.opts.block_size.size();
+        while !input.is_empty() {
+            if self.block.capacity() < block_size && self.block.try_reserve_exact(block_size - self.block.len()).is_err() {
+                return Err(lz4_common::encode_error(lz4_common::ERROR_ALLOCATION_FAILED));
+            }
+            let size = cmp::min(block_size - self.block.len(), input.len());
+            self.block.extend_from_slice(&input[..size]);
+            input = &input[size..];
+
+            if self.block.len() == block_size {
+                self.write_block()?;
+            }
+        }
+
+        match op {
+            EncodeOp::Process => (),
+            EncodeOp::Flush => self.write_block()?,
+            EncodeOp::Finish => {
+                self.write_block()?;
+                self.write_end()?;
+                self.stage = Stage::Finished;
+            }
+        }
+
+        Ok(())
+    }
+
+    //Writes buffered data into output, returning number of bytes written
+    fn drain(&mut self, output: *mut u8, output_len: usize) -> usize {
+        let pending = &self.buffer[self.cursor..];
+        let size = cmp::min(pending.len(), output_len);
+        unsafe {
+            ptr::copy_nonoverlapping(pending.as_ptr(), output, size);
+        }
+        self.cursor += size;
+        if self.cursor == self.buffer.len() {
+            self.buffer.clear();
+            self.cursor = 0;
+        }
+        size
+    }
+}
+
+impl Interface {
+    #[inline]
+    ///Creates encoder with `lz4-rust` interface
+    ///
+    ///Never returns `None` (probably panics on OOM)
+    pub fn lz4_rust(opts: Lz4Options) -> Encoder<'static> {
+        let state = Box::new(State::new(opts));
+        let ptr = ptr::NonNull::from(Box::leak(state));
+        LZ4_RUST.inner_encoder(ptr.cast(), [0; 2])
+    }
+}
+
+unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize, op: EncodeOp) -> Encode {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    let input = unsafe {
+        slice::from_raw_parts(input, input_remain)
+    };
+
+    //Pending output must be written before accepting more input, so that buffer does not grow
+    let mut written = 0;
+    if state.cursor < state.buffer.len() {
+        written = state.drain(output, output_remain);
+        if state.cursor < state.buffer.len() {
+            return Encode {
+                input_remain,
+                output_remain: output_remain - written,
+                status: EncodeStatus::NeedOutput,
+            };
+        }
+    }
+
+    if let Err(error) = state.encode(input, op) {
+        return Encode {
+            input_remain,
+            output_remain: output_remain - written,
+            status: EncodeStatus::Error(error),
+        };
+    }
+
+    written += state.drain(unsafe { output.add(written) }, output_remain - written);
+    Encode {
+        input_remain: 0,
+        output_remain: output_remain - written,
+        status: if state.cursor < state.buffer.len() {
+            EncodeStatus::NeedOutput
+        } else if state.stage == Stage::Finished {
+            EncodeStatus::Finished
+        } else {
+            EncodeStatus::Continue
+        },
+    }
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>, _: [u8; 2]) -> Option<ptr::NonNull<u8>> {
+    let instance = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    instance.block.clear();
+    instance.window.clear();
+    instance.hasher = XxHash32::with_seed(0);
+    instance.buffer.clear();
+    instance.cursor = 0;
+    instance.stage = Stage::Init;
+    Some(state)
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    let _ = unsafe {
+        Box::from_raw(state.as_ptr() as *mut State)
+    };
+}
+
+#[inline]
+fn describe_error_fn(_: ptr::NonNull<u8>, code: i32) -> Option<&'static str> {
+    lz4_common::describe_error(code)
+}
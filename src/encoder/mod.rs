@@ -1,12 +1,18 @@
 //! Encoder
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
-use core::{mem, ptr};
+use core::{fmt, mem, ptr};
+use core::marker::PhantomData;
 
+#[cfg(feature = "alloc")]
 use alloc::collections::TryReserveError;
+#[cfg(feature = "alloc")]
 use alloc::vec::Vec;
 
+use crate::mem::{MemoryTracker, MemoryUsage};
+
 #[derive(Copy, Clone, PartialEq)]
 ///Encoder operation
 pub enum EncodeOp {
@@ -22,7 +28,82 @@ pub enum EncodeOp {
     Finish,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+///Backend independent category of [EncodeError]
+pub enum EncodeErrorKind {
+    ///Encoder rejected parameter or combination of parameters
+    UnsupportedParameter,
+    ///Failed to allocate memory
+    OutOfMemory,
+    ///Encoder is used incorrectly (e.g. new input after `EncodeOp::Finish`)
+    InvalidState,
+    ///Any other error
+    Other,
+}
+
+impl EncodeErrorKind {
+    #[inline]
+    ///Returns generic description of error kind
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::UnsupportedParameter => "unsupported parameter",
+            Self::OutOfMemory => "out of memory",
+            Self::InvalidState => "invalid state",
+            Self::Other => "compression failed",
+        }
+    }
+}
+
+impl fmt::Display for EncodeErrorKind {
+    #[inline(always)]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Encoding error
+///
+///Consists of backend specific raw code and its [EncodeErrorKind]
+pub struct EncodeError {
+    code: i32,
+    kind: EncodeErrorKind,
+}
+
+impl EncodeError {
+    #[inline(always)]
+    ///Creates new error from its kind and backend specific raw code
+    pub const fn new(kind: EncodeErrorKind, code: i32) -> Self {
+        Self {
+            code,
+            kind,
+        }
+    }
+
+    #[inline(always)]
+    ///Returns raw integer
+    pub const fn as_raw(&self) -> i32 {
+        self.code
+    }
+
+    #[inline(always)]
+    ///Returns kind of error
+    pub const fn kind(&self) -> EncodeErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for EncodeError {
+    #[inline(always)]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_fmt(format_args!("{} (code {})", self.kind, self.code))
+    }
+}
+
+impl core::error::Error for EncodeError {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 ///Encode status
 pub enum EncodeStatus {
     ///Encoded, carry on.
@@ -34,7 +115,9 @@ pub enum EncodeStatus {
     ///Result after `EncoderOp::Finish` issued
     Finished,
     ///Failed to encode.
-    Error,
+    ///
+    ///Use [Encoder::describe_error] to get backend specific description.
+    Error(EncodeError),
 }
 
 #[derive(Debug)]
@@ -54,6 +137,19 @@ pub struct Interface {
     reset_fn: fn(ptr::NonNull<u8>, opts: [u8; 2]) -> Option<ptr::NonNull<u8>>,
     encode_fn: unsafe fn(ptr::NonNull<u8>, *const u8, usize, *mut u8, usize, EncodeOp) -> Encode,
     drop_fn: fn(ptr::NonNull<u8>),
+    describe_error_fn: fn(ptr::NonNull<u8>, i32) -> Option<&'static str>,
+    set_pledged_size_fn: fn(ptr::NonNull<u8>, u64) -> bool,
+    memory_tracker_fn: fn(ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>>,
+}
+
+//Used by backends that have no use for pledged size
+pub(crate) fn ignore_pledged_size(_: ptr::NonNull<u8>, _: u64) -> bool {
+    true
+}
+
+//Used by backends that do not allocate via crate allocator
+pub(crate) fn no_memory_tracker(_: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    None
 }
 
 impl Interface {
@@ -62,20 +158,41 @@ impl Interface {
     ///First argument of every function is state as pointer.
     ///
     ///It is user responsibility to pass correct function pointers
-    pub const fn new(reset_fn: fn(ptr::NonNull<u8>, opts: [u8; 2]) -> Option<ptr::NonNull<u8>>, encode_fn: unsafe fn(ptr::NonNull<u8>, *const u8, usize, *mut u8, usize, EncodeOp) -> Encode, drop_fn: fn(ptr::NonNull<u8>)) -> Self {
+    pub const fn new(reset_fn: fn(ptr::NonNull<u8>, opts: [u8; 2]) -> Option<ptr::NonNull<u8>>, encode_fn: unsafe fn(ptr::NonNull<u8>, *const u8, usize, *mut u8, usize, EncodeOp) -> Encode, drop_fn: fn(ptr::NonNull<u8>), describe_error_fn: fn(ptr::NonNull<u8>, i32) -> Option<&'static str>) -> Self {
         Self {
             reset_fn,
             encode_fn,
             drop_fn,
+            describe_error_fn,
+            set_pledged_size_fn: ignore_pledged_size,
+            memory_tracker_fn: no_memory_tracker,
         }
     }
 
+    ///Sets function to handle [Encoder::set_pledged_size].
+    ///
+    ///By default pledged size is ignored.
+    pub const fn with_pledged_size(mut self, set_pledged_size_fn: fn(ptr::NonNull<u8>, u64) -> bool) -> Self {
+        self.set_pledged_size_fn = set_pledged_size_fn;
+        self
+    }
+
+    ///Sets function to access [MemoryTracker] used by encoder's allocations.
+    ///
+    ///By default memory usage is not tracked.
+    pub const fn with_memory_tracker(mut self, memory_tracker_fn: fn(ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>>) -> Self {
+        self.memory_tracker_fn = memory_tracker_fn;
+        self
+    }
+
     #[inline(always)]
-    pub(crate) fn inner_encoder(&'static self, instance: ptr::NonNull<u8>, opts: [u8; 2]) -> Encoder {
+    pub(crate) fn inner_encoder<'a>(&'static self, instance: ptr::NonNull<u8>, opts: [u8; 2]) -> Encoder<'a> {
         Encoder {
             instance,
             interface: self,
             opts,
+            is_started: false,
+            _lifetime: PhantomData,
         }
     }
 
@@ -86,7 +203,9 @@ impl Interface {
     ///
     ///`instance` - Encoder state, passed as first argument to every function in vtable
     ///`opts` - is optional payload for purpose of initialization in `reset_fn`
-    pub unsafe fn encoder(&'static self, state: ptr::NonNull<u8>, opts: [u8; 2]) -> Encoder {
+    ///
+    ///Encoder must not outlive `'a`, which can be used to bind state stored in borrowed memory.
+    pub unsafe fn encoder<'a>(&'static self, state: ptr::NonNull<u8>, opts: [u8; 2]) -> Encoder<'a> {
         self.inner_encoder(state, opts)
     }
 }
@@ -111,7 +230,9 @@ impl Interface {
 ///     let result = encoder.encode_uninit(chunk, spare_capacity, EncodeOp::Flush);
 ///
 ///     assert_eq!(result.input_remain, 0);
-///     assert_ne!(result.status, EncodeStatus::Error);
+///     if let EncodeStatus::Error(error) = result.status {
+///         panic!("failed to encode: {error} ({:?})", encoder.describe_error(error));
+///     }
 ///     assert_eq!(result.status, EncodeStatus::Continue);
 ///     unsafe {
 ///         output.set_len(output.len() + output_len - result.output_remain);
@@ -145,17 +266,20 @@ impl Interface {
 ///compress(&mut encoder, &[&[1, 2, 3, 4], &[5, 6, 7 ,8], &[9, 10]], &mut output);
 ///assert!(output.len() > 0);
 ///```
-pub struct Encoder {
+pub struct Encoder<'a> {
     instance: ptr::NonNull<u8>,
     interface: &'static Interface,
     opts: [u8; 2],
+    //Set once backend is invoked, until reset
+    is_started: bool,
+    _lifetime: PhantomData<&'a mut [mem::MaybeUninit<u8>]>,
 }
 
 const _: () = {
-    assert!(mem::size_of::<Encoder>() == mem::size_of::<usize>() * 3);
+    assert!(mem::size_of::<Encoder<'static>>() == mem::size_of::<usize>() * 3);
 };
 
-impl Encoder {
+impl Encoder<'_> {
     #[inline(always)]
     ///Raw encoding function, with no checks.
     ///
@@ -169,6 +293,7 @@ impl Encoder {
     ///- `output_len` - Size of buffer pointed by `output`
     ///- `op` - Encoding operation to perform.
     pub unsafe fn raw_encode(&mut self, input: *const u8, input_len: usize, output: *mut u8, output_len: usize, op: EncodeOp) -> Encode {
+        self.is_started = true;
         (self.interface.encode_fn)(self.instance, input, input_len, output, output_len, op)
     }
 
@@ -194,6 +319,7 @@ impl Encoder {
         }
     }
 
+    #[cfg(feature = "alloc")]
     #[inline(always)]
     ///Encodes `input` into spare space in `output`.
     ///
@@ -212,6 +338,7 @@ impl Encoder {
         result
     }
 
+    #[cfg(feature = "alloc")]
     #[inline(always)]
     ///Encodes `input` into `output` Vec, performing allocation when necessary
     ///
@@ -232,12 +359,23 @@ impl Encoder {
     ///
     ///Note that the best strategy is always to re-use buffer
     ///
+    ///## Pledged size
+    ///
+    ///If `op` is `EncodeOp::Finish` and encoding has not started yet, then `input` is whole
+    ///content, hence its size is pledged via [set_pledged_size](#method.set_pledged_size).
+    ///If backend rejects pledge, `input` is encoded without it.
+    ///
     ///## Result
     ///
     ///- `Encode::output_remain` will be relatieve to spare capacity of the `output`.
     pub fn encode_vec_full(&mut self, mut input: &[u8], output: &mut Vec<u8>, op: EncodeOp) -> Result<Encode, TryReserveError> {
         const RESERVE_DEFAULT: usize = 1024;
         let input_len = input.len();
+        if op == EncodeOp::Finish && !self.is_started {
+            //Pledge is only a hint, so input is encoded without it if backend rejects it
+            let _is_pledged = self.set_pledged_size(input_len as u64);
+        }
+
         let reserve_size = if input_len < RESERVE_DEFAULT {
             output.try_reserve_exact(input_len)?;
             input_len / 3
@@ -297,7 +435,7 @@ impl Encoder {
             result.status = encode.status;
 
             match result.status {
-                EncodeStatus::Error | EncodeStatus::Finished | EncodeStatus::Continue => break result,
+                EncodeStatus::Error(_) | EncodeStatus::Finished | EncodeStatus::Continue => break result,
                 EncodeStatus::NeedOutput => {
                     if result.output_remain == 0 {
                         break result;
@@ -307,6 +445,47 @@ impl Encoder {
         }
     }
 
+    #[inline(always)]
+    ///Sets total size of input to be encoded in current frame.
+    ///
+    ///Backend may use it to write content size into header and to choose smaller internal tables.
+    ///It must be set before encoding starts and lasts until [reset](#method.reset).
+    ///Encoding different amount of data than pledged may result in error.
+    ///
+    ///Returns `false` if encoding already started or size is rejected by backend.
+    ///Backends that have no use for it (e.g. `zlib`) ignore it, returning `true`.
+    pub fn set_pledged_size(&mut self, size: u64) -> bool {
+        !self.is_started && (self.interface.set_pledged_size_fn)(self.instance, size)
+    }
+
+    #[inline(always)]
+    ///Returns memory allocated by backend.
+    ///
+    ///Available only for backends allocating via crate allocator (i.e. C libraries and `zlib-rust`),
+    ///returning `None` otherwise.
+    ///
+    ///Note that `brotli-c` is not tracked, as it terminates process on allocation failure.
+    pub fn memory_usage(&self) -> Option<MemoryUsage> {
+        (self.interface.memory_tracker_fn)(self.instance).map(|tracker| unsafe { tracker.as_ref() }.usage())
+    }
+
+    #[inline(always)]
+    ///Sets maximum number of bytes backend can allocate at once.
+    ///
+    ///Allocations above budget fail, which results in error of kind [OutOfMemory](enum.EncodeErrorKind.html#variant.OutOfMemory).
+    ///Memory allocated already, including on creation, is counted, but not released.
+    ///
+    ///Returns `false` if backend's memory usage is not tracked (see [memory_usage](#method.memory_usage)).
+    pub fn set_memory_budget(&mut self, budget: Option<usize>) -> bool {
+        match (self.interface.memory_tracker_fn)(self.instance) {
+            Some(tracker) => {
+                unsafe { tracker.as_ref() }.set_budget(budget);
+                true
+            }
+            None => false,
+        }
+    }
+
     #[inline(always)]
     ///Resets `Encoder` state to initial.
     ///
@@ -315,14 +494,21 @@ impl Encoder {
         match (self.interface.reset_fn)(self.instance, self.opts) {
             Some(ptr) => {
                 self.instance = ptr;
+                self.is_started = false;
                 true
             }
             None => false,
         }
     }
+
+    #[inline(always)]
+    ///Returns descriptive text for error.
+    pub fn describe_error(&self, error: EncodeError) -> Option<&'static str> {
+        (self.interface.describe_error_fn)(self.instance, error.as_raw())
+    }
 }
 
-impl Drop for Encoder {
+impl Drop for Encoder<'_> {
     #[inline]
     fn drop(&mut self) {
         (self.interface.drop_fn)(self.instance);
@@ -363,7 +549,7 @@ macro_rules! internal_zlib_impl_encode {
                     $crate::encoder::EncodeStatus::Continue
                 },
                 sys::Z_BUF_ERROR => $crate::encoder::EncodeStatus::NeedOutput,
-                _ => $crate::encoder::EncodeStatus::Error,
+                other => $crate::encoder::EncodeStatus::Error($crate::encoder::zlib_common::encode_error(other as _)),
             }
         }
     }}
@@ -375,8 +561,12 @@ mod brotli_common;
 pub use brotli_common::{BrotliEncoderMode, BrotliOptions};
 #[cfg(feature = "brotli")]
 mod brotli;
+#[cfg(feature = "brotli")]
+mod brotli_static;
 #[cfg(feature = "brotli-c")]
 mod brotli_c;
+#[cfg(feature = "brotli-c")]
+pub use brotli_c::BrotliDictionary;
 #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rs"))]
 mod zlib_common;
 #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rs"))]
@@ -387,10 +577,16 @@ mod zlib;
 mod zlib_ng;
 #[cfg(feature = "zlib-rust")]
 mod zlib_rust;
+#[cfg(feature = "zlib-rust")]
+mod zlib_rust_static;
 #[cfg(feature = "zstd")]
 mod zstd;
 #[cfg(feature = "zstd")]
+mod zstd_static;
+#[cfg(feature = "zstd")]
 pub use zstd::{ZstdOptions, ZstdStrategy};
+#[cfg(all(feature = "zstd", feature = "alloc"))]
+pub use zstd::ZstdCDict;
 
 impl<const N: usize> crate::Buffer<N> {
     ///Decodes `input` using `decoder` returning number of bytes consumed in `input`
@@ -400,7 +596,7 @@ impl<const N: usize> crate::Buffer<N> {
     ///- Decode status:
     ///    - In case of `Finished` or `Error`, you should not continue to invoke decode until you reset decoder
     ///    - In case of `NeedOutput`, you should consume internal buffer.
-    pub fn encode(&mut self, encoder: &mut Encoder, input: &[u8], op: EncodeOp) -> (usize, EncodeStatus) {
+    pub fn encode(&mut self, encoder: &mut Encoder<'_>, input: &[u8], op: EncodeOp) -> (usize, EncodeStatus) {
         let spare_capacity = self.spare_capacity_mut();
         let spare_capacity_len = spare_capacity.len();
 
@@ -410,3 +606,21 @@ impl<const N: usize> crate::Buffer<N> {
         (input.len() - result.input_remain, result.status)
     }
 }
+#[cfg(any(feature = "lz4", feature = "lz4-rust"))]
+mod lz4_common;
+#[cfg(any(feature = "lz4", feature = "lz4-rust"))]
+pub use lz4_common::{Lz4BlockSize, Lz4Options};
+#[cfg(feature = "lz4")]
+mod lz4;
+#[cfg(feature = "lz4-rust")]
+mod lz4_rust;
+#[cfg(feature = "xz")]
+mod xz;
+#[cfg(feature = "xz")]
+pub use xz::{XzCheck, XzOptions};
+#[cfg(feature = "bzip2")]
+mod bzip2;
+#[cfg(feature = "bzip2")]
+pub use bzip2::Bzip2Options;
+#[cfg(feature = "snappy")]
+mod snappy;
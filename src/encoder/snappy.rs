@@ -0,0 +1,335 @@
+//! `snappy` interface implementation
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::{cmp, ptr, slice};
+
+use super::{Encode, EncodeError, EncodeErrorKind, EncodeOp, EncodeStatus, Encoder, Interface};
+use crate::mem::Box;
+use crate::snappy::*;
+
+//Inputs smaller than this are not worth searching for matches
+const MIN_COMPRESS_SIZE: usize = 16;
+const HASH_LOG: u32 = 14;
+
+static SNAPPY: Interface = Interface::new(
+    reset_fn,
+    encode_fn,
+    drop_fn,
+    describe_error_fn
+);
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Stage {
+    Init,
+    Started,
+    Finished,
+}
+
+#[inline(always)]
+fn read_u32(bytes: &[u8], idx: usize) -> u32 {
+    u32::from_le_bytes([bytes[idx], bytes[idx + 1], bytes[idx + 2], bytes[idx + 3]])
+}
+
+#[inline(always)]
+const fn hash(value: u32) -> usize {
+    (value.wrapping_mul(0x1E35A7BD) >> (32 - HASH_LOG)) as usize
+}
+
+#[inline(always)]
+const fn max_compressed_size(size: usize) -> usize {
+    32 + size + size / 6
+}
+
+fn emit_literal(output: &mut Vec<u8>, literal: &[u8]) {
+    let size = literal.len() - 1;
+    if size < 60 {
+        output.push((size as u8) << 2);
+    } else if size < 1 << 8 {
+        output.push(60 << 2);
+        output.push(size as u8);
+    } else if size < 1 << 16 {
+        output.push(61 << 2);
+        output.extend_from_slice(&(size as u16).to_le_bytes());
+    } else {
+        output.push(62 << 2);
+        output.extend_from_slice(&(size as u32).to_le_bytes()[..3]);
+    }
+    output.extend_from_slice(literal);
+}
+
+//Emits copy of at most 64 bytes
+fn emit_copy_upto64(output: &mut Vec<u8>, offset: usize, size: usize) {
+    if size < 12 && offset < 2048 {
+        output.push(0b01 | ((size - 4) << 2) as u8 | ((offset >> 8) << 5) as u8);
+        output.push(offset as u8);
+    } else {
+        output.push(0b10 | ((size - 1) << 2) as u8);
+        output.extend_from_slice(&(offset as u16).to_le_bytes());
+    }
+}
+
+fn emit_copy(output: &mut Vec<u8>, offset: usize, mut size: usize) {
+    //Split so that remainder is never below minimum of 4 bytes
+    while size >= 68 {
+        emit_copy_upto64(output, offset, 64);
+        size -= 64;
+    }
+    if size > 64 {
+        emit_copy_upto64(output, offset, 60);
+        size -= 60;
+    }
+    emit_copy_upto64(output, offset, size);
+}
+
+///Compresses `input` of at most `MAX_BLOCK_SIZE` into raw snappy format
+fn compress(input: &[u8], table: &mut [u16], output: &mut Vec<u8>) {
+    //Uncompressed length as varint
+    let mut size = input.len();
+    while size >= 0x80 {
+        output.push(size as u8 | 0x80);
+        size >>= 7;
+    }
+    output.push(size as u8);
+
+    if input.is_empty() {
+        return;
+    } else if input.len() < MIN_COMPRESS_SIZE {
+        emit_literal(output, input);
+        return;
+    }
+
+    for entry in table.iter_mut() {
+        *entry = 0;
+    }
+
+    let mut literal_start = 0;
+    let mut idx = 1;
+    let limit = input.len() - 4;
+    while idx <= limit {
+        let value = read_u32(input, idx);
+        let entry = &mut table[hash(value)];
+        let candidate = *entry as usize;
+        *entry = idx as u16;
+
+        if candidate >= idx || read_u32(input, candidate) != value {
+            //Skip faster through incompressible data
+            idx += 1 + ((idx - literal_start) >> 5);
+            continue;
+        }
+
+        if literal_start < idx {
+            emit_literal(output, &input[literal_start..idx]);
+        }
+
+        let mut size = 4;
+        while idx + size < input.len() && input[candidate + size] == input[idx + size] {
+            size += 1;
+        }
+        emit_copy(output, idx - candidate, size);
+
+        idx += size;
+        literal_start = idx;
+        if idx <= limit {
+            table[hash(read_u32(input, idx - 1))] = (idx - 1) as u16;
+        }
+    }
+
+    if literal_start < input.len() {
+        emit_literal(output, &input[literal_start..]);
+    }
+}
+
+struct State {
+    //Input accumulated until block is full
+    block: Vec<u8>,
+    table: Vec<u16>,
+    //Compressed data pending to be written into output
+    buffer: Vec<u8>,
+    cursor: usize,
+    stage: Stage,
+}
+
+impl State {
+    fn new() -> Self {
+        Self {
+            block: Vec::new(),
+            table: Vec::new(),
+            buffer: Vec::new(),
+            cursor: 0,
+            stage: Stage::Init,
+        }
+    }
+
+    #[inline(always)]
+    fn reserve(&mut self, size: usize) -> Result<(), EncodeError> {
+        match self.buffer.try_reserve(size) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(encode_error(ERROR_ALLOCATION_FAILED)),
+        }
+    }
+
+    fn write_chunk(&mut self) -> Result<(), EncodeError> {
+        if self.block.is_empty() {
+            return Ok(());
+        }
+
+        if self.table.is_empty() {
+            if self.table.try_reserve_exact(1 << HASH_LOG).is_err() {
+                return Err(encode_error(ERROR_ALLOCATION_FAILED));
+            }
+            self.table.resize(1 << HASH_LOG, 0);
+        }
+        self.reserve(CHUNK_HEADER_SIZE + CHECKSUM_SIZE + max_compressed_size(self.block.len()))?;
+
+        let start = self.buffer.len();
+        self.buffer.extend_from_slice(&[CHUNK_COMPRESSED, 0, 0, 0]);
+        self.buffer.extend_from_slice(&checksum(&self.block).to_le_bytes());
+        let data_start = self.buffer.len();
+        compress(&self.block, &mut self.table, &mut self.buffer);
+
+        //Store data as it is, unless compression saves at least 12.5%
+        if self.buffer.len() - data_start >= self.block.len() - self.block.len() / 8 {
+            self.buffer.truncate(data_start);
+            self.buffer.extend_from_slice(&self.block);
+            self.buffer[start] = CHUNK_UNCOMPRESSED;
+        }
+
+        let size = (self.buffer.len() - start - CHUNK_HEADER_SIZE) as u32;
+        self.buffer[start + 1..start + CHUNK_HEADER_SIZE].copy_from_slice(&size.to_le_bytes()[..3]);
+        self.block.clear();
+        Ok(())
+    }
+
+    fn encode(&mut self, mut input: &[u8], op: EncodeOp) -> Result<(), EncodeError> {
+        if self.stage == Stage::Finished {
+            if input.is_empty() && op == EncodeOp::Finish {
+                return Ok(());
+            }
+            return Err(encode_error(ERROR_STREAM_FINISHED));
+        }
+
+        if self.stage == Stage::Init {
+            self.reserve(STREAM_IDENTIFIER.len())?;
+            self.buffer.extend_from_slice(&STREAM_IDENTIFIER);
+            self.stage = Stage::Started;
+        }
+
+        while !input.is_empty() {
+            if self.block.capacity() < MAX_BLOCK_SIZE && self.block.try_reserve_exact(MAX_BLOCK_SIZE - self.block.len()).is_err() {
+                return Err(encode_error(ERROR_ALLOCATION_FAILED));
+            }
+            let size = cmp::min(MAX_BLOCK_SIZE - self.block.len(), input.len());
+            self.block.extend_from_slice(&input[..size]);
+            input = &input[size..];
+
+            if self.block.len() == MAX_BLOCK_SIZE {
+                self.write_chunk()?;
+            }
+        }
+
+        match op {
+            EncodeOp::Process => (),
+            EncodeOp::Flush => self.write_chunk()?,
+            EncodeOp::Finish => {
+                self.write_chunk()?;
+                self.stage = Stage::Finished;
+            }
+        }
+
+        Ok(())
+    }
+
+    //Writes buffered data into output, returning number of bytes written
+    fn drain(&mut self, output: *mut u8, output_len: usize) -> usize {
+        let pending = &self.buffer[self.cursor..];
+        let size = cmp::min(pending.len(), output_len);
+        unsafe {
+            ptr::copy_nonoverlapping(pending.as_ptr(), output, size);
+        }
+        self.cursor += size;
+        if self.cursor == self.buffer.len() {
+            self.buffer.clear();
+            self.cursor = 0;
+        }
+        size
+    }
+}
+
+impl Interface {
+    #[inline]
+    ///Creates encoder with `snappy` framing format interface
+    ///
+    ///Never returns `None` (probably panics on OOM)
+    pub fn snappy() -> Encoder<'static> {
+        let state = Box::new(State::new());
+        let ptr = ptr::NonNull::from(Box::leak(state));
+        SNAPPY.inner_encoder(ptr.cast(), [0; 2])
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn encode_error(code: i32) -> EncodeError {
+    let kind = match code {
+        ERROR_ALLOCATION_FAILED => EncodeErrorKind::OutOfMemory,
+        ERROR_STREAM_FINISHED => EncodeErrorKind::InvalidState,
+        _ => EncodeErrorKind::Other,
+    };
+    EncodeError::new(kind, code)
+}
+
+unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize, op: EncodeOp) -> Encode {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    let input = unsafe {
+        slice::from_raw_parts(input, input_remain)
+    };
+
+    if let Err(error) = state.encode(input, op) {
+        return Encode {
+            input_remain,
+            output_remain,
+            status: EncodeStatus::Error(error),
+        };
+    }
+
+    let written = state.drain(output, output_remain);
+    Encode {
+        input_remain: 0,
+        output_remain: output_remain - written,
+        status: if state.cursor < state.buffer.len() {
+            EncodeStatus::NeedOutput
+        } else if state.stage == Stage::Finished {
+            EncodeStatus::Finished
+        } else {
+            EncodeStatus::Continue
+        },
+    }
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>, _: [u8; 2]) -> Option<ptr::NonNull<u8>> {
+    let instance = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    instance.block.clear();
+    instance.buffer.clear();
+    instance.cursor = 0;
+    instance.stage = Stage::Init;
+    Some(state)
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    let _ = unsafe {
+        Box::from_raw(state.as_ptr() as *mut State)
+    };
+}
+
+#[inline]
+fn describe_error_fn(_: ptr::NonNull<u8>, code: i32) -> Option<&'static str> {
+    describe_error(code)
+}
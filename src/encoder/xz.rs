@@ -0,0 +1,305 @@
+//! `xz` interface implementation
+
+extern crate alloc;
+
+use lzma_sys as sys;
+
+use alloc::boxed::Box;
+use core::{mem, ptr};
+
+use super::{Encode, EncodeError, EncodeErrorKind, EncodeOp, EncodeStatus, Encoder, Interface};
+use crate::mem::{lzma_allocator, Allocator, MemoryTracker};
+
+static XZ: Interface = Interface {
+    drop_fn,
+    reset_fn,
+    encode_fn,
+    describe_error_fn,
+    set_pledged_size_fn: super::ignore_pledged_size,
+    memory_tracker_fn,
+};
+
+impl EncodeOp {
+    #[inline(always)]
+    const fn into_lzma(self) -> sys::lzma_action {
+        match self {
+            Self::Process => sys::LZMA_RUN,
+            Self::Flush => sys::LZMA_SYNC_FLUSH,
+            Self::Finish => sys::LZMA_FINISH,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+///Integrity check stored in `xz` stream
+pub enum XzCheck {
+    ///No check
+    None,
+    ///CRC32
+    Crc32,
+    ///CRC64
+    ///
+    ///Default.
+    Crc64,
+    ///SHA-256
+    Sha256,
+}
+
+impl XzCheck {
+    #[inline(always)]
+    const fn into_lzma(self) -> sys::lzma_check {
+        match self {
+            Self::None => sys::LZMA_CHECK_NONE,
+            Self::Crc32 => sys::LZMA_CHECK_CRC32,
+            Self::Crc64 => sys::LZMA_CHECK_CRC64,
+            Self::Sha256 => sys::LZMA_CHECK_SHA256,
+        }
+    }
+}
+
+impl Default for XzCheck {
+    #[inline(always)]
+    fn default() -> Self {
+        XzCheck::Crc64
+    }
+}
+
+#[derive(Copy, Clone)]
+///XZ options.
+///
+///Stream is always compressed with single LZMA2 filter.
+pub struct XzOptions {
+    preset: u32,
+    extreme: bool,
+    check: XzCheck,
+    dict_size: u32,
+}
+
+impl XzOptions {
+    #[inline(always)]
+    ///Creates new default value
+    pub const fn new() -> Self {
+        Self {
+            preset: sys::LZMA_PRESET_DEFAULT,
+            extreme: false,
+            check: XzCheck::Crc64,
+            dict_size: 0,
+        }
+    }
+
+    #[inline(always)]
+    ///Sets preset in range from 0 to 9
+    ///
+    ///Defaults to 6.
+    pub const fn preset(mut self, preset: u32) -> Self {
+        assert!(preset <= 9);
+        self.preset = preset;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets extreme variant of preset, which is slower, but may compress slightly better
+    pub const fn extreme(mut self, extreme: bool) -> Self {
+        self.extreme = extreme;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets integrity check
+    pub const fn check(mut self, check: XzCheck) -> Self {
+        self.check = check;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets dictionary size, overriding one chosen by preset
+    ///
+    ///Must be at least 4KiB and no more than 1536MiB.
+    pub const fn dict_size(mut self, dict_size: u32) -> Self {
+        assert!(dict_size >= sys::LZMA_DICT_SIZE_MIN);
+        assert!(dict_size <= 1536 * 1024 * 1024);
+        self.dict_size = dict_size;
+        self
+    }
+
+    #[inline(always)]
+    fn apply(&self, stream: &mut sys::lzma_stream) -> sys::lzma_ret {
+        let mut preset = self.preset;
+        if self.extreme {
+            preset |= sys::LZMA_PRESET_EXTREME;
+        }
+
+        let mut lzma: sys::lzma_options_lzma = unsafe { mem::zeroed() };
+        if unsafe { sys::lzma_lzma_preset(&mut lzma, preset) } != 0 {
+            return sys::LZMA_OPTIONS_ERROR;
+        }
+        if self.dict_size != 0 {
+            lzma.dict_size = self.dict_size;
+        }
+
+        let filters = [
+            sys::lzma_filter {
+                id: sys::LZMA_FILTER_LZMA2,
+                options: &mut lzma as *mut _ as *mut _,
+            },
+            sys::lzma_filter {
+                id: sys::LZMA_VLI_UNKNOWN,
+                options: ptr::null_mut(),
+            },
+        ];
+        unsafe { sys::lzma_stream_encoder(stream, filters.as_ptr(), self.check.into_lzma()) }
+    }
+}
+
+impl Default for XzOptions {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct State {
+    inner: sys::lzma_stream,
+    opts: XzOptions,
+    //liblzma keeps reporting LZMA_STREAM_END after stream is finished, ignoring input
+    finished: bool,
+    //Referenced by stream, hence it must not be moved after being set
+    allocator: sys::lzma_allocator,
+    memory: MemoryTracker,
+}
+
+impl Drop for State {
+    #[inline(always)]
+    fn drop(&mut self) {
+        unsafe {
+            sys::lzma_end(&mut self.inner);
+        }
+    }
+}
+
+impl Interface {
+    #[inline]
+    ///Creates encoder with `xz` interface
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn xz(opts: XzOptions) -> Option<Encoder<'static>> {
+        create(opts, MemoryTracker::new())
+    }
+
+    #[inline]
+    ///Creates encoder with `xz` interface, using `allocator` for all allocations of stream.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory)
+    pub fn xz_with_allocator(opts: XzOptions, allocator: impl Allocator + 'static) -> Option<Encoder<'static>> {
+        create(opts, MemoryTracker::with_allocator(allocator))
+    }
+}
+
+fn create(opts: XzOptions, memory: MemoryTracker) -> Option<Encoder<'static>> {
+    let mut instance = Box::new(State {
+        //LZMA_STREAM_INIT
+        inner: unsafe { mem::zeroed() },
+        opts,
+        finished: false,
+        allocator: unsafe { mem::zeroed() },
+        memory,
+    });
+    instance.allocator = lzma_allocator(&instance.memory);
+    instance.inner.allocator = &instance.allocator;
+
+    if opts.apply(&mut instance.inner) == sys::LZMA_OK {
+        let instance = ptr::NonNull::from(Box::leak(instance)).cast();
+        Some(XZ.inner_encoder(instance, [0; 2]))
+    } else {
+        None
+    }
+}
+
+unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize, op: EncodeOp) -> Encode {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+
+    if state.finished && (input_remain != 0 || op != EncodeOp::Finish) {
+        return Encode {
+            input_remain,
+            output_remain,
+            status: EncodeStatus::Error(encode_error(sys::LZMA_PROG_ERROR as _)),
+        };
+    }
+
+    state.inner.next_in = input;
+    state.inner.avail_in = input_remain;
+    state.inner.next_out = output;
+    state.inner.avail_out = output_remain;
+
+    let result = unsafe {
+        sys::lzma_code(&mut state.inner, op.into_lzma())
+    };
+
+    Encode {
+        input_remain: state.inner.avail_in,
+        output_remain: state.inner.avail_out,
+        status: match result {
+            //Signals completion of both flush and finish
+            sys::LZMA_STREAM_END => match op {
+                EncodeOp::Finish => {
+                    state.finished = true;
+                    EncodeStatus::Finished
+                }
+                _ => EncodeStatus::Continue,
+            },
+            sys::LZMA_OK => match op {
+                EncodeOp::Process if state.inner.avail_out != 0 => EncodeStatus::Continue,
+                //Flush and finish are not complete until LZMA_STREAM_END
+                _ => EncodeStatus::NeedOutput,
+            },
+            sys::LZMA_BUF_ERROR => EncodeStatus::NeedOutput,
+            code => EncodeStatus::Error(encode_error(code as _)),
+        },
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn encode_error(code: i32) -> EncodeError {
+    let kind = match code as sys::lzma_ret {
+        sys::LZMA_OPTIONS_ERROR | sys::LZMA_UNSUPPORTED_CHECK => EncodeErrorKind::UnsupportedParameter,
+        sys::LZMA_MEM_ERROR | sys::LZMA_MEMLIMIT_ERROR => EncodeErrorKind::OutOfMemory,
+        sys::LZMA_PROG_ERROR => EncodeErrorKind::InvalidState,
+        _ => EncodeErrorKind::Other,
+    };
+    EncodeError::new(kind, code)
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>, _: [u8; 2]) -> Option<ptr::NonNull<u8>> {
+    let instance = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    instance.finished = false;
+    //Initializing existing stream re-uses its memory
+    if instance.opts.apply(&mut instance.inner) == sys::LZMA_OK {
+        Some(state)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    unsafe {
+        drop(Box::from_raw(state.as_ptr() as *mut State));
+    }
+}
+
+#[inline]
+fn describe_error_fn(_: ptr::NonNull<u8>, code: i32) -> Option<&'static str> {
+    crate::utils::lzma_error_name(code)
+}
+
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    Some(ptr::NonNull::from(&state.memory))
+}
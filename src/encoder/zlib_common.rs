@@ -1,3 +1,7 @@
+use super::{EncodeError, EncodeErrorKind};
+#[cfg(feature = "alloc")]
+use crate::gzip::GzipHeader;
+
 const MAX_MEM_LEVEL: u8 = 8;
 
 #[derive(Copy, Clone)]
@@ -44,16 +48,19 @@ impl Default for ZlibMode {
 }
 
 ///Zlib configuration for encoder.
-pub struct ZlibOptions {
+pub struct ZlibOptions<'a> {
     ///Mode
     pub mode: ZlibMode,
     ///Strategy
     pub strategy: ZlibStrategy,
     pub(crate) mem_level: u8,
     pub(crate) compression: i8,
+    pub(crate) dictionary: &'a [u8],
+    #[cfg(feature = "alloc")]
+    pub(crate) gzip_header: Option<&'a GzipHeader>,
 }
 
-impl ZlibOptions {
+impl<'a> ZlibOptions<'a> {
     #[inline(always)]
     ///Creates new default options
     pub const fn new() -> Self {
@@ -62,6 +69,9 @@ impl ZlibOptions {
             strategy: ZlibStrategy::Default,
             mem_level: MAX_MEM_LEVEL,
             compression: 9,
+            dictionary: &[],
+            #[cfg(feature = "alloc")]
+            gzip_header: None,
         }
     }
 
@@ -100,11 +110,47 @@ impl ZlibOptions {
         self.compression = compression;
         self
     }
+
+    #[inline]
+    ///Sets preset dictionary, which is copied into encoder and applied again on every reset.
+    ///
+    ///Not supported in `Gzip` mode, making encoder creation to fail.
+    ///Decoder must be provided with the same dictionary.
+    pub const fn dictionary(mut self, dictionary: &'a [u8]) -> Self {
+        self.dictionary = dictionary;
+        self
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline]
+    ///Sets header to write in `Gzip` mode, which is copied into encoder and written again after every reset.
+    ///
+    ///By default bare header is written, with unknown mtime and OS set by backend.
+    ///Not supported in other modes, making encoder creation to fail, as well as header with invalid fields.
+    pub const fn gzip_header(mut self, header: &'a GzipHeader) -> Self {
+        self.gzip_header = Some(header);
+        self
+    }
 }
 
-impl Default for ZlibOptions {
+impl Default for ZlibOptions<'_> {
     #[inline(always)]
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cold]
+#[inline(never)]
+///Maps zlib return code to [EncodeError](../struct.EncodeError.html)
+pub(crate) fn encode_error(code: i32) -> EncodeError {
+    const Z_STREAM_ERROR: i32 = -2;
+    const Z_MEM_ERROR: i32 = -4;
+
+    let kind = match code {
+        Z_STREAM_ERROR => EncodeErrorKind::InvalidState,
+        Z_MEM_ERROR => EncodeErrorKind::OutOfMemory,
+        _ => EncodeErrorKind::Other,
+    };
+    EncodeError::new(kind, code)
+}
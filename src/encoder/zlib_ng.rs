@@ -5,20 +5,33 @@ extern crate alloc;
 use libz_ng_sys as sys;
 
 use alloc::boxed::Box;
+use alloc::vec::Vec;
 use core::ptr;
 
 use super::{Encode, EncodeOp, Encoder, Interface, ZlibOptions, ZlibStrategy};
-use crate::mem::{compu_alloc, compu_free_with_state};
+use crate::mem::{compu_alloc, compu_free_with_state, Allocator, MemoryTracker};
+
+extern "C" {
+    #[link_name = "zng_zError"]
+    pub fn zError(code: core::ffi::c_int) -> *const i8;
+}
 
 static ZLIB: Interface = Interface {
     drop_fn,
     reset_fn,
     encode_fn,
+    describe_error_fn,
+    set_pledged_size_fn: super::ignore_pledged_size,
+    memory_tracker_fn,
 };
 
-#[repr(transparent)]
 struct State {
     inner: sys::z_stream,
+    dictionary: Vec<u8>,
+    //Referenced by stream, hence it must not be moved after being set
+    gzip_header: Option<(crate::gzip::RawHeader, sys::gz_header)>,
+    //Referenced by stream as `opaque`, hence it must not be moved after being set
+    memory: MemoryTracker,
 }
 
 impl State {
@@ -27,9 +40,26 @@ impl State {
         &mut self.inner
     }
 
+    #[inline(always)]
+    fn set_dictionary(&mut self) -> bool {
+        self.dictionary.is_empty() || unsafe { sys::deflateSetDictionary(&mut self.inner, self.dictionary.as_ptr(), self.dictionary.len() as _) == sys::Z_OK }
+    }
+
+    #[inline(always)]
+    fn set_gzip_header(&mut self) -> bool {
+        match self.gzip_header.as_mut() {
+            Some((_, header)) => unsafe { sys::deflateSetHeader(&mut self.inner, header) == sys::Z_OK },
+            None => true,
+        }
+    }
+
     #[inline(always)]
     fn reset(&mut self) -> bool {
-        unsafe { sys::deflateReset(&mut self.inner) == sys::Z_OK }
+        let result = unsafe {
+            sys::deflateReset(&mut self.inner)
+        };
+        //Reset discards dictionary, so it has to be set again
+        result == sys::Z_OK && self.set_dictionary() && self.set_gzip_header()
     }
 }
 
@@ -46,44 +76,68 @@ impl Interface {
     #[inline]
     ///Creates encoder with `zlib-ng` interface
     ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory, dictionary used with `Gzip` mode or invalid gzip header)
+    pub fn zlib_ng(opts: ZlibOptions<'_>) -> Option<Encoder<'static>> {
+        create(opts, MemoryTracker::new())
+    }
+
+    #[inline]
+    ///Creates encoder with `zlib-ng` interface, using `allocator` for all allocations of stream.
+    ///
     ///Returns `None` if unable to initialize it (likely due to lack of memory)
-    pub fn zlib_ng(opts: ZlibOptions) -> Option<Encoder> {
-        let mut instance = Box::new(State {
-            inner: sys::z_stream {
-                next_in: ptr::null_mut(),
-                avail_in: 0,
-                total_in: 0,
-                next_out: ptr::null_mut(),
-                avail_out: 0,
-                total_out: 0,
-                msg: ptr::null_mut(),
-                state: ptr::null_mut(),
-                zalloc: compu_alloc,
-                zfree: compu_free_with_state,
-                opaque: ptr::null_mut(),
-                data_type: 0,
-                adler: 0,
-                reserved: 0,
-            },
-        });
-        let max_bits = opts.mode as _;
-        let strategy = match opts.strategy {
-            ZlibStrategy::Default => sys::Z_DEFAULT_STRATEGY,
-            ZlibStrategy::Filtered => sys::Z_FILTERED,
-            ZlibStrategy::HuffmanOnly => sys::Z_HUFFMAN_ONLY,
-            ZlibStrategy::Rle => sys::Z_RLE,
-            ZlibStrategy::Fixed => sys::Z_FIXED,
-        };
-        let result = unsafe {
-            sys::deflateInit2_(&mut instance.inner, opts.compression as _, sys::Z_DEFLATED, max_bits, opts.mem_level as _, strategy, sys::zlibVersion(), core::mem::size_of::<sys::z_stream>() as _)
-        };
+    pub fn zlib_ng_with_allocator(opts: ZlibOptions<'_>, allocator: impl Allocator + 'static) -> Option<Encoder<'static>> {
+        create(opts, MemoryTracker::with_allocator(allocator))
+    }
+}
 
-        if result == 0 {
-            let instance = ptr::NonNull::from(Box::leak(instance)).cast();
-            Some(ZLIB.inner_encoder(instance, [0; 2]))
-        } else {
-            None
+fn create(opts: ZlibOptions<'_>, memory: MemoryTracker) -> Option<Encoder<'static>> {
+    let gzip_header = match opts.gzip_header {
+        Some(header) => {
+            let mut raw = crate::gzip::RawHeader::encoder(header)?;
+            let header = internal_gz_header_deflate!(&mut raw);
+            Some((raw, header))
         }
+        None => None,
+    };
+    let mut instance = Box::new(State {
+        inner: sys::z_stream {
+            next_in: ptr::null_mut(),
+            avail_in: 0,
+            total_in: 0,
+            next_out: ptr::null_mut(),
+            avail_out: 0,
+            total_out: 0,
+            msg: ptr::null_mut(),
+            state: ptr::null_mut(),
+            zalloc: compu_alloc,
+            zfree: compu_free_with_state,
+            opaque: ptr::null_mut(),
+            data_type: 0,
+            adler: 0,
+            reserved: 0,
+        },
+        dictionary: opts.dictionary.to_vec(),
+        gzip_header,
+        memory,
+    });
+    instance.inner.opaque = instance.memory.as_opaque();
+    let max_bits = opts.mode as _;
+    let strategy = match opts.strategy {
+        ZlibStrategy::Default => sys::Z_DEFAULT_STRATEGY,
+        ZlibStrategy::Filtered => sys::Z_FILTERED,
+        ZlibStrategy::HuffmanOnly => sys::Z_HUFFMAN_ONLY,
+        ZlibStrategy::Rle => sys::Z_RLE,
+        ZlibStrategy::Fixed => sys::Z_FIXED,
+    };
+    let result = unsafe {
+        sys::deflateInit2_(&mut instance.inner, opts.compression as _, sys::Z_DEFLATED, max_bits, opts.mem_level as _, strategy, sys::zlibVersion(), core::mem::size_of::<sys::z_stream>() as _)
+    };
+
+    if result == 0 && instance.set_dictionary() && instance.set_gzip_header() {
+        let instance = ptr::NonNull::from(Box::leak(instance)).cast();
+        Some(ZLIB.inner_encoder(instance, [0; 2]))
+    } else {
+        None
     }
 }
 
@@ -109,3 +163,21 @@ fn drop_fn(state: ptr::NonNull<u8>) {
         drop(Box::from_raw(state.as_ptr() as *mut State));
     }
 }
+
+#[inline]
+fn describe_error_fn(state: ptr::NonNull<u8>, code: i32) -> Option<&'static str> {
+    let state = unsafe {
+        &*(state.as_ptr() as *const State)
+    };
+    //zlib only sets static messages
+    match crate::utils::convert_c_str(state.inner.msg as _) {
+        Some(msg) => Some(msg),
+        None => crate::utils::convert_c_str(unsafe { zError(code) }),
+    }
+}
+
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    Some(ptr::NonNull::from(&state.memory))
+}
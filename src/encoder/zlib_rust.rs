@@ -1,14 +1,25 @@
 //! zlib-rust module
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "alloc")]
 use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "alloc")]
 use core::{ptr, mem};
 
-use super::{Encode, EncodeOp, Encoder, Interface, ZlibOptions, ZlibStrategy};
+use super::{ZlibOptions, ZlibStrategy};
+#[cfg(feature = "alloc")]
+use super::{Encode, EncodeOp, Encoder, Interface};
+#[cfg(feature = "alloc")]
+use crate::mem::{compu_alloc, compu_free_with_state, Allocator, MemoryTracker};
 
-mod sys {
+pub(super) mod sys {
     pub use zlib_rs::c_api::z_stream;
+    #[cfg(feature = "alloc")]
+    pub use zlib_rs::c_api::gz_header;
     pub use zlib_rs::deflate::*;
     pub use zlib_rs::ReturnCode;
     pub use zlib_rs::ReturnCode::Ok as Z_OK;
@@ -20,21 +31,31 @@ mod sys {
     pub use zlib_rs::DeflateFlush::Finish as Z_FINISH;
 }
 
+#[cfg(feature = "alloc")]
 static ZLIB: Interface = Interface {
     drop_fn,
     reset_fn,
     encode_fn,
+    describe_error_fn,
+    set_pledged_size_fn: super::ignore_pledged_size,
+    memory_tracker_fn,
 };
 
-#[repr(transparent)]
+#[cfg(feature = "alloc")]
 pub struct State {
     inner: sys::z_stream,
+    dictionary: Vec<u8>,
+    //Referenced by stream, hence it must not be moved after being set
+    gzip_header: Option<(crate::gzip::RawHeader, sys::gz_header)>,
+    //Referenced by stream as `opaque`, hence it must not be moved after being set
+    memory: MemoryTracker,
 }
 
+#[cfg(feature = "alloc")]
 impl State {
     #[inline(always)]
-    pub fn new() -> Self {
-        let mut this = Self {
+    pub fn new(dictionary: Vec<u8>, gzip_header: Option<(crate::gzip::RawHeader, sys::gz_header)>, memory: MemoryTracker) -> Self {
+        Self {
             inner: sys::z_stream {
                 next_in: ptr::null_mut(),
                 avail_in: 0,
@@ -44,21 +65,55 @@ impl State {
                 total_out: 0,
                 msg: ptr::null_mut(),
                 state: ptr::null_mut(),
-                zalloc: None,
-                zfree: None,
+                zalloc: Some(compu_alloc),
+                zfree: Some(compu_free_with_state),
                 opaque: ptr::null_mut(),
                 data_type: 0,
                 adler: 0,
                 reserved: 0,
-            }
+            },
+            dictionary,
+            gzip_header,
+            memory,
+        }
+    }
+
+    #[inline(always)]
+    fn set_dictionary(&mut self) -> sys::ReturnCode {
+        if self.dictionary.is_empty() {
+            return sys::ReturnCode::Ok;
+        }
+        let stream: &mut sys::DeflateStream<'_> = unsafe {
+            mem::transmute(&mut self.inner)
         };
-        this.inner.configure_default_rust_allocator();
-        this
+        sys::set_dictionary(stream, &self.dictionary)
+    }
+
+    #[inline(always)]
+    fn set_gzip_header(&mut self) -> sys::ReturnCode {
+        match self.gzip_header.as_mut() {
+            Some((_, header)) => {
+                let stream: &mut sys::DeflateStream<'_> = unsafe {
+                    mem::transmute(&mut self.inner)
+                };
+                unsafe {
+                    sys::set_header(stream, Some(header))
+                }
+            }
+            None => sys::ReturnCode::Ok,
+        }
     }
 
     #[inline(always)]
     pub fn reset(&mut self) -> sys::ReturnCode {
-        sys::reset(self.as_mut())
+        //Reset discards dictionary, so it has to be set again
+        match sys::reset(self.as_mut()) {
+            sys::ReturnCode::Ok => match self.set_dictionary() {
+                sys::ReturnCode::Ok => self.set_gzip_header(),
+                result => result,
+            },
+            result => result,
+        }
     }
 
     //z_stream has the same layout as DeflateStream,
@@ -71,6 +126,7 @@ impl State {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl Drop for State {
     #[inline(always)]
     fn drop(&mut self) {
@@ -78,45 +134,73 @@ impl Drop for State {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl Interface {
     #[inline]
     ///Creates encoder with `zlib-rs` interface
     ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory, dictionary used with `Gzip` mode or invalid gzip header)
+    pub fn zlib_rust(opts: ZlibOptions<'_>) -> Option<Encoder<'static>> {
+        create(opts, MemoryTracker::new())
+    }
+
+    #[inline]
+    ///Creates encoder with `zlib-rs` interface, using `allocator` for all allocations of stream.
+    ///
     ///Returns `None` if unable to initialize it (likely due to lack of memory)
-    pub fn zlib_rust(opts: ZlibOptions) -> Option<Encoder> {
-        let mut instance = Box::new(State::new());
-
-        let strategy = match opts.strategy {
-            ZlibStrategy::Default => sys::Strategy::Default,
-            ZlibStrategy::Filtered => sys::Strategy::Filtered,
-            ZlibStrategy::HuffmanOnly => sys::Strategy::HuffmanOnly,
-            ZlibStrategy::Rle => sys::Strategy::Rle,
-            ZlibStrategy::Fixed => sys::Strategy::Fixed,
-        };
+    pub fn zlib_rust_with_allocator(opts: ZlibOptions<'_>, allocator: impl Allocator + 'static) -> Option<Encoder<'static>> {
+        create(opts, MemoryTracker::with_allocator(allocator))
+    }
+}
 
-        let config = sys::DeflateConfig {
-            level: opts.compression as _,
-            method: sys::Method::Deflated,
-            window_bits: opts.mode as _,
-            strategy,
-            mem_level: opts.mem_level as _,
-        };
+#[cfg(feature = "alloc")]
+fn create(opts: ZlibOptions<'_>, memory: MemoryTracker) -> Option<Encoder<'static>> {
+    let gzip_header = match opts.gzip_header {
+        Some(header) => {
+            let mut raw = crate::gzip::RawHeader::encoder(header)?;
+            let header = internal_gz_header_deflate!(&mut raw);
+            Some((raw, header))
+        }
+        None => None,
+    };
+    let mut instance = Box::new(State::new(opts.dictionary.to_vec(), gzip_header, memory));
+    instance.inner.opaque = instance.memory.as_opaque();
 
-        let result = sys::init(&mut instance.inner, config);
+    let result = sys::init(&mut instance.inner, config(&opts));
 
-        if result == sys::ReturnCode::Ok {
-            let instance = ptr::NonNull::from(Box::leak(instance)).cast();
-            Some(ZLIB.inner_encoder(instance, [0; 2]))
-        } else {
-            None
-        }
+    if result == sys::ReturnCode::Ok && instance.set_dictionary() == sys::ReturnCode::Ok && instance.set_gzip_header() == sys::ReturnCode::Ok {
+        let instance = ptr::NonNull::from(Box::leak(instance)).cast();
+        Some(ZLIB.inner_encoder(instance, [0; 2]))
+    } else {
+        None
+    }
+}
+
+//Shared with workspace variant
+pub(super) fn config(opts: &ZlibOptions<'_>) -> sys::DeflateConfig {
+    let strategy = match opts.strategy {
+        ZlibStrategy::Default => sys::Strategy::Default,
+        ZlibStrategy::Filtered => sys::Strategy::Filtered,
+        ZlibStrategy::HuffmanOnly => sys::Strategy::HuffmanOnly,
+        ZlibStrategy::Rle => sys::Strategy::Rle,
+        ZlibStrategy::Fixed => sys::Strategy::Fixed,
+    };
+
+    sys::DeflateConfig {
+        level: opts.compression as _,
+        method: sys::Method::Deflated,
+        window_bits: opts.mode as _,
+        strategy,
+        mem_level: opts.mem_level as _,
     }
 }
 
+#[cfg(feature = "alloc")]
 unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize, op: EncodeOp) -> Encode {
     internal_zlib_impl_encode!(state, input, input_remain, output, output_remain, op)
 }
 
+#[cfg(feature = "alloc")]
 #[inline]
 fn reset_fn(state: ptr::NonNull<u8>, _: [u8; 2]) -> Option<ptr::NonNull<u8>> {
     let result = unsafe {
@@ -128,9 +212,37 @@ fn reset_fn(state: ptr::NonNull<u8>, _: [u8; 2]) -> Option<ptr::NonNull<u8>> {
     }
 }
 
+#[cfg(feature = "alloc")]
 #[inline]
 fn drop_fn(state: ptr::NonNull<u8>) {
     unsafe {
         drop(Box::from_raw(state.as_ptr() as *mut State));
     }
 }
+
+#[cfg(feature = "alloc")]
+#[inline]
+fn describe_error_fn(state: ptr::NonNull<u8>, code: i32) -> Option<&'static str> {
+    let state = unsafe {
+        &*(state.as_ptr() as *const State)
+    };
+    describe_error(&state.inner, code)
+}
+
+#[inline(always)]
+pub(super) fn describe_error(stream: &sys::z_stream, code: i32) -> Option<&'static str> {
+    match crate::utils::convert_c_str(stream.msg as _) {
+        Some(msg) => Some(msg),
+        None => match sys::ReturnCode::try_from_c_int(code as _) {
+            Some(code) => crate::utils::convert_c_str(code.error_message() as _),
+            None => None,
+        },
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    Some(ptr::NonNull::from(&state.memory))
+}
@@ -0,0 +1,120 @@
+//! `zlib-rs` interface implementation with state in caller provided workspace
+
+use core::alloc::Layout;
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+
+use super::zlib_rust::{config, describe_error, sys};
+use super::{Encode, EncodeOp, Encoder, Interface, ZlibOptions};
+use crate::mem::{workspace_alloc, workspace_free, Workspace};
+
+static ZLIB_RUST_STATIC: Interface = Interface::new(
+    reset_fn,
+    encode_fn,
+    drop_fn,
+    describe_error_fn
+);
+
+struct State {
+    inner: sys::z_stream,
+}
+
+impl State {
+    #[inline(always)]
+    fn as_mut(&mut self) -> &mut sys::DeflateStream<'_> {
+        unsafe {
+            mem::transmute(&mut self.inner)
+        }
+    }
+}
+
+impl Drop for State {
+    #[inline(always)]
+    fn drop(&mut self) {
+        let _ = sys::end(self.as_mut());
+    }
+}
+
+impl Interface {
+    ///Creates encoder with `zlib-rs` interface, placing all of its state within `workspace`.
+    ///
+    ///Encoder never allocates, hence its memory usage is limited by `workspace`.
+    ///With default options it requires about 380KiB.
+    ///
+    ///Preset dictionary and gzip header are not supported, as they cannot be stored without allocation.
+    ///
+    ///Returns `None` if `workspace` is too small, options are invalid, dictionary or gzip header is set.
+    pub fn zlib_rust_static<'a>(opts: ZlibOptions<'_>, workspace: &'a mut [MaybeUninit<u8>]) -> Option<Encoder<'a>> {
+        #[cfg(feature = "alloc")]
+        if opts.gzip_header.is_some() {
+            return None;
+        }
+        if !opts.dictionary.is_empty() {
+            return None;
+        }
+
+        let workspace = Workspace::new(workspace)?;
+        let state = workspace.alloc(Layout::new::<State>()) as *mut State;
+        let state = ptr::NonNull::new(state)?;
+
+        let instance = unsafe {
+            state.as_ptr().write(State {
+                inner: sys::z_stream {
+                    next_in: ptr::null_mut(),
+                    avail_in: 0,
+                    total_in: 0,
+                    next_out: ptr::null_mut(),
+                    avail_out: 0,
+                    total_out: 0,
+                    msg: ptr::null_mut(),
+                    state: ptr::null_mut(),
+                    zalloc: Some(workspace_alloc),
+                    zfree: Some(workspace_free),
+                    opaque: workspace as *const Workspace as _,
+                    data_type: 0,
+                    adler: 0,
+                    reserved: 0,
+                },
+            });
+            &mut *state.as_ptr()
+        };
+
+        if sys::init(&mut instance.inner, config(&opts)) == sys::Z_OK {
+            Some(ZLIB_RUST_STATIC.inner_encoder(state.cast(), [0; 2]))
+        } else {
+            //Stream is not initialized, so there is nothing to end
+            None
+        }
+    }
+}
+
+unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize, op: EncodeOp) -> Encode {
+    internal_zlib_impl_encode!(state, input, input_remain, output, output_remain, op)
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>, _: [u8; 2]) -> Option<ptr::NonNull<u8>> {
+    let result = unsafe {
+        sys::reset((*(state.as_ptr() as *mut State)).as_mut())
+    };
+    match result {
+        sys::ReturnCode::Ok => Some(state),
+        _ => None,
+    }
+}
+
+#[inline]
+fn drop_fn(state: ptr::NonNull<u8>) {
+    //Returns all memory to workspace, which itself is owned by caller
+    unsafe {
+        ptr::drop_in_place(state.as_ptr() as *mut State);
+    }
+}
+
+#[inline]
+fn describe_error_fn(state: ptr::NonNull<u8>, code: i32) -> Option<&'static str> {
+    let state = unsafe {
+        &*(state.as_ptr() as *const State)
+    };
+    describe_error(&state.inner, code)
+}
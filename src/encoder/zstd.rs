@@ -1,17 +1,34 @@
 //! `zstd` interface implementation
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 use zstd_sys as sys;
 
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+#[cfg(feature = "alloc")]
+use alloc::sync::Arc;
 use core::ptr;
 
-use super::{Encode, EncodeOp, EncodeStatus, Encoder, Interface};
+use super::{Encode, EncodeError, EncodeErrorKind, EncodeOp, EncodeStatus};
+#[cfg(feature = "alloc")]
+use super::{Encoder, Interface};
+#[cfg(feature = "alloc")]
 use crate::mem::compu_free_with_state;
+#[cfg(feature = "alloc")]
 use crate::mem::compu_malloc_with_state;
+#[cfg(feature = "alloc")]
+use crate::mem::{Allocator, MemoryTracker};
 
+#[cfg(feature = "alloc")]
 static ZSTD: Interface = Interface {
     drop_fn,
     reset_fn,
     encode_fn,
+    describe_error_fn,
+    set_pledged_size_fn,
+    memory_tracker_fn,
 };
 
 extern "C" {
@@ -55,17 +72,128 @@ pub enum ZstdStrategy {
     BtUltra2 = 9,
 }
 
+impl ZstdStrategy {
+    #[inline(always)]
+    const fn into_zstd(self) -> Option<sys::ZSTD_strategy> {
+        match self {
+            Self::Default => None,
+            Self::Fast => Some(sys::ZSTD_strategy::ZSTD_fast),
+            Self::DFast => Some(sys::ZSTD_strategy::ZSTD_dfast),
+            Self::Greedy => Some(sys::ZSTD_strategy::ZSTD_greedy),
+            Self::Lazy => Some(sys::ZSTD_strategy::ZSTD_lazy),
+            Self::Lazy2 => Some(sys::ZSTD_strategy::ZSTD_lazy2),
+            Self::BtLazy2 => Some(sys::ZSTD_strategy::ZSTD_btlazy2),
+            Self::BtOpt => Some(sys::ZSTD_strategy::ZSTD_btopt),
+            Self::BtUltra => Some(sys::ZSTD_strategy::ZSTD_btultra),
+            Self::BtUltra2 => Some(sys::ZSTD_strategy::ZSTD_btultra2),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[inline(always)]
+//`tracker` is `None` for dictionaries, as they are shared between instances
+fn allocator(tracker: Option<&MemoryTracker>) -> sys::ZSTD_customMem {
+    sys::ZSTD_customMem {
+        customAlloc: Some(compu_malloc_with_state),
+        customFree: Some(compu_free_with_state),
+        opaque: tracker.map_or(ptr::null_mut(), MemoryTracker::as_opaque),
+    }
+}
+
+#[cfg(feature = "alloc")]
+struct CDict(ptr::NonNull<sys::ZSTD_CDict>);
+
+//CDict is read-only once created, hence it is safe to share between threads
+#[cfg(feature = "alloc")]
+unsafe impl Send for CDict {}
+#[cfg(feature = "alloc")]
+unsafe impl Sync for CDict {}
+
+#[cfg(feature = "alloc")]
+impl Drop for CDict {
+    #[inline(always)]
+    fn drop(&mut self) {
+        unsafe {
+            sys::ZSTD_freeCDict(self.0.as_ptr());
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+///Digested `zstd` dictionary for compression.
+///
+///It is reference counted, so that single dictionary can be shared by any number of encoders.
+pub struct ZstdCDict {
+    inner: Arc<CDict>,
+}
+
+#[cfg(feature = "alloc")]
+impl ZstdCDict {
+    ///Creates dictionary from `dict` content, digesting it for specified compression `level`.
+    ///
+    ///`dict` can be either in `zstd` dictionary format or raw content.
+    ///
+    ///Returns `None` if unable to create it (due to invalid dictionary or lack of memory)
+    pub fn new(dict: &[u8], level: i32) -> Option<Self> {
+        let dict = unsafe {
+            let params = sys::ZSTD_getCParams(level, 0, dict.len());
+            sys::ZSTD_createCDict_advanced(dict.as_ptr() as _, dict.len(), sys::ZSTD_dictLoadMethod_e::ZSTD_dlm_byCopy, sys::ZSTD_dictContentType_e::ZSTD_dct_auto, params, allocator(None))
+        };
+        ptr::NonNull::new(dict).map(|dict| Self {
+            inner: Arc::new(CDict(dict)),
+        })
+    }
+
+    #[inline(always)]
+    ///Returns dictionary ID, which is 0 for raw content dictionary.
+    pub fn id(&self) -> u32 {
+        unsafe {
+            sys::ZSTD_getDictID_fromCDict(self.inner.0.as_ptr()) as _
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Dictionary<'a> {
+    None,
+    Raw(&'a [u8]),
+    #[cfg(feature = "alloc")]
+    Prepared(&'a ZstdCDict),
+}
+
 #[derive(Copy, Clone)]
 ///ZSTD options.
 ///
+///Parameters are validated against `zstd` bounds on encoder creation, which fails if any of them is out of bounds.
+///Unless stated otherwise, value 0 means `zstd` default.
+///
 ///For details refer to their crappy documentation: `http://facebook.github.io/zstd/zstd_manual.html#Chapter5`
-pub struct ZstdOptions {
+pub struct ZstdOptions<'a> {
     level: i32,
     strategy: ZstdStrategy,
     window_log: i32,
+    hash_log: i32,
+    chain_log: i32,
+    search_log: i32,
+    min_match: i32,
+    target_length: i32,
+    checksum: bool,
+    content_size: bool,
+    dict_id: bool,
+    workers: i32,
+    job_size: i32,
+    overlap_log: i32,
+    long_distance_matching: i32,
+    ldm_hash_log: i32,
+    ldm_min_match: i32,
+    ldm_bucket_size_log: i32,
+    ldm_hash_rate_log: i32,
+    dictionary: Dictionary<'a>,
 }
 
-impl ZstdOptions {
+impl<'a> ZstdOptions<'a> {
     #[inline(always)]
     ///Creates new default value
     pub const fn new() -> Self {
@@ -73,14 +201,31 @@ impl ZstdOptions {
             level: sys::ZSTD_CLEVEL_DEFAULT as _,
             strategy: ZstdStrategy::Default,
             window_log: sys::ZSTD_WINDOWLOG_LIMIT_DEFAULT as _,
+            hash_log: 0,
+            chain_log: 0,
+            search_log: 0,
+            min_match: 0,
+            target_length: 0,
+            checksum: false,
+            content_size: true,
+            dict_id: true,
+            workers: 0,
+            job_size: 0,
+            overlap_log: 0,
+            long_distance_matching: 0,
+            ldm_hash_log: 0,
+            ldm_min_match: 0,
+            ldm_bucket_size_log: 0,
+            ldm_hash_rate_log: 0,
+            dictionary: Dictionary::None,
         }
     }
 
     #[inline(always)]
     ///Sets level
+    ///
+    ///Negative values are allowed for faster compression.
     pub const fn level(mut self, level: i32) -> Self {
-        assert!(level <= sys::ZSTD_TARGETLENGTH_MAX as i32);
-        assert!(level >= -(sys::ZSTD_TARGETLENGTH_MAX as i32));
         self.level = level;
         self
     }
@@ -94,22 +239,220 @@ impl ZstdOptions {
 
     #[inline(always)]
     ///Sets window_log
+    ///
+    ///Default value is 27 (`ZSTD_WINDOWLOG_LIMIT_DEFAULT`), which is the limit of default decoder.
     pub const fn window_log(mut self, window_log: i32) -> Self {
-        #[cfg(any(target_pointer_width = "16", target_pointer_width = "32"))]
-        assert!(window_log <= sys::ZSTD_WINDOWLOG_MAX_32 as i32);
-        #[cfg(not(any(target_pointer_width = "16", target_pointer_width = "32")))]
-        assert!(window_log <= sys::ZSTD_WINDOWLOG_MAX_64 as i32);
-        assert!(window_log >= sys::ZSTD_WINDOWLOG_MIN as i32);
         self.window_log = window_log;
         self
     }
 
     #[inline(always)]
-    fn apply(&self, ctx: ptr::NonNull<sys::ZSTD_CCtx>) -> Option<ptr::NonNull<sys::ZSTD_CCtx>> {
+    ///Sets hash_log, size of the initial probe table as power of 2.
+    pub const fn hash_log(mut self, hash_log: i32) -> Self {
+        self.hash_log = hash_log;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets chain_log, size of the multi-probe search table as power of 2.
+    pub const fn chain_log(mut self, chain_log: i32) -> Self {
+        self.chain_log = chain_log;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets search_log, number of search attempts as power of 2.
+    pub const fn search_log(mut self, search_log: i32) -> Self {
+        self.search_log = search_log;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets min_match, minimum size of searched matches.
+    pub const fn min_match(mut self, min_match: i32) -> Self {
+        self.min_match = min_match;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets target_length, which impact depends on strategy.
+    pub const fn target_length(mut self, target_length: i32) -> Self {
+        self.target_length = target_length;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets whether to write 32bit checksum of content at the end of frame.
+    ///
+    ///Default value is `false`.
+    pub const fn checksum(mut self, checksum: bool) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets whether to write content size into frame header, when it is known.
+    ///
+    ///Default value is `true`.
+    pub const fn content_size(mut self, content_size: bool) -> Self {
+        self.content_size = content_size;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets whether to write dictionary ID into frame header, when dictionary is used.
+    ///
+    ///Default value is `true`.
+    pub const fn dict_id(mut self, dict_id: bool) -> Self {
+        self.dict_id = dict_id;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets number of worker threads to compress in parallel.
+    ///
+    ///Default value is 0, which means single threaded mode.
+    ///Requires `zstd-mt` feature, otherwise only 0 is valid.
+    pub const fn workers(mut self, workers: i32) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets size of job for each worker, in bytes.
+    ///
+    ///Only used in multi-threaded mode.
+    pub const fn job_size(mut self, job_size: i32) -> Self {
+        self.job_size = job_size;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets overlap_log, size of data reloaded from previous job, from 1 (none) to 9 (full window).
+    ///
+    ///Only used in multi-threaded mode.
+    pub const fn overlap_log(mut self, overlap_log: i32) -> Self {
+        self.overlap_log = overlap_log;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets whether to enable long distance matching.
+    ///
+    ///By default it is decided by `zstd` depending on strategy and window size.
+    pub const fn long_distance_matching(mut self, enabled: bool) -> Self {
+        self.long_distance_matching = match enabled {
+            true => sys::ZSTD_ParamSwitch_e::ZSTD_ps_enable as _,
+            false => sys::ZSTD_ParamSwitch_e::ZSTD_ps_disable as _,
+        };
+        self
+    }
+
+    #[inline(always)]
+    ///Sets ldm_hash_log, size of long distance matching table as power of 2.
+    pub const fn ldm_hash_log(mut self, ldm_hash_log: i32) -> Self {
+        self.ldm_hash_log = ldm_hash_log;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets ldm_min_match, minimum size of long distance matches.
+    pub const fn ldm_min_match(mut self, ldm_min_match: i32) -> Self {
+        self.ldm_min_match = ldm_min_match;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets ldm_bucket_size_log, size of long distance matching bucket as power of 2.
+    pub const fn ldm_bucket_size_log(mut self, ldm_bucket_size_log: i32) -> Self {
+        self.ldm_bucket_size_log = ldm_bucket_size_log;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets ldm_hash_rate_log, frequency of inserting into long distance matching table as power of 2.
+    pub const fn ldm_hash_rate_log(mut self, ldm_hash_rate_log: i32) -> Self {
+        self.ldm_hash_rate_log = ldm_hash_rate_log;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets dictionary to load, which can be either in `zstd` dictionary format or raw content.
+    ///
+    ///Its content is copied into encoder and digested on creation.
+    ///Prefer [cdict](#method.cdict) when the same dictionary is used by multiple encoders.
+    pub const fn dictionary(mut self, dict: &'a [u8]) -> Self {
+        self.dictionary = Dictionary::Raw(dict);
+        self
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline(always)]
+    ///Sets already digested dictionary to reference.
+    ///
+    ///Encoder holds its own reference to the dictionary.
+    pub const fn cdict(mut self, dict: &'a ZstdCDict) -> Self {
+        self.dictionary = Dictionary::Prepared(dict);
+        self
+    }
+
+    ///Returns size of workspace required by [zstd_static](struct.Interface.html#method.zstd_static) encoder with these options.
+    ///
+    ///It is computed from compression parameters of level, overridden by explicitly set ones.
+    ///Long distance matching is not accounted for.
+    pub fn estimate_size(&self) -> usize {
+        let mut params = unsafe {
+            sys::ZSTD_getCParams(self.level, 0, 0)
+        };
+        macro_rules! set {
+            ($field:ident => $param:ident) => {{
+                if self.$field != 0 {
+                    params.$param = self.$field as _;
+                }
+            }};
+        }
+
+        set!(window_log => windowLog);
+        set!(hash_log => hashLog);
+        set!(chain_log => chainLog);
+        set!(search_log => searchLog);
+        set!(min_match => minMatch);
+        set!(target_length => targetLength);
+        if let Some(strategy) = self.strategy.into_zstd() {
+            params.strategy = strategy;
+        }
+
+        let size = unsafe {
+            sys::ZSTD_estimateCStreamSize_usingCParams(params)
+        };
+        super::zstd_static::WORKSPACE_OVERHEAD + size
+    }
+
+    #[inline(always)]
+    pub(super) const fn has_dictionary(&self) -> bool {
+        !matches!(self.dictionary, Dictionary::None)
+    }
+
+    #[inline(always)]
+    pub(super) const fn has_workers(&self) -> bool {
+        self.workers != 0
+    }
+
+    #[inline(always)]
+    pub(super) fn apply(&self, ctx: ptr::NonNull<sys::ZSTD_CCtx>) -> Option<ptr::NonNull<sys::ZSTD_CCtx>> {
         macro_rules! set {
             ($field:ident => $param:ident) => {{
+                let param = sys::ZSTD_cParameter::$param;
+                let value = self.$field as i32;
                 unsafe {
-                    let result = sys::ZSTD_isError(sys::ZSTD_CCtx_setParameter(ctx.as_ptr(), sys::ZSTD_cParameter::$param, self.$field as _));
+                    //0 is always accepted as default
+                    if value != 0 {
+                        let bounds = sys::ZSTD_cParam_getBounds(param);
+                        if sys::ZSTD_isError(bounds.error) != 0 || value < bounds.lowerBound || value > bounds.upperBound {
+                            return None;
+                        }
+                    }
+                    let result = sys::ZSTD_isError(sys::ZSTD_CCtx_setParameter(ctx.as_ptr(), param, value));
                     if result != 0 {
                         return None;
                     }
@@ -117,43 +460,127 @@ impl ZstdOptions {
             }};
         }
 
+        //Workers must be set first, as multi-threading parameters depend on it
+        set!(workers => ZSTD_c_nbWorkers);
+        set!(job_size => ZSTD_c_jobSize);
+        set!(overlap_log => ZSTD_c_overlapLog);
         set!(level => ZSTD_c_compressionLevel);
         set!(strategy => ZSTD_c_strategy);
         set!(window_log => ZSTD_c_windowLog);
+        set!(hash_log => ZSTD_c_hashLog);
+        set!(chain_log => ZSTD_c_chainLog);
+        set!(search_log => ZSTD_c_searchLog);
+        set!(min_match => ZSTD_c_minMatch);
+        set!(target_length => ZSTD_c_targetLength);
+        set!(checksum => ZSTD_c_checksumFlag);
+        set!(content_size => ZSTD_c_contentSizeFlag);
+        set!(dict_id => ZSTD_c_dictIDFlag);
+        set!(long_distance_matching => ZSTD_c_enableLongDistanceMatching);
+        set!(ldm_hash_log => ZSTD_c_ldmHashLog);
+        set!(ldm_min_match => ZSTD_c_ldmMinMatch);
+        set!(ldm_bucket_size_log => ZSTD_c_ldmBucketSizeLog);
+        set!(ldm_hash_rate_log => ZSTD_c_ldmHashRateLog);
+
+        //Dictionary must be loaded last, as parameters cannot be changed after that
+        let result = match self.dictionary {
+            Dictionary::None => 0,
+            Dictionary::Raw(dict) => unsafe {
+                sys::ZSTD_CCtx_loadDictionary(ctx.as_ptr(), dict.as_ptr() as _, dict.len())
+            },
+            #[cfg(feature = "alloc")]
+            Dictionary::Prepared(dict) => unsafe {
+                sys::ZSTD_CCtx_refCDict(ctx.as_ptr(), dict.inner.0.as_ptr())
+            },
+        };
+        if unsafe { sys::ZSTD_isError(result) } != 0 {
+            return None;
+        }
 
         Some(ctx)
     }
 }
 
-impl Default for ZstdOptions {
+impl Default for ZstdOptions<'_> {
     #[inline(always)]
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(feature = "alloc")]
+struct State {
+    ctx: ptr::NonNull<sys::ZSTD_CCtx>,
+    //Referenced by context as `opaque`, hence it must outlive it
+    memory: Box<MemoryTracker>,
+    //Referenced dictionary must outlive context
+    _cdict: Option<ZstdCDict>,
+}
+
+#[cfg(feature = "alloc")]
+impl Drop for State {
+    #[inline(always)]
+    fn drop(&mut self) {
+        let result = unsafe {
+            sys::ZSTD_freeCStream(self.ctx.as_ptr())
+        };
+        debug_assert_eq!(result, 0);
+    }
+}
+
+#[cfg(feature = "alloc")]
 impl Interface {
     #[inline]
     ///Creates encoder with `zstd` interface
     ///
-    ///Returns `None` if unable to initialize it (likely due to lack of memory)
-    pub fn zstd(opts: ZstdOptions) -> Option<Encoder> {
-        let allocator = sys::ZSTD_customMem {
-            customAlloc: Some(compu_malloc_with_state),
-            customFree: Some(compu_free_with_state),
-            opaque: ptr::null_mut(),
-        };
-        let ctx = unsafe {
-            sys::ZSTD_createCStream_advanced(allocator)
-        };
-        match ptr::NonNull::new(ctx).and_then(|ctx| opts.apply(ctx)) {
-            Some(ctx) => Some(ZSTD.inner_encoder(ctx.cast(), [0; 2])),
-            None => None,
-        }
+    ///Dictionary, if any, stays applied across resets.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory, invalid options or dictionary)
+    pub fn zstd(opts: ZstdOptions<'_>) -> Option<Encoder<'static>> {
+        create(opts, MemoryTracker::new())
+    }
+
+    #[inline]
+    ///Creates encoder with `zstd` interface, using `allocator` for all allocations of context.
+    ///
+    ///Prepared dictionaries are allocated separately, using Rust's global allocator.
+    ///
+    ///Returns `None` if unable to initialize it (likely due to lack of memory, invalid options or dictionary)
+    pub fn zstd_with_allocator(opts: ZstdOptions<'_>, allocator: impl Allocator + 'static) -> Option<Encoder<'static>> {
+        create(opts, MemoryTracker::with_allocator(allocator))
     }
 }
 
+#[cfg(feature = "alloc")]
+fn create(opts: ZstdOptions<'_>, memory: MemoryTracker) -> Option<Encoder<'static>> {
+    let memory = Box::new(memory);
+    let ctx = unsafe {
+        sys::ZSTD_createCStream_advanced(allocator(Some(&memory)))
+    };
+    let ctx = ptr::NonNull::new(ctx)?;
+    let state = Box::new(State {
+        ctx,
+        memory,
+        _cdict: match opts.dictionary {
+            Dictionary::Prepared(dict) => Some(dict.clone()),
+            _ => None,
+        },
+    });
+    opts.apply(ctx)?;
+
+    let state = ptr::NonNull::from(Box::leak(state));
+    Some(ZSTD.inner_encoder(state.cast(), [0; 2]))
+}
+
+#[cfg(feature = "alloc")]
 unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize, op: EncodeOp) -> Encode {
+    let state = unsafe {
+        &mut *(state.as_ptr() as *mut State)
+    };
+    encode(state.ctx, input, input_remain, output, output_remain, op)
+}
+
+//Shared with static context variant
+pub(super) unsafe fn encode(ctx: ptr::NonNull<sys::ZSTD_CCtx>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize, op: EncodeOp) -> Encode {
     let mut input = sys::ZSTD_inBuffer_s {
         src: input as _,
         size: input_remain,
@@ -165,7 +592,7 @@ unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usi
         pos: 0,
     };
     let result = unsafe {
-        sys::ZSTD_compressStream2(state.cast().as_ptr(), &mut output, &mut input, op.into_zstd())
+        sys::ZSTD_compressStream2(ctx.as_ptr(), &mut output, &mut input, op.into_zstd())
     };
 
     Encode {
@@ -191,28 +618,85 @@ unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usi
             size => match ZSTD_getErrorCode(size) {
                 //https://github.com/facebook/zstd/blob/dev/lib/zstd_errors.h#L64
                 70 | 80 => EncodeStatus::NeedOutput,
-                _ => EncodeStatus::Error,
+                code => EncodeStatus::Error(encode_error(code, size)),
             },
         },
     }
 }
 
+#[cold]
+#[inline(never)]
+fn encode_error(code: i32, result: usize) -> EncodeError {
+    //https://github.com/facebook/zstd/blob/dev/lib/zstd_errors.h#L64
+    let kind = match code {
+        40..=42 => EncodeErrorKind::UnsupportedParameter,
+        64 | 66 => EncodeErrorKind::OutOfMemory,
+        60 | 62 | 72 => EncodeErrorKind::InvalidState,
+        _ => EncodeErrorKind::Other,
+    };
+    EncodeError::new(kind, result as _)
+}
+
+#[cfg(feature = "alloc")]
 #[inline]
 fn reset_fn(state: ptr::NonNull<u8>, _: [u8; 2]) -> Option<ptr::NonNull<u8>> {
-    let result = unsafe {
-        sys::ZSTD_CCtx_reset(state.cast().as_ptr(), sys::ZSTD_ResetDirective::ZSTD_reset_session_only)
+    let ctx = unsafe {
+        (*(state.as_ptr() as *mut State)).ctx
     };
-    if result == 0 {
+    if reset(ctx) {
         Some(state)
     } else {
         None
     }
 }
 
+#[inline(always)]
+pub(super) fn reset(ctx: ptr::NonNull<sys::ZSTD_CCtx>) -> bool {
+    //Session only reset keeps parameters and dictionary
+    let result = unsafe {
+        sys::ZSTD_CCtx_reset(ctx.as_ptr(), sys::ZSTD_ResetDirective::ZSTD_reset_session_only)
+    };
+    result == 0
+}
+
+#[cfg(feature = "alloc")]
+#[inline]
+fn set_pledged_size_fn(state: ptr::NonNull<u8>, size: u64) -> bool {
+    let ctx = unsafe {
+        (*(state.as_ptr() as *mut State)).ctx
+    };
+    set_pledged_size(ctx, size)
+}
+
+#[inline(always)]
+pub(super) fn set_pledged_size(ctx: ptr::NonNull<sys::ZSTD_CCtx>, size: u64) -> bool {
+    let result = unsafe {
+        sys::ZSTD_CCtx_setPledgedSrcSize(ctx.as_ptr(), size as _)
+    };
+    unsafe {
+        sys::ZSTD_isError(result) == 0
+    }
+}
+
+#[cfg(feature = "alloc")]
 #[inline]
 fn drop_fn(state: ptr::NonNull<u8>) {
+    unsafe {
+        drop(Box::from_raw(state.as_ptr() as *mut State));
+    }
+}
+
+#[inline]
+pub(super) fn describe_error_fn(_: ptr::NonNull<u8>, code: i32) -> Option<&'static str> {
     let result = unsafe {
-        sys::ZSTD_freeCStream(state.cast().as_ptr())
+        sys::ZSTD_getErrorName(code as _)
     };
-    debug_assert_eq!(result, 0);
+    crate::utils::convert_c_str(result)
+}
+
+#[cfg(feature = "alloc")]
+#[inline]
+fn memory_tracker_fn(state: ptr::NonNull<u8>) -> Option<ptr::NonNull<MemoryTracker>> {
+    let state = unsafe { &*(state.as_ptr() as *const State) };
+    Some(ptr::NonNull::from(&*state.memory))
 }
@@ -0,0 +1,76 @@
+//! `zstd` interface implementation with context in caller provided workspace
+
+use zstd_sys as sys;
+
+use core::mem::MaybeUninit;
+use core::ptr;
+
+use super::zstd;
+use super::{Encode, EncodeOp, Encoder, Interface, ZstdOptions};
+
+static ZSTD_STATIC: Interface = Interface {
+    drop_fn,
+    reset_fn,
+    encode_fn,
+    describe_error_fn: zstd::describe_error_fn,
+    set_pledged_size_fn,
+    memory_tracker_fn: super::no_memory_tracker,
+};
+
+//zstd requires workspace to be 8 bytes aligned
+const ALIGN: usize = 8;
+//Workspace space not available to context, due to worst case alignment
+pub(super) const WORKSPACE_OVERHEAD: usize = ALIGN - 1;
+
+impl Interface {
+    ///Creates encoder with `zstd` interface, placing all of its state within `workspace`.
+    ///
+    ///Encoder never allocates, hence its memory usage is limited by `workspace`.
+    ///Required size can be computed by [ZstdOptions::estimate_size](struct.ZstdOptions.html#method.estimate_size).
+    ///
+    ///Dictionaries and multi-threading are not supported, as they cannot be used without allocation.
+    ///
+    ///Returns `None` if `workspace` is too small, options are invalid, dictionary or workers are set.
+    pub fn zstd_static<'a>(opts: ZstdOptions<'_>, workspace: &'a mut [MaybeUninit<u8>]) -> Option<Encoder<'a>> {
+        if opts.has_dictionary() || opts.has_workers() {
+            return None;
+        }
+
+        let offset = workspace.as_ptr().align_offset(ALIGN);
+        if workspace.len() < offset {
+            return None;
+        }
+        let workspace = &mut workspace[offset..];
+
+        //Context is the only state, hence it is used as instance directly
+        let ctx = unsafe {
+            sys::ZSTD_initStaticCStream(workspace.as_mut_ptr() as _, workspace.len())
+        };
+        let ctx = opts.apply(ptr::NonNull::new(ctx)?)?;
+        Some(ZSTD_STATIC.inner_encoder(ctx.cast(), [0; 2]))
+    }
+}
+
+#[inline]
+unsafe fn encode_fn(state: ptr::NonNull<u8>, input: *const u8, input_remain: usize, output: *mut u8, output_remain: usize, op: EncodeOp) -> Encode {
+    zstd::encode(state.cast(), input, input_remain, output, output_remain, op)
+}
+
+#[inline]
+fn reset_fn(state: ptr::NonNull<u8>, _: [u8; 2]) -> Option<ptr::NonNull<u8>> {
+    if zstd::reset(state.cast()) {
+        Some(state)
+    } else {
+        None
+    }
+}
+
+#[inline]
+fn set_pledged_size_fn(state: ptr::NonNull<u8>, size: u64) -> bool {
+    zstd::set_pledged_size(state.cast(), size)
+}
+
+#[inline]
+fn drop_fn(_: ptr::NonNull<u8>) {
+    //Static context owns no resources, as everything is within workspace
+}
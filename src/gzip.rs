@@ -0,0 +1,279 @@
+//!`gzip` header
+//!
+//!Used by `zlib` backends in `gzip` mode.
+//!Use [ZlibOptions::gzip_header](../encoder/struct.ZlibOptions.html#method.gzip_header) to write it
+//!and [Decoder::gzip_header](../decoder/struct.Decoder.html#method.gzip_header) to read it, once
+//!capture is enabled via [ZlibDecoderOptions::gzip_header](../decoder/struct.ZlibDecoderOptions.html#method.gzip_header).
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+///Operating system code, meaning unknown OS.
+pub const OS_UNKNOWN: u8 = 255;
+
+///Maximum size of `name` and `comment` captured by decoder.
+///
+///Longer values are truncated.
+pub const MAX_FIELD_SIZE: usize = 4096;
+///Maximum size of extra field.
+pub const MAX_EXTRA_SIZE: usize = u16::MAX as usize;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+///`gzip` member header
+///
+///Note that `name` and `comment` must not contain zero byte, as they are written as zero-terminated strings.
+pub struct GzipHeader {
+    ///Whether content is believed to be text
+    pub text: bool,
+    ///Modification time as unix timestamp, where 0 means unknown
+    pub mtime: u32,
+    ///Operating system on which compression took place
+    pub os: u8,
+    ///Raw extra field, consisting of subfields
+    pub extra: Option<Vec<u8>>,
+    ///Original file name, without terminating zero
+    pub name: Option<Vec<u8>>,
+    ///Comment, without terminating zero
+    pub comment: Option<Vec<u8>>,
+    ///Whether header is protected by CRC16
+    pub hcrc: bool,
+}
+
+impl GzipHeader {
+    #[inline(always)]
+    ///Creates new empty header with unknown OS and mtime.
+    pub const fn new() -> Self {
+        Self {
+            text: false,
+            mtime: 0,
+            os: OS_UNKNOWN,
+            extra: None,
+            name: None,
+            comment: None,
+            hcrc: false,
+        }
+    }
+
+    ///Appends extra subfield with identifier `id`.
+    ///
+    ///Returns `false` if extra field would exceed [MAX_EXTRA_SIZE](constant.MAX_EXTRA_SIZE.html).
+    pub fn push_extra_field(&mut self, id: [u8; 2], data: &[u8]) -> bool {
+        let extra = self.extra.get_or_insert_with(Vec::new);
+        if extra.len() + 4 + data.len() > MAX_EXTRA_SIZE {
+            return false;
+        }
+
+        extra.extend_from_slice(&id);
+        extra.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        extra.extend_from_slice(data);
+        true
+    }
+
+    #[inline(always)]
+    ///Returns iterator over extra subfields as pairs of identifier and data.
+    ///
+    ///Iteration stops at malformed subfield.
+    pub fn extra_fields(&self) -> ExtraFields<'_> {
+        ExtraFields {
+            extra: self.extra.as_deref().unwrap_or(&[]),
+        }
+    }
+
+    #[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+    #[inline(always)]
+    pub(crate) fn is_valid(&self) -> bool {
+        fn is_c_str(field: &Option<Vec<u8>>) -> bool {
+            field.as_ref().map_or(true, |field| !field.contains(&0))
+        }
+
+        is_c_str(&self.name) && is_c_str(&self.comment) && self.extra.as_ref().map_or(true, |extra| extra.len() <= MAX_EXTRA_SIZE)
+    }
+}
+
+impl Default for GzipHeader {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+///Iterator over extra subfields of [GzipHeader](struct.GzipHeader.html)
+pub struct ExtraFields<'a> {
+    extra: &'a [u8],
+}
+
+impl<'a> Iterator for ExtraFields<'a> {
+    type Item = ([u8; 2], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.extra.len() < 4 {
+            return None;
+        }
+
+        let id = [self.extra[0], self.extra[1]];
+        let len = u16::from_le_bytes([self.extra[2], self.extra[3]]) as usize;
+        match self.extra.get(4..4 + len) {
+            Some(data) => {
+                self.extra = &self.extra[4 + len..];
+                Some((id, data))
+            }
+            None => {
+                self.extra = &[];
+                None
+            }
+        }
+    }
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+//Owned header fields in form expected by zlib's `gz_header`
+pub(crate) struct RawHeader {
+    pub(crate) header: GzipHeader,
+    pub(crate) name: Vec<u8>,
+    pub(crate) comment: Vec<u8>,
+    pub(crate) extra: Vec<u8>,
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+impl RawHeader {
+    #[inline]
+    //Prepares header for writing, returning `None` if it is not valid
+    pub(crate) fn encoder(header: &GzipHeader) -> Option<Self> {
+        fn c_str(field: &Option<Vec<u8>>) -> Vec<u8> {
+            match field {
+                Some(field) => {
+                    let mut result = Vec::with_capacity(field.len() + 1);
+                    result.extend_from_slice(field);
+                    result.push(0);
+                    result
+                }
+                None => Vec::new(),
+            }
+        }
+
+        if !header.is_valid() {
+            return None;
+        }
+
+        Some(Self {
+            header: header.clone(),
+            name: c_str(&header.name),
+            comment: c_str(&header.comment),
+            extra: header.extra.clone().unwrap_or_default(),
+        })
+    }
+
+    #[inline]
+    //Allocates buffers to capture header while reading
+    pub(crate) fn decoder() -> Self {
+        Self {
+            header: GzipHeader::new(),
+            name: alloc::vec![0; MAX_FIELD_SIZE],
+            comment: alloc::vec![0; MAX_FIELD_SIZE],
+            extra: alloc::vec![0; MAX_EXTRA_SIZE],
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    //Converts header captured in buffers
+    pub(crate) fn read(&self, text: bool, mtime: u32, os: u8, extra_len: Option<usize>, name: bool, comment: bool, hcrc: bool) -> GzipHeader {
+        #[inline(always)]
+        fn c_str(buffer: &[u8]) -> Vec<u8> {
+            let len = buffer.iter().position(|byte| *byte == 0).unwrap_or(buffer.len());
+            buffer[..len].to_vec()
+        }
+
+        GzipHeader {
+            text,
+            mtime,
+            os,
+            extra: extra_len.map(|len| self.extra[..core::cmp::min(len, self.extra.len())].to_vec()),
+            name: if name { Some(c_str(&self.name)) } else { None },
+            comment: if comment { Some(c_str(&self.comment)) } else { None },
+            hcrc,
+        }
+    }
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+//Creates backend's `gz_header` to be written by deflate.
+//Returned value points to `raw`, so it must not outlive it
+macro_rules! internal_gz_header_deflate {
+    ($raw:expr) => {{
+        let raw: &mut $crate::gzip::RawHeader = $raw;
+        let ptr_or_null = |field: &mut alloc::vec::Vec<u8>| if field.is_empty() {
+            core::ptr::null_mut()
+        } else {
+            field.as_mut_ptr()
+        };
+
+        sys::gz_header {
+            text: raw.header.text as _,
+            time: raw.header.mtime as _,
+            xflags: 0,
+            os: raw.header.os as _,
+            extra: match raw.header.extra {
+                Some(_) => raw.extra.as_mut_ptr(),
+                None => core::ptr::null_mut(),
+            },
+            extra_len: raw.extra.len() as _,
+            extra_max: 0,
+            name: ptr_or_null(&mut raw.name),
+            name_max: 0,
+            comment: ptr_or_null(&mut raw.comment),
+            comm_max: 0,
+            hcrc: raw.header.hcrc as _,
+            done: 0,
+        }
+    }};
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+//Creates backend's `gz_header` to be filled by inflate.
+//Returned value points to `raw`, so it must not outlive it
+macro_rules! internal_gz_header_inflate {
+    ($raw:expr) => {{
+        let raw: &mut $crate::gzip::RawHeader = $raw;
+        sys::gz_header {
+            text: 0,
+            time: 0,
+            xflags: 0,
+            os: 0,
+            extra: raw.extra.as_mut_ptr(),
+            extra_len: 0,
+            extra_max: raw.extra.len() as _,
+            name: raw.name.as_mut_ptr(),
+            name_max: raw.name.len() as _,
+            comment: raw.comment.as_mut_ptr(),
+            comm_max: raw.comment.len() as _,
+            hcrc: 0,
+            done: 0,
+        }
+    }};
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+//Reads backend's `gz_header` filled by inflate, if it is complete
+macro_rules! internal_gz_header_read {
+    ($raw:expr, $header:expr) => {{
+        let raw: &$crate::gzip::RawHeader = $raw;
+        let header: &sys::gz_header = $header;
+        if header.done == 1 {
+            Some(raw.read(
+                header.text != 0,
+                header.time as _,
+                header.os as _,
+                match header.extra.is_null() {
+                    true => None,
+                    false => Some(header.extra_len as usize),
+                },
+                !header.name.is_null(),
+                !header.comment.is_null(),
+                header.hcrc != 0,
+            ))
+        } else {
+            None
+        }
+    }};
+}
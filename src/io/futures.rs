@@ -0,0 +1,231 @@
+//!`futures` async io adapters
+//!
+//!Requires `futures-io` feature.
+//!
+//!Writers propagate backpressure of underlying writer: compressed/decompressed output is written out before accepting new input.
+//!
+//!- `poll_flush` issues [EncodeOp::Flush](../../encoder/enum.EncodeOp.html)
+//!- `poll_close` issues [EncodeOp::Finish](../../encoder/enum.EncodeOp.html) or verifies that decompressed stream is complete.
+
+use core::mem;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+use std::io;
+
+use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
+
+use super::poll::{AsyncDecoderReader, AsyncDecoderWriter, AsyncEncoderReader, AsyncEncoderWriter};
+use super::DEFAULT_BUFFER;
+use crate::{Decoder, Encoder};
+
+///Compresses data written into it, passing compressed output to underlying `AsyncWrite`.
+///
+///Use `close` to complete stream.
+pub struct EncoderWriter<'a, W, const N: usize = DEFAULT_BUFFER> {
+    inner: AsyncEncoderWriter<'a, N>,
+    writer: W,
+}
+
+impl<'a, W: AsyncWrite + Unpin, const N: usize> EncoderWriter<'a, W, N> {
+    #[inline]
+    ///Creates new instance
+    pub const fn new(encoder: Encoder<'a>, writer: W) -> Self {
+        Self {
+            inner: AsyncEncoderWriter::new(encoder),
+            writer,
+        }
+    }
+
+    #[inline(always)]
+    ///Returns reference to underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    #[inline(always)]
+    ///Returns mutable reference to underlying writer
+    ///
+    ///Writing into it directly will corrupt compressed stream.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    #[inline(always)]
+    ///Returns encoder and underlying writer
+    ///
+    ///Any pending output is discarded, so make sure to close writer first.
+    pub fn into_parts(self) -> (Encoder<'a>, W) {
+        (self.inner.encoder, self.writer)
+    }
+}
+
+impl<W: AsyncWrite + Unpin, const N: usize> AsyncWrite for EncoderWriter<'_, W, N> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let writer = &mut this.writer;
+        this.inner.poll_write(cx, buf, |cx, data| Pin::new(&mut *writer).poll_write(cx, data))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let writer = &mut this.writer;
+        ready!(this.inner.poll_flush(cx, |cx, data| Pin::new(&mut *writer).poll_write(cx, data)))?;
+        Pin::new(writer).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let writer = &mut this.writer;
+        ready!(this.inner.poll_finish(cx, |cx, data| Pin::new(&mut *writer).poll_write(cx, data)))?;
+        Pin::new(writer).poll_close(cx)
+    }
+}
+
+///Compresses data read from underlying `AsyncBufRead`.
+///
+///Stream is finished once underlying reader reaches EOF.
+pub struct EncoderReader<'a, R> {
+    inner: AsyncEncoderReader<'a, R>,
+}
+
+impl<'a, R: AsyncBufRead + Unpin> EncoderReader<'a, R> {
+    #[inline]
+    ///Creates new instance
+    pub const fn new(encoder: Encoder<'a>, reader: R) -> Self {
+        Self {
+            inner: AsyncEncoderReader::new(encoder, reader),
+        }
+    }
+
+    #[inline(always)]
+    ///Returns reference to underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.inner.reader
+    }
+
+    #[inline(always)]
+    ///Returns mutable reference to underlying reader
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner.reader
+    }
+
+    #[inline(always)]
+    ///Returns encoder and underlying reader
+    pub fn into_parts(self) -> (Encoder<'a>, R) {
+        (self.inner.encoder, self.inner.reader)
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRead for EncoderReader<'_, R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        //Only initialized bytes are written into output
+        let output = unsafe { &mut *(buf as *mut [u8] as *mut [mem::MaybeUninit<u8>]) };
+        self.get_mut().inner.poll_read(cx, output, R::poll_fill_buf, R::consume)
+    }
+}
+
+///Decompresses data written into it, passing decompressed output to underlying `AsyncWrite`.
+///
+///`close` returns `UnexpectedEof` error if compressed stream is not complete.
+pub struct DecoderWriter<'a, W, const N: usize = DEFAULT_BUFFER> {
+    inner: AsyncDecoderWriter<'a, N>,
+    writer: W,
+}
+
+impl<'a, W: AsyncWrite + Unpin, const N: usize> DecoderWriter<'a, W, N> {
+    #[inline]
+    ///Creates new instance
+    pub const fn new(decoder: Decoder<'a>, writer: W) -> Self {
+        Self {
+            inner: AsyncDecoderWriter::new(decoder),
+            writer,
+        }
+    }
+
+    #[inline(always)]
+    ///Returns reference to underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    #[inline(always)]
+    ///Returns mutable reference to underlying writer
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    #[inline(always)]
+    ///Returns decoder and underlying writer
+    ///
+    ///Any pending output is discarded, so make sure to close writer first.
+    pub fn into_parts(self) -> (Decoder<'a>, W) {
+        (self.inner.decoder, self.writer)
+    }
+}
+
+impl<W: AsyncWrite + Unpin, const N: usize> AsyncWrite for DecoderWriter<'_, W, N> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let writer = &mut this.writer;
+        this.inner.poll_write(cx, buf, |cx, data| Pin::new(&mut *writer).poll_write(cx, data))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let writer = &mut this.writer;
+        ready!(this.inner.poll_flush(cx, |cx, data| Pin::new(&mut *writer).poll_write(cx, data)))?;
+        Pin::new(writer).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let writer = &mut this.writer;
+        ready!(this.inner.poll_finish(cx, |cx, data| Pin::new(&mut *writer).poll_write(cx, data)))?;
+        Pin::new(writer).poll_close(cx)
+    }
+}
+
+///Decompresses data read from underlying `AsyncBufRead`.
+///
+///Returns `UnexpectedEof` error if underlying reader reaches EOF before end of compressed stream.
+pub struct DecoderReader<'a, R> {
+    inner: AsyncDecoderReader<'a, R>,
+}
+
+impl<'a, R: AsyncBufRead + Unpin> DecoderReader<'a, R> {
+    #[inline]
+    ///Creates new instance
+    pub const fn new(decoder: Decoder<'a>, reader: R) -> Self {
+        Self {
+            inner: AsyncDecoderReader::new(decoder, reader),
+        }
+    }
+
+    #[inline(always)]
+    ///Returns reference to underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.inner.reader
+    }
+
+    #[inline(always)]
+    ///Returns mutable reference to underlying reader
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner.reader
+    }
+
+    #[inline(always)]
+    ///Returns decoder and underlying reader
+    ///
+    ///Any data after end of compressed stream is left unconsumed in reader.
+    pub fn into_parts(self) -> (Decoder<'a>, R) {
+        (self.inner.decoder, self.inner.reader)
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncRead for DecoderReader<'_, R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        //Only initialized bytes are written into output
+        let output = unsafe { &mut *(buf as *mut [u8] as *mut [mem::MaybeUninit<u8>]) };
+        self.get_mut().inner.poll_read(cx, output, R::poll_fill_buf, R::consume)
+    }
+}
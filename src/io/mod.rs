@@ -0,0 +1,416 @@
+//!`std::io` adapters
+//!
+//!Requires `std` feature.
+//!
+//!All adapters own [Encoder](../encoder/struct.Encoder.html) or [Decoder](../decoder/struct.Decoder.html) and use [Buffer] as scratch space
+//!when they need to write into underlying `Write`.
+//!
+//!Async adapters are available in [tokio](tokio/index.html) and [futures](futures/index.html) modules.
+
+use std::io::{self, BufRead, Read, Write};
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+mod poll;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+#[cfg(feature = "futures-io")]
+pub mod futures;
+
+use crate::{Buffer, DecodeError, DecodeErrorKind, DecodeStatus, Decoder, EncodeError, EncodeErrorKind, EncodeOp, EncodeStatus, Encoder};
+
+const DEFAULT_BUFFER: usize = 4096;
+
+#[cold]
+#[inline(never)]
+fn encode_error(encoder: &Encoder<'_>, error: EncodeError) -> io::Error {
+    let kind = match error.kind() {
+        EncodeErrorKind::OutOfMemory => io::ErrorKind::OutOfMemory,
+        _ => io::ErrorKind::Other,
+    };
+    match encoder.describe_error(error) {
+        Some(description) => io::Error::new(kind, description),
+        None => io::Error::new(kind, error),
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn finish_error() -> io::Error {
+    io::Error::other("compression cannot be finished")
+}
+
+#[cold]
+#[inline(never)]
+fn decode_error(decoder: &Decoder<'_>, error: DecodeError) -> io::Error {
+    let kind = match error.kind() {
+        DecodeErrorKind::OutOfMemory => io::ErrorKind::OutOfMemory,
+        _ => io::ErrorKind::InvalidData,
+    };
+    match decoder.describe_error(error) {
+        Some(description) => io::Error::new(kind, description),
+        None => io::Error::new(kind, error),
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn unexpected_eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "compressed stream is incomplete")
+}
+
+#[cold]
+#[inline(never)]
+fn trailing_data() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "trailing data after end of compressed stream")
+}
+
+#[inline(always)]
+fn flush_buffer<const N: usize, W: Write>(buffer: &mut Buffer<N>, writer: &mut W) -> io::Result<()> {
+    writer.write_all(buffer.data())?;
+    buffer.consume();
+    Ok(())
+}
+
+///Compresses data written into it, passing compressed output to underlying `Write`.
+///
+///Use [finish](#method.finish) to complete stream.
+///Dropping writer without finishing results in incomplete stream.
+pub struct EncoderWriter<'a, W, const N: usize = DEFAULT_BUFFER> {
+    encoder: Encoder<'a>,
+    writer: W,
+    buffer: Buffer<N>,
+}
+
+impl<'a, W: Write, const N: usize> EncoderWriter<'a, W, N> {
+    #[inline]
+    ///Creates new instance
+    pub const fn new(encoder: Encoder<'a>, writer: W) -> Self {
+        Self {
+            encoder,
+            writer,
+            buffer: Buffer::new(),
+        }
+    }
+
+    #[inline(always)]
+    ///Returns reference to underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    #[inline(always)]
+    ///Returns mutable reference to underlying writer
+    ///
+    ///Writing into it directly will corrupt compressed stream.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    fn encode_all(&mut self, op: EncodeOp) -> io::Result<()> {
+        loop {
+            let (_, status) = self.buffer.encode(&mut self.encoder, &[], op);
+            let written = self.buffer.data().len();
+            flush_buffer(&mut self.buffer, &mut self.writer)?;
+            match status {
+                EncodeStatus::NeedOutput if written > 0 => continue,
+                //Some encoders (e.g. zlib) report lack of output when there is nothing left to flush
+                EncodeStatus::NeedOutput => match op {
+                    EncodeOp::Finish => break Err(finish_error()),
+                    _ => break Ok(()),
+                },
+                EncodeStatus::Continue => match op {
+                    EncodeOp::Finish => continue,
+                    _ => break Ok(()),
+                },
+                EncodeStatus::Finished => break Ok(()),
+                EncodeStatus::Error(error) => break Err(encode_error(&self.encoder, error)),
+            }
+        }
+    }
+
+    ///Finishes compressed stream, returning underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.encode_all(EncodeOp::Finish)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    #[inline(always)]
+    ///Returns encoder and underlying writer, without finishing stream.
+    pub fn into_parts(self) -> (Encoder<'a>, W) {
+        (self.encoder, self.writer)
+    }
+}
+
+impl<W: Write, const N: usize> Write for EncoderWriter<'_, W, N> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let mut input = buf;
+        loop {
+            let (consumed, status) = self.buffer.encode(&mut self.encoder, input, EncodeOp::Process);
+            input = &input[consumed..];
+            let written = self.buffer.data().len();
+            flush_buffer(&mut self.buffer, &mut self.writer)?;
+            match status {
+                EncodeStatus::Error(error) => break Err(encode_error(&self.encoder, error)),
+                //Encoder may hold pending output internally, drain it before accepting more
+                EncodeStatus::NeedOutput if written > 0 => continue,
+                //Some encoders (e.g. zlib) fill output with pending data before consuming any input
+                EncodeStatus::Continue if written > 0 && input.len() == buf.len() => continue,
+                _ => break Ok(buf.len() - input.len()),
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encode_all(EncodeOp::Flush)?;
+        self.writer.flush()
+    }
+}
+
+///Compresses data read from underlying `BufRead`.
+///
+///Stream is finished once underlying reader reaches EOF.
+pub struct EncoderReader<'a, R> {
+    encoder: Encoder<'a>,
+    reader: R,
+    is_finished: bool,
+}
+
+impl<'a, R: BufRead> EncoderReader<'a, R> {
+    #[inline]
+    ///Creates new instance
+    pub const fn new(encoder: Encoder<'a>, reader: R) -> Self {
+        Self {
+            encoder,
+            reader,
+            is_finished: false,
+        }
+    }
+
+    #[inline(always)]
+    ///Returns reference to underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    #[inline(always)]
+    ///Returns mutable reference to underlying reader
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    #[inline(always)]
+    ///Returns encoder and underlying reader
+    pub fn into_parts(self) -> (Encoder<'a>, R) {
+        (self.encoder, self.reader)
+    }
+}
+
+impl<R: BufRead> Read for EncoderReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        while !self.is_finished {
+            let input = self.reader.fill_buf()?;
+            let input_len = input.len();
+            let op = match input_len {
+                0 => EncodeOp::Finish,
+                _ => EncodeOp::Process,
+            };
+            let result = self.encoder.encode(input, buf, op);
+            self.reader.consume(input_len - result.input_remain);
+
+            match result.status {
+                EncodeStatus::Error(error) => return Err(encode_error(&self.encoder, error)),
+                EncodeStatus::Finished => self.is_finished = true,
+                _ => (),
+            }
+
+            let written = buf.len() - result.output_remain;
+            if written > 0 {
+                return Ok(written);
+            }
+        }
+
+        Ok(0)
+    }
+}
+
+///Decompresses data written into it, passing decompressed output to underlying `Write`.
+///
+///Use [finish](#method.finish) to verify that compressed stream is complete.
+///
+///Writing after end of compressed stream fails with `InvalidData` error.
+pub struct DecoderWriter<'a, W, const N: usize = DEFAULT_BUFFER> {
+    decoder: Decoder<'a>,
+    writer: W,
+    buffer: Buffer<N>,
+    is_finished: bool,
+}
+
+impl<'a, W: Write, const N: usize> DecoderWriter<'a, W, N> {
+    #[inline]
+    ///Creates new instance
+    pub const fn new(decoder: Decoder<'a>, writer: W) -> Self {
+        Self {
+            decoder,
+            writer,
+            buffer: Buffer::new(),
+            is_finished: false,
+        }
+    }
+
+    #[inline(always)]
+    ///Returns reference to underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    #[inline(always)]
+    ///Returns mutable reference to underlying writer
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    ///Finishes decompression, returning underlying writer.
+    ///
+    ///Returns `UnexpectedEof` error if compressed stream is not complete
+    pub fn finish(mut self) -> io::Result<W> {
+        //Empty input signals end of input to decoders, that have no end marker in stream
+        while !self.is_finished {
+            let (_, status) = match self.buffer.decode(&mut self.decoder, &[]) {
+                Ok(result) => result,
+                Err(error) => return Err(decode_error(&self.decoder, error)),
+            };
+            let written = self.buffer.data().len();
+            flush_buffer(&mut self.buffer, &mut self.writer)?;
+            match status {
+                DecodeStatus::Finished => self.is_finished = true,
+                DecodeStatus::NeedOutput if written > 0 => continue,
+                _ => return Err(unexpected_eof()),
+            }
+        }
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    #[inline(always)]
+    ///Returns decoder and underlying writer
+    pub fn into_parts(self) -> (Decoder<'a>, W) {
+        (self.decoder, self.writer)
+    }
+}
+
+impl<W: Write, const N: usize> Write for DecoderWriter<'_, W, N> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        } else if self.is_finished {
+            return Err(trailing_data());
+        }
+
+        let mut input = buf;
+        loop {
+            let (consumed, status) = match self.buffer.decode(&mut self.decoder, input) {
+                Ok(result) => result,
+                Err(error) => break Err(decode_error(&self.decoder, error)),
+            };
+            input = &input[consumed..];
+            let written = self.buffer.data().len();
+            flush_buffer(&mut self.buffer, &mut self.writer)?;
+            match status {
+                DecodeStatus::Finished => {
+                    self.is_finished = true;
+                    break match buf.len() - input.len() {
+                        0 => Err(trailing_data()),
+                        consumed => Ok(consumed),
+                    };
+                }
+                //Decoder may hold pending output internally, drain it before accepting more
+                DecodeStatus::NeedOutput if written > 0 => continue,
+                _ => break Ok(buf.len() - input.len()),
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+///Decompresses data read from underlying `BufRead`.
+///
+///Returns `UnexpectedEof` error if underlying reader reaches EOF before end of compressed stream.
+pub struct DecoderReader<'a, R> {
+    decoder: Decoder<'a>,
+    reader: R,
+    is_finished: bool,
+}
+
+impl<'a, R: BufRead> DecoderReader<'a, R> {
+    #[inline]
+    ///Creates new instance
+    pub const fn new(decoder: Decoder<'a>, reader: R) -> Self {
+        Self {
+            decoder,
+            reader,
+            is_finished: false,
+        }
+    }
+
+    #[inline(always)]
+    ///Returns reference to underlying reader
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    #[inline(always)]
+    ///Returns mutable reference to underlying reader
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    #[inline(always)]
+    ///Returns decoder and underlying reader
+    ///
+    ///Any data after end of compressed stream is left unconsumed in reader.
+    pub fn into_parts(self) -> (Decoder<'a>, R) {
+        (self.decoder, self.reader)
+    }
+}
+
+impl<R: BufRead> Read for DecoderReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        while !self.is_finished {
+            let input = self.reader.fill_buf()?;
+            let input_len = input.len();
+            let result = self.decoder.decode(input, buf);
+            self.reader.consume(input_len - result.input_remain);
+
+            let written = buf.len() - result.output_remain;
+            match result.status {
+                Ok(DecodeStatus::Finished) => self.is_finished = true,
+                Ok(_) if input_len == 0 && written == 0 => return Err(unexpected_eof()),
+                Ok(_) => (),
+                Err(error) => return Err(decode_error(&self.decoder, error)),
+            }
+
+            if written > 0 {
+                return Ok(written);
+            }
+        }
+
+        Ok(0)
+    }
+}
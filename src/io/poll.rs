@@ -0,0 +1,306 @@
+//!Poll based core shared by async adapters
+
+use core::mem;
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+use std::io;
+
+use super::{decode_error, encode_error, finish_error, trailing_data, unexpected_eof};
+use crate::{Buffer, DecodeStatus, Decoder, EncodeOp, EncodeStatus, Encoder};
+
+///Buffer with compressed/decompressed output that is pending write into underlying writer.
+pub(crate) struct PendingBuffer<const N: usize> {
+    buffer: Buffer<N>,
+    written: usize,
+}
+
+impl<const N: usize> PendingBuffer<N> {
+    #[inline(always)]
+    pub(crate) const fn new() -> Self {
+        Self {
+            buffer: Buffer::new(),
+            written: 0,
+        }
+    }
+
+    ///Writes all pending data, returning `Ready` only when buffer is empty.
+    pub(crate) fn poll_drain(&mut self, cx: &mut Context<'_>, mut write: impl FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>) -> Poll<io::Result<()>> {
+        loop {
+            let data = &self.buffer.data()[self.written..];
+            if data.is_empty() {
+                break;
+            }
+
+            match ready!(write(cx, data)) {
+                Ok(0) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Ok(size) => self.written += size,
+                Err(error) => return Poll::Ready(Err(error)),
+            }
+        }
+
+        self.buffer.consume();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+pub(crate) struct AsyncEncoderWriter<'a, const N: usize> {
+    pub(crate) encoder: Encoder<'a>,
+    buffer: PendingBuffer<N>,
+    is_finished: bool,
+}
+
+impl<'a, const N: usize> AsyncEncoderWriter<'a, N> {
+    #[inline(always)]
+    pub(crate) const fn new(encoder: Encoder<'a>) -> Self {
+        Self {
+            encoder,
+            buffer: PendingBuffer::new(),
+            is_finished: false,
+        }
+    }
+
+    pub(crate) fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8], mut write: impl FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>) -> Poll<io::Result<usize>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        loop {
+            //Output must be written out before encoder can continue
+            ready!(self.buffer.poll_drain(cx, &mut write))?;
+
+            let (consumed, status) = self.buffer.buffer.encode(&mut self.encoder, buf, EncodeOp::Process);
+            match status {
+                EncodeStatus::Error(error) => break Poll::Ready(Err(encode_error(&self.encoder, error))),
+                EncodeStatus::NeedOutput if consumed == 0 => continue,
+                //Some encoders (e.g. zlib) fill output with pending data before consuming any input
+                EncodeStatus::Continue if consumed == 0 && !self.buffer.buffer.data().is_empty() => continue,
+                _ => break Poll::Ready(Ok(consumed)),
+            }
+        }
+    }
+
+    fn poll_encode_all(&mut self, cx: &mut Context<'_>, op: EncodeOp, mut write: impl FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>) -> Poll<io::Result<()>> {
+        loop {
+            ready!(self.buffer.poll_drain(cx, &mut write))?;
+
+            //Repeating `Flush` is harmless, so it is fine to re-issue it after `Pending`
+            let (_, status) = self.buffer.buffer.encode(&mut self.encoder, &[], op);
+            let written = self.buffer.buffer.data().len();
+            match status {
+                EncodeStatus::NeedOutput if written > 0 => continue,
+                //Some encoders (e.g. zlib) report lack of output when there is nothing left to flush
+                EncodeStatus::NeedOutput => match op {
+                    EncodeOp::Finish => return Poll::Ready(Err(finish_error())),
+                    _ => break,
+                },
+                EncodeStatus::Continue => match op {
+                    EncodeOp::Finish => continue,
+                    _ => break,
+                },
+                EncodeStatus::Finished => break,
+                EncodeStatus::Error(error) => return Poll::Ready(Err(encode_error(&self.encoder, error))),
+            }
+        }
+
+        self.is_finished = op == EncodeOp::Finish;
+        self.buffer.poll_drain(cx, write)
+    }
+
+    #[inline(always)]
+    pub(crate) fn poll_flush(&mut self, cx: &mut Context<'_>, write: impl FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>) -> Poll<io::Result<()>> {
+        if self.is_finished {
+            return Poll::Ready(Ok(()));
+        }
+        self.poll_encode_all(cx, EncodeOp::Flush, write)
+    }
+
+    #[inline(always)]
+    pub(crate) fn poll_finish(&mut self, cx: &mut Context<'_>, write: impl FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>) -> Poll<io::Result<()>> {
+        if self.is_finished {
+            return self.buffer.poll_drain(cx, write);
+        }
+        self.poll_encode_all(cx, EncodeOp::Finish, write)
+    }
+}
+
+pub(crate) struct AsyncDecoderWriter<'a, const N: usize> {
+    pub(crate) decoder: Decoder<'a>,
+    buffer: PendingBuffer<N>,
+    status: DecodeStatus,
+}
+
+impl<'a, const N: usize> AsyncDecoderWriter<'a, N> {
+    #[inline(always)]
+    pub(crate) const fn new(decoder: Decoder<'a>) -> Self {
+        Self {
+            decoder,
+            buffer: PendingBuffer::new(),
+            status: DecodeStatus::NeedInput,
+        }
+    }
+
+    fn decode(&mut self, input: &[u8]) -> io::Result<usize> {
+        match self.buffer.buffer.decode(&mut self.decoder, input) {
+            Ok((consumed, status)) => {
+                self.status = status;
+                Ok(consumed)
+            }
+            Err(error) => Err(decode_error(&self.decoder, error)),
+        }
+    }
+
+    pub(crate) fn poll_write(&mut self, cx: &mut Context<'_>, buf: &[u8], mut write: impl FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>) -> Poll<io::Result<usize>> {
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        loop {
+            ready!(self.buffer.poll_drain(cx, &mut write))?;
+
+            if self.status == DecodeStatus::Finished {
+                break Poll::Ready(Err(trailing_data()));
+            }
+
+            let consumed = self.decode(buf)?;
+            //Stream may finish without consuming input, in which case remaining input is reported as trailing data
+            if consumed > 0 || !matches!(self.status, DecodeStatus::NeedOutput | DecodeStatus::Finished) {
+                break Poll::Ready(Ok(consumed));
+            }
+        }
+    }
+
+    ///Writes out all output, which decoder might hold internally
+    pub(crate) fn poll_flush(&mut self, cx: &mut Context<'_>, mut write: impl FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>) -> Poll<io::Result<()>> {
+        loop {
+            ready!(self.buffer.poll_drain(cx, &mut write))?;
+
+            if self.status != DecodeStatus::NeedOutput {
+                break Poll::Ready(Ok(()));
+            }
+
+            self.decode(&[])?;
+            if self.buffer.buffer.data().is_empty() {
+                break Poll::Ready(Ok(()));
+            }
+        }
+    }
+
+    pub(crate) fn poll_finish(&mut self, cx: &mut Context<'_>, mut write: impl FnMut(&mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>) -> Poll<io::Result<()>> {
+        loop {
+            ready!(self.poll_flush(cx, &mut write))?;
+
+            if self.status == DecodeStatus::Finished {
+                break Poll::Ready(Ok(()));
+            }
+
+            //Empty input signals end of input to decoders, that have no end marker in stream
+            self.decode(&[])?;
+            if self.status != DecodeStatus::Finished && self.buffer.buffer.data().is_empty() {
+                break Poll::Ready(Err(unexpected_eof()));
+            }
+        }
+    }
+}
+
+///`poll_fill_buf` of underlying `AsyncBufRead`
+pub(crate) type FillBuf<R> = for<'r> fn(Pin<&'r mut R>, &mut Context<'_>) -> Poll<io::Result<&'r [u8]>>;
+///`consume` of underlying `AsyncBufRead`
+pub(crate) type Consume<R> = fn(Pin<&mut R>, usize);
+
+pub(crate) struct AsyncEncoderReader<'a, R> {
+    pub(crate) encoder: Encoder<'a>,
+    pub(crate) reader: R,
+    is_finished: bool,
+}
+
+impl<'a, R: Unpin> AsyncEncoderReader<'a, R> {
+    #[inline(always)]
+    pub(crate) const fn new(encoder: Encoder<'a>, reader: R) -> Self {
+        Self {
+            encoder,
+            reader,
+            is_finished: false,
+        }
+    }
+
+    ///Compresses data of underlying reader into `output`, returning number of written bytes.
+    ///
+    ///Stream is finished once underlying reader reaches EOF.
+    pub(crate) fn poll_read(&mut self, cx: &mut Context<'_>, output: &mut [mem::MaybeUninit<u8>], fill_buf: FillBuf<R>, consume: Consume<R>) -> Poll<io::Result<usize>> {
+        if output.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        while !self.is_finished {
+            let input = ready!(fill_buf(Pin::new(&mut self.reader), cx))?;
+            let op = match input.len() {
+                0 => EncodeOp::Finish,
+                _ => EncodeOp::Process,
+            };
+            let result = self.encoder.encode_uninit(input, output, op);
+            let consumed = input.len() - result.input_remain;
+            consume(Pin::new(&mut self.reader), consumed);
+
+            match result.status {
+                EncodeStatus::Error(error) => return Poll::Ready(Err(encode_error(&self.encoder, error))),
+                EncodeStatus::Finished => self.is_finished = true,
+                _ => (),
+            }
+
+            let written = output.len() - result.output_remain;
+            if written > 0 {
+                return Poll::Ready(Ok(written));
+            }
+        }
+
+        Poll::Ready(Ok(0))
+    }
+}
+
+pub(crate) struct AsyncDecoderReader<'a, R> {
+    pub(crate) decoder: Decoder<'a>,
+    pub(crate) reader: R,
+    is_finished: bool,
+}
+
+impl<'a, R: Unpin> AsyncDecoderReader<'a, R> {
+    #[inline(always)]
+    pub(crate) const fn new(decoder: Decoder<'a>, reader: R) -> Self {
+        Self {
+            decoder,
+            reader,
+            is_finished: false,
+        }
+    }
+
+    ///Decompresses data of underlying reader into `output`, returning number of written bytes.
+    ///
+    ///Returns `UnexpectedEof` error if underlying reader reaches EOF before end of compressed stream.
+    pub(crate) fn poll_read(&mut self, cx: &mut Context<'_>, output: &mut [mem::MaybeUninit<u8>], fill_buf: FillBuf<R>, consume: Consume<R>) -> Poll<io::Result<usize>> {
+        if output.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        while !self.is_finished {
+            let input = ready!(fill_buf(Pin::new(&mut self.reader), cx))?;
+            let input_len = input.len();
+            let result = self.decoder.decode_uninit(input, output);
+            consume(Pin::new(&mut self.reader), input_len - result.input_remain);
+
+            let written = output.len() - result.output_remain;
+            match result.status {
+                Ok(DecodeStatus::Finished) => self.is_finished = true,
+                Ok(_) if input_len == 0 && written == 0 => return Poll::Ready(Err(unexpected_eof())),
+                Ok(_) => (),
+                Err(error) => return Poll::Ready(Err(decode_error(&self.decoder, error))),
+            }
+
+            if written > 0 {
+                return Poll::Ready(Ok(written));
+            }
+        }
+
+        Poll::Ready(Ok(0))
+    }
+}
@@ -11,17 +11,33 @@
 //!
 //!## Features
 //!
-//!All features are off by default.
-//!This crate requires `alloc` to be available with system allocator set.
+//!All features are off by default, except `alloc`.
 //!
-//!- `brotli-c` - Enables `brotli` interface using C library.
+//!- `alloc` - Enables heap allocated backends, `Vec` helpers, [gzip header](gzip/index.html) and [dictionary transport](dictionary_transport/index.html). Requires system allocator set.
+//!- `brotli-c` - Enables `brotli` interface using C library, with custom dictionaries support.
 //!- `brotli-rust` - Enables `brotli` interface using pure Rust library.
 //!- `zlib` - Enables `zlib` interface.
 //!- `zlib-static` - Enables `zlib` interface with `static` feature.
 //!- `zlib-ng` - Enables `zlib-ng` interface.
 //!- `zlib-rust` - Enables `zlib-rs` interface.
-//!- `zstd` - Enables `zstd` interface.
+//!- `zstd` - Enables `zstd` interface and [dictionary builder](zstd/index.html).
+//!- `zstd-mt` - Enables multi-threaded compression for `zstd` interface.
+//!- `lz4` - Enables `lz4` frame interface using C library.
+//!- `lz4-rust` - Enables `lz4` frame interface using pure Rust library.
+//!- `xz` - Enables `xz` interface using `liblzma`.
+//!- `bzip2` - Enables `bzip2` interface.
+//!- `snappy` - Enables `snappy` framing format interface using pure Rust implementation.
 //!- `bytes` - Enables `bytes` support
+//!- `std` - Enables `std::io` adapters in [io](io/index.html) module
+//!- `tokio` - Enables `tokio` async adapters in [io::tokio](io/tokio/index.html) module. Implies `std`.
+//!- `futures-io` - Enables `futures-io` async adapters in [io::futures](io/futures/index.html) module. Implies `std`.
+//!
+//!Enabling any `zlib` backend allows to write and read [gzip header](gzip/index.html).
+//!
+//!Enabling `brotli-c` or `zstd` also enables [dictionary transport](dictionary_transport/index.html) framing helpers.
+//!
+//!Without `alloc` crate builds on targets without heap, but only `brotli-rust`, `zlib-rust` and `zstd` are available.
+//!Their `*_static` constructors place all of the state within caller provided memory, producing `Encoder<'a>`/`Decoder<'a>` bound to it.
 //!
 //!## Usage
 //!
@@ -31,6 +47,7 @@
 //!Use [Interface](decoder/struct.Interface.html) to create instance.
 //!
 //!If you unsure about compression used, you can try [detect](decoder/enum.Detection.html#method.detect) it
+//!or use [auto](decoder/struct.Interface.html#method.auto) decoder which does it for you.
 //!
 //!```rust,no_run
 //!use compu::{Decoder, DecodeStatus, DecodeError};
@@ -79,12 +96,11 @@
 //!                 break output;
 //!             }
 //!             //Generally can indicate internal error likely due to OOM condition.
-//!             //Note that all wrappers ensure that Rust's global allocator is used,
+//!             //Note that wrappers use Rust's global allocator, unless created with custom `mem::Allocator`,
 //!             //so take care if you use custom one
-//!             //Generally should not happen, so it is ok to just panic
-//!             //but be a good boy and return error properly if it happens, even if it is unlikely
-//!             EncodeStatus::Error => {
-//!                 panic!("unlikely")
+//!             //Error carries backend specific code, use `Encoder::describe_error` to get its description
+//!             EncodeStatus::Error(error) => {
+//!                 panic!("{error}: {:?}", encoder.describe_error(error))
 //!             }
 //!         }
 //!     }
@@ -95,18 +111,36 @@
 #![warn(missing_docs)]
 #![allow(clippy::style, clippy::derivable_impls)]
 
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+#[macro_use]
+pub mod gzip;
 pub mod decoder;
 #[cfg(any(
     feature = "zlib",
     feature = "zlib-static",
+    feature = "zlib-rust",
     feature = "zlib-ng",
     feature = "brotli-c",
-    feature = "zstd"
+    feature = "zstd",
+    feature = "lz4",
+    feature = "xz",
+    feature = "bzip2"
 ))]
 pub(crate) mod utils;
-pub use decoder::{Decode, DecodeError, DecodeStatus, Decoder, Detection};
+#[cfg(feature = "snappy")]
+mod snappy;
+pub use decoder::{Decode, DecodeError, DecodeErrorKind, DecodeStatus, Decoder, Detection};
 pub mod encoder;
-pub use encoder::{Encode, EncodeOp, EncodeStatus, Encoder};
+pub use encoder::{Encode, EncodeError, EncodeErrorKind, EncodeOp, EncodeStatus, Encoder};
 mod buffer;
 pub mod mem;
 pub use buffer::Buffer;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(all(feature = "zstd", feature = "alloc"))]
+pub mod zstd;
+#[cfg(all(any(feature = "brotli-c", feature = "zstd"), feature = "alloc"))]
+pub mod dictionary_transport;
@@ -1,11 +1,18 @@
 //!Custom malloc implementation which uses Rust's allocator and provides common interface required by compression libraries
-use core::ffi::{c_uint, c_void};
+#[cfg(feature = "alloc")]
+use core::ffi::c_uint;
+use core::ffi::c_void;
+#[cfg(feature = "bzip2")]
+use core::ffi::c_int;
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
-use alloc::alloc::Layout;
+#[cfg(feature = "alloc")]
 pub use alloc::boxed::Box;
-use core::{mem, ptr};
+use core::alloc::Layout;
+use core::cell::Cell;
+use core::{fmt, mem, ptr};
 
 //Linux & win 32 bit are 8
 #[cfg(not(any(target_os = "macos", all(windows, target_pointer_width = "64"))))]
@@ -22,11 +29,195 @@ fn unlikely_null() -> *mut c_void {
     ptr::null_mut()
 }
 
-#[inline]
-///`malloc` impl with Rust allocator
-pub unsafe extern "C" fn compu_malloc(size: usize) -> *mut c_void {
+///Memory allocator, which can be used by backends instead of Rust's global allocator.
+///
+///Allocator is owned by `Encoder`/`Decoder` it is passed to, hence it is only used by single thread at a time.
+///
+///## Safety
+///
+///`alloc` must return either null or memory block valid for `layout`, which stays valid until passed to `dealloc`.
+pub unsafe trait Allocator: Send {
+    ///Allocates memory block for `layout`, returning null on failure.
+    fn alloc(&self, layout: Layout) -> *mut u8;
+
+    ///Deallocates memory block.
+    ///
+    ///## Safety
+    ///
+    ///`ptr` must be returned by `alloc` of the same allocator with the same `layout`.
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout);
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, Default)]
+///Rust's global allocator
+pub struct Global;
+
+#[cfg(feature = "alloc")]
+unsafe impl Allocator for Global {
+    #[inline(always)]
+    fn alloc(&self, layout: Layout) -> *mut u8 {
+        if layout.size() == 0 {
+            return ptr::null_mut();
+        }
+        unsafe { alloc::alloc::alloc(layout) }
+    }
+
+    #[inline(always)]
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        alloc::alloc::dealloc(ptr, layout)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+///Memory allocated by backend
+pub struct MemoryUsage {
+    ///Number of bytes currently allocated
+    pub current: usize,
+    ///Maximum number of bytes allocated at once
+    pub peak: usize,
+}
+
+///Per-instance allocation tracker.
+///
+///Backends using crate allocator pass pointer to tracker as `opaque` argument of allocation functions,
+///which count allocated bytes in it and fail allocations above budget.
+///Memory is allocated by tracker's [Allocator], if any, or Rust's global allocator otherwise.
+///
+///Tracker must outlive every allocation made with it.
+pub struct MemoryTracker {
+    usage: Cell<MemoryUsage>,
+    budget: Cell<Option<usize>>,
+    #[cfg(feature = "alloc")]
+    allocator: Option<Box<dyn Allocator>>,
+}
+
+impl MemoryTracker {
+    #[inline(always)]
+    ///Creates new tracker without budget, using Rust's global allocator
+    pub const fn new() -> Self {
+        Self {
+            usage: Cell::new(MemoryUsage {
+                current: 0,
+                peak: 0,
+            }),
+            budget: Cell::new(None),
+            #[cfg(feature = "alloc")]
+            allocator: None,
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline(always)]
+    ///Creates new tracker without budget, using `allocator`
+    pub fn with_allocator<A: Allocator + 'static>(allocator: A) -> Self {
+        let mut this = Self::new();
+        this.allocator = Some(Box::new(allocator));
+        this
+    }
+
+    #[inline(always)]
+    ///Returns current memory usage
+    pub fn usage(&self) -> MemoryUsage {
+        self.usage.get()
+    }
+
+    #[inline(always)]
+    ///Returns maximum number of bytes that can be allocated at once, if any.
+    pub fn budget(&self) -> Option<usize> {
+        self.budget.get()
+    }
+
+    #[inline(always)]
+    ///Sets maximum number of bytes that can be allocated at once.
+    ///
+    ///Memory already allocated is not affected, but new allocations fail until usage is below budget.
+    pub fn set_budget(&self, budget: Option<usize>) {
+        self.budget.set(budget);
+    }
+
+    #[allow(unused)]
+    #[inline(always)]
+    ///Returns pointer to be passed as `opaque` argument of allocation functions
+    pub(crate) fn as_opaque(&self) -> *mut c_void {
+        self as *const Self as *mut c_void
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn reserve(&self, size: usize) -> bool {
+        let mut usage = self.usage.get();
+        let current = match usage.current.checked_add(size) {
+            Some(current) if self.budget.get().map_or(true, |budget| current <= budget) => current,
+            _ => return false,
+        };
+        usage.current = current;
+        if usage.peak < current {
+            usage.peak = current;
+        }
+        self.usage.set(usage);
+        true
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline]
+    fn release(&self, size: usize) {
+        let mut usage = self.usage.get();
+        usage.current = usage.current.saturating_sub(size);
+        self.usage.set(usage);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline]
+    unsafe fn malloc(&self, size: usize) -> *mut c_void {
+        if !self.reserve(size) {
+            return unlikely_null();
+        }
+        let mem = match self.allocator.as_ref() {
+            Some(allocator) => malloc_with(size, |layout| allocator.alloc(layout)),
+            None => compu_malloc(size),
+        };
+        if mem.is_null() {
+            self.release(size);
+        }
+        mem
+    }
+
+    #[cfg(feature = "alloc")]
+    #[inline]
+    unsafe fn free(&self, mem: *mut c_void) {
+        let size = match self.allocator.as_ref() {
+            Some(allocator) => free_with(mem, |mem, layout| allocator.dealloc(mem, layout)),
+            None => free_with(mem, |mem, layout| alloc::alloc::dealloc(mem, layout)),
+        };
+        self.release(size);
+    }
+}
+
+impl Default for MemoryTracker {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MemoryTracker {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut fmt = fmt.debug_struct("MemoryTracker");
+        fmt.field("usage", &self.usage.get());
+        fmt.field("budget", &self.budget.get());
+        #[cfg(feature = "alloc")]
+        fmt.field("custom_allocator", &self.allocator.is_some());
+        fmt.finish()
+    }
+}
+
+#[allow(unused)]
+#[inline(always)]
+//Allocates memory with size stored in front of it, as C libraries free memory without size
+unsafe fn malloc_with(size: usize, alloc: impl FnOnce(Layout) -> *mut u8) -> *mut c_void {
     if let Ok(layout) = Layout::from_size_align(size + LAYOUT_OFFSET, MIN_ALIGN) {
-        let mem = alloc::alloc::alloc(layout);
+        let mem = alloc(layout);
         if !mem.is_null() {
             ptr::write(mem as *mut usize, size);
             return mem.add(LAYOUT_OFFSET) as _;
@@ -36,30 +227,54 @@ pub unsafe extern "C" fn compu_malloc(size: usize) -> *mut c_void {
     unlikely_null()
 }
 
+#[allow(unused)]
+#[inline(always)]
+//Frees memory allocated by `malloc_with`, returning its size
+unsafe fn free_with(mem: *mut c_void, dealloc: impl FnOnce(*mut u8, Layout)) -> usize {
+    if mem.is_null() {
+        return 0;
+    }
+    let mem = (mem as *mut u8).offset(-(LAYOUT_OFFSET as isize));
+    let size = ptr::read(mem as *const usize);
+    let layout = Layout::from_size_align_unchecked(size + LAYOUT_OFFSET, MIN_ALIGN);
+    dealloc(mem, layout);
+    size
+}
+
+#[cfg(feature = "alloc")]
+#[inline]
+///`malloc` impl with Rust allocator
+pub unsafe extern "C" fn compu_malloc(size: usize) -> *mut c_void {
+    malloc_with(size, |layout| alloc::alloc::alloc(layout))
+}
+
+#[cfg(feature = "alloc")]
 #[inline]
 ///`free` impl with Rust allocator
 pub unsafe extern "C" fn compu_free(mem: *mut c_void) {
-    if !mem.is_null() {
-        let mem = (mem as *mut u8).offset(-(LAYOUT_OFFSET as isize));
-        let size = ptr::read(mem as *const usize);
-        let layout = Layout::from_size_align_unchecked(size + LAYOUT_OFFSET, MIN_ALIGN);
-        alloc::alloc::dealloc(mem, layout);
-    }
+    free_with(mem, |mem, layout| alloc::alloc::dealloc(mem, layout));
 }
 
+#[cfg(feature = "alloc")]
 #[allow(unused)]
 ///`malloc` alternative with Rust allocator
+///
+///`opaque` is either null or pointer to [MemoryTracker]
 pub(crate) unsafe extern "C" fn compu_malloc_with_state(
-    _: *mut c_void,
+    opaque: *mut c_void,
     size: usize,
 ) -> *mut c_void {
-    compu_malloc(size)
+    match (opaque as *const MemoryTracker).as_ref() {
+        Some(tracker) => tracker.malloc(size),
+        None => compu_malloc(size),
+    }
 }
 
+#[cfg(feature = "alloc")]
 #[allow(unused)]
 ///`alloc` alternative with Rust allocator
 pub(crate) unsafe extern "C" fn compu_alloc(
-    _: *mut c_void,
+    opaque: *mut c_void,
     items: c_uint,
     size: c_uint,
 ) -> *mut c_void {
@@ -67,31 +282,265 @@ pub(crate) unsafe extern "C" fn compu_alloc(
         Some(0) | None => return unlikely_null(),
         Some(size) => size,
     };
-    compu_malloc(size)
+    compu_malloc_with_state(opaque, size)
 }
 
+#[cfg(feature = "alloc")]
 #[allow(unused)]
-pub(crate) unsafe extern "C" fn compu_free_with_state(_: *mut c_void, mem: *mut c_void) {
-    compu_free(mem)
+///`free` alternative with Rust allocator
+///
+///`opaque` is either null or pointer to [MemoryTracker]
+pub(crate) unsafe extern "C" fn compu_free_with_state(opaque: *mut c_void, mem: *mut c_void) {
+    match (opaque as *const MemoryTracker).as_ref() {
+        Some(tracker) => tracker.free(mem),
+        None => compu_free(mem),
+    }
+}
+
+#[cfg(feature = "lz4")]
+#[repr(C)]
+///`LZ4F_CustomMem`, which is not exposed by `lz4-sys`
+pub(crate) struct Lz4CustomMem {
+    alloc: unsafe extern "C" fn(*mut c_void, usize) -> *mut c_void,
+    calloc: Option<unsafe extern "C" fn(*mut c_void, usize) -> *mut c_void>,
+    free: unsafe extern "C" fn(*mut c_void, *mut c_void),
+    opaque: *mut c_void,
+}
+
+#[cfg(feature = "lz4")]
+impl Lz4CustomMem {
+    #[inline(always)]
+    ///Creates allocator using `tracker` to allocate and count memory
+    pub(crate) fn new(tracker: &MemoryTracker) -> Self {
+        Self {
+            alloc: compu_malloc_with_state,
+            calloc: None,
+            free: compu_free_with_state,
+            opaque: tracker.as_opaque(),
+        }
+    }
+}
+
+#[cfg(feature = "bzip2")]
+///`bzalloc` alternative with Rust allocator
+pub(crate) extern "C" fn compu_bzalloc(opaque: *mut c_void, items: c_int, size: c_int) -> *mut c_void {
+    unsafe { compu_alloc(opaque, items as _, size as _) }
+}
+
+#[cfg(feature = "bzip2")]
+///`bzfree` alternative with Rust allocator
+pub(crate) extern "C" fn compu_bzfree(opaque: *mut c_void, mem: *mut c_void) {
+    unsafe { compu_free_with_state(opaque, mem) }
+}
+
+#[cfg(feature = "xz")]
+extern "C" fn compu_lzma_alloc(opaque: *mut c_void, items: usize, size: usize) -> *mut c_void {
+    match items.checked_mul(size) {
+        Some(size) => unsafe { compu_malloc_with_state(opaque, size) },
+        None => unlikely_null(),
+    }
+}
+
+#[cfg(feature = "xz")]
+extern "C" fn compu_lzma_free(opaque: *mut c_void, mem: *mut c_void) {
+    unsafe { compu_free_with_state(opaque, mem) }
+}
+
+#[cfg(feature = "xz")]
+#[inline(always)]
+///Creates `lzma_allocator` using `tracker` to allocate and count memory
+pub(crate) fn lzma_allocator(tracker: &MemoryTracker) -> lzma_sys::lzma_allocator {
+    lzma_sys::lzma_allocator {
+        alloc: Some(compu_lzma_alloc),
+        free: Some(compu_lzma_free),
+        opaque: tracker.as_opaque(),
+    }
+}
+
+#[cfg(any(feature = "brotli-rust", feature = "zlib-rust"))]
+#[repr(C)]
+//Header of free block, stored within the block itself
+struct FreeBlock {
+    size: usize,
+    next: *mut FreeBlock,
+}
+
+#[cfg(any(feature = "brotli-rust", feature = "zlib-rust"))]
+//Every block is multiple of header size, hence it is also alignment of every block
+const BLOCK_SIZE: usize = mem::size_of::<FreeBlock>();
+
+#[cfg(any(feature = "brotli-rust", feature = "zlib-rust"))]
+#[inline(always)]
+const fn block_size(size: usize) -> Option<usize> {
+    let size = if size == 0 { 1 } else { size };
+    match size.checked_add(BLOCK_SIZE - 1) {
+        Some(size) => Some(size / BLOCK_SIZE * BLOCK_SIZE),
+        None => None,
+    }
+}
+
+#[cfg(any(feature = "brotli-rust", feature = "zlib-rust"))]
+///First-fit allocator within caller provided memory.
+///
+///Free blocks are kept in list sorted by address, so that adjacent blocks are merged on release.
+pub(crate) struct Workspace {
+    free: Cell<*mut FreeBlock>,
+}
+
+#[cfg(any(feature = "brotli-rust", feature = "zlib-rust"))]
+impl Workspace {
+    ///Places workspace at the start of `memory`, managing the rest of it.
+    ///
+    ///Returns `None` if `memory` cannot fit workspace itself.
+    pub(crate) fn new(memory: &mut [mem::MaybeUninit<u8>]) -> Option<&Self> {
+        const HEADER_SIZE: usize = mem::size_of::<Workspace>().div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+
+        let offset = memory.as_ptr().align_offset(BLOCK_SIZE);
+        let size = memory.len().checked_sub(offset)?.checked_sub(HEADER_SIZE)?;
+        let size = size / BLOCK_SIZE * BLOCK_SIZE;
+        unsafe {
+            let this = memory.as_mut_ptr().add(offset) as *mut Self;
+            let free = match size {
+                0 => ptr::null_mut(),
+                size => {
+                    let block = (this as *mut u8).add(HEADER_SIZE) as *mut FreeBlock;
+                    block.write(FreeBlock {
+                        size,
+                        next: ptr::null_mut(),
+                    });
+                    block
+                }
+            };
+            this.write(Self {
+                free: Cell::new(free),
+            });
+            Some(&*this)
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn link(&self, prev: *mut FreeBlock, next: *mut FreeBlock) {
+        match prev.is_null() {
+            true => self.free.set(next),
+            false => (*prev).next = next,
+        }
+    }
+
+    ///Allocates memory block for `layout`, returning null if there is no free block big enough.
+    pub(crate) fn alloc(&self, layout: Layout) -> *mut u8 {
+        let size = match block_size(layout.size()) {
+            Some(size) if layout.align() <= BLOCK_SIZE => size,
+            _ => return ptr::null_mut(),
+        };
+
+        let mut prev = ptr::null_mut();
+        let mut block = self.free.get();
+        while !block.is_null() {
+            let FreeBlock { size: available, next } = unsafe { block.read() };
+            if available >= size {
+                //Remainder stays free, as it is always multiple of block size
+                let next = if available > size {
+                    unsafe {
+                        let rest = (block as *mut u8).add(size) as *mut FreeBlock;
+                        rest.write(FreeBlock {
+                            size: available - size,
+                            next,
+                        });
+                        rest
+                    }
+                } else {
+                    next
+                };
+                unsafe {
+                    self.link(prev, next);
+                }
+                return block as *mut u8;
+            }
+            prev = block;
+            block = next;
+        }
+
+        unlikely_null() as _
+    }
+
+    ///Returns memory block to workspace.
+    ///
+    ///`ptr` must be returned by `alloc` of the same workspace with the same `layout`.
+    pub(crate) unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let mut size = match block_size(layout.size()) {
+            Some(size) => size,
+            None => return,
+        };
+        let block = ptr as *mut FreeBlock;
+
+        let mut prev: *mut FreeBlock = ptr::null_mut();
+        let mut next = self.free.get();
+        while !next.is_null() && next < block {
+            prev = next;
+            next = (*next).next;
+        }
+
+        if !next.is_null() && ptr.add(size) == next as *mut u8 {
+            size += (*next).size;
+            next = (*next).next;
+        }
+
+        if !prev.is_null() && (prev as *mut u8).add((*prev).size) == ptr {
+            (*prev).size += size;
+            (*prev).next = next;
+        } else {
+            block.write(FreeBlock {
+                size,
+                next,
+            });
+            self.link(prev, block);
+        }
+    }
+}
+
+#[cfg(feature = "zlib-rust")]
+///`alloc` alternative using [Workspace] passed as `opaque`
+pub(crate) unsafe extern "C" fn workspace_alloc(opaque: *mut c_void, items: core::ffi::c_uint, size: core::ffi::c_uint) -> *mut c_void {
+    let workspace = &*(opaque as *const Workspace);
+    match (items as usize).checked_mul(size as usize) {
+        Some(0) | None => unlikely_null(),
+        Some(size) => malloc_with(size, |layout| workspace.alloc(layout)),
+    }
+}
+
+#[cfg(feature = "zlib-rust")]
+///`free` alternative using [Workspace] passed as `opaque`
+pub(crate) unsafe extern "C" fn workspace_free(opaque: *mut c_void, mem: *mut c_void) {
+    let workspace = &*(opaque as *const Workspace);
+    free_with(mem, |mem, layout| workspace.dealloc(mem, layout));
 }
 
 #[cfg(feature = "brotli-rust")]
-///Allocator implementation using Rust's global allocator
+///Allocator implementations for `brotli-rust`
 pub mod brotli_rust {
+    #[cfg(feature = "alloc")]
     extern crate alloc;
 
+    #[cfg(feature = "alloc")]
     use super::Box;
+    use super::Workspace;
+    #[cfg(feature = "alloc")]
     use alloc::vec::Vec;
+    use core::alloc::Layout;
+    use core::{ptr, slice};
 
+    #[cfg(feature = "alloc")]
     ///Boxed slice wrapper
     pub struct BoxedSlice<T>(Box<[T]>);
 
+    #[cfg(feature = "alloc")]
     impl<T> Default for BoxedSlice<T> {
         #[inline(always)]
         fn default() -> Self {
             Self(Vec::new().into_boxed_slice())
         }
     }
+    #[cfg(feature = "alloc")]
     impl<T> brotli::SliceWrapper<T> for BoxedSlice<T> {
         #[inline(always)]
         fn slice(&self) -> &[T] {
@@ -99,6 +548,7 @@ pub mod brotli_rust {
         }
     }
 
+    #[cfg(feature = "alloc")]
     impl<T> brotli::SliceWrapperMut<T> for BoxedSlice<T> {
         #[inline(always)]
         fn slice_mut(&mut self) -> &mut [T] {
@@ -106,10 +556,12 @@ pub mod brotli_rust {
         }
     }
 
+    #[cfg(feature = "alloc")]
     #[derive(Copy, Clone, Default)]
     ///Default allocator
     pub struct BrotliAllocator;
 
+    #[cfg(feature = "alloc")]
     impl<T: Default> brotli::Allocator<T> for BrotliAllocator {
         type AllocatedMemory = BoxedSlice<T>;
         fn alloc_cell(&mut self, len: usize) -> Self::AllocatedMemory {
@@ -123,5 +575,96 @@ pub mod brotli_rust {
         fn free_cell(&mut self, _data: Self::AllocatedMemory) {}
     }
 
+    #[cfg(feature = "alloc")]
     impl brotli::enc::BrotliAlloc for BrotliAllocator {}
+
+    ///Slice allocated within workspace, which is returned to it on drop
+    pub struct WorkspaceSlice<'a, T> {
+        data: &'a mut [T],
+        workspace: Option<&'a Workspace>,
+    }
+
+    impl<T> Default for WorkspaceSlice<'_, T> {
+        #[inline(always)]
+        fn default() -> Self {
+            Self {
+                data: &mut [],
+                workspace: None,
+            }
+        }
+    }
+
+    impl<T> brotli::SliceWrapper<T> for WorkspaceSlice<'_, T> {
+        #[inline(always)]
+        fn slice(&self) -> &[T] {
+            self.data
+        }
+    }
+
+    impl<T> brotli::SliceWrapperMut<T> for WorkspaceSlice<'_, T> {
+        #[inline(always)]
+        fn slice_mut(&mut self) -> &mut [T] {
+            self.data
+        }
+    }
+
+    impl<T> Drop for WorkspaceSlice<'_, T> {
+        fn drop(&mut self) {
+            if let Some(workspace) = self.workspace {
+                let len = self.data.len();
+                let data = self.data.as_mut_ptr();
+                unsafe {
+                    ptr::drop_in_place(self.data as *mut [T]);
+                    workspace.dealloc(data as _, Layout::array::<T>(len).unwrap_unchecked());
+                }
+            }
+        }
+    }
+
+    #[derive(Copy, Clone)]
+    ///Allocator within caller provided memory
+    ///
+    ///Allocation failure results in empty slice, which `brotli` either reports as error or panics on.
+    pub struct WorkspaceAllocator<'a> {
+        workspace: &'a Workspace,
+    }
+
+    impl<'a> WorkspaceAllocator<'a> {
+        #[inline(always)]
+        pub(crate) const fn new(workspace: &'a Workspace) -> Self {
+            Self {
+                workspace,
+            }
+        }
+    }
+
+    impl<'a, T: Default + 'a> brotli::Allocator<T> for WorkspaceAllocator<'a> {
+        type AllocatedMemory = WorkspaceSlice<'a, T>;
+        fn alloc_cell(&mut self, len: usize) -> Self::AllocatedMemory {
+            let layout = match Layout::array::<T>(len) {
+                Ok(layout) if layout.size() != 0 => layout,
+                _ => return WorkspaceSlice::default(),
+            };
+            let data = self.workspace.alloc(layout) as *mut T;
+            if data.is_null() {
+                return WorkspaceSlice::default();
+            }
+            unsafe {
+                for idx in 0..len {
+                    data.add(idx).write(T::default());
+                }
+                WorkspaceSlice {
+                    data: slice::from_raw_parts_mut(data, len),
+                    workspace: Some(self.workspace),
+                }
+            }
+        }
+
+        #[inline(always)]
+        fn free_cell(&mut self, data: Self::AllocatedMemory) {
+            drop(data);
+        }
+    }
+
+    impl brotli::enc::BrotliAlloc for WorkspaceAllocator<'_> {}
 }
@@ -0,0 +1,66 @@
+//!Common parts of snappy framing format
+//!
+//!https://github.com/google/snappy/blob/main/framing_format.txt
+
+pub(crate) const STREAM_IDENTIFIER: [u8; 10] = [0xFF, 0x06, 0x00, 0x00, b's', b'N', b'a', b'P', b'p', b'Y'];
+pub(crate) const CHUNK_STREAM_IDENTIFIER: u8 = 0xFF;
+pub(crate) const CHUNK_COMPRESSED: u8 = 0x00;
+pub(crate) const CHUNK_UNCOMPRESSED: u8 = 0x01;
+//Chunk type byte followed by 3 bytes of length
+pub(crate) const CHUNK_HEADER_SIZE: usize = 4;
+pub(crate) const CHECKSUM_SIZE: usize = 4;
+//Maximum size of uncompressed data within single chunk
+pub(crate) const MAX_BLOCK_SIZE: usize = 65536;
+
+pub(crate) const ERROR_STREAM_IDENTIFIER_INVALID: i32 = 1;
+pub(crate) const ERROR_CHUNK_TYPE_UNSUPPORTED: i32 = 2;
+pub(crate) const ERROR_CHUNK_SIZE_INVALID: i32 = 3;
+pub(crate) const ERROR_DECOMPRESSION_FAILED: i32 = 4;
+pub(crate) const ERROR_CHECKSUM_INVALID: i32 = 5;
+pub(crate) const ERROR_ALLOCATION_FAILED: i32 = 6;
+pub(crate) const ERROR_STREAM_FINISHED: i32 = 7;
+
+const CRC32C_POLY: u32 = 0x82F63B78;
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut idx = 0;
+    while idx < table.len() {
+        let mut crc = idx as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ CRC32C_POLY,
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[idx] = crc;
+        idx += 1;
+    }
+    table
+};
+
+///Calculates masked CRC32C of `data` as it is stored in chunk
+pub(crate) fn checksum(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc = CRC32C_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    let crc = !crc;
+    crc.rotate_right(15).wrapping_add(0xA282EAD8)
+}
+
+///Returns description for errors that can be produced by `snappy`
+pub(crate) fn describe_error(code: i32) -> Option<&'static str> {
+    match code {
+        0 => Some("No error"),
+        ERROR_STREAM_IDENTIFIER_INVALID => Some("Stream identifier is missing or invalid"),
+        ERROR_CHUNK_TYPE_UNSUPPORTED => Some("Reserved unskippable chunk type"),
+        ERROR_CHUNK_SIZE_INVALID => Some("Chunk size is invalid"),
+        ERROR_DECOMPRESSION_FAILED => Some("Compressed data is corrupted"),
+        ERROR_CHECKSUM_INVALID => Some("Chunk checksum mismatch"),
+        ERROR_ALLOCATION_FAILED => Some("Allocation failed"),
+        ERROR_STREAM_FINISHED => Some("Stream is already finished"),
+        _ => None,
+    }
+}
@@ -1,5 +1,6 @@
 use core::ffi::CStr;
 
+#[allow(unused)]
 #[inline(always)]
 ///This always rely on the fact that most common libraries return static strings.
 ///If that's not the case, do not use this function
@@ -11,3 +12,57 @@ pub fn convert_c_str(ptr: *const i8) -> Option<&'static str> {
         text.to_str().ok()
     }
 }
+
+#[cfg(feature = "xz")]
+///Returns description of `lzma_ret` code as liblzma has no function for it
+pub fn lzma_error_name(code: i32) -> Option<&'static str> {
+    //https://github.com/tukaani-project/xz/blob/master/src/liblzma/api/lzma/base.h
+    match code as lzma_sys::lzma_ret {
+        lzma_sys::LZMA_OK => Some("Operation completed successfully"),
+        lzma_sys::LZMA_STREAM_END => Some("End of stream was reached"),
+        lzma_sys::LZMA_NO_CHECK => Some("Input stream has no integrity check"),
+        lzma_sys::LZMA_UNSUPPORTED_CHECK => Some("Cannot calculate the integrity check"),
+        lzma_sys::LZMA_GET_CHECK => Some("Integrity check type is now available"),
+        lzma_sys::LZMA_MEM_ERROR => Some("Cannot allocate memory"),
+        lzma_sys::LZMA_MEMLIMIT_ERROR => Some("Memory usage limit was reached"),
+        lzma_sys::LZMA_FORMAT_ERROR => Some("File format not recognized"),
+        lzma_sys::LZMA_OPTIONS_ERROR => Some("Invalid or unsupported options"),
+        lzma_sys::LZMA_DATA_ERROR => Some("Data is corrupt"),
+        lzma_sys::LZMA_BUF_ERROR => Some("No progress is possible"),
+        lzma_sys::LZMA_PROG_ERROR => Some("Programming error"),
+        _ => None,
+    }
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static"))]
+///Returns description of zlib return code
+pub fn zlib_error_name(code: i32) -> Option<&'static str> {
+    //libz-sys has no binding for it
+    extern "C" {
+        fn zError(code: core::ffi::c_int) -> *const core::ffi::c_char;
+    }
+
+    convert_c_str(unsafe { zError(code) }.cast())
+}
+
+#[cfg(feature = "bzip2")]
+///Returns description of bzip2 return code as library has no function for it
+pub fn bz_error_name(code: i32) -> Option<&'static str> {
+    match code {
+        bzip2_sys::BZ_OK => Some("OK"),
+        bzip2_sys::BZ_RUN_OK => Some("RUN_OK"),
+        bzip2_sys::BZ_FLUSH_OK => Some("FLUSH_OK"),
+        bzip2_sys::BZ_FINISH_OK => Some("FINISH_OK"),
+        bzip2_sys::BZ_STREAM_END => Some("STREAM_END"),
+        bzip2_sys::BZ_SEQUENCE_ERROR => Some("SEQUENCE_ERROR"),
+        bzip2_sys::BZ_PARAM_ERROR => Some("PARAM_ERROR"),
+        bzip2_sys::BZ_MEM_ERROR => Some("MEM_ERROR"),
+        bzip2_sys::BZ_DATA_ERROR => Some("DATA_ERROR"),
+        bzip2_sys::BZ_DATA_ERROR_MAGIC => Some("DATA_ERROR_MAGIC"),
+        bzip2_sys::BZ_IO_ERROR => Some("IO_ERROR"),
+        bzip2_sys::BZ_UNEXPECTED_EOF => Some("UNEXPECTED_EOF"),
+        bzip2_sys::BZ_OUTBUFF_FULL => Some("OUTBUFF_FULL"),
+        bzip2_sys::BZ_CONFIG_ERROR => Some("CONFIG_ERROR"),
+        _ => None,
+    }
+}
@@ -0,0 +1,246 @@
+//!`zstd` dictionary builder
+//!
+//!Dictionaries produced here can be used via [encoder::ZstdOptions](../encoder/struct.ZstdOptions.html)
+//!and [decoder::ZstdOptions](../decoder/struct.ZstdOptions.html).
+//!
+//!Note that dictionary builder uses system allocator directly, rather than Rust's global one.
+
+extern crate alloc;
+
+use zstd_sys as sys;
+
+use alloc::vec::Vec;
+use core::ffi::c_uint;
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Dictionary builder error
+pub struct DictionaryError {
+    code: usize,
+}
+
+impl DictionaryError {
+    #[inline(always)]
+    ///Returns raw `zstd` error code
+    pub const fn as_raw(&self) -> usize {
+        self.code
+    }
+
+    #[inline(always)]
+    ///Returns textual description of the error, if available
+    pub fn describe(&self) -> Option<&'static str> {
+        let result = unsafe {
+            sys::ZDICT_getErrorName(self.code)
+        };
+        crate::utils::convert_c_str(result)
+    }
+}
+
+impl fmt::Display for DictionaryError {
+    #[inline(always)]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.describe() {
+            Some(description) => fmt.write_str(description),
+            None => fmt.write_fmt(format_args!("Unknown error (code {})", self.code)),
+        }
+    }
+}
+
+impl core::error::Error for DictionaryError {}
+
+#[derive(Copy, Clone)]
+///Dictionary options, used by finalization and `cover` training.
+pub struct DictionaryOptions {
+    level: i32,
+    id: u32,
+}
+
+impl DictionaryOptions {
+    #[inline(always)]
+    ///Creates new default value
+    pub const fn new() -> Self {
+        Self {
+            level: 0,
+            id: 0,
+        }
+    }
+
+    #[inline(always)]
+    ///Sets compression level to optimize dictionary for.
+    ///
+    ///Defaults to 0, which means default level.
+    pub const fn level(mut self, level: i32) -> Self {
+        self.level = level;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets dictionary ID.
+    ///
+    ///Defaults to 0, which means random ID.
+    ///Note that ranges `<= 32767` and `>= 2^31` are reserved by `zstd` for future use.
+    pub const fn id(mut self, id: u32) -> Self {
+        self.id = id;
+        self
+    }
+
+    #[inline(always)]
+    const fn into_zstd(self) -> sys::ZDICT_params_t {
+        sys::ZDICT_params_t {
+            compressionLevel: self.level,
+            notificationLevel: 0,
+            dictID: self.id,
+        }
+    }
+}
+
+impl Default for DictionaryOptions {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone)]
+///Parameters of `cover` training algorithm.
+///
+///Parameters with value 0 are searched for, using more time to find optimal ones.
+pub struct CoverOptions {
+    k: u32,
+    d: u32,
+    steps: u32,
+}
+
+impl CoverOptions {
+    #[inline(always)]
+    ///Creates new default value
+    pub const fn new() -> Self {
+        Self {
+            k: 0,
+            d: 0,
+            steps: 0,
+        }
+    }
+
+    #[inline(always)]
+    ///Sets segment size, which must be at least `d`.
+    pub const fn k(mut self, k: u32) -> Self {
+        self.k = k;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets size of dmer, in range from 6 to 16 when specified.
+    pub const fn d(mut self, d: u32) -> Self {
+        assert!(d == 0 || (d >= 6 && d <= 16));
+        self.d = d;
+        self
+    }
+
+    #[inline(always)]
+    ///Sets number of steps used to search for `k`.
+    ///
+    ///Defaults to 0, which means 40.
+    pub const fn steps(mut self, steps: u32) -> Self {
+        self.steps = steps;
+        self
+    }
+}
+
+impl Default for CoverOptions {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//zstd requires samples in single flat buffer
+struct Samples {
+    buffer: Vec<u8>,
+    sizes: Vec<usize>,
+}
+
+impl Samples {
+    fn new(samples: &[&[u8]]) -> Self {
+        assert!(samples.len() <= c_uint::MAX as usize);
+
+        let mut buffer = Vec::with_capacity(samples.iter().map(|sample| sample.len()).sum());
+        let mut sizes = Vec::with_capacity(samples.len());
+        for sample in samples {
+            buffer.extend_from_slice(sample);
+            sizes.push(sample.len());
+        }
+
+        Self {
+            buffer,
+            sizes,
+        }
+    }
+}
+
+#[inline(always)]
+fn build(capacity: usize, build: impl FnOnce(*mut u8, usize) -> usize) -> Result<Vec<u8>, DictionaryError> {
+    let mut dict = Vec::with_capacity(capacity);
+    let result = build(dict.as_mut_ptr(), capacity);
+    if unsafe { sys::ZDICT_isError(result) } != 0 {
+        Err(DictionaryError {
+            code: result,
+        })
+    } else {
+        unsafe {
+            dict.set_len(result);
+        }
+        Ok(dict)
+    }
+}
+
+///Trains dictionary of at most `capacity` bytes from `samples` using default parameters.
+///
+///Generally it is recommended to provide a few thousands samples, with total size about 100 times
+///of dictionary size. Training fails if there is not enough samples.
+pub fn train_dictionary(samples: &[&[u8]], capacity: usize) -> Result<Vec<u8>, DictionaryError> {
+    let samples = Samples::new(samples);
+    build(capacity, |dict, capacity| unsafe {
+        sys::ZDICT_trainFromBuffer(dict as _, capacity, samples.buffer.as_ptr() as _, samples.sizes.as_ptr(), samples.sizes.len() as _)
+    })
+}
+
+///Trains dictionary of at most `capacity` bytes from `samples` using `cover` algorithm.
+///
+///It is slower than [train_dictionary](fn.train_dictionary.html), especially when parameters are
+///searched for, but allows to tune them.
+pub fn train_dictionary_cover(samples: &[&[u8]], capacity: usize, cover: CoverOptions, opts: DictionaryOptions) -> Result<Vec<u8>, DictionaryError> {
+    let samples = Samples::new(samples);
+    let mut params = sys::ZDICT_cover_params_t {
+        k: cover.k,
+        d: cover.d,
+        steps: cover.steps,
+        nbThreads: 1,
+        splitPoint: 0.0,
+        shrinkDict: 0,
+        shrinkDictMaxRegression: 0,
+        zParams: opts.into_zstd(),
+    };
+    build(capacity, |dict, capacity| unsafe {
+        sys::ZDICT_optimizeTrainFromBuffer_cover(dict as _, capacity, samples.buffer.as_ptr() as _, samples.sizes.as_ptr(), samples.sizes.len() as _, &mut params)
+    })
+}
+
+///Creates dictionary of at most `capacity` bytes from custom `content`, adding headers and
+///statistics gathered from `samples`.
+///
+///If content does not fit `capacity` with headers, its beginning is truncated.
+pub fn finalize_dictionary(content: &[u8], samples: &[&[u8]], capacity: usize, opts: DictionaryOptions) -> Result<Vec<u8>, DictionaryError> {
+    let samples = Samples::new(samples);
+    build(capacity, |dict, capacity| unsafe {
+        sys::ZDICT_finalizeDictionary(dict as _, capacity, content.as_ptr() as _, content.len(), samples.buffer.as_ptr() as _, samples.sizes.as_ptr(), samples.sizes.len() as _, opts.into_zstd())
+    })
+}
+
+#[inline]
+///Returns ID of `dict`, or 0 if it is not valid `zstd` dictionary.
+pub fn dictionary_id(dict: &[u8]) -> u32 {
+    unsafe {
+        sys::ZDICT_getDictID(dict.as_ptr() as _, dict.len()) as _
+    }
+}
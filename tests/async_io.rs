@@ -0,0 +1,439 @@
+#![cfg(any(feature = "tokio", feature = "futures-io"))]
+
+use compu::{decoder, encoder, Decoder, Encoder};
+
+use core::future::Future;
+use core::pin::{pin, Pin};
+use core::task::{Context, Poll, Waker};
+use std::io;
+
+const DATA: [&[u8]; 2] = [
+    include_bytes!("data/10x10y"),
+    include_bytes!("data/alice29.txt"),
+];
+
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let mut cx = Context::from_waker(Waker::noop());
+    //All IO is in memory so we only need to re-poll until `Pending` is over
+    loop {
+        if let Poll::Ready(result) = fut.as_mut().poll(&mut cx) {
+            break result;
+        }
+    }
+}
+
+///Wrapper that returns `Pending` on every other call to simulate backpressure
+struct Slow<T> {
+    inner: T,
+    is_pending: bool,
+}
+
+impl<T> Slow<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            is_pending: false,
+        }
+    }
+
+    fn poll_pending(&mut self) -> Poll<()> {
+        self.is_pending = !self.is_pending;
+        if self.is_pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+///Output writer that accepts at most 7 bytes per call
+#[derive(Default)]
+struct Output(Vec<u8>);
+
+impl Output {
+    fn write(&mut self, buf: &[u8]) -> usize {
+        let len = core::cmp::min(buf.len(), 7);
+        self.0.extend_from_slice(&buf[..len]);
+        len
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod tokio_impl {
+    use super::*;
+    use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf};
+
+    impl AsyncWrite for Slow<Output> {
+        fn poll_write(mut self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            match self.poll_pending() {
+                Poll::Ready(()) => Poll::Ready(Ok(self.inner.write(buf))),
+                Poll::Pending => Poll::Pending,
+            }
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.poll_pending().map(Ok)
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.poll_pending().map(Ok)
+        }
+    }
+
+    impl AsyncRead for Slow<&[u8]> {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+            match self.poll_pending() {
+                Poll::Ready(()) => Pin::new(&mut self.inner).poll_read(cx, buf),
+                Poll::Pending => Poll::Pending,
+            }
+        }
+    }
+
+    impl AsyncBufRead for Slow<&[u8]> {
+        fn poll_fill_buf(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+            let this = self.get_mut();
+            match this.poll_pending() {
+                //Limit chunk to test partial input
+                Poll::Ready(()) => Poll::Ready(Ok(&this.inner[..core::cmp::min(this.inner.len(), 99)])),
+                Poll::Pending => Poll::Pending,
+            }
+        }
+
+        fn consume(mut self: Pin<&mut Self>, amt: usize) {
+            self.inner = &self.inner[amt..];
+        }
+    }
+
+    async fn write_all<W: AsyncWrite + Unpin>(writer: &mut W, mut data: &[u8]) -> io::Result<()> {
+        while !data.is_empty() {
+            let written = core::future::poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, data)).await?;
+            if written == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            data = &data[written..];
+        }
+        Ok(())
+    }
+
+    async fn read_to_end<R: AsyncRead + Unpin>(reader: &mut R, output: &mut Vec<u8>) -> io::Result<()> {
+        let mut buffer = [0u8; 333];
+        loop {
+            let mut buf = ReadBuf::new(&mut buffer);
+            core::future::poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, &mut buf)).await?;
+            if buf.filled().is_empty() {
+                break Ok(());
+            }
+            output.extend_from_slice(buf.filled());
+        }
+    }
+
+    pub fn test_case(idx: usize, mut create_encoder: impl FnMut() -> Encoder<'static>, mut create_decoder: impl FnMut() -> Decoder<'static>, data: &[u8]) {
+        use compu::io::tokio::{DecoderReader, DecoderWriter, EncoderReader, EncoderWriter};
+
+        println!("tokio({idx}): DATA.len()={}", data.len());
+        block_on(async {
+            let mut writer = EncoderWriter::<_, 256>::new(create_encoder(), Slow::new(Output::default()));
+            for chunk in data.chunks(1000) {
+                write_all(&mut writer, chunk).await.expect("to write");
+                core::future::poll_fn(|cx| Pin::new(&mut writer).poll_flush(cx)).await.expect("to flush");
+            }
+            core::future::poll_fn(|cx| Pin::new(&mut writer).poll_shutdown(cx)).await.expect("to shutdown");
+            let compressed = writer.into_parts().1.inner.0;
+
+            let mut decompressed = Vec::new();
+            let mut reader = DecoderReader::new(create_decoder(), Slow::new(compressed.as_slice()));
+            read_to_end(&mut reader, &mut decompressed).await.expect("to decompress");
+            assert_eq!(data, decompressed);
+
+            //Write whole input at once
+            let mut writer = EncoderWriter::<_, 256>::new(create_encoder(), Slow::new(Output::default()));
+            write_all(&mut writer, data).await.expect("to write");
+            core::future::poll_fn(|cx| Pin::new(&mut writer).poll_shutdown(cx)).await.expect("to shutdown");
+            let compressed = writer.into_parts().1.inner.0;
+
+            let mut decompressed = Vec::new();
+            let mut reader = DecoderReader::new(create_decoder(), Slow::new(compressed.as_slice()));
+            read_to_end(&mut reader, &mut decompressed).await.expect("to decompress");
+            assert_eq!(data, decompressed);
+
+            let mut compressed_read = Vec::new();
+            let mut reader = EncoderReader::new(create_encoder(), Slow::new(data));
+            read_to_end(&mut reader, &mut compressed_read).await.expect("to compress");
+
+            let mut writer = DecoderWriter::<_, 128>::new(create_decoder(), Slow::new(Output::default()));
+            for chunk in compressed_read.chunks(100) {
+                write_all(&mut writer, chunk).await.expect("to write");
+            }
+            core::future::poll_fn(|cx| Pin::new(&mut writer).poll_shutdown(cx)).await.expect("to shutdown");
+            assert_eq!(data, writer.into_parts().1.inner.0);
+
+            //Trailing data after end of stream
+            let mut trailing = compressed_read.clone();
+            trailing.extend_from_slice(b"garbage");
+            let mut writer = DecoderWriter::<_, 128>::new(create_decoder(), Slow::new(Output::default()));
+            let error = write_all(&mut writer, &trailing).await.expect_err("should fail");
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+            //Incomplete stream
+            let mut reader = DecoderReader::new(create_decoder(), Slow::new(&compressed_read[..compressed_read.len() / 2]));
+            let error = read_to_end(&mut reader, &mut Vec::new()).await.expect_err("should fail");
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+            let mut writer = DecoderWriter::<_, 128>::new(create_decoder(), Slow::new(Output::default()));
+            write_all(&mut writer, &compressed_read[..compressed_read.len() / 2]).await.expect("to write");
+            let error = core::future::poll_fn(|cx| Pin::new(&mut writer).poll_shutdown(cx)).await.expect_err("should fail");
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        });
+    }
+}
+
+#[cfg(feature = "futures-io")]
+mod futures_impl {
+    use super::*;
+    use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
+
+    impl AsyncWrite for Slow<Output> {
+        fn poll_write(mut self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+            match self.poll_pending() {
+                Poll::Ready(()) => Poll::Ready(Ok(self.inner.write(buf))),
+                Poll::Pending => Poll::Pending,
+            }
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.poll_pending().map(Ok)
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.poll_pending().map(Ok)
+        }
+    }
+
+    impl AsyncRead for Slow<&[u8]> {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+            match self.poll_pending() {
+                Poll::Ready(()) => Pin::new(&mut self.inner).poll_read(cx, buf),
+                Poll::Pending => Poll::Pending,
+            }
+        }
+    }
+
+    impl AsyncBufRead for Slow<&[u8]> {
+        fn poll_fill_buf(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+            let this = self.get_mut();
+            match this.poll_pending() {
+                //Limit chunk to test partial input
+                Poll::Ready(()) => Poll::Ready(Ok(&this.inner[..core::cmp::min(this.inner.len(), 99)])),
+                Poll::Pending => Poll::Pending,
+            }
+        }
+
+        fn consume(mut self: Pin<&mut Self>, amt: usize) {
+            self.inner = &self.inner[amt..];
+        }
+    }
+
+    async fn write_all<W: AsyncWrite + Unpin>(writer: &mut W, mut data: &[u8]) -> io::Result<()> {
+        while !data.is_empty() {
+            let written = core::future::poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, data)).await?;
+            if written == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            data = &data[written..];
+        }
+        Ok(())
+    }
+
+    async fn read_to_end<R: AsyncRead + Unpin>(reader: &mut R, output: &mut Vec<u8>) -> io::Result<()> {
+        let mut buffer = [0u8; 333];
+        loop {
+            let read = core::future::poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, &mut buffer)).await?;
+            if read == 0 {
+                break Ok(());
+            }
+            output.extend_from_slice(&buffer[..read]);
+        }
+    }
+
+    pub fn test_case(idx: usize, mut create_encoder: impl FnMut() -> Encoder<'static>, mut create_decoder: impl FnMut() -> Decoder<'static>, data: &[u8]) {
+        use compu::io::futures::{DecoderReader, DecoderWriter, EncoderReader, EncoderWriter};
+
+        println!("futures({idx}): DATA.len()={}", data.len());
+        block_on(async {
+            let mut writer = EncoderWriter::<_, 256>::new(create_encoder(), Slow::new(Output::default()));
+            for chunk in data.chunks(1000) {
+                write_all(&mut writer, chunk).await.expect("to write");
+                core::future::poll_fn(|cx| Pin::new(&mut writer).poll_flush(cx)).await.expect("to flush");
+            }
+            core::future::poll_fn(|cx| Pin::new(&mut writer).poll_close(cx)).await.expect("to close");
+            let compressed = writer.into_parts().1.inner.0;
+
+            let mut decompressed = Vec::new();
+            let mut reader = DecoderReader::new(create_decoder(), Slow::new(compressed.as_slice()));
+            read_to_end(&mut reader, &mut decompressed).await.expect("to decompress");
+            assert_eq!(data, decompressed);
+
+            //Write whole input at once
+            let mut writer = EncoderWriter::<_, 256>::new(create_encoder(), Slow::new(Output::default()));
+            write_all(&mut writer, data).await.expect("to write");
+            core::future::poll_fn(|cx| Pin::new(&mut writer).poll_close(cx)).await.expect("to close");
+            let compressed = writer.into_parts().1.inner.0;
+
+            let mut decompressed = Vec::new();
+            let mut reader = DecoderReader::new(create_decoder(), Slow::new(compressed.as_slice()));
+            read_to_end(&mut reader, &mut decompressed).await.expect("to decompress");
+            assert_eq!(data, decompressed);
+
+            let mut compressed_read = Vec::new();
+            let mut reader = EncoderReader::new(create_encoder(), Slow::new(data));
+            read_to_end(&mut reader, &mut compressed_read).await.expect("to compress");
+
+            let mut writer = DecoderWriter::<_, 128>::new(create_decoder(), Slow::new(Output::default()));
+            for chunk in compressed_read.chunks(100) {
+                write_all(&mut writer, chunk).await.expect("to write");
+            }
+            core::future::poll_fn(|cx| Pin::new(&mut writer).poll_close(cx)).await.expect("to close");
+            assert_eq!(data, writer.into_parts().1.inner.0);
+
+            //Trailing data after end of stream
+            let mut trailing = compressed_read.clone();
+            trailing.extend_from_slice(b"garbage");
+            let mut writer = DecoderWriter::<_, 128>::new(create_decoder(), Slow::new(Output::default()));
+            let error = write_all(&mut writer, &trailing).await.expect_err("should fail");
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+            //Incomplete stream
+            let mut reader = DecoderReader::new(create_decoder(), Slow::new(&compressed_read[..compressed_read.len() / 2]));
+            let error = read_to_end(&mut reader, &mut Vec::new()).await.expect_err("should fail");
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+            let mut writer = DecoderWriter::<_, 128>::new(create_decoder(), Slow::new(Output::default()));
+            write_all(&mut writer, &compressed_read[..compressed_read.len() / 2]).await.expect("to write");
+            let error = core::future::poll_fn(|cx| Pin::new(&mut writer).poll_close(cx)).await.expect_err("should fail");
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        });
+    }
+}
+
+fn test_case(idx: usize, mut create_encoder: impl FnMut() -> Encoder<'static>, mut create_decoder: impl FnMut() -> Decoder<'static>, data: &[u8]) {
+    #[cfg(feature = "tokio")]
+    tokio_impl::test_case(idx, &mut create_encoder, &mut create_decoder, data);
+    #[cfg(feature = "futures-io")]
+    futures_impl::test_case(idx, &mut create_encoder, &mut create_decoder, data);
+}
+
+#[cfg(feature = "brotli-c")]
+#[test]
+fn should_encode_and_decode_async_brotli_c() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::brotli_c(Default::default()).expect("create brotli encoder"),
+            || decoder::Interface::brotli_c().expect("create brotli decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(feature = "brotli-rust")]
+#[test]
+fn should_encode_and_decode_async_brotli_rust() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(idx, || encoder::Interface::brotli_rust(Default::default()), decoder::Interface::brotli_rust, data);
+    }
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_encode_and_decode_async_zstd() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::zstd(Default::default()).expect("create zstd encoder"),
+            || decoder::Interface::zstd(Default::default()).expect("create zstd decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static"))]
+#[test]
+fn should_encode_and_decode_async_zlib_gzip() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::zlib(encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip)).expect("create zlib encoder"),
+            || decoder::Interface::zlib(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_encode_and_decode_async_zlib_rust_gzip() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::zlib_rust(encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip)).expect("create zlib-rust encoder"),
+            || decoder::Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib-rust decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(feature = "lz4")]
+#[test]
+fn should_encode_and_decode_async_lz4() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::lz4(Default::default()).expect("create lz4 encoder"),
+            || decoder::Interface::lz4().expect("create lz4 decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(feature = "lz4-rust")]
+#[test]
+fn should_encode_and_decode_async_lz4_rust() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(idx, || encoder::Interface::lz4_rust(Default::default()), decoder::Interface::lz4_rust, data);
+    }
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_encode_and_decode_async_xz() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::xz(Default::default()).expect("create xz encoder"),
+            || decoder::Interface::xz(Default::default()).expect("create xz decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn should_encode_and_decode_async_bzip2() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::bzip2(Default::default()).expect("create bzip2 encoder"),
+            || decoder::Interface::bzip2(false).expect("create bzip2 decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(feature = "snappy")]
+#[test]
+fn should_encode_and_decode_async_snappy() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(idx, encoder::Interface::snappy, decoder::Interface::snappy, data);
+    }
+}
@@ -1,5 +1,7 @@
+#![cfg(feature = "alloc")]
+
 use compu::{decoder, Buffer};
-use decoder::{DecodeError, DecodeStatus, Interface};
+use decoder::{DecodeError, DecodeErrorKind, DecodeStatus, Interface};
 
 const DATA: [&[u8]; 2] = [
     include_bytes!("data/10x10y"),
@@ -17,6 +19,22 @@ const DATA_ZSTD: [&[u8]; 2] = [
     include_bytes!("data/10x10y.compressed.zstd"),
     include_bytes!("data/alice29.txt.compressed.zstd"),
 ];
+const DATA_LZ4: [&[u8]; 2] = [
+    include_bytes!("data/10x10y.compressed.lz4"),
+    include_bytes!("data/alice29.txt.compressed.lz4"),
+];
+const DATA_XZ: [&[u8]; 2] = [
+    include_bytes!("data/10x10y.compressed.xz"),
+    include_bytes!("data/alice29.txt.compressed.xz"),
+];
+const DATA_BZIP2: [&[u8]; 2] = [
+    include_bytes!("data/10x10y.compressed.bz2"),
+    include_bytes!("data/alice29.txt.compressed.bz2"),
+];
+const DATA_SNAPPY: [&[u8]; 2] = [
+    include_bytes!("data/10x10y.compressed.sz"),
+    include_bytes!("data/alice29.txt.compressed.sz"),
+];
 
 fn test_case(idx: usize, decoder: &mut decoder::Decoder, data: &[u8], compressed: &[u8]) {
     println!("{idx}: DATA.len()={} || COMPRESSED.len()={}", data.len(), compressed.len());
@@ -74,6 +92,31 @@ fn test_case(idx: usize, decoder: &mut decoder::Decoder, data: &[u8], compressed
     let error = DecodeError::no_error();
     let error = decoder.describe_error(error).expect("to get generic error");
     println!("error={error}");
+
+    //Corrupted stream
+    let mut corrupted = compressed.to_vec();
+    for byte in corrupted.iter_mut().take(8) {
+        *byte = !*byte;
+    }
+    output.clear();
+    let result = decoder.decode_vec_full(&corrupted, output.as_mut()).expect("success");
+    let error = result.status.expect_err("should fail");
+    println!("error={error}: {:?}", decoder.describe_error(error));
+    assert_eq!(error.kind(), DecodeErrorKind::Corrupted);
+    decoder.reset();
+}
+
+fn test_case_checksum(decoder: &mut decoder::Decoder, data: &[u8], compressed: &[u8], checksum_offset: usize) {
+    let mut corrupted = compressed.to_vec();
+    let checksum_idx = corrupted.len() - checksum_offset;
+    corrupted[checksum_idx] = !corrupted[checksum_idx];
+
+    let mut output = vec![0; data.len()];
+    let result = decoder.decode(&corrupted, output.as_mut());
+    let error = result.status.expect_err("should fail");
+    println!("error={error}: {:?}", decoder.describe_error(error));
+    assert_eq!(error.kind(), DecodeErrorKind::Checksum);
+    decoder.reset();
 }
 
 #[cfg(feature = "bytes")]
@@ -94,6 +137,75 @@ fn test_case_bytes(idx: usize, decoder: &mut decoder::Decoder, data: &[u8], comp
     decoder.reset();
 }
 
+fn test_case_trailing_data(create_decoder: impl Fn() -> decoder::Decoder<'static>, data: &[u8], compressed: &[u8]) {
+    const GARBAGE: &[u8] = b"garbage!";
+    let mut input = compressed.to_vec();
+    input.extend_from_slice(GARBAGE);
+
+    //Not tracked by default
+    let mut decoder = create_decoder();
+    let mut output = Vec::new();
+    let result = decoder.decode_vec_full(&input, &mut output).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(result.input_remain, GARBAGE.len());
+    assert_eq!(decoder.trailing_data_offset(), None);
+
+    let mut decoder = create_decoder().with_trailing_data(decoder::TrailingData::Lenient);
+    let mut output = Vec::new();
+    let result = decoder.decode_vec_full(&input, &mut output).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(result.input_remain, GARBAGE.len());
+    assert_eq!(output, data);
+    assert_eq!(decoder.trailing_data_offset(), Some(compressed.len() as u64));
+    assert!(decoder.reset());
+    assert_eq!(decoder.trailing_data_offset(), None);
+
+    //Trailing data after stream finished in previous call
+    let mut output = Vec::new();
+    let (first, second) = compressed.split_at(compressed.len() / 2);
+    let result = decoder.decode_vec_full(first, &mut output).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+    let result = decoder.decode_vec_full(second, &mut output).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(decoder.trailing_data_offset(), None);
+    let result = decoder.decode_vec_full(GARBAGE, &mut output).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(result.input_remain, GARBAGE.len());
+    assert_eq!(output, data);
+    assert_eq!(decoder.trailing_data_offset(), Some(compressed.len() as u64));
+
+    let mut decoder = create_decoder().with_trailing_data(decoder::TrailingData::Strict);
+    let mut output = Vec::new();
+    let result = decoder.decode_vec_full(&input, &mut output).expect("Success");
+    let error = result.status.expect_err("should fail");
+    println!("error={error}: {:?}", decoder.describe_error(error));
+    assert_eq!(error.kind(), DecodeErrorKind::TrailingData);
+    assert!(decoder.describe_error(error).is_some());
+    assert_eq!(result.input_remain, GARBAGE.len());
+    assert_eq!(output, data);
+    assert_eq!(decoder.trailing_data_offset(), Some(compressed.len() as u64));
+
+    //Stream without trailing data is unaffected
+    assert!(decoder.reset());
+    let mut output = Vec::new();
+    let result = decoder.decode_vec_full(compressed, &mut output).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(result.input_remain, 0);
+    assert_eq!(output, data);
+    assert_eq!(decoder.trailing_data_offset(), None);
+
+    //Auto decoder reports trailing data of buffered input
+    let mut decoder = Interface::auto(Default::default()).with_trailing_data(decoder::TrailingData::Strict);
+    let mut output = Vec::new();
+    let result = decoder.decode_vec_full(&input[..1], &mut output).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+    let result = decoder.decode_vec_full(&input[1..], &mut output).expect("Success");
+    assert_eq!(result.status.map_err(|error| error.kind()), Err(DecodeErrorKind::TrailingData));
+    assert_eq!(result.input_remain, GARBAGE.len());
+    assert_eq!(output, data);
+    assert_eq!(decoder.trailing_data_offset(), Some(compressed.len() as u64));
+}
+
 #[cfg(feature = "brotli-c")]
 #[test]
 fn should_decode_brotli_c() {
@@ -122,6 +234,7 @@ fn should_decode_zstd() {
     let mut decoder = Interface::zstd(Default::default()).expect("create zstd decoder");
     for idx in 0..DATA.len() {
         test_case(idx, &mut decoder, DATA[idx], DATA_ZSTD[idx]);
+        test_case_checksum(&mut decoder, DATA[idx], DATA_ZSTD[idx], 4);
         #[cfg(feature = "bytes")]
         test_case_bytes(idx, &mut decoder, DATA[idx], DATA_ZSTD[idx]);
     }
@@ -130,20 +243,255 @@ fn should_decode_zstd() {
 #[cfg(any(feature = "zlib", feature = "zlib-static"))]
 #[test]
 fn should_decode_zlib_gzip() {
-    let mut decoder = Interface::zlib(decoder::ZlibMode::Gzip).expect("create zlib-ng decoder");
+    let mut decoder = Interface::zlib(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib-ng decoder");
     for idx in 0..DATA.len() {
         test_case(idx, &mut decoder, DATA[idx], DATA_GZIP[idx]);
+        test_case_checksum(&mut decoder, DATA[idx], DATA_GZIP[idx], 8);
         #[cfg(feature = "bytes")]
         test_case_bytes(idx, &mut decoder, DATA[idx], DATA_GZIP[idx]);
     }
 }
 
+#[cfg(feature = "lz4")]
+#[test]
+fn should_decode_lz4() {
+    let mut decoder = Interface::lz4().expect("create lz4 decoder");
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut decoder, DATA[idx], DATA_LZ4[idx]);
+        test_case_checksum(&mut decoder, DATA[idx], DATA_LZ4[idx], 4);
+        #[cfg(feature = "bytes")]
+        test_case_bytes(idx, &mut decoder, DATA[idx], DATA_LZ4[idx]);
+    }
+}
+
+#[cfg(feature = "lz4-rust")]
+#[test]
+fn should_decode_lz4_rust() {
+    let mut decoder = Interface::lz4_rust();
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut decoder, DATA[idx], DATA_LZ4[idx]);
+        test_case_checksum(&mut decoder, DATA[idx], DATA_LZ4[idx], 4);
+        #[cfg(feature = "bytes")]
+        test_case_bytes(idx, &mut decoder, DATA[idx], DATA_LZ4[idx]);
+    }
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_decode_xz() {
+    let mut decoder = Interface::xz(Default::default()).expect("create xz decoder");
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut decoder, DATA[idx], DATA_XZ[idx]);
+        #[cfg(feature = "bytes")]
+        test_case_bytes(idx, &mut decoder, DATA[idx], DATA_XZ[idx]);
+    }
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_decode_xz_concatenated() {
+    let mut decoder = Interface::xz(decoder::XzDecoderOptions::new().concatenated(true)).expect("create xz decoder");
+    let mut compressed = DATA_XZ[0].to_vec();
+    //Stream padding
+    compressed.extend_from_slice(&[0; 4]);
+    compressed.extend_from_slice(DATA_XZ[1]);
+
+    let mut output = vec![0; DATA[0].len() + DATA[1].len()];
+    let result = decoder.decode(&compressed, output.as_mut());
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(result.input_remain, 0);
+    assert_eq!(result.output_remain, 0);
+    assert_eq!(DATA[0], &output[..DATA[0].len()]);
+    assert_eq!(DATA[1], &output[DATA[0].len()..]);
+    decoder.reset();
+
+    //Stream ends exactly at the end of input, so decoding continues with next input
+    let result = decoder.decode(DATA_XZ[0], output.as_mut());
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    let result = decoder.decode(DATA_XZ[1], &mut output[DATA[0].len()..]);
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(DATA[0], &output[..DATA[0].len()]);
+    assert_eq!(DATA[1], &output[DATA[0].len()..]);
+    decoder.reset();
+
+    //Without concatenation trailing stream is left in input
+    let mut decoder = Interface::xz(Default::default()).expect("create xz decoder");
+    let result = decoder.decode(&compressed, output.as_mut());
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(result.input_remain, compressed.len() - DATA_XZ[0].len());
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_report_memlimit_xz() {
+    let mut decoder = Interface::xz(decoder::XzDecoderOptions::new().memlimit(1024)).expect("create xz decoder");
+    let mut output = vec![0; DATA[1].len()];
+    let result = decoder.decode(DATA_XZ[1], output.as_mut());
+    let error = result.status.expect_err("should fail");
+    println!("error={error}: {:?}", decoder.describe_error(error));
+    assert_eq!(error.kind(), DecodeErrorKind::WindowTooLarge);
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn should_decode_bzip2() {
+    for small in [false, true] {
+        let mut decoder = Interface::bzip2(small).expect("create bzip2 decoder");
+        for idx in 0..DATA.len() {
+            test_case(idx, &mut decoder, DATA[idx], DATA_BZIP2[idx]);
+            #[cfg(feature = "bytes")]
+            test_case_bytes(idx, &mut decoder, DATA[idx], DATA_BZIP2[idx]);
+        }
+    }
+}
+
+//Snappy framing format has no end marker, so end of stream is signaled with empty input
+#[cfg(feature = "snappy")]
+fn test_case_snappy(idx: usize, decoder: &mut decoder::Decoder, data: &[u8], compressed: &[u8]) {
+    println!("snappy({idx}): DATA.len()={} || COMPRESSED.len()={}", data.len(), compressed.len());
+
+    //Full
+    let mut output = vec![0; data.len()];
+    let result = decoder.decode(compressed, output.as_mut());
+    assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+    assert_eq!(result.input_remain, 0);
+    assert_eq!(result.output_remain, 0);
+    assert_eq!(data, output);
+    let result = decoder.decode(&[], &mut []);
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    decoder.reset();
+
+    //Partial buffer
+    let result = decoder.decode(compressed, &mut output[..data.len() / 2]);
+    assert_eq!(result.status, Ok(DecodeStatus::NeedOutput));
+    assert_eq!(result.output_remain, 0);
+
+    let remaining = &compressed[compressed.len() - result.input_remain..];
+    let result = decoder.decode(remaining, &mut output[data.len() / 2..]);
+    assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+    assert_eq!(result.input_remain, 0);
+    let result = decoder.decode(&[], &mut output[data.len()..]);
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(data, output);
+    decoder.reset();
+
+    //Byte by byte
+    output.clear();
+    let mut chunk = [0u8; 64];
+    for byte in compressed.chunks(1).chain(core::iter::once(&[][..])) {
+        let mut input = byte;
+        loop {
+            let result = decoder.decode(input, &mut chunk);
+            input = &input[input.len() - result.input_remain..];
+            output.extend_from_slice(&chunk[..chunk.len() - result.output_remain]);
+            match result.status {
+                Ok(DecodeStatus::NeedOutput) => continue,
+                Ok(DecodeStatus::NeedInput) => break,
+                Ok(DecodeStatus::Finished) => assert!(byte.is_empty()),
+                Err(error) => panic!("Unexpected failure: {:?}", decoder.describe_error(error)),
+            }
+            break;
+        }
+    }
+    assert_eq!(data, output);
+    decoder.reset();
+
+    //Padding chunk after stream identifier is skipped
+    let mut padded = compressed[..10].to_vec();
+    padded.extend_from_slice(&[0xFE, 3, 0, 0, 0, 0, 0]);
+    padded.extend_from_slice(&compressed[10..]);
+    output.resize(data.len(), 0);
+    let result = decoder.decode(&padded, output.as_mut());
+    assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+    assert_eq!(result.input_remain, 0);
+    assert_eq!(data, output);
+    decoder.reset();
+
+    //Incomplete stream is never finished
+    let result = decoder.decode(&compressed[..compressed.len() - 1], output.as_mut());
+    assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+    let result = decoder.decode(&[], output.as_mut());
+    assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+    decoder.reset();
+
+    //Corrupted stream identifier
+    let mut corrupted = compressed.to_vec();
+    for byte in corrupted.iter_mut().take(8) {
+        *byte = !*byte;
+    }
+    let result = decoder.decode(&corrupted, output.as_mut());
+    let error = result.status.expect_err("should fail");
+    println!("error={error}: {:?}", decoder.describe_error(error));
+    assert_eq!(error.kind(), DecodeErrorKind::Corrupted);
+    decoder.reset();
+
+    //Checksum of first chunk
+    let mut corrupted = compressed.to_vec();
+    corrupted[14] = !corrupted[14];
+    let result = decoder.decode(&corrupted, output.as_mut());
+    let error = result.status.expect_err("should fail");
+    println!("error={error}: {:?}", decoder.describe_error(error));
+    assert_eq!(error.kind(), DecodeErrorKind::Checksum);
+    decoder.reset();
+
+    //Reserved unskippable chunk
+    let mut corrupted = compressed.to_vec();
+    corrupted[10] = 0x02;
+    let result = decoder.decode(&corrupted, output.as_mut());
+    let error = result.status.expect_err("should fail");
+    println!("error={error}: {:?}", decoder.describe_error(error));
+    assert_eq!(error.kind(), DecodeErrorKind::UnsupportedParameter);
+    decoder.reset();
+}
+
+#[cfg(feature = "snappy")]
+#[test]
+fn should_decode_snappy() {
+    let mut decoder = Interface::snappy();
+    for idx in 0..DATA.len() {
+        test_case_snappy(idx, &mut decoder, DATA[idx], DATA_SNAPPY[idx]);
+    }
+}
+
+#[cfg(any(feature = "brotli-c", feature = "brotli-rust"))]
+#[test]
+fn should_report_invalid_window_bits_brotli() {
+    //Large window header, which is not valid in regular brotli stream
+    const INVALID_WINDOW: [u8; 4] = [0x11, 0, 0, 0];
+
+    let mut decoders = Vec::new();
+    #[cfg(feature = "brotli-c")]
+    decoders.push(Interface::brotli_c().expect("create brotli decoder"));
+    #[cfg(feature = "brotli-rust")]
+    decoders.push(Interface::brotli_rust());
+
+    for mut decoder in decoders {
+        let mut output = vec![0; 1024];
+        let result = decoder.decode(&INVALID_WINDOW, output.as_mut());
+        let error = result.status.expect_err("should fail");
+        println!("error={error}: {:?}", decoder.describe_error(error));
+        assert_eq!(error.kind(), DecodeErrorKind::Corrupted);
+    }
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_report_window_too_large_zstd() {
+    let mut decoder = Interface::zstd(decoder::ZstdOptions::new().window_log(10)).expect("create zstd decoder");
+    //Output must be smaller than frame content, otherwise zstd decodes it in single pass without window
+    let mut output = vec![0; 1024];
+    let result = decoder.decode(DATA_ZSTD[1], output.as_mut());
+    let error = result.status.expect_err("should fail");
+    assert_eq!(error.kind(), DecodeErrorKind::WindowTooLarge);
+}
+
 #[cfg(feature = "zlib-ng")]
 #[test]
 fn should_decode_zlib_ng_gzip() {
-    let mut decoder = Interface::zlib_ng(decoder::ZlibMode::Gzip).expect("create zlib-ng decoder");
+    let mut decoder = Interface::zlib_ng(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib-ng decoder");
     for idx in 0..DATA.len() {
         test_case(idx, &mut decoder, DATA[idx], DATA_GZIP[idx]);
+        test_case_checksum(&mut decoder, DATA[idx], DATA_GZIP[idx], 8);
         #[cfg(feature = "bytes")]
         test_case_bytes(idx, &mut decoder, DATA[idx], DATA_GZIP[idx]);
     }
@@ -152,10 +500,738 @@ fn should_decode_zlib_ng_gzip() {
 #[cfg(any(feature = "zlib-rust"))]
 #[test]
 fn should_decode_zlib_rust_gzip() {
-    let mut decoder = Interface::zlib_rust(decoder::ZlibMode::Gzip).expect("create zlib-ng decoder");
+    let mut decoder = Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib-ng decoder");
     for idx in 0..DATA.len() {
         test_case(idx, &mut decoder, DATA[idx], DATA_GZIP[idx]);
+        test_case_checksum(&mut decoder, DATA[idx], DATA_GZIP[idx], 8);
         #[cfg(feature = "bytes")]
         test_case_bytes(idx, &mut decoder, DATA[idx], DATA_GZIP[idx]);
     }
 }
+
+#[test]
+fn should_inspect_gzip() {
+    const NAMES: [&[u8]; 2] = [b"10x10y", b"alice29.txt"];
+    const MTIMES: [u32; 2] = [0x5ce132f1, 0x5ce2f743];
+
+    for idx in 0..DATA.len() {
+        let info = decoder::inspect(DATA_GZIP[idx]).expect("inspect gzip");
+        assert_eq!(info.format, decoder::Detection::Gzip);
+        assert_eq!(info.content_size, Some(DATA[idx].len() as u64));
+        assert_eq!(info.window_size, None);
+        assert_eq!(info.dictionary_id, 0);
+        assert!(info.checksum);
+        assert_eq!(info.name, Some(NAMES[idx]));
+        assert_eq!(info.mtime, Some(MTIMES[idx]));
+        //NTFS
+        assert_eq!(info.os, Some(11));
+    }
+
+    //Content size of first member is reported, regardless of what follows it
+    for trailing in [&b"garbage"[..], DATA_GZIP[0]] {
+        let mut data = DATA_GZIP[1].to_vec();
+        data.extend_from_slice(trailing);
+        let info = decoder::inspect(&data).expect("inspect gzip");
+        assert_eq!(info.content_size, Some(DATA[1].len() as u64));
+    }
+    //Incomplete trailer
+    let info = decoder::inspect(&DATA_GZIP[1][..DATA_GZIP[1].len() - 1]).expect("inspect gzip");
+    assert_eq!(info.content_size, None);
+    //Header without trailer has no content size
+    let info = decoder::inspect(&DATA_GZIP[1][..22]).expect("inspect gzip");
+    assert_eq!(info.content_size, None);
+    assert_eq!(info.name, Some(NAMES[1]));
+    //Incomplete header
+    assert_eq!(decoder::inspect(&DATA_GZIP[1][..15]), None);
+    //Not supported format
+    assert_eq!(decoder::inspect(DATA_BZIP2[1]), None);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_inspect_zstd() {
+    for idx in 0..DATA.len() {
+        let info = decoder::inspect(DATA_ZSTD[idx]).expect("inspect zstd");
+        assert_eq!(info.format, decoder::Detection::Zstd);
+        assert_eq!(info.content_size, Some(DATA[idx].len() as u64));
+        assert_eq!(info.window_size, Some(DATA[idx].len() as u64));
+        assert_eq!(info.dictionary_id, 0);
+        assert!(info.checksum);
+        assert_eq!(info.name, None);
+        assert_eq!(info.mtime, None);
+        assert_eq!(info.os, None);
+
+        //Exact content size is allocated once, unless it is too large to be trusted
+        let mut decoder = Interface::zstd(Default::default()).expect("create zstd decoder");
+        let mut output = Vec::new();
+        let result = decoder.decode_vec_full(DATA_ZSTD[idx], &mut output).expect("Success");
+        assert_eq!(result.status, Ok(DecodeStatus::Finished));
+        assert_eq!(output, DATA[idx]);
+        if DATA[idx].len() <= 64 * 1024 {
+            assert_eq!(output.capacity(), DATA[idx].len());
+        }
+    }
+
+    assert_eq!(decoder::inspect(&DATA_ZSTD[1][..4]), None);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_not_trust_zstd_content_size_for_allocation() {
+    const BLOCK_SIZE: usize = 64 * 1024;
+    //Single segment frame with 8 bytes content size
+    let mut input = vec![0x28, 0xb5, 0x2f, 0xfd, 0b1110_0000];
+    input.extend_from_slice(&(BLOCK_SIZE as u64 * 1000).to_le_bytes());
+    //Last raw block
+    input.extend_from_slice(&((BLOCK_SIZE << 3) as u32 | 1).to_le_bytes()[..3]);
+    input.resize(input.len() + BLOCK_SIZE, 0);
+
+    let info = decoder::inspect(&input).expect("inspect zstd");
+    assert_eq!(info.content_size, Some(BLOCK_SIZE as u64 * 1000));
+
+    let mut decoder = Interface::zstd(Default::default()).expect("create zstd decoder");
+    let mut output = Vec::new();
+    let result = decoder.decode_vec_full(&input, &mut output).expect("Success");
+    assert!(result.status.is_err());
+    assert!(output.capacity() <= input.len() * 2);
+}
+
+fn test_case_auto(idx: usize, decoder: &mut decoder::Decoder, data: &[u8], compressed: &[u8]) {
+    println!("auto({idx}): DATA.len()={} || COMPRESSED.len()={}", data.len(), compressed.len());
+
+    //Full
+    let mut output = vec![0; data.len()];
+    let result = decoder.decode(compressed, output.as_mut());
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(result.input_remain, 0);
+    assert_eq!(result.output_remain, 0);
+    assert_eq!(data, output);
+    decoder.reset();
+
+    //Chunks smaller than required for detection
+    for chunk_size in [1, 3, 64] {
+        let mut output = Vec::with_capacity(16);
+        let mut finished = false;
+        'chunks: for mut chunk in compressed.chunks(chunk_size) {
+            loop {
+                let result = decoder.decode_vec(chunk, &mut output);
+                chunk = &chunk[chunk.len() - result.input_remain..];
+                match result.status {
+                    Ok(DecodeStatus::NeedInput) => break,
+                    Ok(DecodeStatus::NeedOutput) => output.reserve(1024),
+                    Ok(DecodeStatus::Finished) => {
+                        finished = true;
+                        break 'chunks;
+                    }
+                    Err(error) => panic!("Unexpected failure: {error}: {:?}", decoder.describe_error(error)),
+                }
+            }
+        }
+        assert!(finished);
+        assert_eq!(data, output);
+        decoder.reset();
+    }
+}
+
+#[cfg(any(feature = "brotli-c", feature = "brotli-rust"))]
+#[test]
+fn should_decode_auto_brotli() {
+    let mut decoder = Interface::auto(Default::default());
+    for idx in 0..DATA.len() {
+        test_case_auto(idx, &mut decoder, DATA[idx], DATA_BROTLI[idx]);
+    }
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_decode_auto_zstd() {
+    let mut decoder = Interface::auto(Default::default());
+    for idx in 0..DATA.len() {
+        test_case_auto(idx, &mut decoder, DATA[idx], DATA_ZSTD[idx]);
+    }
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_describe_backend_error_auto() {
+    let mut corrupted = DATA_ZSTD[1].to_vec();
+    for byte in corrupted.iter_mut().skip(10).take(20) {
+        *byte = !*byte;
+    }
+    let mut output = vec![0; DATA[1].len()];
+
+    let mut expected = Interface::zstd(Default::default()).expect("create zstd decoder");
+    let expected_error = expected.decode(&corrupted, output.as_mut()).status.expect_err("should fail");
+
+    let mut decoder = Interface::auto(Default::default());
+    let error = decoder.decode(&corrupted, output.as_mut()).status.expect_err("should fail");
+    assert_eq!(error, expected_error);
+    assert!(decoder.describe_error(error).is_some());
+    assert_eq!(decoder.describe_error(error), expected.describe_error(expected_error));
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+#[test]
+fn should_decode_auto_gzip() {
+    let mut decoder = Interface::auto(Default::default());
+    for idx in 0..DATA.len() {
+        test_case_auto(idx, &mut decoder, DATA[idx], DATA_GZIP[idx]);
+        test_case_checksum(&mut decoder, DATA[idx], DATA_GZIP[idx], 8);
+    }
+}
+
+#[cfg(any(feature = "lz4", feature = "lz4-rust"))]
+#[test]
+fn should_decode_auto_lz4() {
+    let mut decoder = Interface::auto(Default::default());
+    for idx in 0..DATA.len() {
+        test_case_auto(idx, &mut decoder, DATA[idx], DATA_LZ4[idx]);
+    }
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_decode_auto_xz() {
+    let mut decoder = Interface::auto(Default::default());
+    for idx in 0..DATA.len() {
+        test_case_auto(idx, &mut decoder, DATA[idx], DATA_XZ[idx]);
+    }
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn should_decode_auto_bzip2() {
+    let mut decoder = Interface::auto(Default::default());
+    for idx in 0..DATA.len() {
+        test_case_auto(idx, &mut decoder, DATA[idx], DATA_BZIP2[idx]);
+    }
+}
+
+#[test]
+fn should_report_unknown_format_auto() {
+    let mut decoder = Interface::auto(Default::default());
+    let mut output = vec![0; 1024];
+
+    let result = decoder.decode(DATA[1], output.as_mut());
+    let error = result.status.expect_err("should fail");
+    println!("error={error}: {:?}", decoder.describe_error(error));
+    assert_eq!(error.kind(), DecodeErrorKind::UnknownFormat);
+    assert!(decoder.describe_error(error).is_some());
+    decoder.reset();
+
+    //ZSTD dictionary cannot be decoded
+    let result = decoder.decode(&[0x37, 0xA4, 0x30, 0xEC, 0x00, 0x00, 0x00, 0x00], output.as_mut());
+    let error = result.status.expect_err("should fail");
+    println!("error={error}: {:?}", decoder.describe_error(error));
+    assert_eq!(error.kind(), DecodeErrorKind::UnsupportedFormat);
+    assert!(decoder.describe_error(error).is_some());
+    decoder.reset();
+
+    //Nothing can be detected within limit
+    let mut decoder = Interface::auto(decoder::AutoOptions::new().detection_limit(2));
+    let result = decoder.decode(&[0x1F], output.as_mut());
+    assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+    assert_eq!(result.input_remain, 0);
+    let result = decoder.decode(&[0x00], output.as_mut());
+    let error = result.status.expect_err("should fail");
+    assert_eq!(error.kind(), DecodeErrorKind::UnknownFormat);
+
+    //Short input, that might be start of brotli stream, is unknown at the end of input
+    let mut decoder = Interface::auto(Default::default());
+    let result = decoder.decode(b"GET / HTTP/1.1\r\n", output.as_mut());
+    assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+    let result = decoder.decode(&[], output.as_mut());
+    let error = result.status.expect_err("should fail");
+    assert_eq!(error.kind(), DecodeErrorKind::UnknownFormat);
+}
+
+#[cfg(feature = "brotli-c")]
+#[test]
+fn should_report_trailing_data_brotli_c() {
+    for idx in 0..DATA.len() {
+        test_case_trailing_data(|| Interface::brotli_c().expect("create brotli decoder"), DATA[idx], DATA_BROTLI[idx]);
+    }
+}
+
+#[cfg(feature = "brotli-rust")]
+#[test]
+fn should_report_trailing_data_brotli_rust() {
+    for idx in 0..DATA.len() {
+        test_case_trailing_data(Interface::brotli_rust, DATA[idx], DATA_BROTLI[idx]);
+    }
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_report_trailing_data_zstd() {
+    for idx in 0..DATA.len() {
+        test_case_trailing_data(|| Interface::zstd(Default::default()).expect("create zstd decoder"), DATA[idx], DATA_ZSTD[idx]);
+    }
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static"))]
+#[test]
+fn should_report_trailing_data_zlib() {
+    for idx in 0..DATA.len() {
+        test_case_trailing_data(|| Interface::zlib(Default::default()).expect("create zlib decoder"), DATA[idx], DATA_GZIP[idx]);
+    }
+}
+
+#[cfg(feature = "zlib-ng")]
+#[test]
+fn should_report_trailing_data_zlib_ng() {
+    for idx in 0..DATA.len() {
+        test_case_trailing_data(|| Interface::zlib_ng(Default::default()).expect("create zlib-ng decoder"), DATA[idx], DATA_GZIP[idx]);
+    }
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_report_trailing_data_zlib_rust() {
+    for idx in 0..DATA.len() {
+        test_case_trailing_data(|| Interface::zlib_rust(Default::default()).expect("create zlib-rust decoder"), DATA[idx], DATA_GZIP[idx]);
+    }
+}
+
+fn test_case_limits(create_decoder: impl Fn() -> decoder::Decoder<'static>, data: &[u8], compressed: &[u8]) {
+    //Limits are inclusive
+    let limits = decoder::DecodeLimits::new().max_output(data.len() as u64).max_window(1 << 20);
+    let mut decoder = create_decoder().with_limits(limits);
+    let mut output = Vec::new();
+    let result = decoder.decode_vec_full(compressed, &mut output).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(output, data);
+
+    //Small output buffer is continued up to the limit
+    assert!(decoder.reset());
+    let mut output = vec![0; data.len() + 1];
+    let mut output_len = 0;
+    let mut input = compressed;
+    loop {
+        let output_end = core::cmp::min(output_len + 7, output.len());
+        let result = decoder.decode(input, &mut output[output_len..output_end]);
+        input = &input[input.len() - result.input_remain..];
+        output_len = output_end - result.output_remain;
+        match result.status {
+            Ok(DecodeStatus::Finished) => break,
+            Ok(_) => continue,
+            Err(error) => panic!("Unexpected failure: {:?}", decoder.describe_error(error)),
+        }
+    }
+    assert_eq!(&output[..output_len], data);
+
+    let limits = decoder::DecodeLimits::new().max_output(data.len() as u64 - 1);
+    let mut decoder = create_decoder().with_limits(limits);
+    let mut output = vec![0; data.len()];
+    let result = decoder.decode(compressed, &mut output);
+    let error = result.status.expect_err("should fail");
+    println!("error={error}: {:?}", decoder.describe_error(error));
+    assert_eq!(error.kind(), DecodeErrorKind::LimitExceeded);
+    assert!(decoder.describe_error(error).is_some());
+    //Output is never written more than one byte above limit
+    assert_eq!(result.output_remain, 0);
+
+    //Decoding keeps failing without producing output until reset
+    let limits = decoder::DecodeLimits::new().max_output(data.len() as u64 / 2);
+    let mut decoder = create_decoder().with_limits(limits);
+    let result = decoder.decode(compressed, &mut output);
+    let error = result.status.expect_err("should fail");
+    assert_eq!(error.kind(), DecodeErrorKind::LimitExceeded);
+    assert_eq!(result.output_remain, output.len() - data.len() / 2 - 1);
+    let input = &compressed[compressed.len() - result.input_remain..];
+    let mut extra = vec![0; data.len()];
+    let result = decoder.decode(input, &mut extra);
+    assert_eq!(result.status, Err(error));
+    assert_eq!(result.input_remain, input.len());
+    assert_eq!(result.output_remain, extra.len());
+    let result = decoder.decode(&[], &mut extra);
+    assert_eq!(result.status, Err(error));
+    assert_eq!(result.output_remain, extra.len());
+
+    //Limit applies to total output since reset
+    assert!(decoder.reset());
+    let mut output = vec![0; data.len()];
+    let result = decoder.decode(compressed, &mut output[..data.len() / 2]);
+    assert!(result.status.is_ok());
+    let input = &compressed[compressed.len() - result.input_remain..];
+    let result = decoder.decode(input, &mut output[data.len() / 2..]);
+    assert_eq!(result.status.expect_err("should fail").kind(), DecodeErrorKind::LimitExceeded);
+
+    if data.len() > compressed.len() * 2 {
+        let limits = decoder::DecodeLimits::new().max_ratio(1);
+        let mut decoder = create_decoder().with_limits(limits);
+        let mut output = Vec::new();
+        let result = decoder.decode_vec_full(compressed, &mut output).expect("Success");
+        let error = result.status.expect_err("should fail");
+        println!("error={error}: {:?}", decoder.describe_error(error));
+        assert_eq!(error.kind(), DecodeErrorKind::LimitExceeded);
+        assert!(decoder.describe_error(error).is_some());
+    }
+
+    let limits = decoder::DecodeLimits::new().max_ratio(data.len() as u64 + 1);
+    let mut decoder = create_decoder().with_limits(limits);
+    let mut output = Vec::new();
+    let result = decoder.decode_vec_full(compressed, &mut output).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(output, data);
+}
+
+#[cfg(feature = "brotli-c")]
+#[test]
+fn should_limit_brotli_c() {
+    for idx in 0..DATA.len() {
+        test_case_limits(|| Interface::brotli_c().expect("create brotli decoder"), DATA[idx], DATA_BROTLI[idx]);
+    }
+}
+
+#[cfg(feature = "brotli-rust")]
+#[test]
+fn should_limit_brotli_rust() {
+    for idx in 0..DATA.len() {
+        test_case_limits(Interface::brotli_rust, DATA[idx], DATA_BROTLI[idx]);
+    }
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_limit_zstd() {
+    for idx in 0..DATA.len() {
+        test_case_limits(|| Interface::zstd(Default::default()).expect("create zstd decoder"), DATA[idx], DATA_ZSTD[idx]);
+    }
+
+    let compressed = DATA_ZSTD[1];
+    let window_size = decoder::inspect(compressed).expect("inspect").window_size.expect("window size");
+    let limits = decoder::DecodeLimits::new().max_window(window_size - 1);
+    let mut decoder = Interface::zstd(Default::default()).expect("create zstd decoder").with_limits(limits);
+    let mut output = Vec::new();
+    let result = decoder.decode_vec_full(compressed, &mut output).expect("Success");
+    let error = result.status.expect_err("should fail");
+    println!("error={error}: {:?}", decoder.describe_error(error));
+    assert_eq!(error.kind(), DecodeErrorKind::LimitExceeded);
+    assert!(decoder.describe_error(error).is_some());
+    assert_eq!(result.input_remain, compressed.len());
+    //Window is not checked again, but decoding still fails
+    let result = decoder.decode_vec_full(compressed, &mut output).expect("Success");
+    assert_eq!(result.status, Err(error));
+    assert!(output.is_empty());
+
+    //Header split across calls
+    assert!(decoder.reset());
+    let mut output = Vec::new();
+    let result = decoder.decode_vec_full(&compressed[..3], &mut output).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+    let result = decoder.decode_vec_full(&compressed[3..], &mut output).expect("Success");
+    assert_eq!(result.status.expect_err("should fail").kind(), DecodeErrorKind::LimitExceeded);
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static"))]
+#[test]
+fn should_limit_zlib() {
+    for idx in 0..DATA.len() {
+        test_case_limits(|| Interface::zlib(Default::default()).expect("create zlib decoder"), DATA[idx], DATA_GZIP[idx]);
+    }
+}
+
+#[cfg(feature = "zlib-ng")]
+#[test]
+fn should_limit_zlib_ng() {
+    for idx in 0..DATA.len() {
+        test_case_limits(|| Interface::zlib_ng(Default::default()).expect("create zlib-ng decoder"), DATA[idx], DATA_GZIP[idx]);
+    }
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_limit_zlib_rust() {
+    for idx in 0..DATA.len() {
+        test_case_limits(|| Interface::zlib_rust(Default::default()).expect("create zlib-rust decoder"), DATA[idx], DATA_GZIP[idx]);
+    }
+}
+
+#[cfg(feature = "lz4")]
+#[test]
+fn should_limit_lz4() {
+    for idx in 0..DATA.len() {
+        test_case_limits(|| Interface::lz4().expect("create lz4 decoder"), DATA[idx], DATA_LZ4[idx]);
+    }
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_limit_xz() {
+    for idx in 0..DATA.len() {
+        test_case_limits(|| Interface::xz(Default::default()).expect("create xz decoder"), DATA[idx], DATA_XZ[idx]);
+    }
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn should_limit_bzip2() {
+    for idx in 0..DATA.len() {
+        test_case_limits(|| Interface::bzip2(false).expect("create bzip2 decoder"), DATA[idx], DATA_BZIP2[idx]);
+    }
+}
+
+//Decodes into small output chunks, which requires backend to keep its own window
+fn decode_chunked(decoder: &mut decoder::Decoder, mut input: &[u8], output: &mut Vec<u8>) -> Result<DecodeStatus, DecodeError> {
+    let mut chunk = [0; 1024];
+    loop {
+        let result = decoder.decode(input, &mut chunk);
+        input = &input[input.len() - result.input_remain..];
+        output.extend_from_slice(&chunk[..chunk.len() - result.output_remain]);
+        match result.status {
+            Ok(DecodeStatus::Finished) => break Ok(DecodeStatus::Finished),
+            Ok(_) => continue,
+            Err(error) => break Err(error),
+        }
+    }
+}
+
+fn test_case_memory_budget(create_decoder: impl Fn() -> decoder::Decoder<'static>, data: &[u8], compressed: &[u8]) {
+    let mut decoder = create_decoder();
+    let initial = decoder.memory_usage().expect("memory usage");
+    let mut output = Vec::new();
+    assert_eq!(decode_chunked(&mut decoder, compressed, &mut output), Ok(DecodeStatus::Finished));
+    assert_eq!(output, data);
+    let usage = decoder.memory_usage().expect("memory usage");
+    assert!(usage.peak > 0);
+    assert!(usage.peak >= initial.peak);
+    assert!(usage.current <= usage.peak);
+
+    //Budget covering peak usage is enough
+    let mut decoder = create_decoder();
+    assert!(decoder.set_memory_budget(Some(usage.peak)));
+    let mut output = Vec::new();
+    assert_eq!(decode_chunked(&mut decoder, compressed, &mut output), Ok(DecodeStatus::Finished));
+    assert_eq!(output, data);
+    assert!(decoder.memory_usage().expect("memory usage").peak <= usage.peak);
+
+    //Memory allocated on creation is kept, but anything above fails
+    if usage.peak > initial.current {
+        let mut decoder = create_decoder();
+        assert!(decoder.set_memory_budget(Some(initial.current)));
+        let mut output = Vec::new();
+        let error = decode_chunked(&mut decoder, compressed, &mut output).expect_err("should fail");
+        println!("error={error}: {:?}", decoder.describe_error(error));
+        assert_eq!(error.kind(), DecodeErrorKind::OutOfMemory);
+        assert!(decoder.memory_usage().expect("memory usage").peak <= initial.current);
+
+        //Budget is forwarded by wrappers
+        let mut decoder = create_decoder().with_limits(Default::default()).with_trailing_data(Default::default());
+        assert!(decoder.set_memory_budget(Some(initial.current)));
+        let mut output = Vec::new();
+        let error = decode_chunked(&mut decoder, compressed, &mut output).expect_err("should fail");
+        assert_eq!(error.kind(), DecodeErrorKind::OutOfMemory);
+    }
+}
+
+#[cfg(feature = "brotli-c")]
+#[test]
+fn should_limit_memory_brotli_c() {
+    test_case_memory_budget(|| Interface::brotli_c().expect("create brotli decoder"), DATA[1], DATA_BROTLI[1]);
+}
+
+#[cfg(feature = "brotli-rust")]
+#[test]
+fn should_not_track_memory_brotli_rust() {
+    let mut decoder = Interface::brotli_rust();
+    assert_eq!(decoder.memory_usage(), None);
+    assert!(!decoder.set_memory_budget(Some(0)));
+}
+
+#[cfg(any(feature = "zstd", feature = "brotli-rust", feature = "zlib-rust"))]
+fn workspace(size: usize) -> Vec<core::mem::MaybeUninit<u8>> {
+    vec![core::mem::MaybeUninit::uninit(); size]
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_decode_zstd_static() {
+    let options = decoder::ZstdOptions::new().window_log(20).concatenated(true);
+    let mut memory = workspace(options.estimate_size());
+    let mut decoder = Interface::zstd_static(options, &mut memory).expect("create zstd static decoder");
+    let mut input = DATA_ZSTD[0].to_vec();
+    input.extend_from_slice(DATA_ZSTD[1]);
+    let mut expected = DATA[0].to_vec();
+    expected.extend_from_slice(DATA[1]);
+    let mut output = Vec::new();
+    assert_eq!(decode_chunked(&mut decoder, &input, &mut output), Ok(DecodeStatus::Finished));
+    assert_eq!(output, expected);
+
+    //Window above cap is refused, as workspace is sized for it
+    let options = decoder::ZstdOptions::new().window_log(10);
+    let mut memory = workspace(options.estimate_size());
+    let mut decoder = Interface::zstd_static(options, &mut memory).expect("create zstd static decoder");
+    let mut output = Vec::new();
+    let error = decode_chunked(&mut decoder, DATA_ZSTD[1], &mut output).expect_err("should fail");
+    assert_eq!(error.kind(), DecodeErrorKind::WindowTooLarge);
+}
+
+#[cfg(feature = "brotli-rust")]
+#[test]
+fn should_decode_brotli_rust_static() {
+    let mut memory = workspace(1024 * 1024);
+    let mut decoder = Interface::brotli_rust_static(&mut memory).expect("create brotli static decoder");
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut decoder, DATA[idx], DATA_BROTLI[idx]);
+    }
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_decode_zlib_rust_static() {
+    let mut memory = workspace(64 * 1024);
+    let mut decoder = Interface::zlib_rust_static(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip), &mut memory).expect("create zlib-rust static decoder");
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut decoder, DATA[idx], DATA_GZIP[idx]);
+    }
+    drop(decoder);
+
+    let mut decoder = Interface::zlib_rust_static(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip).multi_member(true), &mut memory).expect("create zlib-rust static decoder");
+    let mut input = DATA_GZIP[0].to_vec();
+    input.extend_from_slice(DATA_GZIP[1]);
+    let mut expected = DATA[0].to_vec();
+    expected.extend_from_slice(DATA[1]);
+    let mut output = Vec::new();
+    assert_eq!(decode_chunked(&mut decoder, &input, &mut output), Ok(DecodeStatus::Finished));
+    assert_eq!(output, expected);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_limit_memory_zstd() {
+    test_case_memory_budget(|| Interface::zstd(Default::default()).expect("create zstd decoder"), DATA[1], DATA_ZSTD[1]);
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static"))]
+#[test]
+fn should_limit_memory_zlib() {
+    test_case_memory_budget(|| Interface::zlib(Default::default()).expect("create zlib decoder"), DATA[1], DATA_GZIP[1]);
+}
+
+#[cfg(feature = "zlib-ng")]
+#[test]
+fn should_limit_memory_zlib_ng() {
+    test_case_memory_budget(|| Interface::zlib_ng(Default::default()).expect("create zlib-ng decoder"), DATA[1], DATA_GZIP[1]);
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_limit_memory_zlib_rust() {
+    test_case_memory_budget(|| Interface::zlib_rust(Default::default()).expect("create zlib-rust decoder"), DATA[1], DATA_GZIP[1]);
+}
+
+#[cfg(feature = "lz4")]
+#[test]
+fn should_limit_memory_lz4() {
+    test_case_memory_budget(|| Interface::lz4().expect("create lz4 decoder"), DATA[1], DATA_LZ4[1]);
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_limit_memory_xz() {
+    test_case_memory_budget(|| Interface::xz(Default::default()).expect("create xz decoder"), DATA[1], DATA_XZ[1]);
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn should_limit_memory_bzip2() {
+    test_case_memory_budget(|| Interface::bzip2(false).expect("create bzip2 decoder"), DATA[1], DATA_BZIP2[1]);
+}
+
+#[derive(Clone, Default)]
+struct CountingAllocator {
+    allocations: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    live: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+unsafe impl compu::mem::Allocator for CountingAllocator {
+    fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        self.allocations.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.live.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        unsafe { std::alloc::alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        self.live.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+        std::alloc::dealloc(ptr, layout)
+    }
+}
+
+impl CountingAllocator {
+    fn allocations(&self) -> usize {
+        self.allocations.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    fn live(&self) -> usize {
+        self.live.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+fn test_case_allocator(create_decoder: impl Fn(CountingAllocator) -> decoder::Decoder<'static>, data: &[u8], compressed: &[u8]) {
+    let allocator = CountingAllocator::default();
+    let mut decoder = create_decoder(allocator.clone());
+    let mut output = Vec::new();
+    assert_eq!(decode_chunked(&mut decoder, compressed, &mut output), Ok(DecodeStatus::Finished));
+    assert_eq!(output, data);
+    assert!(allocator.allocations() > 0);
+    //Usage is tracked regardless of allocator
+    let usage = decoder.memory_usage().expect("memory usage");
+    assert!(usage.peak > 0);
+
+    assert!(decoder.reset());
+    let mut output = Vec::new();
+    assert_eq!(decode_chunked(&mut decoder, compressed, &mut output), Ok(DecodeStatus::Finished));
+    assert_eq!(output, data);
+
+    drop(decoder);
+    assert_eq!(allocator.live(), 0);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_use_allocator_zstd() {
+    test_case_allocator(|allocator| Interface::zstd_with_allocator(Default::default(), allocator).expect("create zstd decoder"), DATA[1], DATA_ZSTD[1]);
+}
+
+#[cfg(feature = "brotli-c")]
+#[test]
+fn should_use_allocator_brotli_c() {
+    test_case_allocator(|allocator| Interface::brotli_c_with_allocator(&[], allocator).expect("create brotli-c decoder"), DATA[1], DATA_BROTLI[1]);
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static"))]
+#[test]
+fn should_use_allocator_zlib() {
+    test_case_allocator(|allocator| Interface::zlib_with_allocator(Default::default(), allocator).expect("create zlib decoder"), DATA[1], DATA_GZIP[1]);
+}
+
+#[cfg(feature = "zlib-ng")]
+#[test]
+fn should_use_allocator_zlib_ng() {
+    test_case_allocator(|allocator| Interface::zlib_ng_with_allocator(Default::default(), allocator).expect("create zlib-ng decoder"), DATA[1], DATA_GZIP[1]);
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_use_allocator_zlib_rust() {
+    test_case_allocator(|allocator| Interface::zlib_rust_with_allocator(Default::default(), allocator).expect("create zlib-rust decoder"), DATA[1], DATA_GZIP[1]);
+}
+
+#[cfg(feature = "lz4")]
+#[test]
+fn should_use_allocator_lz4() {
+    test_case_allocator(|allocator| Interface::lz4_with_allocator(allocator).expect("create lz4 decoder"), DATA[1], DATA_LZ4[1]);
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_use_allocator_xz() {
+    test_case_allocator(|allocator| Interface::xz_with_allocator(Default::default(), allocator).expect("create xz decoder"), DATA[1], DATA_XZ[1]);
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn should_use_allocator_bzip2() {
+    test_case_allocator(|allocator| Interface::bzip2_with_allocator(false, allocator).expect("create bzip2 decoder"), DATA[1], DATA_BZIP2[1]);
+}
@@ -0,0 +1,116 @@
+use compu::decoder::Detection;
+
+#[test]
+fn should_detect_formats_by_signature() {
+    const CASES: &[(&[u8], Detection)] = &[
+        (&[0x28, 0xB5, 0x2F, 0xFD, 0x00, 0x00], Detection::Zstd),
+        (&[0x50, 0x2A, 0x4D, 0x18, 0x00, 0x00], Detection::Zstd),
+        (&[0x5F, 0x2A, 0x4D, 0x18, 0x00, 0x00], Detection::Zstd),
+        (&[0x37, 0xA4, 0x30, 0xEC, 0x00, 0x00], Detection::ZstdDictionary),
+        (&[0x1F, 0x8B, 0x08, 0x00], Detection::Gzip),
+        (&[0x78, 0x9C, 0x00, 0x00], Detection::Zlib),
+        (&[0x68, 0x81, 0x00, 0x00], Detection::Zlib),
+        (&[0xFD, b'7', b'z', b'X', b'Z', 0x00, 0x00], Detection::Xz),
+        (&[0x04, 0x22, 0x4D, 0x18, 0x64, 0x40], Detection::Lz4),
+        (b"BZh91AY&SY", Detection::Bzip2),
+    ];
+
+    for (idx, (bytes, expected)) in CASES.iter().enumerate() {
+        assert_eq!(Detection::detect(bytes), Some(*expected), "{}: {:?}", idx, bytes);
+    }
+}
+
+#[test]
+fn should_require_more_data_for_partial_signature() {
+    assert_eq!(Detection::detect(&[]), None);
+    assert_eq!(Detection::detect(&[0x28]), None);
+    assert_eq!(Detection::detect(&[0xFD, b'7', b'z', b'X']), None);
+    assert_eq!(Detection::detect(&[0xFD, b'7', b'z', b'X', b'Z']), None);
+}
+
+#[test]
+fn should_not_detect_unknown_formats() {
+    assert_eq!(Detection::detect(b"BZh0 not a bzip2"), Some(Detection::Unknown));
+    assert_eq!(Detection::detect(&[0xFD, b'7', b'z', b'X', b'Y', 0x00]), Some(Detection::Unknown));
+    assert_eq!(Detection::detect(include_bytes!("data/alice29.txt")), Some(Detection::Unknown));
+}
+
+#[test]
+fn should_detect_brotli() {
+    let compressed: [&[u8]; 2] = [
+        include_bytes!("data/10x10y.compressed.br"),
+        include_bytes!("data/alice29.txt.compressed.br"),
+    ];
+
+    for compressed in compressed {
+        assert_eq!(Detection::detect(compressed), Some(Detection::Brotli));
+        //Header of alice29 does not fit in 16 bytes
+        if compressed.len() > 16 {
+            assert_eq!(Detection::detect(&compressed[..16]), None);
+        }
+    }
+}
+
+#[test]
+fn should_not_panic_on_many_brotli_literal_types() {
+    struct BitWriter {
+        bytes: [u8; 128],
+        pos: usize,
+    }
+
+    impl BitWriter {
+        fn write(&mut self, value: u32, num: usize) {
+            for idx in 0..num {
+                self.bytes[self.pos >> 3] |= (((value >> idx) & 1) as u8) << (self.pos & 7);
+                self.pos += 1;
+            }
+        }
+    }
+
+    let mut writer = BitWriter {
+        bytes: [0; 128],
+        pos: 0,
+    };
+    //WBITS=16
+    writer.write(0, 1);
+    //ISLAST=0, MNIBBLES=4, MLEN-1=0, ISUNCOMPRESSED=0
+    writer.write(0, 1);
+    writer.write(0, 2);
+    writer.write(0, 16);
+    writer.write(0, 1);
+    //NBLTYPESL=256
+    writer.write(1, 1);
+    writer.write(7, 3);
+    writer.write(127, 7);
+    //Simple prefix codes with single symbol for block types and block counts, followed by block count extra bits
+    writer.write(1, 2);
+    writer.write(0, 2);
+    writer.write(0, 9);
+    writer.write(1, 2);
+    writer.write(0, 2);
+    writer.write(0, 5);
+    writer.write(0, 2);
+    //NBLTYPESI=1, NBLTYPESD=1, NPOSTFIX=0, NDIRECT=0
+    writer.write(0, 1);
+    writer.write(0, 1);
+    writer.write(0, 2);
+    writer.write(0, 4);
+
+    //Context modes of all literal block types are available and must not overflow
+    assert!(writer.bytes.len() * 8 - writer.pos >= 2 * 256);
+    let _ = Detection::detect(&writer.bytes);
+}
+
+#[test]
+fn should_not_confuse_other_formats_with_brotli() {
+    let compressed: [(&[u8], Detection); 4] = [
+        (include_bytes!("data/10x10y.compressed.gz"), Detection::Gzip),
+        (include_bytes!("data/alice29.txt.compressed.gz"), Detection::Gzip),
+        (include_bytes!("data/10x10y.compressed.zstd"), Detection::Zstd),
+        (include_bytes!("data/alice29.txt.compressed.zstd"), Detection::Zstd),
+    ];
+
+    for (compressed, expected) in compressed {
+        assert_eq!(Detection::detect(compressed), Some(expected));
+    }
+}
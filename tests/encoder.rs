@@ -1,6 +1,8 @@
+#![cfg(feature = "alloc")]
+
 use compu::{decoder, encoder, Buffer};
 use decoder::{DecodeStatus, Decoder, Detection};
-use encoder::{EncodeOp, EncodeStatus, Encoder, Interface};
+use encoder::{EncodeErrorKind, EncodeOp, EncodeStatus, Encoder, Interface};
 
 const DATA: [&[u8]; 2] = [
     include_bytes!("data/10x10y"),
@@ -48,7 +50,7 @@ fn test_case(idx: usize, encoder: &mut Encoder, decoder: &mut Decoder, data: &[u
         buffer.consume();
 
         match status {
-            EncodeStatus::Error => panic!("unexpected error"),
+            EncodeStatus::Error(error) => panic!("unexpected error: {error} {:?}", encoder.describe_error(error)),
             EncodeStatus::Finished => break,
             _ => continue,
         }
@@ -122,7 +124,7 @@ fn test_case_empty_final(idx: usize, encoder: &mut Encoder, decoder: &mut Decode
     let mut output = compressed.spare_capacity_mut();
     let mut output_len = output.len();
     let result = encoder.encode_uninit(data, output, EncodeOp::Process);
-    assert_ne!(result.status, EncodeStatus::Error);
+    assert!(!matches!(result.status, EncodeStatus::Error(_)));
     unsafe {
         compressed.set_len(output_len - result.output_remain);
     }
@@ -172,14 +174,294 @@ fn test_case_empty_final(idx: usize, encoder: &mut Encoder, decoder: &mut Decode
     decoder.reset();
 }
 
+fn test_case_error_after_finish(encoder: &mut Encoder, expected_kind: EncodeErrorKind) {
+    let mut compressed = Vec::with_capacity(DATA[0].len() + 100);
+    let result = encoder.encode_vec(DATA[0], &mut compressed, EncodeOp::Finish);
+    assert_eq!(result.status, EncodeStatus::Finished);
+
+    //No new input is allowed once stream is finished
+    let result = encoder.encode_vec(DATA[0], &mut compressed, EncodeOp::Process);
+    let error = match result.status {
+        EncodeStatus::Error(error) => error,
+        status => panic!("unexpected status: {:?}", status),
+    };
+    let description = encoder.describe_error(error).expect("to describe error");
+    println!("error={error}: {description}");
+    assert_eq!(error.kind(), expected_kind);
+
+    encoder.reset();
+}
+
+#[cfg(any(feature = "zstd", feature = "brotli-c", feature = "brotli-rust"))]
+fn test_case_pledged_size(encoder: &mut Encoder, decoder: &mut Decoder, data: &[u8]) {
+    let (first, second) = data.split_at(data.len() / 2);
+    assert!(encoder.set_pledged_size(data.len() as u64));
+
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(first, &mut compressed, EncodeOp::Process).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Continue);
+    //Cannot be changed once encoding started
+    assert!(!encoder.set_pledged_size(data.len() as u64));
+    let result = encoder.encode_vec_full(second, &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert!(encoder.reset());
+
+    let mut decompressed = Vec::new();
+    let result = decoder.decode_vec_full(&compressed, &mut decompressed).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(decompressed, data);
+    decoder.reset();
+}
+
+fn test_case_memory_budget(create_encoder: impl Fn() -> Encoder<'static>) {
+    let data = DATA[1];
+    let mut encoder = create_encoder();
+    let initial = encoder.memory_usage().expect("memory usage");
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(data, &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    let usage = encoder.memory_usage().expect("memory usage");
+    assert!(usage.peak > 0);
+    assert!(usage.peak >= initial.peak);
+    assert!(usage.current <= usage.peak);
+
+    //Budget covering peak usage is enough
+    let mut encoder = create_encoder();
+    assert!(encoder.set_memory_budget(Some(usage.peak)));
+    let mut budget_compressed = Vec::new();
+    let result = encoder.encode_vec_full(data, &mut budget_compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert_eq!(budget_compressed, compressed);
+
+    //Memory allocated on creation is kept, but anything above fails
+    if usage.peak > initial.current {
+        let mut encoder = create_encoder();
+        assert!(encoder.set_memory_budget(Some(initial.current)));
+        let mut compressed = Vec::new();
+        let result = encoder.encode_vec_full(data, &mut compressed, EncodeOp::Finish).expect("Success");
+        let error = match result.status {
+            EncodeStatus::Error(error) => error,
+            status => panic!("unexpected status: {:?}", status),
+        };
+        println!("error={error}: {:?}", encoder.describe_error(error));
+        assert_eq!(error.kind(), EncodeErrorKind::OutOfMemory);
+    }
+}
+
+#[cfg(any(feature = "zstd", feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+fn test_case_concatenated(encoder: &mut Encoder, decoder: &mut Decoder, single: &mut Decoder) {
+    let mut compressed = Vec::new();
+    let mut boundaries = Vec::new();
+    for data in DATA {
+        let result = encoder.encode_vec_full(data, &mut compressed, EncodeOp::Finish).expect("Success");
+        assert_eq!(result.status, EncodeStatus::Finished);
+        assert!(encoder.reset());
+        boundaries.push(compressed.len());
+    }
+    let expected = DATA.concat();
+
+    //Decoder without option stops after the first member
+    let mut decompressed = Vec::new();
+    let result = single.decode_vec_full(&compressed, &mut decompressed).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(result.input_remain, compressed.len() - boundaries[0]);
+    assert_eq!(decompressed, DATA[0]);
+
+    let mut decompressed = Vec::new();
+    let result = decoder.decode_vec_full(&compressed, &mut decompressed).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(result.input_remain, 0);
+    assert_eq!(decompressed, expected);
+    assert!(decoder.reset());
+
+    //Input ending within member requires more input
+    let mut decompressed = Vec::new();
+    let result = decoder.decode_vec_full(&compressed[..boundaries[0] + 10], &mut decompressed).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+    assert_eq!(result.input_remain, 0);
+    assert!(decoder.reset());
+
+    //Finished is reported at member boundary, but decoding continues with more input
+    let mut decompressed = Vec::new();
+    let result = decoder.decode_vec_full(&compressed[..boundaries[0]], &mut decompressed).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    for chunk in compressed[boundaries[0]..].chunks(7) {
+        let result = decoder.decode_vec_full(chunk, &mut decompressed).expect("Success");
+        assert_eq!(result.input_remain, 0);
+        assert!(result.status.is_ok());
+    }
+    assert_eq!(decompressed, expected);
+    assert!(decoder.reset());
+}
+
+//Snappy framing format has no end marker, so end of stream is signaled to decoder with empty input
+#[cfg(feature = "snappy")]
+fn test_case_snappy(idx: usize, encoder: &mut Encoder, decoder: &mut Decoder, data: &[u8]) {
+    println!("{idx}: DATA.len()={}", data.len());
+
+    //Buffered encoder
+    let mut compressed = Vec::new();
+    let mut buffer = Buffer::<4096>::new();
+    let mut buffer_input = data;
+    loop {
+        let (consumed, status) = buffer.encode(encoder, buffer_input, EncodeOp::Finish);
+        buffer_input = &buffer_input[consumed..];
+        compressed.extend_from_slice(buffer.data());
+        buffer.consume();
+
+        match status {
+            EncodeStatus::Error(error) => panic!("unexpected error: {error} {:?}", encoder.describe_error(error)),
+            EncodeStatus::Finished => break,
+            _ => continue,
+        }
+    }
+
+    //Full vec encoding
+    encoder.reset();
+    let mut compressed_full = Vec::new();
+    let result = encoder.encode_vec_full(data, &mut compressed_full, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert_eq!(result.input_remain, 0);
+    assert!(compressed == compressed_full);
+
+    let mut decompressed = Vec::new();
+    let result = decoder.decode_vec_full(&compressed, &mut decompressed).expect("success");
+    assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+    assert_eq!(result.input_remain, 0);
+    let result = decoder.decode_vec(&[], &mut decompressed);
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(data, decompressed);
+    decoder.reset();
+
+    //Flushed in small pieces
+    encoder.reset();
+    compressed.clear();
+    for chunk in data.chunks(1000) {
+        let result = encoder.encode_vec_full(chunk, &mut compressed, EncodeOp::Flush).expect("Success");
+        assert_eq!(result.status, EncodeStatus::Continue);
+        assert_eq!(result.input_remain, 0);
+    }
+    let result = encoder.encode_vec_full(&[], &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+
+    decompressed.clear();
+    let result = decoder.decode_vec_full(&compressed, &mut decompressed).expect("success");
+    assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+    let result = decoder.decode_vec(&[], &mut decompressed);
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(data, decompressed);
+
+    encoder.reset();
+    decoder.reset();
+}
+
 #[cfg(feature = "brotli-c")]
 #[test]
 fn should_encode_and_decode_brotli_c() {
     let mut encoder = Interface::brotli_c(Default::default()).expect("create brotli encoder");
     let mut decoder = decoder::Interface::brotli_c().expect("create brotli decoder");
     for idx in 0..DATA.len() {
-        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Unknown);
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Brotli);
+    }
+}
+
+#[cfg(feature = "brotli-c")]
+#[test]
+fn should_encode_and_decode_brotli_c_dictionary() {
+    use compu::dictionary_transport::{Format, HeaderError};
+
+    let dict = &DATA[1][DATA[1].len() - 4096..];
+    let prepared = encoder::BrotliDictionary::new(dict).expect("create brotli dictionary");
+    let mut encoder = Interface::brotli_c(encoder::BrotliOptions::new().dictionary(&prepared)).expect("create brotli encoder");
+    let mut decoder = decoder::Interface::brotli_c_with_dictionary(dict).expect("create brotli decoder");
+    //Encoder keeps own reference to dictionary
+    drop(prepared);
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Brotli);
     }
+
+    //Small message benefits from dictionary
+    let message = &dict[..1024];
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(message, &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert!(encoder.reset());
+
+    let mut encoder = Interface::brotli_c(Default::default()).expect("create brotli encoder");
+    let mut compressed_no_dict = Vec::new();
+    let result = encoder.encode_vec_full(message, &mut compressed_no_dict, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert!(compressed.len() < compressed_no_dict.len());
+
+    //Dictionary is required to decompress
+    let mut no_dict_decoder = decoder::Interface::brotli_c().expect("create brotli decoder");
+    let mut decompressed = vec![0; message.len()];
+    let result = no_dict_decoder.decode(&compressed, decompressed.as_mut());
+    result.status.expect_err("should fail");
+
+    //dcb framing
+    const HASH: [u8; 32] = [0xab; 32];
+    let mut framed = Vec::new();
+    Format::Dcb.write_header(&HASH, &mut framed);
+    assert_eq!(framed.len(), Format::Dcb.header_size());
+    framed.extend_from_slice(&compressed);
+
+    assert_eq!(Format::from_content_encoding("dcb"), Some(Format::Dcb));
+    assert_eq!(Format::Dcb.content_encoding(), "dcb");
+    assert_eq!(Format::Dcb.parse_header(&framed[..10]), Err(HeaderError::Incomplete));
+    assert_eq!(Format::Dcz.parse_header(&framed), Err(HeaderError::InvalidMagic));
+    assert_eq!(Format::Dcb.parse_header(&framed), Ok(&HASH));
+
+    let mut decompressed = Vec::new();
+    let result = decoder.decode_vec_full(&framed[Format::Dcb.header_size()..], &mut decompressed).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(decompressed, message);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_encode_and_decode_zstd_dictionary_transport() {
+    use compu::dictionary_transport::{Format, HeaderError};
+
+    let dict = &DATA[1][DATA[1].len() - 4096..];
+    let message = &dict[..1024];
+    let mut encoder = Interface::zstd(encoder::ZstdOptions::new().dictionary(dict)).expect("create zstd encoder");
+    let mut decoder = decoder::Interface::zstd(decoder::ZstdOptions::new().dictionary(dict)).expect("create zstd decoder");
+
+    const HASH: [u8; 32] = [0xcd; 32];
+    let mut framed = Vec::new();
+    Format::Dcz.write_header(&HASH, &mut framed);
+    assert_eq!(framed.len(), Format::Dcz.header_size());
+    let result = encoder.encode_vec_full(message, &mut framed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+
+    assert_eq!(Format::from_content_encoding("dcz"), Some(Format::Dcz));
+    assert_eq!(Format::from_content_encoding("br"), None);
+    assert_eq!(Format::Dcz.content_encoding(), "dcz");
+    assert_eq!(Format::Dcz.parse_header(&framed[..4]), Err(HeaderError::Incomplete));
+    assert_eq!(Format::Dcb.parse_header(&framed), Err(HeaderError::InvalidMagic));
+    assert_eq!(Format::Dcz.parse_header(&framed), Ok(&HASH));
+
+    let mut decompressed = Vec::new();
+    let result = decoder.decode_vec_full(&framed[Format::Dcz.header_size()..], &mut decompressed).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(decompressed, message);
+}
+
+#[cfg(feature = "brotli-c")]
+#[test]
+fn should_pledge_size_brotli_c() {
+    let mut encoder = Interface::brotli_c(Default::default()).expect("create brotli encoder");
+    let mut decoder = decoder::Interface::brotli_c().expect("create brotli decoder");
+    test_case_pledged_size(&mut encoder, &mut decoder, DATA[1]);
+}
+
+#[cfg(feature = "brotli-rust")]
+#[test]
+fn should_pledge_size_brotli_rust() {
+    let mut encoder = Interface::brotli_rust(Default::default());
+    let mut decoder = decoder::Interface::brotli_rust();
+    test_case_pledged_size(&mut encoder, &mut decoder, DATA[1]);
 }
 
 #[cfg(feature = "brotli-rust")]
@@ -188,7 +470,7 @@ fn should_encode_and_decode_brotli_rust() {
     let mut encoder = Interface::brotli_rust(Default::default());
     let mut decoder = decoder::Interface::brotli_rust();
     for idx in 0..DATA.len() {
-        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Unknown);
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Brotli);
     }
 }
 
@@ -202,12 +484,301 @@ fn should_encode_and_decode_zstd() {
     }
 }
 
+#[cfg(feature = "zstd")]
+#[test]
+fn should_encode_and_decode_zstd_options() {
+    let options = encoder::ZstdOptions::new().level(19)
+                                             .checksum(true)
+                                             .content_size(false)
+                                             .dict_id(false)
+                                             .hash_log(20)
+                                             .chain_log(20)
+                                             .search_log(4)
+                                             .min_match(5)
+                                             .target_length(16)
+                                             .long_distance_matching(true)
+                                             .ldm_hash_log(20)
+                                             .ldm_min_match(64)
+                                             .ldm_bucket_size_log(3)
+                                             .ldm_hash_rate_log(4);
+    let mut encoder = Interface::zstd(options).expect("create zstd encoder");
+    let mut decoder = decoder::Interface::zstd(Default::default()).expect("create zstd decoder");
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Zstd);
+    }
+
+    let mut encoder = Interface::zstd(encoder::ZstdOptions::new().level(-5)).expect("create zstd encoder");
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Zstd);
+    }
+
+    //Out of bounds values are rejected on creation
+    assert!(Interface::zstd(encoder::ZstdOptions::new().level(100)).is_none());
+    assert!(Interface::zstd(encoder::ZstdOptions::new().window_log(100)).is_none());
+    assert!(Interface::zstd(encoder::ZstdOptions::new().hash_log(100)).is_none());
+    assert!(Interface::zstd(encoder::ZstdOptions::new().min_match(1)).is_none());
+    assert!(Interface::zstd(encoder::ZstdOptions::new().ldm_min_match(-1)).is_none());
+    assert!(Interface::zstd(encoder::ZstdOptions::new().overlap_log(10)).is_none());
+
+    #[cfg(not(feature = "zstd-mt"))]
+    assert!(Interface::zstd(encoder::ZstdOptions::new().workers(2)).is_none());
+    #[cfg(feature = "zstd-mt")]
+    {
+        let options = encoder::ZstdOptions::new().workers(2).job_size(1024 * 1024).overlap_log(6);
+        let mut encoder = Interface::zstd(options).expect("create zstd encoder");
+        for idx in 0..DATA.len() {
+            test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Zstd);
+        }
+    }
+}
+
+#[cfg(any(feature = "zstd", feature = "brotli-rust", feature = "zlib-rust"))]
+fn workspace(size: usize) -> Vec<core::mem::MaybeUninit<u8>> {
+    vec![core::mem::MaybeUninit::uninit(); size]
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_encode_and_decode_zstd_static() {
+    let encoder_options = encoder::ZstdOptions::new().window_log(20);
+    let decoder_options = decoder::ZstdOptions::new().window_log(20);
+    let mut encoder_workspace = workspace(encoder_options.estimate_size());
+    let mut decoder_workspace = workspace(decoder_options.estimate_size());
+    let mut encoder = Interface::zstd_static(encoder_options, &mut encoder_workspace).expect("create zstd static encoder");
+    let mut decoder = decoder::Interface::zstd_static(decoder_options, &mut decoder_workspace).expect("create zstd static decoder");
+    assert_eq!(encoder.memory_usage(), None);
+    assert_eq!(decoder.memory_usage(), None);
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Zstd);
+    }
+
+    //Output is the same as of heap allocated context
+    let mut expected = Vec::new();
+    let mut heap_encoder = Interface::zstd(encoder_options).expect("create zstd encoder");
+    heap_encoder.encode_vec_full(DATA[1], &mut expected, EncodeOp::Finish).expect("Success");
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(DATA[1], &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert_eq!(compressed, expected);
+    drop(encoder);
+    drop(decoder);
+
+    //Workspace must fit context
+    assert!(Interface::zstd_static(encoder_options, &mut workspace(64)).is_none());
+    assert!(decoder::Interface::zstd_static(decoder_options, &mut workspace(64)).is_none());
+    //Dictionary cannot be loaded into static context
+    assert!(Interface::zstd_static(encoder_options.dictionary(b"dictionary"), &mut encoder_workspace).is_none());
+    assert!(decoder::Interface::zstd_static(decoder_options.dictionary(b"dictionary"), &mut decoder_workspace).is_none());
+    //Workers cannot be spawned within static context
+    assert!(Interface::zstd_static(encoder_options.workers(2), &mut encoder_workspace).is_none());
+}
+
+#[cfg(feature = "brotli-rust")]
+#[test]
+fn should_encode_and_decode_brotli_rust_static() {
+    let mut encoder_workspace = workspace(64 * 1024 * 1024);
+    let mut decoder_workspace = workspace(1024 * 1024);
+    let mut encoder = Interface::brotli_rust_static(Default::default(), &mut encoder_workspace).expect("create brotli static encoder");
+    let mut decoder = decoder::Interface::brotli_rust_static(&mut decoder_workspace).expect("create brotli static decoder");
+    assert_eq!(encoder.memory_usage(), None);
+    assert_eq!(decoder.memory_usage(), None);
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Brotli);
+    }
+
+    //Output is the same as of heap allocated state
+    let mut expected = Vec::new();
+    let mut heap_encoder = Interface::brotli_rust(Default::default());
+    heap_encoder.encode_vec_full(DATA[1], &mut expected, EncodeOp::Finish).expect("Success");
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(DATA[1], &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert_eq!(compressed, expected);
+
+    //Workspace must fit state
+    assert!(Interface::brotli_rust_static(Default::default(), &mut workspace(64)).is_none());
+    assert!(decoder::Interface::brotli_rust_static(&mut workspace(64)).is_none());
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_encode_and_decode_zlib_rust_static() {
+    let mut encoder_workspace = workspace(512 * 1024);
+    let mut decoder_workspace = workspace(64 * 1024);
+    let mut encoder = Interface::zlib_rust_static(encoder::ZlibOptions::new(), &mut encoder_workspace).expect("create zlib-rust static encoder");
+    let mut decoder = decoder::Interface::zlib_rust_static(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip), &mut decoder_workspace).expect("create zlib-rust static decoder");
+    assert_eq!(encoder.memory_usage(), None);
+    assert_eq!(decoder.memory_usage(), None);
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Gzip);
+    }
+
+    //Output is the same as of heap allocated stream
+    let mut expected = Vec::new();
+    let mut heap_encoder = Interface::zlib_rust(encoder::ZlibOptions::new()).expect("create zlib-rust encoder");
+    heap_encoder.encode_vec_full(DATA[1], &mut expected, EncodeOp::Finish).expect("Success");
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(DATA[1], &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert_eq!(compressed, expected);
+    drop(encoder);
+
+    //Workspace must fit stream
+    assert!(Interface::zlib_rust_static(encoder::ZlibOptions::new(), &mut workspace(1024)).is_none());
+    assert!(decoder::Interface::zlib_rust_static(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip), &mut workspace(1024)).is_none());
+    //Dictionary cannot be stored without allocation
+    assert!(Interface::zlib_rust_static(encoder::ZlibOptions::new().dictionary(b"dictionary"), &mut encoder_workspace).is_none());
+    //Neither can decoder's dictionary and gzip header
+    assert!(decoder::Interface::zlib_rust_static(decoder::ZlibDecoderOptions::new().dictionary(b"dictionary"), &mut workspace(64 * 1024)).is_none());
+    assert!(decoder::Interface::zlib_rust_static(decoder::ZlibDecoderOptions::new().gzip_header(true), &mut workspace(64 * 1024)).is_none());
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_pledge_size_zstd() {
+    //Frame_Header_Descriptor has Single_Segment or Frame_Content_Size flag set when content size is written
+    fn has_content_size(frame: &[u8]) -> bool {
+        frame[4] & 0xe0 != 0
+    }
+
+    let mut encoder = Interface::zstd(Default::default()).expect("create zstd encoder");
+    let mut decoder = decoder::Interface::zstd(Default::default()).expect("create zstd decoder");
+    test_case_pledged_size(&mut encoder, &mut decoder, DATA[1]);
+
+    //Whole input is pledged automatically
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(DATA[1], &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert!(has_content_size(&compressed));
+    assert!(encoder.reset());
+
+    let (first, second) = DATA[1].split_at(DATA[1].len() / 2);
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(first, &mut compressed, EncodeOp::Process).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Continue);
+    let result = encoder.encode_vec_full(second, &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert!(!has_content_size(&compressed));
+    assert!(encoder.reset());
+
+    //Pledged size must match actual size
+    assert!(encoder.set_pledged_size(1));
+    let mut compressed = Vec::with_capacity(DATA[0].len() + 100);
+    let result = encoder.encode_vec(DATA[0], &mut compressed, EncodeOp::Process);
+    assert!(matches!(result.status, EncodeStatus::Error(_)));
+}
+
+#[cfg(feature = "zstd")]
+const ZSTD_DICT: &[u8] = include_bytes!("data/alice29.txt.dict.zstd");
+#[cfg(feature = "zstd")]
+const ZSTD_DICT_ID: u32 = 1234;
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_decode_concatenated_zstd() {
+    let mut encoder = Interface::zstd(Default::default()).expect("create zstd encoder");
+    let mut decoder = decoder::Interface::zstd(decoder::ZstdOptions::new().concatenated(true)).expect("create zstd decoder");
+    let mut single = decoder::Interface::zstd(Default::default()).expect("create zstd decoder");
+    test_case_concatenated(&mut encoder, &mut decoder, &mut single);
+
+    let mut decoder = decoder::Interface::auto(decoder::AutoOptions::new().zstd(decoder::ZstdOptions::new().concatenated(true)));
+    let mut single = decoder::Interface::auto(Default::default());
+    test_case_concatenated(&mut encoder, &mut decoder, &mut single);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_encode_and_decode_zstd_dictionary() {
+    let options = encoder::ZstdOptions::new().dictionary(ZSTD_DICT);
+    let mut encoder = Interface::zstd(options).expect("create zstd encoder");
+    let mut decoder = decoder::Interface::zstd(decoder::ZstdOptions::new().dictionary(ZSTD_DICT)).expect("create zstd decoder");
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Zstd);
+    }
+
+    let cdict = encoder::ZstdCDict::new(ZSTD_DICT, 3).expect("create cdict");
+    let ddict = decoder::ZstdDDict::new(ZSTD_DICT).expect("create ddict");
+    assert_eq!(cdict.id(), ZSTD_DICT_ID);
+    assert_eq!(ddict.id(), ZSTD_DICT_ID);
+
+    //Encoders keep own reference to dictionary
+    let mut encoders = [
+        Interface::zstd(encoder::ZstdOptions::new().cdict(&cdict)).expect("create zstd encoder"),
+        Interface::zstd(encoder::ZstdOptions::new().cdict(&cdict.clone())).expect("create zstd encoder"),
+    ];
+    let mut decoder = decoder::Interface::zstd(decoder::ZstdOptions::new().ddict(&ddict)).expect("create zstd decoder");
+    drop(cdict);
+    drop(ddict);
+    for encoder in encoders.iter_mut() {
+        for idx in 0..DATA.len() {
+            test_case(idx, encoder, &mut decoder, DATA[idx], Detection::Zstd);
+        }
+    }
+
+    //Small message benefits from dictionary
+    let message = &DATA[1][..1024];
+    let mut compressed = Vec::new();
+    let result = encoders[0].encode_vec_full(message, &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert_eq!(decoder::zstd_frame_dictionary_id(&compressed), ZSTD_DICT_ID);
+
+    let mut encoder = Interface::zstd(Default::default()).expect("create zstd encoder");
+    let mut compressed_no_dict = Vec::new();
+    let result = encoder.encode_vec_full(message, &mut compressed_no_dict, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert_eq!(decoder::zstd_frame_dictionary_id(&compressed_no_dict), 0);
+    assert!(compressed.len() < compressed_no_dict.len());
+
+    //Dictionary is required to decompress
+    let mut decoder = decoder::Interface::zstd(Default::default()).expect("create zstd decoder");
+    let mut decompressed = vec![0; message.len()];
+    let result = decoder.decode(&compressed, decompressed.as_mut());
+    let error = result.status.expect_err("should fail");
+    assert_eq!(error.kind(), decoder::DecodeErrorKind::DictionaryRequired);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_train_zstd_dictionary() {
+    use compu::zstd::{dictionary_id, finalize_dictionary, train_dictionary, train_dictionary_cover, CoverOptions, DictionaryOptions};
+
+    let samples = DATA[1].chunks(1024).collect::<Vec<_>>();
+    let opts = DictionaryOptions::new().level(3).id(ZSTD_DICT_ID);
+
+    let trained = train_dictionary(&samples, 4096).expect("train dictionary");
+    assert!(trained.len() <= 4096);
+    assert_ne!(dictionary_id(&trained), 0);
+
+    let cover = CoverOptions::new().k(200).d(8);
+    let trained_cover = train_dictionary_cover(&samples, 4096, cover, opts).expect("train dictionary with cover");
+    assert!(trained_cover.len() <= 4096);
+    assert_eq!(dictionary_id(&trained_cover), ZSTD_DICT_ID);
+
+    let finalized = finalize_dictionary(&DATA[1][..2048], &samples, 4096, opts).expect("finalize dictionary");
+    assert!(finalized.len() <= 4096);
+    assert_eq!(dictionary_id(&finalized), ZSTD_DICT_ID);
+    assert_eq!(dictionary_id(DATA[1]), 0);
+
+    for dict in [&trained, &trained_cover, &finalized] {
+        let mut encoder = Interface::zstd(encoder::ZstdOptions::new().dictionary(dict)).expect("create zstd encoder");
+        let mut decoder = decoder::Interface::zstd(decoder::ZstdOptions::new().dictionary(dict)).expect("create zstd decoder");
+        for idx in 0..DATA.len() {
+            test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Zstd);
+        }
+    }
+
+    //Not enough samples
+    let error = train_dictionary(&samples[..1], 4096).expect_err("should fail");
+    let description = error.describe().expect("to describe error");
+    println!("error={error}: {description}");
+}
+
 #[cfg(any(feature = "zlib", feature = "zlib-static"))]
 #[test]
 fn should_encode_and_decode_zlib_gzip() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip);
     let mut encoder = Interface::zlib(options).expect("create zlib encoder");
-    let mut decoder = decoder::Interface::zlib(decoder::ZlibMode::Gzip).expect("create zlib decoder");
+    let mut decoder = decoder::Interface::zlib(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib decoder");
     for idx in 0..DATA.len() {
         test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Gzip);
     }
@@ -218,7 +789,7 @@ fn should_encode_and_decode_zlib_gzip() {
 fn should_encode_and_decode_zlib_ng_gzip() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip);
     let mut encoder = Interface::zlib_ng(options).expect("create zlib-ng encoder");
-    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibMode::Gzip).expect("create zlib-ng decoder");
+    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib-ng decoder");
     for idx in 0..DATA.len() {
         test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Gzip);
     }
@@ -229,7 +800,7 @@ fn should_encode_and_decode_zlib_ng_gzip() {
 fn should_encode_and_decode_zlib_rust_gzip() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip);
     let mut encoder = Interface::zlib_rust(options).expect("create zlib-rust encoder");
-    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibMode::Gzip).expect("create zlib-rust decoder");
+    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib-rust decoder");
     for idx in 0..DATA.len() {
         test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Gzip);
     }
@@ -240,10 +811,19 @@ fn should_encode_and_decode_zlib_rust_gzip() {
 fn should_encode_and_decode_zlib() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib);
     let mut encoder = Interface::zlib(options).expect("create zlib encoder");
-    let mut decoder = decoder::Interface::zlib(decoder::ZlibMode::Zlib).expect("create zlib decoder");
+    let mut decoder = decoder::Interface::zlib(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Zlib)).expect("create zlib decoder");
+    //Pledged size is ignored
+    assert!(encoder.set_pledged_size(1));
     for idx in 0..DATA.len() {
         test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Zlib);
     }
+
+    //But still refused once encoding started
+    let mut compressed = Vec::new();
+    encoder.encode_vec_full(DATA[1], &mut compressed, EncodeOp::Process).expect("Success");
+    assert!(!encoder.set_pledged_size(1));
+    assert!(encoder.reset());
+    assert!(encoder.set_pledged_size(1));
 }
 
 #[cfg(feature = "zlib-ng")]
@@ -251,7 +831,7 @@ fn should_encode_and_decode_zlib() {
 fn should_encode_and_decode_zlib_ng() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib);
     let mut encoder = Interface::zlib_ng(options).expect("create zlib-ng encoder");
-    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibMode::Zlib).expect("create zlib-ng decoder");
+    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Zlib)).expect("create zlib-ng decoder");
     for idx in 0..DATA.len() {
         test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Zlib);
     }
@@ -262,7 +842,7 @@ fn should_encode_and_decode_zlib_ng() {
 fn should_encode_and_decode_zlib_rust() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib);
     let mut encoder = Interface::zlib_rust(options).expect("create zlib-rust encoder");
-    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibMode::Zlib).expect("create zlib-rust decoder");
+    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Zlib)).expect("create zlib-rust decoder");
     for idx in 0..DATA.len() {
         test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Zlib);
     }
@@ -273,7 +853,7 @@ fn should_encode_and_decode_zlib_rust() {
 fn should_encode_and_decode_zlib_deflate() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Deflate);
     let mut encoder = Interface::zlib(options).expect("create zlib encoder");
-    let mut decoder = decoder::Interface::zlib(decoder::ZlibMode::Deflate).expect("create zlib decoder");
+    let mut decoder = decoder::Interface::zlib(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Deflate)).expect("create zlib decoder");
     for idx in 0..DATA.len() {
         test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Unknown);
     }
@@ -284,7 +864,7 @@ fn should_encode_and_decode_zlib_deflate() {
 fn should_encode_and_decode_zlib_ng_deflate() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Deflate);
     let mut encoder = Interface::zlib_ng(options).expect("create zlib-ng encoder");
-    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibMode::Deflate).expect("create zlib-ng decoder");
+    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Deflate)).expect("create zlib-ng decoder");
     for idx in 0..DATA.len() {
         test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Unknown);
     }
@@ -294,49 +874,415 @@ fn should_encode_and_decode_zlib_ng_deflate() {
 #[test]
 fn should_encode_and_decode_zlib_rust_deflate() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Deflate);
-    let mut encoder = Interface::zlib_ng(options).expect("create zlib-rust encoder");
-    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibMode::Deflate).expect("create zlib-rust decoder");
+    let mut encoder = Interface::zlib_rust(options).expect("create zlib-rust encoder");
+    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Deflate)).expect("create zlib-rust decoder");
     for idx in 0..DATA.len() {
         test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Unknown);
     }
 }
 
-#[cfg(feature = "brotli-c")]
-#[test]
-fn should_encode_with_empty_final_and_decode_brotli_c() {
-    let mut encoder = Interface::brotli_c(Default::default()).expect("create brotli encoder");
-    let mut decoder = decoder::Interface::brotli_c().expect("create brotli decoder");
+#[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+fn test_case_zlib_dictionary(create_encoder: impl Fn(encoder::ZlibOptions<'_>) -> Option<Encoder<'static>>, create_decoder: impl Fn(decoder::ZlibDecoderOptions<'_>) -> Option<Decoder<'static>>) {
+    let dictionary = &DATA[1][DATA[1].len() - 4096..];
+
+    //Dictionary is applied again after every reset
+    //Note that zlib header with dictionary flag is not recognized by detection
+    let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib).dictionary(dictionary);
+    let mut encoder = create_encoder(options).expect("create encoder");
+    let mut decoder = create_decoder(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Zlib).dictionary(dictionary)).expect("create decoder");
     for idx in 0..DATA.len() {
-        test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Unknown);
     }
-}
-
-#[cfg(feature = "brotli-rust")]
-#[test]
-fn should_encode_with_empty_final_and_decode_brotli_rust() {
-    let mut encoder = Interface::brotli_rust(Default::default());
-    let mut decoder = decoder::Interface::brotli_rust();
+    let mut decoder = create_decoder(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Auto).dictionary(dictionary)).expect("create decoder");
     for idx in 0..DATA.len() {
-        test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Unknown);
     }
-}
 
-#[cfg(feature = "zstd")]
-#[test]
-fn should_encode_with_empty_final_and_decode_zstd() {
-    let mut encoder = Interface::zstd(Default::default()).expect("create zstd encoder");
-    let mut decoder = decoder::Interface::zstd(Default::default()).expect("create zstd decoder");
+    let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Deflate).dictionary(dictionary);
+    let mut encoder = create_encoder(options).expect("create encoder");
+    let mut decoder = create_decoder(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Deflate).dictionary(dictionary)).expect("create decoder");
     for idx in 0..DATA.len() {
-        test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Unknown);
+    }
+
+    //Dictionary is supplied to every member
+    let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib).dictionary(dictionary);
+    let mut encoder = create_encoder(options).expect("create encoder");
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(DATA[1], &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    compressed.extend_from_within(..);
+    let options = decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Zlib).dictionary(dictionary).multi_member(true);
+    let mut decoder = create_decoder(options).expect("create decoder");
+    let mut decompressed = Vec::new();
+    let result = decoder.decode_vec_full(&compressed, &mut decompressed).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(decompressed, [DATA[1], DATA[1]].concat());
+
+    //Gzip has no dictionary support
+    let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip).dictionary(dictionary);
+    assert!(create_encoder(options).is_none());
+
+    //Missing or wrong dictionary
+    let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib).dictionary(dictionary);
+    let mut encoder = create_encoder(options).expect("create encoder");
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(DATA[1], &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    for dictionary in [&[][..], &DATA[1][..4096]] {
+        let mut decoder = create_decoder(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Zlib).dictionary(dictionary)).expect("create decoder");
+        let mut decompressed = vec![0; DATA[1].len()];
+        let result = decoder.decode(&compressed, decompressed.as_mut());
+        let error = result.status.expect_err("should fail");
+        println!("error={error}: {:?}", decoder.describe_error(error));
+        assert_eq!(error.kind(), decoder::DecodeErrorKind::DictionaryRequired);
     }
 }
 
 #[cfg(any(feature = "zlib", feature = "zlib-static"))]
 #[test]
-fn should_encode_with_empty_final_and_decode_zlib_gzip() {
-    let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip);
-    let mut encoder = Interface::zlib(options).expect("create zlib encoder");
-    let mut decoder = decoder::Interface::zlib(decoder::ZlibMode::Gzip).expect("create zlib decoder");
+fn should_encode_and_decode_zlib_dictionary() {
+    test_case_zlib_dictionary(Interface::zlib, decoder::Interface::zlib);
+}
+
+#[cfg(feature = "zlib-ng")]
+#[test]
+fn should_encode_and_decode_zlib_ng_dictionary() {
+    test_case_zlib_dictionary(Interface::zlib_ng, decoder::Interface::zlib_ng);
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_encode_and_decode_zlib_rust_dictionary() {
+    test_case_zlib_dictionary(Interface::zlib_rust, decoder::Interface::zlib_rust);
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static", feature = "zlib-ng", feature = "zlib-rust"))]
+fn test_case_zlib_gzip_header(create_encoder: impl Fn(encoder::ZlibOptions<'_>) -> Option<Encoder<'static>>, create_decoder: impl Fn(decoder::ZlibDecoderOptions<'_>) -> Option<Decoder<'static>>) {
+    let mut header = compu::gzip::GzipHeader::new();
+    header.mtime = 0x5ce2f743;
+    header.os = 3;
+    header.name = Some(b"alice29.txt".to_vec());
+    header.comment = Some(b"Alice's Adventures in Wonderland".to_vec());
+    header.hcrc = true;
+    assert!(header.push_extra_field(*b"AB", b"first"));
+    assert!(header.push_extra_field(*b"CD", &[]));
+    assert!(!header.push_extra_field(*b"EF", &[0; compu::gzip::MAX_EXTRA_SIZE]));
+    let fields = header.extra_fields().collect::<Vec<_>>();
+    assert_eq!(fields, [(*b"AB", &b"first"[..]), (*b"CD", &b""[..])]);
+
+    //Header is written again after every reset, producing the same output
+    let options = encoder::ZlibOptions::new().gzip_header(&header);
+    let mut encoder = create_encoder(options).expect("create encoder");
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(DATA[1], &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert!(encoder.reset());
+    let mut compressed_again = Vec::new();
+    let result = encoder.encode_vec_full(DATA[1], &mut compressed_again, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert_eq!(compressed, compressed_again);
+    assert_eq!(Detection::detect(&compressed), Some(Detection::Gzip));
+
+    let info = decoder::inspect(&compressed).expect("inspect");
+    assert_eq!(info.mtime, Some(header.mtime));
+    assert_eq!(info.os, Some(header.os));
+    assert_eq!(info.name, Some(&b"alice29.txt"[..]));
+
+    for mode in [decoder::ZlibMode::Gzip, decoder::ZlibMode::Auto] {
+        //Header is captured only on demand
+        let mut decoder = create_decoder(decoder::ZlibDecoderOptions::new().mode(mode)).expect("create decoder");
+        let mut decompressed = vec![0; DATA[1].len()];
+        let result = decoder.decode(&compressed, decompressed.as_mut());
+        assert_eq!(result.status, Ok(DecodeStatus::Finished));
+        assert_eq!(decoder.gzip_header(), None);
+
+        let mut decoder = create_decoder(decoder::ZlibDecoderOptions::new().mode(mode).gzip_header(true)).expect("create decoder");
+        assert_eq!(decoder.gzip_header(), None);
+        //Header is available once it is decoded
+        let mut decompressed = vec![0; DATA[1].len()];
+        let result = decoder.decode(&compressed[..10], decompressed.as_mut());
+        assert_eq!(result.status, Ok(DecodeStatus::NeedInput));
+        assert_eq!(decoder.gzip_header(), None);
+        let result = decoder.decode(&compressed[10..], decompressed.as_mut());
+        assert_eq!(result.status, Ok(DecodeStatus::Finished));
+        assert_eq!(decompressed, DATA[1]);
+        assert_eq!(decoder.gzip_header().as_ref(), Some(&header));
+
+        assert!(decoder.reset());
+        assert_eq!(decoder.gzip_header(), None);
+    }
+
+    //Bare header has no optional fields
+    let mut encoder = create_encoder(encoder::ZlibOptions::new()).expect("create encoder");
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(DATA[0], &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    let mut decoder = create_decoder(decoder::ZlibDecoderOptions::new().gzip_header(true)).expect("create decoder");
+    let mut decompressed = Vec::new();
+    let result = decoder.decode_vec_full(&compressed, &mut decompressed).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    let bare = decoder.gzip_header().expect("header");
+    assert_eq!(bare.mtime, 0);
+    assert_eq!(bare.name, None);
+    assert_eq!(bare.comment, None);
+    assert_eq!(bare.extra, None);
+    assert!(!bare.hcrc);
+
+    //Header is available via auto decoder
+    let mut decoder = decoder::Interface::auto(decoder::AutoOptions::new().zlib(decoder::ZlibDecoderOptions::new().gzip_header(true)));
+    let mut decompressed = Vec::new();
+    let result = decoder.decode_vec_full(&compressed_again, &mut decompressed).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(decoder.gzip_header().as_ref(), Some(&header));
+
+    //Zlib stream has no gzip header
+    let mut encoder = create_encoder(encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib)).expect("create encoder");
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(DATA[0], &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    let mut decoder = create_decoder(decoder::ZlibDecoderOptions::new().gzip_header(true)).expect("create decoder");
+    let mut decompressed = Vec::new();
+    let result = decoder.decode_vec_full(&compressed, &mut decompressed).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(decoder.gzip_header(), None);
+
+    //Header is only supported in gzip mode
+    let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib).gzip_header(&header);
+    assert!(create_encoder(options).is_none());
+    //Name must not contain zero
+    header.name = Some(b"alice\x00.txt".to_vec());
+    let options = encoder::ZlibOptions::new().gzip_header(&header);
+    assert!(create_encoder(options).is_none());
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static"))]
+#[test]
+fn should_encode_and_decode_zlib_gzip_header() {
+    test_case_zlib_gzip_header(Interface::zlib, decoder::Interface::zlib);
+}
+
+#[cfg(feature = "zlib-ng")]
+#[test]
+fn should_encode_and_decode_zlib_ng_gzip_header() {
+    test_case_zlib_gzip_header(Interface::zlib_ng, decoder::Interface::zlib_ng);
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_encode_and_decode_zlib_rust_gzip_header() {
+    test_case_zlib_gzip_header(Interface::zlib_rust, decoder::Interface::zlib_rust);
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static"))]
+#[test]
+fn should_decode_multi_member_zlib() {
+    let mut encoder = Interface::zlib(Default::default()).expect("create zlib encoder");
+    for mode in [decoder::ZlibMode::Gzip, decoder::ZlibMode::Auto] {
+        let mut decoder = decoder::Interface::zlib(decoder::ZlibDecoderOptions::new().mode(mode).multi_member(true)).expect("create zlib decoder");
+        let mut single = decoder::Interface::zlib(decoder::ZlibDecoderOptions::new().mode(mode)).expect("create zlib decoder");
+        test_case_concatenated(&mut encoder, &mut decoder, &mut single);
+    }
+
+    let mut decoder = decoder::Interface::auto(decoder::AutoOptions::new().zlib(decoder::ZlibDecoderOptions::new().multi_member(true)));
+    let mut single = decoder::Interface::auto(Default::default());
+    test_case_concatenated(&mut encoder, &mut decoder, &mut single);
+}
+
+#[cfg(feature = "zlib-ng")]
+#[test]
+fn should_decode_multi_member_zlib_ng() {
+    let mut encoder = Interface::zlib_ng(Default::default()).expect("create zlib-ng encoder");
+    for mode in [decoder::ZlibMode::Gzip, decoder::ZlibMode::Auto] {
+        let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibDecoderOptions::new().mode(mode).multi_member(true)).expect("create zlib-ng decoder");
+        let mut single = decoder::Interface::zlib_ng(decoder::ZlibDecoderOptions::new().mode(mode)).expect("create zlib-ng decoder");
+        test_case_concatenated(&mut encoder, &mut decoder, &mut single);
+    }
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_decode_multi_member_zlib_rust() {
+    let mut encoder = Interface::zlib_rust(Default::default()).expect("create zlib-rust encoder");
+    for mode in [decoder::ZlibMode::Gzip, decoder::ZlibMode::Auto] {
+        let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(mode).multi_member(true)).expect("create zlib-rust decoder");
+        let mut single = decoder::Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(mode)).expect("create zlib-rust decoder");
+        test_case_concatenated(&mut encoder, &mut decoder, &mut single);
+    }
+}
+
+#[cfg(feature = "lz4")]
+#[test]
+fn should_encode_and_decode_lz4() {
+    let mut encoder = Interface::lz4(Default::default()).expect("create lz4 encoder");
+    let mut decoder = decoder::Interface::lz4().expect("create lz4 decoder");
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Lz4);
+    }
+}
+
+#[cfg(feature = "lz4-rust")]
+#[test]
+fn should_encode_and_decode_lz4_rust() {
+    let mut encoder = Interface::lz4_rust(Default::default());
+    let mut decoder = decoder::Interface::lz4_rust();
+    for idx in 0..DATA.len() {
+        test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Lz4);
+    }
+}
+
+#[cfg(any(feature = "lz4", feature = "lz4-rust"))]
+fn test_case_backpressure(encoder: &mut Encoder, decoder: &mut Decoder, data: &[u8]) {
+    let mut compressed = Vec::new();
+    let mut output = [0u8; 16];
+    let mut input = data;
+
+    //Flush makes output pending, so that following input is not accepted until it is written
+    let result = encoder.encode(input, &mut output, EncodeOp::Flush);
+    assert_eq!(result.status, EncodeStatus::NeedOutput);
+    input = &input[input.len() - result.input_remain..];
+    compressed.extend_from_slice(&output[..output.len() - result.output_remain]);
+    let result = encoder.encode(b"more", &mut output[..0], EncodeOp::Process);
+    assert_eq!(result.status, EncodeStatus::NeedOutput);
+    assert_eq!(result.input_remain, 4);
+
+    loop {
+        let result = encoder.encode(input, &mut output, EncodeOp::Finish);
+        input = &input[input.len() - result.input_remain..];
+        compressed.extend_from_slice(&output[..output.len() - result.output_remain]);
+        match result.status {
+            EncodeStatus::Finished => break,
+            EncodeStatus::NeedOutput => continue,
+            status => panic!("Unexpected status: {:?}", status),
+        }
+    }
+    assert!(input.is_empty());
+
+    let mut decompressed = Vec::new();
+    let result = decoder.decode_vec_full(&compressed, &mut decompressed).expect("Success");
+    assert_eq!(result.status, Ok(DecodeStatus::Finished));
+    assert_eq!(decompressed, data);
+}
+
+#[cfg(feature = "lz4")]
+#[test]
+fn should_apply_backpressure_lz4() {
+    let mut encoder = Interface::lz4(Default::default()).expect("create lz4 encoder");
+    let mut decoder = decoder::Interface::lz4().expect("create lz4 decoder");
+    test_case_backpressure(&mut encoder, &mut decoder, DATA[1]);
+}
+
+#[cfg(feature = "lz4-rust")]
+#[test]
+fn should_apply_backpressure_lz4_rust() {
+    let mut encoder = Interface::lz4_rust(Default::default());
+    let mut decoder = decoder::Interface::lz4_rust();
+    test_case_backpressure(&mut encoder, &mut decoder, DATA[1]);
+}
+
+#[cfg(all(feature = "lz4", feature = "lz4-rust"))]
+#[test]
+fn should_encode_and_decode_lz4_across_implementations() {
+    use encoder::{Lz4BlockSize, Lz4Options};
+
+    const OPTIONS: [Lz4Options; 3] = [
+        Lz4Options::new(),
+        Lz4Options::new().block_linked(false).content_checksum(true),
+        Lz4Options::new().block_size(Lz4BlockSize::Max256KB).level(9),
+    ];
+
+    for opts in OPTIONS {
+        let mut encoder = Interface::lz4(opts).expect("create lz4 encoder");
+        let mut decoder = decoder::Interface::lz4_rust();
+        for idx in 0..DATA.len() {
+            test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Lz4);
+        }
+
+        let mut encoder = Interface::lz4_rust(opts);
+        let mut decoder = decoder::Interface::lz4().expect("create lz4 decoder");
+        for idx in 0..DATA.len() {
+            test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Lz4);
+        }
+    }
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_encode_and_decode_xz() {
+    use encoder::{XzCheck, XzOptions};
+
+    const OPTIONS: [XzOptions; 3] = [
+        XzOptions::new(),
+        XzOptions::new().preset(1).check(XzCheck::None),
+        XzOptions::new().preset(9).extreme(true).check(XzCheck::Sha256).dict_size(64 * 1024),
+    ];
+
+    let mut decoder = decoder::Interface::xz(Default::default()).expect("create xz decoder");
+    for opts in OPTIONS {
+        let mut encoder = Interface::xz(opts).expect("create xz encoder");
+        //Headers exceed size of the first sample, so input is not consumed, which does not fit `test_case` expectations
+        for idx in 1..DATA.len() {
+            test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Xz);
+        }
+    }
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn should_encode_and_decode_bzip2() {
+    use encoder::Bzip2Options;
+
+    const OPTIONS: [Bzip2Options; 3] = [
+        Bzip2Options::new(),
+        Bzip2Options::new().block_size(1),
+        Bzip2Options::new().block_size(5).work_factor(250),
+    ];
+
+    let mut decoder = decoder::Interface::bzip2(false).expect("create bzip2 decoder");
+    for opts in OPTIONS {
+        let mut encoder = Interface::bzip2(opts).expect("create bzip2 encoder");
+        for idx in 0..DATA.len() {
+            test_case(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Bzip2);
+        }
+    }
+}
+
+#[cfg(feature = "brotli-c")]
+#[test]
+fn should_encode_with_empty_final_and_decode_brotli_c() {
+    let mut encoder = Interface::brotli_c(Default::default()).expect("create brotli encoder");
+    let mut decoder = decoder::Interface::brotli_c().expect("create brotli decoder");
+    for idx in 0..DATA.len() {
+        test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
+    }
+}
+
+#[cfg(feature = "brotli-rust")]
+#[test]
+fn should_encode_with_empty_final_and_decode_brotli_rust() {
+    let mut encoder = Interface::brotli_rust(Default::default());
+    let mut decoder = decoder::Interface::brotli_rust();
+    for idx in 0..DATA.len() {
+        test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
+    }
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_encode_with_empty_final_and_decode_zstd() {
+    let mut encoder = Interface::zstd(Default::default()).expect("create zstd encoder");
+    let mut decoder = decoder::Interface::zstd(Default::default()).expect("create zstd decoder");
+    for idx in 0..DATA.len() {
+        test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
+    }
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static"))]
+#[test]
+fn should_encode_with_empty_final_and_decode_zlib_gzip() {
+    let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip);
+    let mut encoder = Interface::zlib(options).expect("create zlib encoder");
+    let mut decoder = decoder::Interface::zlib(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib decoder");
     for idx in 0..DATA.len() {
         test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
     }
@@ -347,7 +1293,7 @@ fn should_encode_with_empty_final_and_decode_zlib_gzip() {
 fn should_encode_with_empty_final_and_decode_zlib_ng_gzip() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip);
     let mut encoder = Interface::zlib_ng(options).expect("create zlib-ng encoder");
-    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibMode::Gzip).expect("create zlib-ng decoder");
+    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib-ng decoder");
     for idx in 0..DATA.len() {
         test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
     }
@@ -358,7 +1304,7 @@ fn should_encode_with_empty_final_and_decode_zlib_ng_gzip() {
 fn should_encode_with_empty_final_and_decode_zlib_rust_gzip() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip);
     let mut encoder = Interface::zlib_rust(options).expect("create zlib-rust encoder");
-    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibMode::Gzip).expect("create zlib-rust decoder");
+    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib-rust decoder");
     for idx in 0..DATA.len() {
         test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
     }
@@ -369,7 +1315,7 @@ fn should_encode_with_empty_final_and_decode_zlib_rust_gzip() {
 fn should_encode_with_empty_final_and_decode_zlib() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib);
     let mut encoder = Interface::zlib(options).expect("create zlib encoder");
-    let mut decoder = decoder::Interface::zlib(decoder::ZlibMode::Zlib).expect("create zlib decoder");
+    let mut decoder = decoder::Interface::zlib(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Zlib)).expect("create zlib decoder");
     for idx in 0..DATA.len() {
         test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
     }
@@ -380,7 +1326,7 @@ fn should_encode_with_empty_final_and_decode_zlib() {
 fn should_encode_with_empty_final_and_decode_zlib_ng() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib);
     let mut encoder = Interface::zlib_ng(options).expect("create zlib-ng encoder");
-    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibMode::Zlib).expect("create zlib-ng decoder");
+    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Zlib)).expect("create zlib-ng decoder");
     for idx in 0..DATA.len() {
         test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
     }
@@ -391,7 +1337,7 @@ fn should_encode_with_empty_final_and_decode_zlib_ng() {
 fn should_encode_with_empty_final_and_decode_zlib_rust() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib);
     let mut encoder = Interface::zlib_rust(options).expect("create zlib-rust encoder");
-    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibMode::Zlib).expect("create zlib-rust decoder");
+    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Zlib)).expect("create zlib-rust decoder");
     for idx in 0..DATA.len() {
         test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
     }
@@ -402,7 +1348,7 @@ fn should_encode_with_empty_final_and_decode_zlib_rust() {
 fn should_encode_with_empty_final_and_decode_zlib_deflate() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Deflate);
     let mut encoder = Interface::zlib(options).expect("create zlib encoder");
-    let mut decoder = decoder::Interface::zlib(decoder::ZlibMode::Deflate).expect("create zlib decoder");
+    let mut decoder = decoder::Interface::zlib(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Deflate)).expect("create zlib decoder");
     for idx in 0..DATA.len() {
         test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
     }
@@ -413,7 +1359,7 @@ fn should_encode_with_empty_final_and_decode_zlib_deflate() {
 fn should_encode_with_empty_final_and_decode_zlib_ng_deflate() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Deflate);
     let mut encoder = Interface::zlib_ng(options).expect("create zlib-ng encoder");
-    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibMode::Deflate).expect("create zlib-ng decoder");
+    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Deflate)).expect("create zlib-ng decoder");
     for idx in 0..DATA.len() {
         test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
     }
@@ -424,19 +1370,77 @@ fn should_encode_with_empty_final_and_decode_zlib_ng_deflate() {
 fn should_encode_with_empty_final_and_decode_zlib_rust_deflate() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Deflate);
     let mut encoder = Interface::zlib_rust(options).expect("create zlib-rust encoder");
-    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibMode::Deflate).expect("create zlib-rust decoder");
+    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Deflate)).expect("create zlib-rust decoder");
     for idx in 0..DATA.len() {
         test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
     }
 }
 
+#[cfg(feature = "lz4")]
+#[test]
+fn should_encode_with_empty_final_and_decode_lz4() {
+    let mut encoder = Interface::lz4(Default::default()).expect("create lz4 encoder");
+    let mut decoder = decoder::Interface::lz4().expect("create lz4 decoder");
+    //Frame overhead exceeds size of the first sample, which does not fit `test_case_empty_final` expectations
+    for idx in 1..DATA.len() {
+        test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
+    }
+}
+
+#[cfg(feature = "lz4-rust")]
+#[test]
+fn should_encode_with_empty_final_and_decode_lz4_rust() {
+    let mut encoder = Interface::lz4_rust(Default::default());
+    let mut decoder = decoder::Interface::lz4_rust();
+    //Frame overhead exceeds size of the first sample, which does not fit `test_case_empty_final` expectations
+    for idx in 1..DATA.len() {
+        test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
+    }
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_encode_with_empty_final_and_decode_xz() {
+    let mut encoder = Interface::xz(Default::default()).expect("create xz encoder");
+    let mut decoder = decoder::Interface::xz(Default::default()).expect("create xz decoder");
+    //Stream overhead exceeds size of the first sample, which does not fit `test_case_empty_final` expectations
+    for idx in 1..DATA.len() {
+        test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
+    }
+}
+
+#[cfg(feature = "snappy")]
+#[test]
+fn should_encode_and_decode_snappy() {
+    let mut encoder = Interface::snappy();
+    let mut decoder = decoder::Interface::snappy();
+    for idx in 0..DATA.len() {
+        test_case_snappy(idx, &mut encoder, &mut decoder, DATA[idx]);
+    }
+
+    //Spans multiple chunks
+    let data = DATA[1].repeat(3);
+    test_case_snappy(DATA.len(), &mut encoder, &mut decoder, &data);
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn should_encode_with_empty_final_and_decode_bzip2() {
+    let mut encoder = Interface::bzip2(Default::default()).expect("create bzip2 encoder");
+    let mut decoder = decoder::Interface::bzip2(false).expect("create bzip2 decoder");
+    //Block overhead exceeds size of the first sample, which does not fit `test_case_empty_final` expectations
+    for idx in 1..DATA.len() {
+        test_case_empty_final(idx, &mut encoder, &mut decoder, DATA[idx]);
+    }
+}
+
 #[cfg(all(feature = "brotli-c", feature = "bytes"))]
 #[test]
 fn should_encode_and_decode_brotli_c_bytes() {
     let mut encoder = Interface::brotli_c(Default::default()).expect("create brotli encoder");
     let mut decoder = decoder::Interface::brotli_c().expect("create brotli decoder");
     for idx in 0..DATA.len() {
-        test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Unknown);
+        test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Brotli);
     }
 }
 
@@ -446,7 +1450,7 @@ fn should_encode_and_decode_brotli_rust_bytes() {
     let mut encoder = Interface::brotli_rust(Default::default());
     let mut decoder = decoder::Interface::brotli_rust();
     for idx in 0..DATA.len() {
-        test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Unknown);
+        test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Brotli);
     }
 }
 
@@ -465,7 +1469,7 @@ fn should_encode_and_decode_zstd_bytes() {
 fn should_encode_and_decode_zlib_gzip_bytes() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip);
     let mut encoder = Interface::zlib(options).expect("create zlib encoder");
-    let mut decoder = decoder::Interface::zlib(decoder::ZlibMode::Gzip).expect("create zlib decoder");
+    let mut decoder = decoder::Interface::zlib(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib decoder");
     for idx in 0..DATA.len() {
         test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Gzip);
     }
@@ -476,7 +1480,7 @@ fn should_encode_and_decode_zlib_gzip_bytes() {
 fn should_encode_and_decode_zlib_ng_gzip_bytes() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip);
     let mut encoder = Interface::zlib_ng(options).expect("create zlib-ng encoder");
-    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibMode::Gzip).expect("create zlib-ng decoder");
+    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib-ng decoder");
     for idx in 0..DATA.len() {
         test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Gzip);
     }
@@ -487,7 +1491,7 @@ fn should_encode_and_decode_zlib_ng_gzip_bytes() {
 fn should_encode_and_decode_zlib_rust_gzip_bytes() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip);
     let mut encoder = Interface::zlib_rust(options).expect("create zlib-rust encoder");
-    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibMode::Gzip).expect("create zlib-rust decoder");
+    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib-rust decoder");
     for idx in 0..DATA.len() {
         test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Gzip);
     }
@@ -498,7 +1502,7 @@ fn should_encode_and_decode_zlib_rust_gzip_bytes() {
 fn should_encode_and_decode_zlib_bytes() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib);
     let mut encoder = Interface::zlib(options).expect("create zlib encoder");
-    let mut decoder = decoder::Interface::zlib(decoder::ZlibMode::Zlib).expect("create zlib decoder");
+    let mut decoder = decoder::Interface::zlib(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Zlib)).expect("create zlib decoder");
     for idx in 0..DATA.len() {
         test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Zlib);
     }
@@ -509,7 +1513,7 @@ fn should_encode_and_decode_zlib_bytes() {
 fn should_encode_and_decode_zlib_ng_bytes() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib);
     let mut encoder = Interface::zlib_ng(options).expect("create zlib-ng encoder");
-    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibMode::Zlib).expect("create zlib-ng decoder");
+    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Zlib)).expect("create zlib-ng decoder");
     for idx in 0..DATA.len() {
         test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Zlib);
     }
@@ -520,7 +1524,7 @@ fn should_encode_and_decode_zlib_ng_bytes() {
 fn should_encode_and_decode_zlib_rust_bytes() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Zlib);
     let mut encoder = Interface::zlib_rust(options).expect("create zlib-rust encoder");
-    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibMode::Zlib).expect("create zlib-rust decoder");
+    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Zlib)).expect("create zlib-rust decoder");
     for idx in 0..DATA.len() {
         test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Zlib);
     }
@@ -531,7 +1535,7 @@ fn should_encode_and_decode_zlib_rust_bytes() {
 fn should_encode_and_decode_zlib_deflate_bytes() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Deflate);
     let mut encoder = Interface::zlib(options).expect("create zlib encoder");
-    let mut decoder = decoder::Interface::zlib(decoder::ZlibMode::Deflate).expect("create zlib decoder");
+    let mut decoder = decoder::Interface::zlib(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Deflate)).expect("create zlib decoder");
     for idx in 0..DATA.len() {
         test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Unknown);
     }
@@ -542,7 +1546,7 @@ fn should_encode_and_decode_zlib_deflate_bytes() {
 fn should_encode_and_decode_zlib_ng_deflate_bytes() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Deflate);
     let mut encoder = Interface::zlib_ng(options).expect("create zlib-ng encoder");
-    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibMode::Deflate).expect("create zlib-ng decoder");
+    let mut decoder = decoder::Interface::zlib_ng(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Deflate)).expect("create zlib-ng decoder");
     for idx in 0..DATA.len() {
         test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Unknown);
     }
@@ -553,8 +1557,277 @@ fn should_encode_and_decode_zlib_ng_deflate_bytes() {
 fn should_encode_and_decode_zlib_rust_deflate_bytes() {
     let options = encoder::ZlibOptions::new().mode(encoder::ZlibMode::Deflate);
     let mut encoder = Interface::zlib_rust(options).expect("create zlib-rust encoder");
-    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibMode::Deflate).expect("create zlib-rust decoder");
+    let mut decoder = decoder::Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Deflate)).expect("create zlib-rust decoder");
     for idx in 0..DATA.len() {
         test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Unknown);
     }
 }
+
+#[cfg(all(feature = "lz4", feature = "bytes"))]
+#[test]
+fn should_encode_and_decode_lz4_bytes() {
+    let mut encoder = Interface::lz4(Default::default()).expect("create lz4 encoder");
+    let mut decoder = decoder::Interface::lz4().expect("create lz4 decoder");
+    for idx in 0..DATA.len() {
+        test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Lz4);
+    }
+}
+
+#[cfg(all(feature = "lz4-rust", feature = "bytes"))]
+#[test]
+fn should_encode_and_decode_lz4_rust_bytes() {
+    let mut encoder = Interface::lz4_rust(Default::default());
+    let mut decoder = decoder::Interface::lz4_rust();
+    for idx in 0..DATA.len() {
+        test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Lz4);
+    }
+}
+
+#[cfg(all(feature = "xz", feature = "bytes"))]
+#[test]
+fn should_encode_and_decode_xz_bytes() {
+    let mut encoder = Interface::xz(Default::default()).expect("create xz encoder");
+    let mut decoder = decoder::Interface::xz(Default::default()).expect("create xz decoder");
+    for idx in 1..DATA.len() {
+        test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Xz);
+    }
+}
+
+#[cfg(all(feature = "bzip2", feature = "bytes"))]
+#[test]
+fn should_encode_and_decode_bzip2_bytes() {
+    let mut encoder = Interface::bzip2(Default::default()).expect("create bzip2 encoder");
+    let mut decoder = decoder::Interface::bzip2(false).expect("create bzip2 decoder");
+    for idx in 0..DATA.len() {
+        test_case_bytes(idx, &mut encoder, &mut decoder, DATA[idx], Detection::Bzip2);
+    }
+}
+
+#[cfg(feature = "brotli-c")]
+#[test]
+fn should_report_error_after_finish_brotli_c() {
+    let mut encoder = Interface::brotli_c(Default::default()).expect("create brotli encoder");
+    test_case_error_after_finish(&mut encoder, EncodeErrorKind::Other);
+}
+
+#[cfg(feature = "brotli-rust")]
+#[test]
+fn should_report_error_after_finish_brotli_rust() {
+    let mut encoder = Interface::brotli_rust(Default::default());
+    test_case_error_after_finish(&mut encoder, EncodeErrorKind::Other);
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static"))]
+#[test]
+fn should_report_error_after_finish_zlib() {
+    let mut encoder = Interface::zlib(Default::default()).expect("create zlib encoder");
+    test_case_error_after_finish(&mut encoder, EncodeErrorKind::InvalidState);
+}
+
+#[cfg(feature = "zlib-ng")]
+#[test]
+fn should_report_error_after_finish_zlib_ng() {
+    let mut encoder = Interface::zlib_ng(Default::default()).expect("create zlib-ng encoder");
+    test_case_error_after_finish(&mut encoder, EncodeErrorKind::InvalidState);
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_report_error_after_finish_zlib_rust() {
+    let mut encoder = Interface::zlib_rust(Default::default()).expect("create zlib-rust encoder");
+    test_case_error_after_finish(&mut encoder, EncodeErrorKind::InvalidState);
+}
+
+#[cfg(feature = "lz4")]
+#[test]
+fn should_report_error_after_finish_lz4() {
+    let mut encoder = Interface::lz4(Default::default()).expect("create lz4 encoder");
+    test_case_error_after_finish(&mut encoder, EncodeErrorKind::InvalidState);
+}
+
+#[cfg(feature = "lz4-rust")]
+#[test]
+fn should_report_error_after_finish_lz4_rust() {
+    let mut encoder = Interface::lz4_rust(Default::default());
+    test_case_error_after_finish(&mut encoder, EncodeErrorKind::InvalidState);
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_report_error_after_finish_xz() {
+    let mut encoder = Interface::xz(Default::default()).expect("create xz encoder");
+    test_case_error_after_finish(&mut encoder, EncodeErrorKind::InvalidState);
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn should_report_error_after_finish_bzip2() {
+    let mut encoder = Interface::bzip2(Default::default()).expect("create bzip2 encoder");
+    test_case_error_after_finish(&mut encoder, EncodeErrorKind::InvalidState);
+}
+
+#[cfg(feature = "snappy")]
+#[test]
+fn should_report_error_after_finish_snappy() {
+    let mut encoder = Interface::snappy();
+    test_case_error_after_finish(&mut encoder, EncodeErrorKind::InvalidState);
+}
+
+#[cfg(feature = "brotli-c")]
+#[test]
+fn should_not_track_memory_brotli_c() {
+    let mut encoder = Interface::brotli_c(Default::default()).expect("create brotli encoder");
+    assert_eq!(encoder.memory_usage(), None);
+    assert!(!encoder.set_memory_budget(Some(0)));
+}
+
+#[cfg(feature = "brotli-rust")]
+#[test]
+fn should_not_track_memory_brotli_rust() {
+    let mut encoder = Interface::brotli_rust(Default::default());
+    assert_eq!(encoder.memory_usage(), None);
+    assert!(!encoder.set_memory_budget(Some(0)));
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_limit_memory_zstd() {
+    test_case_memory_budget(|| Interface::zstd(Default::default()).expect("create zstd encoder"));
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static"))]
+#[test]
+fn should_limit_memory_zlib() {
+    test_case_memory_budget(|| Interface::zlib(Default::default()).expect("create zlib encoder"));
+}
+
+#[cfg(feature = "zlib-ng")]
+#[test]
+fn should_limit_memory_zlib_ng() {
+    test_case_memory_budget(|| Interface::zlib_ng(Default::default()).expect("create zlib-ng encoder"));
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_limit_memory_zlib_rust() {
+    test_case_memory_budget(|| Interface::zlib_rust(Default::default()).expect("create zlib-rust encoder"));
+}
+
+#[cfg(feature = "lz4")]
+#[test]
+fn should_limit_memory_lz4() {
+    test_case_memory_budget(|| Interface::lz4(Default::default()).expect("create lz4 encoder"));
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_limit_memory_xz() {
+    test_case_memory_budget(|| Interface::xz(Default::default()).expect("create xz encoder"));
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn should_limit_memory_bzip2() {
+    test_case_memory_budget(|| Interface::bzip2(Default::default()).expect("create bzip2 encoder"));
+}
+
+#[derive(Clone, Default)]
+struct CountingAllocator {
+    allocations: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    live: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+unsafe impl compu::mem::Allocator for CountingAllocator {
+    fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        self.allocations.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.live.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        unsafe { std::alloc::alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        self.live.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+        std::alloc::dealloc(ptr, layout)
+    }
+}
+
+impl CountingAllocator {
+    fn allocations(&self) -> usize {
+        self.allocations.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    fn live(&self) -> usize {
+        self.live.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+fn test_case_allocator(create_encoder: impl Fn(CountingAllocator) -> Encoder<'static>, expected: impl Fn() -> Encoder<'static>) {
+    let data = DATA[1];
+    let mut expected_compressed = Vec::new();
+    let result = expected().encode_vec_full(data, &mut expected_compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+
+    let allocator = CountingAllocator::default();
+    let mut encoder = create_encoder(allocator.clone());
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(data, &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+    assert_eq!(compressed, expected_compressed);
+    assert!(allocator.allocations() > 0);
+
+    //Reset re-uses allocated memory, which may leave stale match state, hence only completion is checked
+    assert!(encoder.reset());
+    let mut compressed = Vec::new();
+    let result = encoder.encode_vec_full(data, &mut compressed, EncodeOp::Finish).expect("Success");
+    assert_eq!(result.status, EncodeStatus::Finished);
+
+    drop(encoder);
+    assert_eq!(allocator.live(), 0);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_use_allocator_zstd() {
+    test_case_allocator(|allocator| Interface::zstd_with_allocator(Default::default(), allocator).expect("create zstd encoder"), || Interface::zstd(Default::default()).expect("create zstd encoder"));
+}
+
+#[cfg(feature = "brotli-c")]
+#[test]
+fn should_use_allocator_brotli_c() {
+    test_case_allocator(|allocator| Interface::brotli_c_with_allocator(Default::default(), allocator).expect("create brotli-c encoder"), || Interface::brotli_c(Default::default()).expect("create brotli-c encoder"));
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static"))]
+#[test]
+fn should_use_allocator_zlib() {
+    test_case_allocator(|allocator| Interface::zlib_with_allocator(Default::default(), allocator).expect("create zlib encoder"), || Interface::zlib(Default::default()).expect("create zlib encoder"));
+}
+
+#[cfg(feature = "zlib-ng")]
+#[test]
+fn should_use_allocator_zlib_ng() {
+    test_case_allocator(|allocator| Interface::zlib_ng_with_allocator(Default::default(), allocator).expect("create zlib-ng encoder"), || Interface::zlib_ng(Default::default()).expect("create zlib-ng encoder"));
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_use_allocator_zlib_rust() {
+    test_case_allocator(|allocator| Interface::zlib_rust_with_allocator(Default::default(), allocator).expect("create zlib-rust encoder"), || Interface::zlib_rust(Default::default()).expect("create zlib-rust encoder"));
+}
+
+#[cfg(feature = "lz4")]
+#[test]
+fn should_use_allocator_lz4() {
+    test_case_allocator(|allocator| Interface::lz4_with_allocator(Default::default(), allocator).expect("create lz4 encoder"), || Interface::lz4(Default::default()).expect("create lz4 encoder"));
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_use_allocator_xz() {
+    test_case_allocator(|allocator| Interface::xz_with_allocator(Default::default(), allocator).expect("create xz encoder"), || Interface::xz(Default::default()).expect("create xz encoder"));
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn should_use_allocator_bzip2() {
+    test_case_allocator(|allocator| Interface::bzip2_with_allocator(Default::default(), allocator).expect("create bzip2 encoder"), || Interface::bzip2(Default::default()).expect("create bzip2 encoder"));
+}
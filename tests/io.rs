@@ -0,0 +1,229 @@
+#![cfg(feature = "std")]
+
+use compu::io::{DecoderReader, DecoderWriter, EncoderReader, EncoderWriter};
+use compu::{decoder, encoder, Decoder, Encoder};
+
+use std::io::{self, BufReader, Read, Write};
+
+const DATA: [&[u8]; 2] = [
+    include_bytes!("data/10x10y"),
+    include_bytes!("data/alice29.txt"),
+];
+
+fn test_case(idx: usize, mut create_encoder: impl FnMut() -> Encoder<'static>, mut create_decoder: impl FnMut() -> Decoder<'static>, data: &[u8]) {
+    println!("{idx}: DATA.len()={}", data.len());
+
+    //Write in small chunks with intermediate flush
+    let mut writer = EncoderWriter::<_, 512>::new(create_encoder(), Vec::new());
+    for chunk in data.chunks(1000) {
+        writer.write_all(chunk).expect("to write");
+        writer.flush().expect("to flush");
+    }
+    //Flushing without new data must be no-op
+    writer.flush().expect("to flush");
+    let compressed = writer.finish().expect("to finish");
+    assert!(!compressed.is_empty());
+
+    let mut decompressed = Vec::new();
+    let mut reader = DecoderReader::new(create_decoder(), BufReader::with_capacity(64, compressed.as_slice()));
+    reader.read_to_end(&mut decompressed).expect("to decompress");
+    assert_eq!(data, decompressed);
+
+    //Write whole input at once
+    let mut writer = EncoderWriter::<_, 512>::new(create_encoder(), Vec::new());
+    writer.write_all(data).expect("to write");
+    let compressed = writer.finish().expect("to finish");
+
+    let mut decompressed = Vec::new();
+    let mut reader = DecoderReader::new(create_decoder(), compressed.as_slice());
+    reader.read_to_end(&mut decompressed).expect("to decompress");
+    assert_eq!(data, decompressed);
+
+    //Read compressed stream
+    let mut compressed_read = Vec::new();
+    let mut reader = EncoderReader::new(create_encoder(), BufReader::with_capacity(333, data));
+    reader.read_to_end(&mut compressed_read).expect("to compress");
+
+    let mut writer = DecoderWriter::<_, 128>::new(create_decoder(), Vec::new());
+    for chunk in compressed_read.chunks(100) {
+        writer.write_all(chunk).expect("to write");
+    }
+    let decompressed = writer.finish().expect("to finish");
+    assert_eq!(data, decompressed);
+
+    //Trailing data after end of stream
+    let mut trailing = compressed_read.clone();
+    trailing.extend_from_slice(b"garbage");
+    let mut writer = DecoderWriter::<_, 128>::new(create_decoder(), Vec::new());
+    let error = writer.write_all(&trailing).expect_err("should fail");
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    //Incomplete stream
+    let mut reader = DecoderReader::new(create_decoder(), &compressed_read[..compressed_read.len() / 2]);
+    let error = reader.read_to_end(&mut Vec::new()).expect_err("should fail");
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+    let mut writer = DecoderWriter::<_, 128>::new(create_decoder(), Vec::new());
+    writer.write_all(&compressed_read[..compressed_read.len() / 2]).expect("to write");
+    let error = writer.finish().expect_err("should fail");
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+    //Corrupted stream
+    let mut corrupted = compressed_read.clone();
+    for byte in corrupted.iter_mut().skip(10).take(20) {
+        *byte = !*byte;
+    }
+    let mut reader = DecoderReader::new(create_decoder(), corrupted.as_slice());
+    let error = reader.read_to_end(&mut Vec::new()).expect_err("should fail");
+    println!("error={error}");
+}
+
+#[cfg(feature = "brotli-c")]
+#[test]
+fn should_encode_and_decode_io_brotli_c() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::brotli_c(Default::default()).expect("create brotli encoder"),
+            || decoder::Interface::brotli_c().expect("create brotli decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(feature = "brotli-rust")]
+#[test]
+fn should_encode_and_decode_io_brotli_rust() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(idx, || encoder::Interface::brotli_rust(Default::default()), decoder::Interface::brotli_rust, data);
+    }
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_encode_and_decode_io_zstd() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::zstd(Default::default()).expect("create zstd encoder"),
+            || decoder::Interface::zstd(Default::default()).expect("create zstd decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(any(feature = "zlib", feature = "zlib-static"))]
+#[test]
+fn should_encode_and_decode_io_zlib_gzip() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::zlib(encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip)).expect("create zlib encoder"),
+            || decoder::Interface::zlib(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(feature = "zlib-ng")]
+#[test]
+fn should_encode_and_decode_io_zlib_ng_gzip() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::zlib_ng(encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip)).expect("create zlib-ng encoder"),
+            || decoder::Interface::zlib_ng(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib-ng decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_encode_and_decode_io_zlib_rust_gzip() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::zlib_rust(encoder::ZlibOptions::new().mode(encoder::ZlibMode::Gzip)).expect("create zlib-rust encoder"),
+            || decoder::Interface::zlib_rust(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip)).expect("create zlib-rust decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(feature = "lz4")]
+#[test]
+fn should_encode_and_decode_io_lz4() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::lz4(Default::default()).expect("create lz4 encoder"),
+            || decoder::Interface::lz4().expect("create lz4 decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(feature = "lz4-rust")]
+#[test]
+fn should_encode_and_decode_io_lz4_rust() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(idx, || encoder::Interface::lz4_rust(Default::default()), decoder::Interface::lz4_rust, data);
+    }
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn should_encode_and_decode_io_xz() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::xz(Default::default()).expect("create xz encoder"),
+            || decoder::Interface::xz(Default::default()).expect("create xz decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn should_encode_and_decode_io_bzip2() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(
+            idx,
+            || encoder::Interface::bzip2(Default::default()).expect("create bzip2 encoder"),
+            || decoder::Interface::bzip2(false).expect("create bzip2 decoder"),
+            data,
+        );
+    }
+}
+
+#[cfg(feature = "snappy")]
+#[test]
+fn should_encode_and_decode_io_snappy() {
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(idx, encoder::Interface::snappy, decoder::Interface::snappy, data);
+    }
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_encode_and_decode_io_with_borrowed_workspace() {
+    let mut encoder_workspace = vec![core::mem::MaybeUninit::<u8>::uninit(); 512 * 1024];
+    let mut decoder_workspace = vec![core::mem::MaybeUninit::<u8>::uninit(); 64 * 1024];
+
+    for (idx, data) in DATA.iter().enumerate() {
+        println!("{idx}: DATA.len()={}", data.len());
+
+        let encoder = encoder::Interface::zlib_rust_static(encoder::ZlibOptions::new(), &mut encoder_workspace).expect("create zlib-rust static encoder");
+        let mut writer = EncoderWriter::<_, 512>::new(encoder, Vec::new());
+        writer.write_all(data).expect("to write");
+        let compressed = writer.finish().expect("to finish");
+
+        let decoder = decoder::Interface::zlib_rust_static(decoder::ZlibDecoderOptions::new().mode(decoder::ZlibMode::Gzip), &mut decoder_workspace).expect("create zlib-rust static decoder");
+        let mut reader = DecoderReader::new(decoder, BufReader::with_capacity(64, compressed.as_slice()));
+        let mut decompressed = Vec::new();
+        reader.read_to_end(&mut decompressed).expect("to decompress");
+        assert_eq!(*data, decompressed);
+    }
+}
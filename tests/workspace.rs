@@ -0,0 +1,89 @@
+//!Workspace backends, which do not require `alloc`
+#![cfg(any(feature = "brotli-rust", feature = "zlib-rust", feature = "zstd"))]
+
+use compu::{decoder, encoder};
+use core::mem::MaybeUninit;
+use decoder::{DecodeStatus, Decoder};
+use encoder::{EncodeOp, EncodeStatus, Encoder};
+
+const DATA: [&[u8]; 2] = [
+    include_bytes!("data/10x10y"),
+    include_bytes!("data/alice29.txt"),
+];
+
+fn workspace(size: usize) -> Vec<MaybeUninit<u8>> {
+    vec![MaybeUninit::uninit(); size]
+}
+
+fn test_case(idx: usize, encoder: &mut Encoder, decoder: &mut Decoder, data: &[u8]) {
+    println!("{idx}: DATA.len()={}", data.len());
+
+    //Encode in chunks into small output
+    let mut compressed = vec![0; data.len() + 1024];
+    let mut compressed_len = 0;
+    for chunk in data.chunks(1000) {
+        let result = encoder.encode(chunk, &mut compressed[compressed_len..], EncodeOp::Process);
+        assert_eq!(result.input_remain, 0);
+        assert_eq!(result.status, EncodeStatus::Continue);
+        compressed_len = compressed.len() - result.output_remain;
+    }
+    let result = encoder.encode(&[], &mut compressed[compressed_len..], EncodeOp::Finish);
+    assert_eq!(result.status, EncodeStatus::Finished);
+    compressed_len = compressed.len() - result.output_remain;
+    let compressed = &compressed[..compressed_len];
+
+    //Decode in chunks
+    let mut decompressed = vec![0; data.len()];
+    let mut decompressed_len = 0;
+    let mut status = DecodeStatus::NeedInput;
+    for chunk in compressed.chunks(100) {
+        let result = decoder.decode(chunk, &mut decompressed[decompressed_len..]);
+        assert_eq!(result.input_remain, 0);
+        status = result.status.expect("success");
+        decompressed_len = decompressed.len() - result.output_remain;
+    }
+    assert_eq!(status, DecodeStatus::Finished);
+    assert_eq!(decompressed, data);
+
+    //Both can be re-used after reset, without more memory
+    assert!(encoder.reset());
+    assert!(decoder.reset());
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn should_encode_and_decode_zstd_static() {
+    let encoder_options = encoder::ZstdOptions::new().window_log(20);
+    let decoder_options = decoder::ZstdOptions::new().window_log(20);
+    let mut encoder_workspace = workspace(encoder_options.estimate_size());
+    let mut decoder_workspace = workspace(decoder_options.estimate_size());
+    let mut encoder = encoder::Interface::zstd_static(encoder_options, &mut encoder_workspace).expect("create zstd static encoder");
+    let mut decoder = decoder::Interface::zstd_static(decoder_options, &mut decoder_workspace).expect("create zstd static decoder");
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(idx, &mut encoder, &mut decoder, data);
+    }
+}
+
+#[cfg(feature = "brotli-rust")]
+#[test]
+fn should_encode_and_decode_brotli_rust_static() {
+    let mut encoder_workspace = workspace(64 * 1024 * 1024);
+    let mut decoder_workspace = workspace(1024 * 1024);
+    let mut encoder = encoder::Interface::brotli_rust_static(Default::default(), &mut encoder_workspace).expect("create brotli static encoder");
+    let mut decoder = decoder::Interface::brotli_rust_static(&mut decoder_workspace).expect("create brotli static decoder");
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(idx, &mut encoder, &mut decoder, data);
+    }
+}
+
+#[cfg(feature = "zlib-rust")]
+#[test]
+fn should_encode_and_decode_zlib_rust_static() {
+    let mut encoder_workspace = workspace(512 * 1024);
+    let mut decoder_workspace = workspace(64 * 1024);
+    let mut encoder = encoder::Interface::zlib_rust_static(Default::default(), &mut encoder_workspace).expect("create zlib-rust static encoder");
+    let mut decoder = decoder::Interface::zlib_rust_static(Default::default(), &mut decoder_workspace).expect("create zlib-rust static decoder");
+    for (idx, data) in DATA.iter().enumerate() {
+        test_case(idx, &mut encoder, &mut decoder, data);
+    }
+}